@@ -0,0 +1,311 @@
+use std::{
+    fs::File,
+    io::{BufRead, Cursor, Read, Seek},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use eframe::egui::{self, ColorImage, TextureHandle, TextureOptions};
+use image::{
+    AnimationDecoder, Frames, RgbaImage,
+    codecs::{gif::GifDecoder, webp::WebPDecoder},
+};
+use log::{debug, warn};
+
+use crate::path::to_path;
+
+// decoded frames kept on the GPU per animation, the rest of a longer one is not played
+const FRAME_MEMORY: usize = 1 << 30;
+// browsers show frames with a shorter delay for 100 ms, many GIFs rely on that
+const MIN_DELAY: f64 = 0.02;
+const DEFAULT_DELAY: f64 = 0.1;
+// playback speeds offered in the controls
+pub const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 1.5, 2.0, 4.0];
+
+// GIF and WebP files, the only formats that can hold more than one frame here
+pub fn is_animated_uri(uri: &str) -> bool {
+    to_path(uri).is_some_and(|path| animation_format(&path).is_some())
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Gif,
+    WebP,
+}
+
+fn animation_format(path: &Path) -> Option<Format> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "gif" => Some(Format::Gif),
+        "webp" => Some(Format::WebP),
+        _ => None,
+    }
+}
+
+// the frames of `path`, each composited onto the ones before it
+fn frames(path: &Path) -> Result<Frames<'static>, String> {
+    let format = animation_format(path).ok_or("Not an animation")?;
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut bytes))
+        .map_err(|e| e.to_string())?;
+    frames_of(Cursor::new(bytes), format)
+}
+
+fn frames_of<R: BufRead + Seek + 'static>(
+    reader: R,
+    format: Format,
+) -> Result<Frames<'static>, String> {
+    match format {
+        Format::Gif => Ok(GifDecoder::new(reader)
+            .map_err(|e| e.to_string())?
+            .into_frames()),
+        Format::WebP => {
+            let decoder = WebPDecoder::new(reader).map_err(|e| e.to_string())?;
+            if !decoder.has_animation() {
+                return Err("Not an animation".to_owned());
+            }
+            Ok(decoder.into_frames())
+        }
+    }
+}
+
+// decode the frame at `index` of `path` again, composited exactly like it was shown
+pub fn frame(path: &Path, index: usize) -> Result<RgbaImage, String> {
+    frames(path)?
+        .nth(index)
+        .ok_or("The file has fewer frames now")?
+        .map(|frame| frame.into_buffer())
+        .map_err(|e| e.to_string())
+}
+
+enum Message {
+    Frame(ColorImage, f64),
+    // all frames were sent or decoding stopped with an error
+    Done(Option<String>),
+}
+
+struct Frame {
+    texture: TextureHandle,
+    // seconds the frame stays on screen at normal speed
+    delay: f64,
+}
+
+// an animated image decoded into textures on a background thread, frames can be played as soon
+// as they arrive
+pub struct Animation {
+    src: String,
+    path: PathBuf,
+    options: TextureOptions,
+    frames: Vec<Frame>,
+    // None once all frames were received
+    receiver: Option<Receiver<Message>>,
+    pub playing: bool,
+    pub speed: f32,
+    index: usize,
+    // input time the current frame went up at normal speed
+    shown_at: f64,
+}
+
+impl Animation {
+    pub fn start(ctx: &egui::Context, src: &str, options: TextureOptions, now: f64) -> Self {
+        let path = to_path(src).unwrap_or_default();
+        let (tx, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        let decode = path.clone();
+        thread::spawn(move || {
+            let result = frames(&decode).and_then(|frames| {
+                let mut memory = 0;
+                for frame in frames {
+                    let frame = frame.map_err(|e| e.to_string())?;
+                    let (numer, denom) = frame.delay().numer_denom_ms();
+                    let delay = f64::from(numer) / f64::from(denom.max(1)) / 1000.0;
+                    let buffer = frame.into_buffer();
+                    memory += buffer.len();
+                    if memory > FRAME_MEMORY {
+                        warn!("Only playing the first frames of {:?}", decode);
+                        break;
+                    }
+                    let image = ColorImage::from_rgba_unmultiplied(
+                        [buffer.width() as usize, buffer.height() as usize],
+                        buffer.as_raw(),
+                    );
+                    if tx.send(Message::Frame(image, delay)).is_err() {
+                        return Ok(());
+                    }
+                    ctx.request_repaint();
+                }
+                Ok(())
+            });
+            let _ = tx.send(Message::Done(result.err()));
+            ctx.request_repaint();
+        });
+        Self {
+            src: src.to_owned(),
+            path,
+            options,
+            frames: Vec::new(),
+            receiver: Some(receiver),
+            playing: true,
+            speed: 1.0,
+            index: 0,
+            shown_at: now,
+        }
+    }
+
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn options(&self) -> TextureOptions {
+        self.options
+    }
+
+    // upload the frames that were decoded since the last call
+    pub fn poll(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+        for message in receiver.try_iter() {
+            match message {
+                Message::Frame(image, delay) => {
+                    let name = format!("{}#{}", self.src, self.frames.len());
+                    self.frames.push(Frame {
+                        texture: ctx.load_texture(name, image, self.options),
+                        delay: if delay < MIN_DELAY {
+                            DEFAULT_DELAY
+                        } else {
+                            delay
+                        },
+                    });
+                }
+                Message::Done(err) => {
+                    if let Some(err) = err {
+                        debug!("Stopped decoding {:?}: {}", self.path, err);
+                    }
+                    self.receiver = None;
+                    return;
+                }
+            }
+        }
+    }
+
+    // None while it is unknown whether a second frame follows
+    pub fn is_animated(&self) -> Option<bool> {
+        match (self.frames.len(), &self.receiver) {
+            (0 | 1, Some(_)) => None,
+            (0 | 1, None) => Some(false),
+            _ => Some(true),
+        }
+    }
+
+    pub fn texture(&self) -> Option<&TextureHandle> {
+        self.frames.get(self.index).map(|f| &f.texture)
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    // whether frames are still being decoded
+    pub fn loading(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    // the frame to show at input time `now`, returns the seconds until the next one is due
+    pub fn advance(&mut self, now: f64) -> Option<f64> {
+        if !self.playing || self.frames.len() < 2 {
+            self.shown_at = now;
+            return None;
+        }
+        let speed = f64::from(self.speed);
+        loop {
+            let due = self.shown_at + self.frames[self.index].delay / speed;
+            if now < due {
+                return Some(due - now);
+            }
+            // the decoder is behind, stay on the last frame until the next one arrives
+            if self.index + 1 == self.frames.len() && self.loading() {
+                self.shown_at = now;
+                return None;
+            }
+            self.index = (self.index + 1) % self.frames.len();
+            self.shown_at = due;
+            // a long stall would otherwise run through many frames at once
+            if now - self.shown_at > 1.0 {
+                self.shown_at = now;
+            }
+        }
+    }
+
+    pub fn toggle(&mut self, now: f64) {
+        self.playing = !self.playing;
+        self.shown_at = now;
+    }
+
+    // pause and show the next or previous frame
+    pub fn step(&mut self, forward: bool, now: f64) {
+        let len = self.frames.len();
+        if len == 0 {
+            return;
+        }
+        self.playing = false;
+        self.shown_at = now;
+        self.index = if forward {
+            (self.index + 1) % len
+        } else {
+            (self.index + len - 1) % len
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_follow_their_delays() {
+        let ctx = egui::Context::default();
+        let mut animation = Animation {
+            src: "file:///a.gif".to_owned(),
+            path: PathBuf::new(),
+            options: TextureOptions::default(),
+            frames: (0..3)
+                .map(|i| Frame {
+                    texture: ctx.load_texture(
+                        i.to_string(),
+                        ColorImage::filled([1, 1], egui::Color32::BLACK),
+                        TextureOptions::default(),
+                    ),
+                    delay: 0.1 * f64::from(i + 1),
+                })
+                .collect(),
+            receiver: None,
+            playing: true,
+            speed: 1.0,
+            index: 0,
+            shown_at: 0.0,
+        };
+        assert_eq!(animation.is_animated(), Some(true));
+        assert!(animation.advance(0.05).is_some_and(|next| next > 0.049));
+        assert_eq!(animation.index(), 0);
+        animation.advance(0.35);
+        assert_eq!(animation.index(), 2);
+        // at twice the speed the last frame ends at 0.45 instead of 0.6
+        animation.speed = 2.0;
+        animation.advance(0.47);
+        assert_eq!(animation.index(), 0);
+        animation.step(false, 0.47);
+        assert_eq!(animation.index(), 2);
+        assert!(!animation.playing && animation.advance(10.0).is_none());
+    }
+}
@@ -0,0 +1,141 @@
+// images inside ZIP and CBZ archives, listed like the files of a directory: an entry is known by
+// its path below the archive, e.g. `comic.cbz/ch1/001.jpg`, and read into memory when shown
+
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use zip::ZipArchive;
+
+use crate::path;
+
+pub const EXTENSIONS: [&str; 2] = ["zip", "cbz"];
+// the URLs of entries, so the file loader does not look for them on disk
+const SCHEME: &str = "zip://";
+// largest entry read into memory, the size in its header is not trusted
+const MAX_ENTRY: u64 = 1 << 30;
+
+pub fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+}
+
+// the entries of `archive` whose names are accepted, subdirectories included
+pub fn list(archive: &Path, accepts: impl Fn(&Path) -> bool) -> Result<Vec<PathBuf>, String> {
+    let file = File::open(archive).map_err(|e| e.to_string())?;
+    let mut zip = ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let mut entries = Vec::new();
+    for i in 0..zip.len() {
+        let entry = zip.by_index_raw(i).map_err(|e| e.to_string())?;
+        // names leaving the archive with `..` or an absolute path are skipped
+        let Some(name) = entry.enclosed_name().filter(|_| !entry.is_dir()) else {
+            continue;
+        };
+        if !accepts(&name) {
+            continue;
+        }
+        if entry.encrypted() {
+            return Err(format!(
+                "{} is password protected",
+                path::file_name(archive)
+            ));
+        }
+        entries.push(archive.join(name));
+    }
+    Ok(entries)
+}
+
+// `/books/comic.cbz/001.jpg` becomes `zip:///books/comic.cbz/001.jpg`
+pub fn to_url(entry: &Path) -> String {
+    let url = path::to_url(entry);
+    format!("{}{}", SCHEME, url.strip_prefix("file://").unwrap_or(&url))
+}
+
+pub fn to_path(url: &str) -> Option<PathBuf> {
+    let rest = url.strip_prefix(SCHEME)?;
+    path::to_path(&format!("file://{}", rest))
+}
+
+// the archive file among the ancestors of an entry
+fn archive_of(entry: &Path) -> Option<&Path> {
+    entry
+        .ancestors()
+        .skip(1)
+        .find(|p| is_archive(p) && p.is_file())
+}
+
+pub fn contains(entry: &Path) -> bool {
+    archive_of(entry).is_some()
+}
+
+pub fn read(entry: &Path) -> Result<Vec<u8>, String> {
+    let archive =
+        archive_of(entry).ok_or_else(|| format!("No archive contains {}", entry.display()))?;
+    let name = entry.strip_prefix(archive).map_err(|e| e.to_string())?;
+    let file = File::open(archive).map_err(|e| e.to_string())?;
+    let mut zip = ZipArchive::new(file).map_err(|e| e.to_string())?;
+    // names in the archive are separated by slashes on every system
+    let key = name
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let index = zip
+        .index_for_name(&key)
+        .ok_or_else(|| format!("{} is not in the archive", name.display()))?;
+    let entry = zip.by_index(index).map_err(|e| e.to_string())?;
+    let too_large = || format!("{} is larger than 1 GiB", name.display());
+    if entry.size() > MAX_ENTRY {
+        return Err(too_large());
+    }
+    let mut bytes = Vec::with_capacity(entry.size() as usize);
+    entry
+        .take(MAX_ENTRY + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    if bytes.len() as u64 > MAX_ENTRY {
+        return Err(too_large());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+
+    #[test]
+    fn entries_are_listed_and_read() {
+        let dir = std::env::temp_dir().join(format!("view-rs-archive-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cbz = dir.join("comic #1.cbz");
+        let mut zip = ZipWriter::new(File::create(&cbz).unwrap());
+        for (name, data) in [
+            ("ch2/001.png", "second"),
+            ("notes.txt", "skipped"),
+            ("ch1/001.png", "first"),
+        ] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        zip.add_directory("empty/", SimpleFileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+
+        let png = |p: &Path| p.extension().is_some_and(|e| e == "png");
+        let entries = list(&cbz, png).unwrap();
+        assert_eq!(entries, [cbz.join("ch2/001.png"), cbz.join("ch1/001.png")]);
+        let url = to_url(&entries[1]);
+        assert!(url.starts_with("zip://"));
+        assert_eq!(to_path(&url).as_ref(), Some(&entries[1]));
+        assert_eq!(read(&entries[1]).unwrap(), b"first");
+        assert!(read(&cbz.join("ch3.png")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
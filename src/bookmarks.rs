@@ -0,0 +1,103 @@
+// images marked with B, kept per folder in the session state so a large folder opens with them
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bookmarks(BTreeMap<String, Vec<PathBuf>>);
+
+impl Bookmarks {
+    // the files marked in `folder`, in the order they were marked
+    pub fn marked(&self, folder: &Path) -> &[PathBuf] {
+        self.0
+            .get(&*folder.to_string_lossy())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn contains(&self, folder: &Path, file: &Path) -> bool {
+        self.marked(folder).iter().any(|p| p == file)
+    }
+
+    // mark `file` or take its mark away, true if it is marked now
+    pub fn toggle(&mut self, folder: &Path, file: &Path) -> bool {
+        let key = folder.to_string_lossy().into_owned();
+        let marked = self.0.entry(key.clone()).or_default();
+        let added = match marked.iter().position(|p| p == file) {
+            Some(i) => {
+                marked.remove(i);
+                false
+            }
+            None => {
+                marked.push(file.to_path_buf());
+                true
+            }
+        };
+        if marked.is_empty() {
+            self.0.remove(&key);
+        }
+        added
+    }
+
+    // the mark stays with a renamed file and goes away with a deleted or moved one
+    pub fn rename(&mut self, folder: &Path, from: &Path, to: Option<&Path>) {
+        let Some(marked) = self.0.get_mut(&*folder.to_string_lossy()) else {
+            return;
+        };
+        match to {
+            Some(to) => {
+                for p in marked.iter_mut().filter(|p| p.as_path() == from) {
+                    *p = to.to_path_buf();
+                }
+            }
+            None => marked.retain(|p| p != from),
+        }
+        if marked.is_empty() {
+            self.0.remove(&*folder.to_string_lossy());
+        }
+    }
+}
+
+// one absolute path per line, for other tools to read
+pub fn export(files: &[PathBuf], to: &Path) -> Result<(), String> {
+    let mut list = String::new();
+    for file in files {
+        let file = std::path::absolute(file).unwrap_or_else(|_| file.clone());
+        list.push_str(&file.to_string_lossy());
+        list.push('\n');
+    }
+    std::fs::write(to, list).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_are_kept_per_folder() {
+        let (a, b) = (Path::new("/photos/a"), Path::new("/photos/b"));
+        let mut bookmarks = Bookmarks::default();
+        assert!(bookmarks.toggle(a, Path::new("/photos/a/1.jpg")));
+        assert!(bookmarks.toggle(a, Path::new("/photos/a/2.jpg")));
+        assert!(bookmarks.contains(a, Path::new("/photos/a/1.jpg")));
+        assert!(!bookmarks.contains(b, Path::new("/photos/a/1.jpg")));
+
+        bookmarks.rename(
+            a,
+            Path::new("/photos/a/1.jpg"),
+            Some(Path::new("/photos/a/x.jpg")),
+        );
+        assert_eq!(
+            bookmarks.marked(a),
+            [PathBuf::from("/photos/a/x.jpg"), "/photos/a/2.jpg".into()]
+        );
+        bookmarks.rename(a, Path::new("/photos/a/x.jpg"), None);
+        assert!(!bookmarks.toggle(a, Path::new("/photos/a/2.jpg")));
+        assert!(bookmarks.marked(a).is_empty());
+        assert!(bookmarks.0.is_empty());
+    }
+}
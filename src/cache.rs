@@ -0,0 +1,106 @@
+use std::{collections::HashMap, path::Path, time::SystemTime};
+
+use eframe::egui;
+use log::debug;
+
+// images kept resident on each side of the current one
+pub const WINDOW: usize = 5;
+
+// keeps track of the textures egui holds for viewed images and forgets the ones that are no
+// longer needed, egui itself never evicts them
+pub struct Cache {
+    // upper bound for the estimated texture memory in bytes
+    pub budget: usize,
+    entries: HashMap<String, Entry>,
+    // incremented on every visit, orders the entries by recency
+    clock: u64,
+}
+
+struct Entry {
+    // estimated texture size, 0 until the intrinsic size is known
+    bytes: usize,
+    // modification time of the file when it was loaded
+    modified: Option<SystemTime>,
+    last_viewed: u64,
+}
+
+impl Cache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    // called when `path`, shown as `uri`, becomes the current image, forgets a texture that is
+    // older than the file
+    pub fn visit(&mut self, ctx: &egui::Context, uri: &str, path: &Path) {
+        let modified = path.metadata().and_then(|m| m.modified()).ok();
+        self.clock += 1;
+        if let Some(entry) = self.entries.get_mut(uri) {
+            if entry.modified != modified {
+                debug!("{:?} changed on disk, reloading", path);
+                ctx.forget_image(uri);
+                entry.bytes = 0;
+                entry.modified = modified;
+            }
+            entry.last_viewed = self.clock;
+        } else {
+            self.entries.insert(
+                uri.to_owned(),
+                Entry {
+                    bytes: 0,
+                    modified,
+                    last_viewed: self.clock,
+                },
+            );
+        }
+    }
+
+    // record the intrinsic size of a loaded image to estimate its memory use
+    pub fn loaded(&mut self, uri: &str, size: [usize; 2]) {
+        if let Some(entry) = self.entries.get_mut(uri) {
+            entry.bytes = size[0] * size[1] * 4;
+        }
+    }
+
+    // forget everything but `current` and the neighbours in `keep`, then the least recently
+    // viewed images until the estimate fits into the budget
+    pub fn trim(&mut self, ctx: &egui::Context, current: &str, keep: &[String]) {
+        let mut forget = self
+            .entries
+            .keys()
+            .filter(|uri| *uri != current && !keep.contains(uri))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut by_age = self
+            .entries
+            .iter()
+            .filter(|(uri, _)| *uri != current && !forget.contains(uri))
+            .map(|(uri, e)| (e.last_viewed, e.bytes, uri.clone()))
+            .collect::<Vec<_>>();
+        by_age.sort();
+        let mut total = by_age.iter().map(|(_, bytes, _)| bytes).sum::<usize>()
+            + self.entries.get(current).map_or(0, |e| e.bytes);
+        for (_, bytes, uri) in by_age {
+            if total <= self.budget {
+                break;
+            }
+            total -= bytes;
+            forget.push(uri);
+        }
+
+        for uri in forget {
+            debug!("Evicting {}", uri);
+            ctx.forget_image(&uri);
+            self.entries.remove(&uri);
+        }
+    }
+
+    pub fn clear(&mut self, ctx: &egui::Context) {
+        ctx.forget_all_images();
+        self.entries.clear();
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use clap::{ArgAction, Parser};
+
+use crate::path::SortKey;
+use crate::window::{self, Geometry};
+
+#[derive(Parser)]
+#[command(version, about = "Image viewer, powered by Rust+egui.")]
+pub struct Args {
+    /// Image file, directory or playlist to open at startup, `-` reads a list of paths from stdin
+    pub path: Option<PathBuf>,
+
+    /// Shuffle the file order (default)
+    #[arg(long, overrides_with = "no_random")]
+    pub random: bool,
+
+    /// Keep the files in sorted order
+    #[arg(long)]
+    pub no_random: bool,
+
+    /// Sort order of the files, implies --no-random
+    #[arg(long, value_enum, value_name = "KEY", conflicts_with = "random")]
+    pub sort: Option<SortKey>,
+
+    /// Sort in descending order, e.g. newest first
+    #[arg(long)]
+    pub descending: bool,
+
+    /// Start a slideshow advancing every SECONDS
+    #[arg(long, value_name = "SECONDS", value_parser = parse_interval)]
+    pub slideshow: Option<f32>,
+
+    /// Skip images that fail to load after a short delay
+    #[arg(long)]
+    pub auto_skip: bool,
+
+    /// Include images in subdirectories
+    #[arg(long)]
+    pub recursive: bool,
+
+    /// Detect images by their content instead of only the extension
+    #[arg(long)]
+    pub sniff: bool,
+
+    /// Open a window of its own even in single-instance mode
+    #[arg(long)]
+    pub new_window: bool,
+
+    /// Window size and position like 1280x800+100+50, instead of those of the last session
+    #[arg(long, value_name = "WxH+X+Y", value_parser = window::parse)]
+    pub geometry: Option<Geometry>,
+
+    /// Number of images to preload in each direction
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub prefetch: usize,
+
+    /// Memory budget for decoded images in MiB
+    #[arg(long, value_name = "MIB", default_value_t = 1024)]
+    pub cache_mb: usize,
+
+    /// Downscale images larger than PX pixels on either side before display
+    #[arg(long, value_name = "PX", default_value_t = 16384)]
+    pub max_texture_size: usize,
+
+    /// Show how long the current image took to load and the memory it holds, Ctrl+Shift+F12 in
+    /// the window
+    #[arg(long)]
+    pub debug_overlay: bool,
+    /// Log more, -vv for everything; RUST_LOG sets the level otherwise, Info if it isn't set
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Log less, -qq for errors only and -qqq for nothing
+    #[arg(short, long, action = ArgAction::Count)]
+    pub quiet: u8,
+
+    /// Append the log to PATH as well, with timestamps, for bug reports
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+}
+
+fn parse_interval(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(secs) if secs > 0.0 && secs.is_finite() => Ok(secs),
+        Ok(_) => Err("interval must be a positive number of seconds".into()),
+        Err(e) => Err(e.to_string()),
+    }
+}
@@ -0,0 +1,80 @@
+// ICC profiles embedded in JPEG, PNG, WebP, AVIF and TIFF files: the pixels are turned into
+// sRGB once when the texture is made, untagged images are taken to be sRGB already
+
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageDecoder, ImageReader, RgbaImage};
+use moxcms::{ColorProfile, DataColorSpace, Layout, ProfileText, TransformOptions};
+
+// the profile stored in `bytes`, if the format can carry one
+pub fn embedded(bytes: &[u8]) -> Option<Vec<u8>> {
+    let reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?;
+    reader.into_decoder().ok()?.icc_profile().ok().flatten()
+}
+
+fn name(profile: &ColorProfile) -> Option<String> {
+    let text = match profile.description.as_ref()? {
+        ProfileText::PlainString(text) => text.clone(),
+        ProfileText::Localizable(texts) => texts.first()?.value.clone(),
+        ProfileText::Description(description) => description.ascii_string.clone(),
+    };
+    let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    (!text.is_empty()).then(|| text.to_owned())
+}
+
+// `image` in sRGB, from the colors `icc` describes; returns the name of the profile it was
+// converted from, None for sRGB and for profiles that are not of RGB images
+pub fn to_srgb(image: &mut DynamicImage, icc: &[u8]) -> Result<Option<String>, String> {
+    let source = ColorProfile::new_from_slice(icc).map_err(|e| e.to_string())?;
+    if source.color_space != DataColorSpace::Rgb {
+        return Ok(None);
+    }
+    let name = name(&source).unwrap_or_else(|| "Unnamed profile".to_owned());
+    // nothing to do for the usual sRGB profiles that are embedded anyway
+    if name.contains("sRGB") {
+        return Ok(None);
+    }
+    let transform = source
+        .create_transform_8bit(
+            Layout::Rgba,
+            &ColorProfile::new_srgb(),
+            Layout::Rgba,
+            TransformOptions::default(),
+        )
+        .map_err(|e| e.to_string())?;
+    let rgba = image.to_rgba8();
+    let mut converted = vec![0; rgba.as_raw().len()];
+    transform
+        .transform(rgba.as_raw(), &mut converted)
+        .map_err(|e| e.to_string())?;
+    let converted = RgbaImage::from_raw(rgba.width(), rgba.height(), converted)
+        .expect("the converted pixels have the size of the image");
+    *image = DynamicImage::ImageRgba8(converted);
+    Ok(Some(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_gamut_colors_are_converted() {
+        // the purest green of Display P3 is beyond sRGB, it comes out as its most saturated green
+        let p3 = ColorProfile::new_display_p3();
+        let icc = p3.encode().unwrap();
+        let mut image =
+            DynamicImage::ImageRgb8(image::RgbImage::from_pixel(2, 2, [0, 255, 0].into()));
+        assert!(to_srgb(&mut image, &icc).unwrap().is_some());
+        let [r, g, b, a] = image.to_rgba8().get_pixel(0, 0).0;
+        assert!(g > 240 && r < 30 && b < 30, "{:?}", (r, g, b));
+        assert_eq!(a, 255);
+
+        let mut srgb =
+            DynamicImage::ImageRgb8(image::RgbImage::from_pixel(1, 1, [10, 20, 30].into()));
+        let icc = ColorProfile::new_srgb().encode().unwrap();
+        assert_eq!(to_srgb(&mut srgb, &icc), Ok(None));
+        assert_eq!(srgb.to_rgb8().get_pixel(0, 0).0, [10, 20, 30]);
+    }
+}
@@ -0,0 +1,75 @@
+// an image pinned next to the current one, both shown through the same zoom and pan
+
+use eframe::egui::{Rect, vec2};
+
+use crate::{diff::DiffView, view::Transform};
+
+pub struct Compare {
+    pub src: String,
+    pub name: String,
+    // EXIF orientation of the pinned file, the turns chosen by hand apply to both
+    pub orientation: Transform,
+    // the pinned image is on the right
+    pub swapped: bool,
+    // one image fills the window instead, Some(true) while it is the pinned one
+    pub ab: Option<bool>,
+    pub diff: DiffView,
+}
+
+impl Compare {
+    pub fn new(src: String, name: String, orientation: Transform) -> Self {
+        Self {
+            src,
+            name,
+            orientation,
+            swapped: false,
+            ab: None,
+            diff: DiffView::Off,
+        }
+    }
+
+    // switch to a single view or flip it between the two images
+    pub fn flip(&mut self) {
+        self.ab = Some(!self.ab.unwrap_or(false));
+    }
+}
+
+// the halves of `rect` for the pinned and the current image, `gap` points apart
+pub fn panes(rect: Rect, gap: f32, swapped: bool) -> [Rect; 2] {
+    let width = ((rect.width() - gap) / 2.0).max(0.0);
+    let left = Rect::from_min_size(rect.min, vec2(width, rect.height()));
+    let right = Rect::from_min_size(rect.min + vec2(width + gap, 0.0), left.size());
+    if swapped {
+        [right, left]
+    } else {
+        [left, right]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui::pos2;
+
+    use super::*;
+
+    #[test]
+    fn panes_split_the_window() {
+        let rect = Rect::from_min_max(pos2(10.0, 20.0), pos2(212.0, 120.0));
+        let [pinned, current] = panes(rect, 2.0, false);
+        assert_eq!(
+            pinned,
+            Rect::from_min_max(pos2(10.0, 20.0), pos2(110.0, 120.0))
+        );
+        assert_eq!(
+            current,
+            Rect::from_min_max(pos2(112.0, 20.0), pos2(212.0, 120.0))
+        );
+        assert_eq!(panes(rect, 2.0, true), [current, pinned]);
+
+        let mut compare = Compare::new(String::new(), String::new(), Transform::default());
+        compare.flip();
+        assert_eq!(compare.ab, Some(true));
+        compare.flip();
+        assert_eq!(compare.ab, Some(false));
+    }
+}
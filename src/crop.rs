@@ -0,0 +1,165 @@
+// a part of the image marked with Shift and a drag, kept until Escape or another image so its
+// edges can be moved, then saved or copied from the full resolution pixels
+
+use eframe::egui::{self, Pos2, Rect, ResizeDirection, Vec2, pos2};
+use image::DynamicImage;
+
+use crate::view::Transform;
+
+// how far from an edge in points it can be grabbed
+const REACH: f32 = 6.0;
+
+pub struct Selection {
+    // the image and how it was turned when it was marked
+    pub src: String,
+    pub transform: Transform,
+    // 0-1 in both directions of the image as it is shown, min may pass max while it is dragged
+    pub rect: Rect,
+    // the edge or corner being dragged, the bottom right one while it is drawn
+    pub grabbed: Option<ResizeDirection>,
+}
+
+impl Selection {
+    pub fn new(src: &str, transform: Transform, at: Pos2) -> Self {
+        Self {
+            src: src.to_owned(),
+            transform,
+            rect: Rect::from_min_max(at, at),
+            grabbed: Some(ResizeDirection::SouthEast),
+        }
+    }
+
+    pub fn area(&self) -> Rect {
+        Rect::from_two_pos(self.rect.min, self.rect.max)
+    }
+
+    // move the grabbed edges to `to`
+    pub fn drag(&mut self, to: Pos2) {
+        use ResizeDirection::*;
+        let Some(direction) = self.grabbed else {
+            return;
+        };
+        if matches!(direction, West | NorthWest | SouthWest) {
+            self.rect.min.x = to.x;
+        }
+        if matches!(direction, East | NorthEast | SouthEast) {
+            self.rect.max.x = to.x;
+        }
+        if matches!(direction, North | NorthWest | NorthEast) {
+            self.rect.min.y = to.y;
+        }
+        if matches!(direction, South | SouthWest | SouthEast) {
+            self.rect.max.y = to.y;
+        }
+    }
+
+    // whole pixels it covers of an image shown at `size`
+    pub fn pixels(&self, size: Vec2) -> [u32; 2] {
+        let size = (self.area().size() * size).round();
+        [size.x.max(1.0) as u32, size.y.max(1.0) as u32]
+    }
+
+    // the marked part of `image` as it is stored, before `transform` turns it
+    pub fn cut(&self, image: &DynamicImage) -> DynamicImage {
+        let area = self.area();
+        let corners = [area.min, area.max].map(|p| self.transform.texture_uv(p.to_vec2()));
+        let source = Rect::from_two_pos(corners[0].to_pos2(), corners[1].to_pos2());
+        let (w, h) = (image.width() as f32, image.height() as f32);
+        let x = (source.min.x * w).round().clamp(0.0, w - 1.0);
+        let y = (source.min.y * h).round().clamp(0.0, h - 1.0);
+        let right = (source.max.x * w).round().clamp(x + 1.0, w);
+        let bottom = (source.max.y * h).round().clamp(y + 1.0, h);
+        image.crop_imm(x as u32, y as u32, (right - x) as u32, (bottom - y) as u32)
+    }
+}
+
+// the edge or corner of `rect` on screen that the pointer at `pos` would move
+pub fn grab(rect: Rect, pos: Pos2) -> Option<ResizeDirection> {
+    use ResizeDirection::*;
+    if !rect.expand(REACH).contains(pos) {
+        return None;
+    }
+    let near = |a: f32, b: f32| (a - b).abs() <= REACH;
+    let (west, east) = (near(pos.x, rect.left()), near(pos.x, rect.right()));
+    let (north, south) = (near(pos.y, rect.top()), near(pos.y, rect.bottom()));
+    match (north, south, west, east) {
+        (true, _, true, _) => Some(NorthWest),
+        (true, _, _, true) => Some(NorthEast),
+        (_, true, true, _) => Some(SouthWest),
+        (_, true, _, true) => Some(SouthEast),
+        (true, ..) => Some(North),
+        (_, true, ..) => Some(South),
+        (_, _, true, _) => Some(West),
+        (_, _, _, true) => Some(East),
+        _ => None,
+    }
+}
+
+// `marked` on the image drawn at `image` on screen
+pub fn on_screen(marked: Rect, image: Rect) -> Rect {
+    Rect::from_min_max(
+        image.min + marked.min.to_vec2() * image.size(),
+        image.min + marked.max.to_vec2() * image.size(),
+    )
+}
+
+// the point on screen as a part of the image drawn at `image`, kept on the image
+pub fn on_image(pos: Pos2, image: Rect) -> Pos2 {
+    let at = (image.clamp(pos) - image.min) / image.size();
+    pos2(at.x, at.y)
+}
+
+// darken everything of `visible` around `marked` and outline it
+pub fn paint(painter: &egui::Painter, visible: Rect, marked: Rect) {
+    let painter = painter.with_clip_rect(visible);
+    let dim = egui::Color32::from_black_alpha(120);
+    let outside = [
+        Rect::from_x_y_ranges(visible.x_range(), visible.top()..=marked.top()),
+        Rect::from_x_y_ranges(visible.x_range(), marked.bottom()..=visible.bottom()),
+        Rect::from_x_y_ranges(visible.left()..=marked.left(), marked.y_range()),
+        Rect::from_x_y_ranges(marked.right()..=visible.right(), marked.y_range()),
+    ];
+    for part in outside {
+        painter.rect_filled(part, 0.0, dim);
+    }
+    let stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+    painter.rect_stroke(marked, 0.0, stroke, egui::StrokeKind::Outside);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_part_shown_is_cut_from_the_stored_pixels() {
+        // 40×20 stored, the left half red; a clockwise turn puts it at the top
+        let stored = image::RgbImage::from_fn(40, 20, |x, _| {
+            image::Rgb(if x < 20 { [255, 0, 0] } else { [0, 0, 255] })
+        });
+        let image = DynamicImage::ImageRgb8(stored);
+        let mut transform = Transform::default();
+        transform.rotate(true);
+        let mut selection = Selection::new("a.png", transform, pos2(0.0, 0.0));
+        // drawn from the bottom right to the top left, all across and down to the middle
+        selection.rect = Rect::from_min_max(pos2(1.0, 0.5), pos2(0.0, 0.0));
+        let cut = selection.cut(&image);
+        assert_eq!((cut.width(), cut.height()), (20, 20));
+        assert!(cut.to_rgb8().pixels().all(|p| p.0 == [255, 0, 0]));
+        assert_eq!(selection.pixels(egui::vec2(20.0, 40.0)), [20, 20]);
+
+        selection.grabbed = grab(
+            on_screen(
+                selection.area(),
+                Rect::from_min_size(Pos2::ZERO, egui::vec2(100.0, 100.0)),
+            ),
+            pos2(102.0, 30.0),
+        );
+        assert_eq!(selection.grabbed, Some(ResizeDirection::East));
+        selection.rect = selection.area();
+        selection.drag(pos2(0.25, 0.2));
+        assert_eq!(
+            selection.area(),
+            Rect::from_min_max(pos2(0.0, 0.0), pos2(0.25, 0.5))
+        );
+    }
+}
@@ -0,0 +1,106 @@
+// how long the image navigated to took to show and what it holds in memory, for the overlay of
+// Ctrl+Shift+F12 or `--debug-overlay`; every image is also logged once its texture is ready
+
+use std::time::{Duration, Instant};
+
+use crate::loader::Stats;
+use crate::metadata::human_size;
+
+pub struct Load {
+    pub src: String,
+    since: Instant,
+    // decoded already when it was navigated to, by a prefetch or an earlier visit
+    hit: bool,
+    // from the navigation to the first frame with the texture
+    ready: Option<Duration>,
+}
+
+impl Load {
+    pub fn new(src: &str, since: Instant, hit: bool) -> Self {
+        Self {
+            src: src.to_owned(),
+            since,
+            hit,
+            ready: None,
+        }
+    }
+
+    // the texture is there, true only the first time
+    pub fn ready(&mut self, now: Instant) -> bool {
+        if self.ready.is_some() {
+            return false;
+        }
+        self.ready = Some(now.saturating_duration_since(self.since));
+        true
+    }
+
+    fn cache(&self) -> &'static str {
+        if self.hit { "hit" } else { "miss" }
+    }
+
+    // label and value of each measure for the overlay, `stats` are unknown for the images other
+    // loaders decode
+    pub fn rows(&self, stats: Option<Stats>, resident: usize) -> Vec<(&'static str, String)> {
+        let ms = |d: Duration| format!("{:.1} ms", d.as_secs_f64() * 1000.0);
+        let unknown = || "–".to_owned();
+        vec![
+            ("Ready after", self.ready.map_or_else(|| "…".to_owned(), ms)),
+            ("Cache", self.cache().to_owned()),
+            (
+                "Decoded",
+                stats.map_or_else(unknown, |s| format!("{}×{}", s.size[0], s.size[1])),
+            ),
+            ("Decoding", stats.map_or_else(unknown, |s| ms(s.took))),
+            (
+                "Texture",
+                stats.map_or_else(unknown, |s| human_size(s.bytes() as u64)),
+            ),
+            ("Resident", resident.to_string()),
+        ]
+    }
+
+    // the same as `key=value` pairs for the log, so reports can be compared and grepped
+    pub fn log_line(&self, stats: Option<Stats>, resident: usize) -> String {
+        let ms = |d: Duration| format!("{:.1}", d.as_secs_f64() * 1000.0);
+        let mut line = format!(
+            "src={} ready_ms={} cache={}",
+            self.src,
+            self.ready.map_or_else(|| "-".to_owned(), ms),
+            self.cache()
+        );
+        if let Some(stats) = stats {
+            line += &format!(
+                " decoded={}x{} decode_ms={} texture_bytes={}",
+                stats.size[0],
+                stats.size[1],
+                ms(stats.took),
+                stats.bytes()
+            );
+        }
+        line + &format!(" resident={}", resident)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_load_is_reported_once_it_is_ready() {
+        let since = Instant::now();
+        let mut load = Load::new("file:///a.png", since, false);
+        assert_eq!(load.rows(None, 2)[0].1, "…");
+        assert!(load.ready(since + Duration::from_millis(40)));
+        assert!(!load.ready(since + Duration::from_millis(90)));
+        let stats = Stats {
+            size: [200, 100],
+            took: Duration::from_millis(25),
+        };
+        assert_eq!(
+            load.log_line(Some(stats), 3),
+            "src=file:///a.png ready_ms=40.0 cache=miss decoded=200x100 decode_ms=25.0 \
+             texture_bytes=80000 resident=3"
+        );
+        assert_eq!(load.rows(Some(stats), 3)[4].1, "80.0 KB");
+    }
+}
@@ -0,0 +1,215 @@
+// where the pinned and the current image differ, pixel by pixel, as a heatmap and a few numbers
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread,
+};
+
+use eframe::egui;
+use image::RgbaImage;
+
+use crate::pixels::Pixels;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffView {
+    #[default]
+    Off,
+    // the heatmap on top of the current image
+    Overlay,
+    // the heatmap instead of the pinned image
+    Only,
+}
+
+impl DiffView {
+    pub const ALL: [DiffView; 3] = [DiffView::Off, DiffView::Overlay, DiffView::Only];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DiffView::Off => "No diff",
+            DiffView::Overlay => "Diff overlay",
+            DiffView::Only => "Diff only",
+        }
+    }
+}
+
+pub struct Stats {
+    // the largest difference of a channel, 0 to 255
+    pub max: u8,
+    pub mean: f32,
+    pub differing: usize,
+}
+
+struct Outcome {
+    stats: Stats,
+    heatmap: egui::ColorImage,
+}
+
+// the difference of two images, computed once on a background thread
+pub struct Diff {
+    // pinned and current
+    pixels: [Pixels; 2],
+    rows: usize,
+    done: Arc<AtomicUsize>,
+    computing: Option<Receiver<Result<Outcome, String>>>,
+    result: Option<Result<(Stats, egui::TextureHandle), String>>,
+}
+
+pub enum State<'a> {
+    Decoding,
+    // the share of rows compared so far
+    Computing(f32),
+    Done(&'a Stats, &'a egui::TextureHandle),
+    Failed(&'a str),
+}
+
+impl Diff {
+    pub fn new(pinned: &str, current: &str) -> Self {
+        Self {
+            pixels: [Pixels::new(pinned), Pixels::new(current)],
+            rows: 0,
+            done: Arc::new(AtomicUsize::new(0)),
+            computing: None,
+            result: None,
+        }
+    }
+
+    pub fn of(&self, pinned: &str, current: &str) -> bool {
+        self.pixels[0].src() == pinned && self.pixels[1].src() == current
+    }
+
+    pub fn poll(&mut self, ctx: &egui::Context) -> State<'_> {
+        if self.result.is_none() {
+            match &self.computing {
+                Some(computing) => {
+                    if let Ok(outcome) = computing.try_recv() {
+                        self.result = Some(outcome.map(|o| {
+                            let texture =
+                                ctx.load_texture("diff", o.heatmap, egui::TextureOptions::NEAREST);
+                            (o.stats, texture)
+                        }));
+                    }
+                }
+                None => {
+                    let [pinned, current] = &mut self.pixels;
+                    let failed = pinned.failed() || current.failed();
+                    match (pinned.get(ctx), current.get(ctx)) {
+                        (Some(a), Some(b)) => {
+                            let (a, b) = (a.clone(), b.clone());
+                            let (tx, computing) = mpsc::channel();
+                            let (ctx, done) = (ctx.clone(), self.done.clone());
+                            self.rows = a.height() as usize;
+                            thread::spawn(move || {
+                                if tx.send(compute(&a, &b, &done, &ctx)).is_ok() {
+                                    ctx.request_repaint();
+                                }
+                            });
+                            self.computing = Some(computing);
+                        }
+                        _ if failed => {
+                            self.result = Some(Err("An image could not be decoded".to_owned()));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        match &self.result {
+            Some(Ok((stats, texture))) => State::Done(stats, texture),
+            Some(Err(err)) => State::Failed(err),
+            None if self.computing.is_some() => {
+                let done = self.done.load(Ordering::Relaxed);
+                State::Computing(done as f32 / self.rows.max(1) as f32)
+            }
+            None => State::Decoding,
+        }
+    }
+}
+
+// the largest channel difference of each pixel, counting rows into `done`
+fn compute(
+    a: &RgbaImage,
+    b: &RgbaImage,
+    done: &AtomicUsize,
+    ctx: &egui::Context,
+) -> Result<Outcome, String> {
+    if a.dimensions() != b.dimensions() {
+        return Err(format!(
+            "The sizes differ: {}×{} pinned, {}×{} current",
+            a.width(),
+            a.height(),
+            b.width(),
+            b.height()
+        ));
+    }
+    let (width, height) = a.dimensions();
+    let mut heatmap = Vec::with_capacity(width as usize * height as usize);
+    let (mut max, mut sum, mut differing) = (0u8, 0u64, 0usize);
+    for y in 0..height {
+        for x in 0..width {
+            let (p, q) = (a.get_pixel(x, y).0, b.get_pixel(x, y).0);
+            let d = (0..4).map(|i| p[i].abs_diff(q[i])).max().unwrap_or(0);
+            if d > 0 {
+                max = max.max(d);
+                sum += u64::from(d);
+                differing += 1;
+            }
+            heatmap.push(heat(d));
+        }
+        done.store(y as usize + 1, Ordering::Relaxed);
+        if y % 256 == 0 {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+    }
+    let pixels = (width as usize * height as usize).max(1);
+    Ok(Outcome {
+        stats: Stats {
+            max,
+            mean: sum as f32 / pixels as f32,
+            differing,
+        },
+        heatmap: egui::ColorImage::new([width as usize, height as usize], heatmap),
+    })
+}
+
+// equal pixels stay clear, small differences are a faint yellow and large ones a solid red
+fn heat(d: u8) -> egui::Color32 {
+    if d == 0 {
+        return egui::Color32::TRANSPARENT;
+    }
+    // stretched so that a difference of a few levels is still visible
+    let t = (f32::from(d) / 255.0).sqrt();
+    let green = (255.0 * (1.0 - t)) as u8;
+    egui::Color32::from_rgba_unmultiplied(255, green, 0, (96.0 + 159.0 * t) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn differences_are_counted() {
+        let ctx = egui::Context::default();
+        let done = AtomicUsize::new(0);
+        let a = RgbaImage::from_pixel(4, 2, Rgba([10, 20, 30, 255]));
+        let mut b = a.clone();
+        b.put_pixel(1, 0, Rgba([10, 60, 30, 255]));
+        b.put_pixel(3, 1, Rgba([12, 20, 30, 255]));
+
+        let outcome = compute(&a, &b, &done, &ctx).unwrap();
+        assert_eq!(outcome.stats.max, 40);
+        assert_eq!(outcome.stats.differing, 2);
+        assert_eq!(outcome.stats.mean, 42.0 / 8.0);
+        assert_eq!(done.load(Ordering::Relaxed), 2);
+        assert_eq!(outcome.heatmap[(0, 0)], egui::Color32::TRANSPARENT);
+        assert_ne!(outcome.heatmap[(1, 0)], egui::Color32::TRANSPARENT);
+
+        let small = RgbaImage::new(2, 2);
+        assert!(compute(&a, &small, &done, &ctx).is_err());
+    }
+}
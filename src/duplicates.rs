@@ -0,0 +1,370 @@
+// files of the list with the same content, or images that look alike, found on a background
+// thread; hashes are kept in `hashes.toml` in the cache directory by path, size and modification
+// time so a second search only reads the files that changed
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use directories::ProjectDirs;
+use eframe::egui;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{read_toml, write_toml};
+
+// bits in which two similar images may differ, out of 64
+const SIMILAR_BITS: u32 = 6;
+// the count of files done is shown at most this often
+const PROGRESS: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Likeness {
+    #[default]
+    Identical,
+    // by a perceptual hash, also finds resized and recompressed copies
+    Similar,
+}
+
+impl Likeness {
+    pub const ALL: [Likeness; 2] = [Likeness::Identical, Likeness::Similar];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Likeness::Identical => "Identical files",
+            Likeness::Similar => "Similar images",
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Cache {
+    files: BTreeMap<String, Hashes>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Hashes {
+    size: u64,
+    // nanoseconds since 1970
+    modified: u64,
+    // CRC-32 of the content
+    content: Option<u32>,
+    // difference hash in hex, TOML has no unsigned 64 bit integers
+    look: Option<String>,
+}
+
+pub struct DuplicateScan {
+    pub total: usize,
+    done: Arc<AtomicUsize>,
+    result: Receiver<Vec<Vec<PathBuf>>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl DuplicateScan {
+    pub fn start(ctx: &egui::Context, paths: Vec<PathBuf>, likeness: Likeness) -> Self {
+        let (tx, result) = mpsc::channel();
+        let done = Arc::new(AtomicUsize::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let total = paths.len();
+
+        let ctx = ctx.clone();
+        let (counter, flag) = (done.clone(), cancel.clone());
+        thread::spawn(move || {
+            let mut cache: Cache = cache_path().map(|p| read_toml(&p)).unwrap_or_default();
+            let progress = |i: usize| {
+                counter.fetch_add(i, Ordering::Relaxed);
+                ctx.request_repaint_after(PROGRESS);
+            };
+            let groups = match likeness {
+                Likeness::Identical => identical(&paths, &mut cache, &flag, progress),
+                Likeness::Similar => similar(&paths, &mut cache, &flag, progress),
+            };
+            // what was hashed before the cancel is kept as well
+            if let Some(path) = cache_path() {
+                if let Err(err) = write_toml(&path, &cache) {
+                    warn!("Failed to save hashes: {}", err);
+                }
+            }
+            let Some(groups) = groups else {
+                debug!("Duplicate search cancelled");
+                return;
+            };
+            info!("Found {} groups of duplicates", groups.len());
+            if tx.send(groups).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+
+        Self {
+            total,
+            done,
+            result,
+            cancel,
+        }
+    }
+
+    pub fn progress(&self) -> usize {
+        self.done.load(Ordering::Relaxed).min(self.total)
+    }
+
+    // groups of two or more files in list order, once all are compared
+    pub fn poll(&self) -> Option<Vec<Vec<PathBuf>>> {
+        self.result.try_recv().ok()
+    }
+}
+
+impl Drop for DuplicateScan {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+fn cache_path() -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("", "", "view-rs")?;
+    Some(dirs.cache_dir().join("hashes.toml"))
+}
+
+// the cached hashes of `path`, emptied when the file changed since
+fn entry<'a>(cache: &'a mut Cache, path: &Path) -> Option<&'a mut Hashes> {
+    let meta = std::fs::metadata(path).ok()?;
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64);
+    let hashes = cache
+        .files
+        .entry(path.to_string_lossy().into_owned())
+        .or_default();
+    if hashes.size != meta.len() || hashes.modified != modified {
+        *hashes = Hashes {
+            size: meta.len(),
+            modified,
+            ..Hashes::default()
+        };
+    }
+    Some(hashes)
+}
+
+// files of the same size are hashed, files of the same hash compared byte by byte
+fn identical(
+    paths: &[PathBuf],
+    cache: &mut Cache,
+    cancel: &AtomicBool,
+    progress: impl Fn(usize),
+) -> Option<Vec<Vec<PathBuf>>> {
+    let mut sizes: HashMap<u64, Vec<&PathBuf>> = HashMap::new();
+    for path in paths {
+        match entry(cache, path) {
+            Some(hashes) => sizes.entry(hashes.size).or_default().push(path),
+            None => progress(1),
+        }
+    }
+    let mut groups = Vec::new();
+    for same_size in sizes.into_values() {
+        if same_size.len() < 2 {
+            progress(1);
+            continue;
+        }
+        let mut hashed: HashMap<u32, Vec<&PathBuf>> = HashMap::new();
+        for path in same_size {
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            let Some(hashes) = entry(cache, path) else {
+                continue;
+            };
+            let content = hashes
+                .content
+                .or_else(|| crc(path).inspect_err(|e| warn!("{:?}: {}", path, e)).ok());
+            hashes.content = content;
+            if let Some(content) = content {
+                hashed.entry(content).or_default().push(path);
+            }
+            progress(1);
+        }
+        for candidates in hashed.into_values().filter(|c| c.len() > 1) {
+            groups.extend(confirm(candidates));
+        }
+    }
+    Some(in_list_order(groups, paths))
+}
+
+// files that really are equal among ones with the same hash
+fn confirm(mut candidates: Vec<&PathBuf>) -> Vec<Vec<PathBuf>> {
+    let mut groups = Vec::new();
+    while let Some(first) = candidates.pop() {
+        let (same, rest): (Vec<_>, Vec<_>) = candidates
+            .into_iter()
+            .partition(|other| equal(first, other).unwrap_or(false));
+        candidates = rest;
+        if !same.is_empty() {
+            groups.push(std::iter::once(first).chain(same).cloned().collect());
+        }
+    }
+    groups
+}
+
+// a cheap checksum only narrows the candidates down, `confirm` compares their bytes so a collision
+// never makes a group
+fn crc(path: &Path) -> std::io::Result<u32> {
+    let mut file = File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0; 1 << 16];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok(hasher.finalize());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+fn equal(a: &Path, b: &Path) -> std::io::Result<bool> {
+    let (mut a, mut b) = (File::open(a)?, File::open(b)?);
+    let (mut x, mut y) = (vec![0; 1 << 16], vec![0; 1 << 16]);
+    loop {
+        let n = a.read(&mut x)?;
+        if n == 0 {
+            return Ok(b.read(&mut y)? == 0);
+        }
+        // the other file may hand out its part in smaller reads
+        b.read_exact(&mut y[..n])?;
+        if x[..n] != y[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+// images whose difference hashes are at most a few bits apart, joined transitively
+fn similar(
+    paths: &[PathBuf],
+    cache: &mut Cache,
+    cancel: &AtomicBool,
+    progress: impl Fn(usize),
+) -> Option<Vec<Vec<PathBuf>>> {
+    let mut looks = Vec::new();
+    for path in paths {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        if let Some(hashes) = entry(cache, path) {
+            let cached = hashes.look.as_deref();
+            let look = cached
+                .and_then(|h| u64::from_str_radix(h, 16).ok())
+                .or_else(|| {
+                    crate::loader::open(path)
+                        .inspect_err(|e| debug!("{:?}: {}", path, e))
+                        .ok()
+                        .map(|image| look(&image))
+                });
+            hashes.look = look.map(|l| format!("{:016x}", l));
+            looks.extend(look.map(|l| (path, l)));
+        }
+        progress(1);
+    }
+
+    // union-find over all pairs, a few thousand images are compared in moments
+    let mut parent: Vec<usize> = (0..looks.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..looks.len() {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        for j in i + 1..looks.len() {
+            if (looks[i].1 ^ looks[j].1).count_ones() <= SIMILAR_BITS {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+    let mut groups: HashMap<usize, Vec<PathBuf>> = HashMap::new();
+    for (i, (path, _)) in looks.iter().enumerate() {
+        let r = root(&mut parent, i);
+        groups.entry(r).or_default().push(path.to_path_buf());
+    }
+    let groups = groups.into_values().filter(|g| g.len() > 1).collect();
+    Some(in_list_order(groups, paths))
+}
+
+// whether each pixel of a 9×8 gray version is brighter than its right neighbour
+fn look(image: &image::DynamicImage) -> u64 {
+    let small = image
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle)
+        .to_luma8();
+    let mut bits = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            bits <<= 1;
+            bits |= u64::from(small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0]);
+        }
+    }
+    bits
+}
+
+// members and groups sorted the way the list shows them
+fn in_list_order(mut groups: Vec<Vec<PathBuf>>, paths: &[PathBuf]) -> Vec<Vec<PathBuf>> {
+    let position: HashMap<&Path, usize> = paths
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (p.as_path(), i))
+        .collect();
+    let at = |p: &PathBuf| position.get(p.as_path()).copied().unwrap_or(usize::MAX);
+    for group in &mut groups {
+        group.sort_by_key(at);
+    }
+    groups.sort_by_key(|g| at(&g[0]));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_files_are_grouped() {
+        let dir = std::env::temp_dir().join(format!("view-rs-duplicates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths: Vec<PathBuf> = ["a.jpg", "b.jpg", "c.jpg", "d.jpg", "e.jpg"]
+            .iter()
+            .map(|n| dir.join(n))
+            .collect();
+        // c has the size of the others but not their content, e is not even read
+        for (path, content) in paths.iter().zip(["same", "same", "sane", "same", "samey"]) {
+            std::fs::write(path, content).unwrap();
+        }
+
+        let mut cache = Cache::default();
+        let groups = identical(&paths, &mut cache, &AtomicBool::new(false), |_| {}).unwrap();
+        assert_eq!(
+            groups,
+            [vec![paths[0].clone(), paths[1].clone(), paths[3].clone()]]
+        );
+        let key = paths[4].to_string_lossy();
+        assert!(cache.files[&*key].content.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let gradient = image::RgbImage::from_fn(90, 80, |x, y| image::Rgb([x as u8, y as u8, 0]));
+        let image = image::DynamicImage::ImageRgb8(gradient);
+        let resized = image.resize_exact(45, 40, image::imageops::FilterType::Nearest);
+        assert!((look(&image) ^ look(&resized)).count_ones() <= SIMILAR_BITS);
+    }
+}
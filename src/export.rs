@@ -0,0 +1,45 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use image::{DynamicImage, ImageFormat, codecs::jpeg::JpegEncoder, imageops::FilterType};
+
+use crate::view::Transform;
+
+// the formats a copy can be saved as, with their extensions
+pub const FORMATS: [(&str, &[&str]); 4] = [
+    ("JPEG", &["jpg", "jpeg"]),
+    ("PNG", &["png"]),
+    ("WebP", &["webp"]),
+    ("BMP", &["bmp"]),
+];
+
+// write `image` to `path` in the format its extension names, turned by `transform` and scaled
+// down so its long edge is at most `max_edge` pixels
+pub fn export(
+    image: DynamicImage,
+    transform: Transform,
+    path: &Path,
+    quality: u8,
+    max_edge: Option<u32>,
+) -> Result<(), String> {
+    let format = ImageFormat::from_path(path)
+        .ok()
+        .filter(|f| {
+            matches!(
+                f,
+                ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP | ImageFormat::Bmp
+            )
+        })
+        .ok_or("Choose a .jpg, .png, .webp or .bmp file name")?;
+    let mut image = transform.apply(image);
+    if let Some(edge) = max_edge.filter(|&e| e < image.width().max(image.height())) {
+        image = image.resize(edge, edge, FilterType::Lanczos3);
+    }
+    let mut file = BufWriter::new(File::create(path).map_err(|e| e.to_string())?);
+    let result = match format {
+        // JPEG has no alpha channel
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut file, quality)),
+        _ => image.write_to(&mut file, format),
+    };
+    result.map_err(|e| e.to_string())
+}
@@ -0,0 +1,218 @@
+// other programs the current file is handed to: the system's default application or a command
+// from the settings like `gimp "{path}"`
+
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use eframe::egui;
+use log::{debug, info, warn};
+
+// `template` split into the program and its arguments, `{path}`, `{dir}` and `{filename}` in
+// a word replaced by the file, its folder and its name, no shell is involved so each stays one
+// argument whatever it contains
+pub fn expand(template: &str, path: &Path) -> Result<Vec<String>, String> {
+    let dir = path.parent().unwrap_or(Path::new("")).to_string_lossy();
+    let filename = path.file_name().unwrap_or_default().to_string_lossy();
+    let path = path.to_string_lossy();
+    let words: Vec<String> = words(template)?
+        .into_iter()
+        .map(|w| {
+            w.replace("{path}", &path)
+                .replace("{dir}", &dir)
+                .replace("{filename}", &filename)
+        })
+        .collect();
+    if words.is_empty() {
+        return Err("The command is empty".to_owned());
+    }
+    Ok(words)
+}
+
+// split at spaces outside of double or single quotes, backslashes are kept as they are for
+// Windows paths
+fn words(template: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in template.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.get_or_insert_default().push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                // `""` is an empty argument
+                word.get_or_insert_default();
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_default().push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("A quote is not closed".to_owned());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+// start `words` without waiting for it, its output and exit status go to the log and the
+// channel gets whether it succeeded, `wake` is repainted once it did
+pub fn spawn(words: &[String], wake: Option<&egui::Context>) -> Result<Receiver<bool>, String> {
+    let (program, args) = words.split_first().ok_or("The command is empty")?;
+    info!("Running {:?}", words);
+    let child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{}: {}", program, e))?;
+    let program = program.clone();
+    let (tx, done) = mpsc::channel();
+    let wake = wake.cloned();
+    thread::spawn(move || {
+        let success = match child.wait_with_output() {
+            Ok(output) => {
+                for line in String::from_utf8_lossy(&output.stdout).lines() {
+                    info!("{}: {}", program, line);
+                }
+                for line in String::from_utf8_lossy(&output.stderr).lines() {
+                    warn!("{}: {}", program, line);
+                }
+                if output.status.success() {
+                    debug!("{} finished", program);
+                } else {
+                    warn!("{} failed with {}", program, output.status);
+                }
+                output.status.success()
+            }
+            Err(err) => {
+                warn!("{}: {}", program, err);
+                false
+            }
+        };
+        let _ = tx.send(success);
+        if let Some(ctx) = wake {
+            ctx.request_repaint();
+        }
+    });
+    Ok(done)
+}
+
+// open `path` with the application the system associates with it
+pub fn open(path: &Path) -> Result<(), String> {
+    let path = path.to_string_lossy().into_owned();
+    let words = if cfg!(target_os = "macos") {
+        vec!["open".to_owned(), path]
+    } else if cfg!(windows) {
+        // like `start`, without cmd interpreting `&` or `%` in the name
+        vec!["explorer".to_owned(), path]
+    } else {
+        vec!["xdg-open".to_owned(), path]
+    };
+    spawn(&words, None).map(|_| ())
+}
+
+// the file manager showing the folder of `path` with the file selected
+pub fn reveal(path: &Path) -> Result<(), String> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        // explorer reads its command line itself and wants the quotes after the comma
+        Command::new("explorer")
+            .raw_arg(format!("/select,\"{}\"", path.display()))
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| format!("explorer: {}", e))
+    }
+    #[cfg(target_os = "macos")]
+    {
+        spawn(
+            &[
+                "open".to_owned(),
+                "-R".to_owned(),
+                path.to_string_lossy().into_owned(),
+            ],
+            None,
+        )
+        .map(|_| ())
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        // file managers implementing the freedesktop interface can select the file, others only
+        // get to open the folder
+        // the array elements of dbus-send are separated by commas
+        let url = crate::path::to_url(path).replace(',', "%2C");
+        let dir = path.parent().ok_or("No parent folder")?.to_path_buf();
+        thread::spawn(move || {
+            let selected = Command::new("dbus-send")
+                .args([
+                    "--session",
+                    "--print-reply",
+                    "--dest=org.freedesktop.FileManager1",
+                    "/org/freedesktop/FileManager1",
+                    "org.freedesktop.FileManager1.ShowItems",
+                ])
+                .arg(format!("array:string:{}", url))
+                .arg("string:")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|s| s.success());
+            if !selected {
+                debug!("No file manager over D-Bus, opening {:?}", dir);
+                if let Err(err) = spawn(
+                    &["xdg-open".to_owned(), dir.to_string_lossy().into_owned()],
+                    None,
+                ) {
+                    warn!("Failed to show {:?}: {}", dir, err);
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+// `path` in the editor given by `template`, or the default application if it is blank
+pub fn edit(template: &str, path: &Path) -> Result<(), String> {
+    if template.trim().is_empty() {
+        open(path)
+    } else {
+        spawn(&expand(template, path)?, None).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_are_split() {
+        let path = Path::new("/photos/my trip/a'b.jpg");
+        assert_eq!(
+            expand(r#"gimp --new-instance "{path}""#, path).unwrap(),
+            ["gimp", "--new-instance", "/photos/my trip/a'b.jpg"]
+        );
+        assert_eq!(
+            expand(r#"'C:\Program Files\Paint\paint.exe' {path} """#, path).unwrap(),
+            [
+                r"C:\Program Files\Paint\paint.exe",
+                "/photos/my trip/a'b.jpg",
+                ""
+            ]
+        );
+        assert_eq!(
+            expand("cp {path} {dir}/copy-{filename}", path).unwrap(),
+            [
+                "cp",
+                "/photos/my trip/a'b.jpg",
+                "/photos/my trip/copy-a'b.jpg"
+            ]
+        );
+        assert!(expand("gimp \"{path}", path).is_err());
+        assert!(expand("  ", path).is_err());
+    }
+}
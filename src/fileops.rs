@@ -0,0 +1,275 @@
+use std::{
+    fs::File,
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread,
+    time::Duration,
+};
+
+use eframe::egui;
+
+// how often a copy in progress updates its bar
+const PROGRESS: Duration = Duration::from_millis(100);
+
+// move `file` into `dir`, renaming it instead of replacing an existing file, returns the new path
+pub fn move_to(file: &Path, dir: &Path) -> Result<PathBuf, String> {
+    let name = file.file_name().ok_or("Not a file")?;
+    let target = unique_path(&dir.join(name));
+    match std::fs::rename(file, &target) {
+        Ok(()) => Ok(target),
+        // rename can't move between file systems
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => {
+            std::fs::copy(file, &target).map_err(|e| e.to_string())?;
+            if let Err(err) = std::fs::remove_file(file) {
+                let _ = std::fs::remove_file(&target);
+                return Err(err.to_string());
+            }
+            Ok(target)
+        }
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+// a file copied or moved into another folder on a background thread
+pub struct Transfer {
+    pub file: PathBuf,
+    pub moving: bool,
+    pub total: u64,
+    done: Arc<AtomicU64>,
+    result: Receiver<Result<PathBuf, String>>,
+}
+
+impl Transfer {
+    // `file` into `dir`, replacing a file of the same name there or next to it with a number
+    pub fn start(
+        ctx: &egui::Context,
+        file: &Path,
+        dir: &Path,
+        moving: bool,
+        replace: bool,
+    ) -> Self {
+        let (tx, result) = mpsc::channel();
+        let done = Arc::new(AtomicU64::new(0));
+        let total = file.metadata().map_or(0, |m| m.len());
+        let name = file.file_name().unwrap_or_default();
+        let target = if replace {
+            dir.join(name)
+        } else {
+            unique_path(&dir.join(name))
+        };
+        let (ctx, from, counter) = (ctx.clone(), file.to_path_buf(), done.clone());
+        thread::spawn(move || {
+            let result = transfer(&from, &target, moving, &counter, &ctx).map(|()| target);
+            if tx.send(result).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+        Self {
+            file: file.to_path_buf(),
+            moving,
+            total,
+            done,
+            result,
+        }
+    }
+
+    // the share of bytes copied so far
+    pub fn progress(&self) -> f32 {
+        self.done.load(Ordering::Relaxed) as f32 / self.total.max(1) as f32
+    }
+
+    // where the file went once it is done
+    pub fn poll(&self) -> Option<Result<PathBuf, String>> {
+        self.result.try_recv().ok()
+    }
+}
+
+fn transfer(
+    file: &Path,
+    target: &Path,
+    moving: bool,
+    done: &AtomicU64,
+    ctx: &egui::Context,
+) -> Result<(), String> {
+    if target == file {
+        return Err("The file is in that folder already".to_owned());
+    }
+    if moving {
+        match std::fs::rename(file, target) {
+            Ok(()) => return Ok(()),
+            // rename can't move between file systems
+            Err(err) if err.kind() == ErrorKind::CrossesDevices => {}
+            Err(err) => return Err(err.to_string()),
+        }
+    }
+    // a file that is replaced stays intact until the copy is complete
+    let part = unique_path(&target.with_file_name(format!("{}.part", file_name(target))));
+    if let Err(err) = copy(file, &part, done, ctx)
+        .and_then(|()| std::fs::rename(&part, target).map_err(|e| e.to_string()))
+    {
+        let _ = std::fs::remove_file(&part);
+        return Err(err);
+    }
+    if moving {
+        if let Err(err) = std::fs::remove_file(file) {
+            let _ = std::fs::remove_file(target);
+            return Err(err.to_string());
+        }
+    }
+    Ok(())
+}
+
+// like `std::fs::copy`, counting the bytes into `done`
+fn copy(from: &Path, to: &Path, done: &AtomicU64, ctx: &egui::Context) -> Result<(), String> {
+    let mut reader = File::open(from).map_err(|e| e.to_string())?;
+    let mut writer = File::create(to).map_err(|e| e.to_string())?;
+    let mut buffer = vec![0; 1 << 20];
+    let mut copied = 0;
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.to_string()),
+        };
+        writer.write_all(&buffer[..n]).map_err(|e| e.to_string())?;
+        copied += n as u64;
+        done.store(copied, Ordering::Relaxed);
+        ctx.request_repaint_after(PROGRESS);
+    }
+    writer.sync_all().map_err(|e| e.to_string())?;
+    if let Ok(meta) = from.metadata() {
+        let _ = std::fs::set_permissions(to, meta.permissions());
+    }
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// give `file` the name `name` in its directory, an existing file is never replaced
+pub fn rename(file: &Path, name: &str) -> Result<PathBuf, String> {
+    check_name(name)?;
+    let target = file.with_file_name(name);
+    // changing only the case finds the file itself on case-insensitive file systems
+    if target.exists() && !same_file(file, &target) {
+        return Err(format!("{} already exists", name));
+    }
+    std::fs::rename(file, &target).map_err(|e| e.to_string())?;
+    Ok(target)
+}
+
+// why `name` can't be a file name, Windows forbids more characters and a few device names
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() || name == "." || name == ".." {
+        return Err("The name is empty".to_owned());
+    }
+    let forbidden: &[char] = if cfg!(windows) {
+        &['/', '\\', '<', '>', ':', '"', '|', '?', '*']
+    } else {
+        &['/']
+    };
+    if let Some(c) = name
+        .chars()
+        .find(|c| forbidden.contains(c) || c.is_control())
+    {
+        return Err(format!("A name can't contain {:?}", c));
+    }
+    if cfg!(windows) {
+        if name.ends_with(['.', ' ']) {
+            return Err("A name can't end with a dot or a space".to_owned());
+        }
+        let stem = name.split('.').next().unwrap_or(name).trim_end();
+        let reserved = ["CON", "PRN", "AUX", "NUL"]
+            .iter()
+            .any(|r| stem.eq_ignore_ascii_case(r))
+            || ((stem.len() == 4)
+                && ["COM", "LPT"]
+                    .iter()
+                    .any(|r| stem[..3].eq_ignore_ascii_case(r))
+                && stem.as_bytes()[3].is_ascii_digit());
+        if reserved {
+            return Err(format!("{} is reserved by Windows", stem));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (a.metadata(), b.metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+// Windows file names ignore case
+#[cfg(not(unix))]
+fn same_file(a: &Path, b: &Path) -> bool {
+    a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+}
+
+// `path`, or `name (1).ext`, `name (2).ext`, … if it exists already
+pub fn unique_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{stem} ({n}){ext}")))
+        .find(|p| !p.exists())
+        .expect("ran out of file names")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_path_adds_suffix() {
+        let dir = std::env::temp_dir().join(format!("view-rs-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.jpg");
+        assert_eq!(unique_path(&path), path);
+
+        std::fs::write(&path, b"").unwrap();
+        std::fs::write(dir.join("a (1).jpg"), b"").unwrap();
+        assert_eq!(unique_path(&path), dir.join("a (2).jpg"));
+
+        assert!(rename(&path, "a (1).jpg").is_err());
+        assert!(rename(&path, "sub/b.jpg").is_err());
+        assert_eq!(rename(&path, "b.jpg").unwrap(), dir.join("b.jpg"));
+        assert!(!path.exists());
+
+        // a copy next to an existing file of the same name is numbered
+        let sub = dir.join("sub");
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(sub.join("b.jpg"), b"old").unwrap();
+        std::fs::write(dir.join("b.jpg"), b"new").unwrap();
+        let ctx = egui::Context::default();
+        let copied = Transfer::start(&ctx, &dir.join("b.jpg"), &sub, false, false);
+        assert_eq!(copied.result.recv().unwrap(), Ok(sub.join("b (1).jpg")));
+        let moved = Transfer::start(&ctx, &dir.join("b.jpg"), &sub, true, true);
+        assert_eq!(moved.result.recv().unwrap(), Ok(sub.join("b.jpg")));
+        assert_eq!(std::fs::read(sub.join("b.jpg")).unwrap(), b"new");
+        assert!(!dir.join("b.jpg").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use crate::path::{PathSortable, file_name};
+
+// file name and rating filter of the toolbar, navigation only visits the files it matches
+#[derive(Default)]
+pub struct Filter {
+    pub pattern: String,
+    // the least number of stars and the files that have as many
+    pub rated: Option<(u8, HashSet<PathBuf>)>,
+    // indices into the file list that match, in list order, `None` while nothing is filtered
+    visible: Option<Vec<usize>>,
+}
+
+impl Filter {
+    // match `files` against the pattern again, after the pattern or the list changed
+    pub fn update(&mut self, files: &[PathSortable]) {
+        let pattern = self.pattern.trim().to_lowercase();
+        let rated = self.rated.as_ref().map(|(_, files)| files);
+        self.visible = (!pattern.is_empty() || rated.is_some()).then(|| {
+            files
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| matches(&pattern, &file_name(p).to_lowercase()))
+                .filter(|(_, p)| rated.is_none_or(|r| r.contains(p.as_path())))
+                .map(|(i, _)| i)
+                .collect()
+        });
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.visible.is_some()
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.visible
+            .as_ref()
+            .is_none_or(|v| v.binary_search(&index).is_ok())
+    }
+
+    // number of visible files out of `len`
+    pub fn count(&self, len: usize) -> usize {
+        self.visible.as_ref().map_or(len, Vec::len)
+    }
+
+    // 1-based position of `index` among the visible files
+    pub fn position(&self, index: usize) -> usize {
+        match &self.visible {
+            Some(v) => v.partition_point(|&i| i < index) + 1,
+            None => index + 1,
+        }
+    }
+
+    // the visible file at 0-based position `pos`, the last one if there are fewer
+    pub fn nth(&self, pos: usize, len: usize) -> Option<usize> {
+        match &self.visible {
+            Some(v) => v.get(pos.min(v.len().checked_sub(1)?)).copied(),
+            None => len.checked_sub(1).map(|last| pos.min(last)),
+        }
+    }
+
+    // the visible file `delta` steps away from `index`, wrapping around
+    pub fn step(&self, index: usize, delta: isize, len: usize) -> Option<usize> {
+        match &self.visible {
+            _ if len == 0 => None,
+            None => Some((index as isize + delta).rem_euclid(len as isize) as usize),
+            Some(v) if v.is_empty() => None,
+            Some(v) => {
+                // `index` itself may be hidden, then the next visible file counts as step 1
+                let (pos, found) = match v.binary_search(&index) {
+                    Ok(pos) => (pos as isize, true),
+                    Err(pos) => (pos as isize, false),
+                };
+                let pos = if !found && delta > 0 { pos - 1 } else { pos };
+                Some(v[(pos + delta).rem_euclid(v.len() as isize) as usize])
+            }
+        }
+    }
+
+    // `index` if it is visible, otherwise the next visible file
+    pub fn nearest(&self, index: usize, len: usize) -> Option<usize> {
+        if self.contains(index) && index < len {
+            Some(index)
+        } else {
+            self.step(index, 1, len)
+        }
+    }
+
+    // visible files around `index`, nearest first, at most `distance` in each direction
+    pub fn neighbours(&self, index: usize, distance: usize, len: usize) -> Vec<usize> {
+        let mut out = Vec::new();
+        for step in 1..=distance.min(self.count(len) / 2) as isize {
+            for i in [self.step(index, step, len), self.step(index, -step, len)]
+                .into_iter()
+                .flatten()
+            {
+                if i != index && !out.contains(&i) {
+                    out.push(i);
+                }
+            }
+        }
+        out
+    }
+}
+
+// case-insensitive substring match, `*` and `?` make it a glob over the whole name
+fn matches(pattern: &str, name: &str) -> bool {
+    if !pattern.contains(['*', '?']) {
+        return name.contains(pattern);
+    }
+    let (p, n) = (
+        pattern.chars().collect::<Vec<_>>(),
+        name.chars().collect::<Vec<_>>(),
+    );
+    // iterative wildcard matching with backtracking to the last `*`
+    let (mut pi, mut ni) = (0, 0);
+    let mut star = None;
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substring_and_glob() {
+        assert!(matches("2023-07", "img_2023-07-01.jpg"));
+        assert!(!matches("2023-07", "img_2023-08-01.jpg"));
+        assert!(matches("*.png", "shot.png"));
+        assert!(!matches("*.png", "shot.png.jpg"));
+        assert!(matches("img_??.jpg", "img_01.jpg"));
+        assert!(!matches("img_??.jpg", "img_001.jpg"));
+    }
+
+    #[test]
+    fn step_skips_hidden_files() {
+        let files =
+            ["a.png", "b.jpg", "c.png", "d.jpg"].map(|n| PathSortable::from(PathBuf::from(n)));
+        let mut filter = Filter {
+            pattern: "*.png".to_owned(),
+            ..Default::default()
+        };
+        filter.update(&files);
+        assert_eq!(filter.step(0, 1, 4), Some(2));
+        assert_eq!(filter.step(2, 1, 4), Some(0));
+        assert_eq!(filter.step(1, 1, 4), Some(2));
+        assert_eq!(filter.step(1, -1, 4), Some(0));
+        assert_eq!(filter.nearest(3, 4), Some(0));
+        assert_eq!(filter.position(2), 2);
+
+        // both the pattern and the rating have to match
+        filter.rated = Some((3, HashSet::from([PathBuf::from("c.png"), "d.jpg".into()])));
+        filter.update(&files);
+        assert_eq!(filter.count(4), 1);
+        assert_eq!(filter.nearest(0, 4), Some(2));
+    }
+}
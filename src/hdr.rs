@@ -0,0 +1,331 @@
+// HDR photos on an SDR display: values coded with the PQ or HLG curves of BT.2100 are turned into
+// light and tone-mapped into sRGB when the texture is made; of images with a gain map the SDR
+// picture is shown. A display that takes HDR could be handed `linear` as it is instead.
+
+use image::{DynamicImage, Rgba32FImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+// luminance of SDR white in nits, as BT.2408 places it among HDR values
+const SDR_WHITE: f32 = 203.0;
+// peak of the display HLG is shown on, in nits
+const HLG_PEAK: f32 = 1000.0;
+// how far into the file the boxes and chunks that tell are looked for
+const HEADER: usize = 1 << 16;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Transfer {
+    Pq,
+    Hlg,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Primaries {
+    Bt709,
+    DisplayP3,
+    Bt2020,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Hdr {
+    Signal {
+        transfer: Transfer,
+        primaries: Primaries,
+    },
+    // an SDR picture with a map that brightens it on HDR displays
+    GainMap,
+}
+
+impl Hdr {
+    // from the code points of H.273 as PNG, AVIF and HEIF store them
+    fn from_cicp(primaries: u16, transfer: u16) -> Option<Self> {
+        let transfer = match transfer {
+            16 => Transfer::Pq,
+            18 => Transfer::Hlg,
+            _ => return None,
+        };
+        let primaries = match primaries {
+            9 => Primaries::Bt2020,
+            11 | 12 => Primaries::DisplayP3,
+            _ => Primaries::Bt709,
+        };
+        Some(Hdr::Signal {
+            transfer,
+            primaries,
+        })
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Hdr::Signal {
+                transfer: Transfer::Pq,
+                ..
+            } => "PQ coded, tone-mapped to SDR",
+            Hdr::Signal {
+                transfer: Transfer::Hlg,
+                ..
+            } => "HLG coded, tone-mapped to SDR",
+            Hdr::GainMap => "The SDR picture of an image with a gain map",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Debug, Serialize, Deserialize)]
+pub enum Operator {
+    // the brightest pixel of the image becomes white
+    #[default]
+    Reinhard,
+    // the filmic curve of ACES, more contrast
+    Aces,
+}
+
+impl Operator {
+    pub const ALL: [Operator; 2] = [Operator::Reinhard, Operator::Aces];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Operator::Reinhard => "Reinhard",
+            Operator::Aces => "ACES",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToneMap {
+    pub operator: Operator,
+    // in stops, 0 leaves SDR white where it is
+    pub exposure: f32,
+}
+
+impl Default for ToneMap {
+    fn default() -> Self {
+        Self {
+            operator: Operator::default(),
+            exposure: 0.0,
+        }
+    }
+}
+
+// what the header of `bytes` says about HDR content
+pub fn detect(bytes: &[u8]) -> Option<Hdr> {
+    let header = &bytes[..bytes.len().min(HEADER)];
+    let signal = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_cicp(bytes)
+    } else if header.get(4..8) == Some(b"ftyp") {
+        // the `colr` box of AVIF and HEIF, an `nclx` one holds the code points
+        find(header, b"colrnclx")
+            .and_then(|i| header.get(i + 8..i + 12))
+            .and_then(|c| {
+                Hdr::from_cicp(
+                    u16::from_be_bytes([c[0], c[1]]),
+                    u16::from_be_bytes([c[2], c[3]]),
+                )
+            })
+    } else {
+        None
+    };
+    // Ultra HDR and ISO 21496-1 name their gain map in the metadata, Apple in the HEIF items
+    let gain_map = [
+        &b"hdrgm:Version"[..],
+        b"urn:iso:std:iso:ts:21496",
+        b"urn:com:apple:photo:2020:aux:hdrgainmap",
+    ]
+    .iter()
+    .any(|marker| find(header, marker).is_some());
+    signal.or(gain_map.then_some(Hdr::GainMap))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+// the `cICP` chunk, which comes before the image data
+fn png_cicp(bytes: &[u8]) -> Option<Hdr> {
+    let mut at = 8;
+    while let Some(chunk) = bytes.get(at..at + 8) {
+        let len = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as usize;
+        match &chunk[4..8] {
+            b"cICP" => {
+                let data = bytes.get(at + 8..at + 10)?;
+                return Hdr::from_cicp(data[0].into(), data[1].into());
+            }
+            b"IDAT" | b"IEND" => return None,
+            _ => at += 12 + len,
+        }
+    }
+    None
+}
+
+// the image in `hdr` made ready for an SDR display, None if it is one already
+pub fn to_sdr(image: &DynamicImage, hdr: Hdr, tone: ToneMap) -> Option<DynamicImage> {
+    let Hdr::Signal {
+        transfer,
+        primaries,
+    } = hdr
+    else {
+        return None;
+    };
+    let light = linear(image, transfer, primaries);
+    Some(DynamicImage::ImageRgba8(tone_map(&light, tone)))
+}
+
+// light of each pixel relative to SDR white in BT.709 primaries, alpha is kept
+pub fn linear(image: &DynamicImage, transfer: Transfer, primaries: Primaries) -> Rgba32FImage {
+    let rgba = image.to_rgba16();
+    // a table of the curve for each 16 bit value, cheaper than working it out per sample
+    let curve: Vec<f32> = (0..=u16::MAX)
+        .map(|v| {
+            let signal = f32::from(v) / f32::from(u16::MAX);
+            match transfer {
+                Transfer::Pq => pq_nits(signal),
+                Transfer::Hlg => hlg_scene(signal),
+            }
+        })
+        .collect();
+    let matrix = to_bt709(primaries);
+    let mut out = Rgba32FImage::new(rgba.width(), rgba.height());
+    for (from, to) in rgba.pixels().zip(out.pixels_mut()) {
+        let [r, g, b, a] = from.0;
+        let mut rgb = [curve[r as usize], curve[g as usize], curve[b as usize]];
+        if transfer == Transfer::Hlg {
+            // the system gamma of BT.2100 on the luminance of the scene, for a 1000 nit display
+            let y = 0.2627 * rgb[0] + 0.678 * rgb[1] + 0.0593 * rgb[2];
+            let gain = HLG_PEAK * y.max(1e-6).powf(0.2);
+            rgb = rgb.map(|c| c * gain);
+        }
+        let rgb = rgb.map(|c| c / SDR_WHITE);
+        let mixed =
+            matrix.map(|row| (row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]).max(0.0));
+        to.0 = [
+            mixed[0],
+            mixed[1],
+            mixed[2],
+            f32::from(a) / f32::from(u16::MAX),
+        ];
+    }
+    out
+}
+
+// light relative to SDR white into sRGB
+pub fn tone_map(light: &Rgba32FImage, tone: ToneMap) -> RgbaImage {
+    let exposure = tone.exposure.exp2();
+    let brightest = light
+        .pixels()
+        .flat_map(|p| p.0[..3].iter().copied())
+        .fold(1.0f32, f32::max)
+        * exposure;
+    let curve = |x: f32| match tone.operator {
+        Operator::Reinhard => x * (1.0 + x / (brightest * brightest)) / (1.0 + x),
+        Operator::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+    };
+    let mut out = RgbaImage::new(light.width(), light.height());
+    for (from, to) in light.pixels().zip(out.pixels_mut()) {
+        let [r, g, b, a] = from.0;
+        let [r, g, b] = [r, g, b].map(|c| srgb(curve(c * exposure)));
+        to.0 = [r, g, b, (a * 255.0).round() as u8];
+    }
+    out
+}
+
+// nits of a PQ signal
+fn pq_nits(signal: f32) -> f32 {
+    const M1: f32 = 2610.0 / 16384.0;
+    const M2: f32 = 2523.0 / 4096.0 * 128.0;
+    const C1: f32 = 3424.0 / 4096.0;
+    const C2: f32 = 2413.0 / 4096.0 * 32.0;
+    const C3: f32 = 2392.0 / 4096.0 * 32.0;
+    let p = signal.powf(1.0 / M2);
+    ((p - C1).max(0.0) / (C2 - C3 * p)).powf(1.0 / M1) * 10000.0
+}
+
+// light of the scene, 0-1, of an HLG signal
+fn hlg_scene(signal: f32) -> f32 {
+    const A: f32 = 0.178_832_77;
+    const B: f32 = 0.284_668_92;
+    const C: f32 = 0.559_910_7;
+    if signal <= 0.5 {
+        signal * signal / 3.0
+    } else {
+        (((signal - C) / A).exp() + B) / 12.0
+    }
+}
+
+fn to_bt709(primaries: Primaries) -> [[f32; 3]; 3] {
+    match primaries {
+        Primaries::Bt709 => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        Primaries::DisplayP3 => [
+            [1.2249, -0.2247, 0.0],
+            [-0.0420, 1.0419, 0.0],
+            [-0.0197, -0.0786, 1.0979],
+        ],
+        Primaries::Bt2020 => [
+            [1.6605, -0.5876, -0.0728],
+            [-0.1246, 1.1329, -0.0083],
+            [-0.0182, -0.1006, 1.1187],
+        ],
+    }
+}
+
+// 0-1 linear light into an sRGB value
+fn srgb(linear: f32) -> u8 {
+    let linear = linear.clamp(0.0, 1.0);
+    let coded = if linear <= 0.003_130_8 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+    (coded * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pq_images_are_found_and_tone_mapped() {
+        // a PNG signature, an IHDR chunk and a cICP chunk for BT.2020 primaries with PQ
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png.extend([0, 0, 0, 13]);
+        png.extend(b"IHDR");
+        png.extend([0; 13 + 4]);
+        png.extend([0, 0, 0, 4]);
+        png.extend(b"cICP");
+        png.extend([9, 16, 0, 1, 0, 0, 0, 0]);
+        let hdr = detect(&png);
+        assert_eq!(
+            hdr,
+            Some(Hdr::Signal {
+                transfer: Transfer::Pq,
+                primaries: Primaries::Bt2020
+            })
+        );
+        assert_eq!(
+            detect(b"\xff\xd8 <x hdrgm:Version=\"1.0\">"),
+            Some(Hdr::GainMap)
+        );
+        assert_eq!(detect(b"\xff\xd8 plain"), None);
+
+        // 0.58 is about SDR white in PQ, 1.0 is 10000 nits
+        assert!((pq_nits(0.58) - SDR_WHITE).abs() < 10.0);
+        let gray = |v: u16| image::Rgb([v, v, v]);
+        let signal = image::ImageBuffer::from_fn(2, 1, |x, _| gray([38000, 65535][x as usize]));
+        let sdr = to_sdr(
+            &DynamicImage::ImageRgb16(signal),
+            hdr.unwrap(),
+            ToneMap::default(),
+        );
+        let sdr = sdr.unwrap().to_rgba8();
+        // the highlight is white instead of clipping everything above SDR white
+        assert_eq!(sdr.get_pixel(1, 0).0, [255, 255, 255, 255]);
+        let white = sdr.get_pixel(0, 0).0[0];
+        assert!((100..200).contains(&white), "{}", white);
+        assert_eq!(
+            to_sdr(
+                &DynamicImage::new_rgb8(1, 1),
+                Hdr::GainMap,
+                ToneMap::default()
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use image::DynamicImage;
+
+// shown instead of the image when the build has no libheif
+#[cfg(not(feature = "heic"))]
+const NOT_ENABLED: &str = "HEIC support not enabled in this build";
+
+// listed by default also without libheif, so the photos of a phone say why they don't show
+// instead of missing from the folder
+pub const EXTENSIONS: [&str; 2] = ["heic", "heif"];
+
+// the brands of HEIF images in the `ftyp` box, AVIF files are read by the image crate
+const BRANDS: [&[u8]; 8] = [
+    b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"mif1", b"msf1",
+];
+
+pub fn is_heif_data(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && &bytes[4..8] == b"ftyp" && BRANDS.contains(&&bytes[8..12])
+}
+
+// the primary image, not rotated: the EXIF orientation iPhones write is applied like for JPEGs
+#[cfg(feature = "heic")]
+pub fn decode(bytes: &[u8]) -> Result<DynamicImage, String> {
+    use libheif_rs::{ColorSpace, DecodingOptions, HeifContext, LibHeif, RgbChroma};
+
+    let context = HeifContext::read_from_bytes(bytes).map_err(|e| e.to_string())?;
+    let handle = context.primary_image_handle().map_err(|e| e.to_string())?;
+    let mut options = DecodingOptions::new();
+    if let Some(options) = &mut options {
+        options.set_ignore_transformations(true);
+    }
+    let decoded = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), options)
+        .map_err(|e| e.to_string())?;
+    let plane = decoded
+        .planes()
+        .interleaved
+        .ok_or("The image has no RGBA plane")?;
+    let row = plane.width as usize * 4;
+    let pixels = plane
+        .data
+        .chunks(plane.stride)
+        .take(plane.height as usize)
+        .flat_map(|line| line.get(..row).unwrap_or(line))
+        .copied()
+        .collect();
+    image::RgbaImage::from_raw(plane.width, plane.height, pixels)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| "The image is shorter than its size".to_owned())
+}
+
+#[cfg(not(feature = "heic"))]
+pub fn decode(_: &[u8]) -> Result<DynamicImage, String> {
+    Err(NOT_ENABLED.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heif_is_told_from_avif() {
+        assert!(is_heif_data(b"\0\0\0\x18ftypheic\0\0\0\0"));
+        assert!(!is_heif_data(b"\0\0\0\x18ftypavif\0\0\0\0"));
+        assert!(!is_heif_data(b"ftyp"));
+        #[cfg(not(feature = "heic"))]
+        assert_eq!(decode(b"").unwrap_err(), NOT_ENABLED);
+    }
+}
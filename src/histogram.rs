@@ -0,0 +1,84 @@
+use eframe::egui::{self, Color32, Stroke, pos2, vec2};
+use image::RgbaImage;
+
+// red, green, blue and luminance
+pub const CHANNELS: [(&str, Color32); 4] = [
+    ("R", Color32::from_rgb(230, 60, 60)),
+    ("G", Color32::from_rgb(60, 200, 60)),
+    ("B", Color32::from_rgb(70, 110, 240)),
+    ("L", Color32::from_gray(220)),
+];
+
+// number of pixels at each value of each channel
+#[derive(Clone)]
+pub struct Histogram {
+    bins: [[u32; 256]; 4],
+}
+
+impl Histogram {
+    pub fn new(image: &RgbaImage) -> Self {
+        let mut bins = [[0; 256]; 4];
+        for pixel in image.pixels() {
+            let [r, g, b, _] = pixel.0;
+            bins[0][usize::from(r)] += 1;
+            bins[1][usize::from(g)] += 1;
+            bins[2][usize::from(b)] += 1;
+            // Rec. 709 weights
+            let luma = (2126 * u32::from(r) + 7152 * u32::from(g) + 722 * u32::from(b)) / 10000;
+            bins[3][luma as usize] += 1;
+        }
+        Self { bins }
+    }
+}
+
+// the curves of the `shown` channels, faded while `stale` is set, counts on a log scale with `log`
+pub fn show(ui: &mut egui::Ui, histogram: &Histogram, shown: [bool; 4], log: bool, stale: bool) {
+    let (rect, _) = ui.allocate_exact_size(vec2(256.0, 100.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, Color32::from_black_alpha(120));
+    let scale = |count: u32| {
+        let count = count as f32;
+        if log { count.ln_1p() } else { count }
+    };
+    let max = (0..4)
+        .filter(|&c| shown[c])
+        .flat_map(|c| histogram.bins[c].iter().copied())
+        .map(scale)
+        .fold(0.0, f32::max);
+    if max <= 0.0 {
+        return;
+    }
+    for (c, (_, color)) in CHANNELS.iter().enumerate().filter(|&(c, _)| shown[c]) {
+        let points = histogram.bins[c]
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| {
+                let x = rect.left() + i as f32 / 255.0 * rect.width();
+                pos2(x, rect.bottom() - scale(count) / max * rect.height())
+            })
+            .collect();
+        let color = if stale {
+            color.gamma_multiply(0.3)
+        } else {
+            *color
+        };
+        painter.add(egui::Shape::line(points, Stroke::new(1.0, color)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_each_channel() {
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        image.put_pixel(1, 0, image::Rgba([255, 255, 255, 0]));
+        let histogram = Histogram::new(&image);
+        assert_eq!(histogram.bins[0][255], 2);
+        assert_eq!(histogram.bins[1][0], 1);
+        assert_eq!(histogram.bins[3][54], 1);
+        assert_eq!(histogram.bins[3][255], 1);
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
+
+// oldest entries are dropped beyond this many
+const LIMIT: usize = 5000;
+
+// files in the order they were shown, Prev and Next walk through it like a browser's back and forward
+#[derive(Default)]
+pub struct History {
+    entries: VecDeque<PathBuf>,
+    // entry of the displayed file
+    pos: usize,
+}
+
+impl History {
+    // `path` is displayed, a new file drops the entries ahead of the current one
+    pub fn visit(&mut self, path: &Path) {
+        if self.entries.get(self.pos).map(PathBuf::as_path) == Some(path) {
+            return;
+        }
+        self.entries.truncate(self.pos + 1);
+        self.entries.push_back(path.to_path_buf());
+        if self.entries.len() > LIMIT {
+            self.entries.pop_front();
+        }
+        self.pos = self.entries.len() - 1;
+    }
+
+    // `path` is displayed before the oldest entry
+    pub fn visit_before(&mut self, path: &Path) {
+        self.entries.insert(self.pos, path.to_path_buf());
+        self.entries.truncate(LIMIT);
+    }
+
+    pub fn back(&mut self) -> Option<&Path> {
+        self.pos = self.pos.checked_sub(1)?;
+        Some(&self.entries[self.pos])
+    }
+
+    pub fn forward(&mut self) -> Option<&Path> {
+        if self.pos + 1 >= self.entries.len() {
+            return None;
+        }
+        self.pos += 1;
+        Some(&self.entries[self.pos])
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.pos = 0;
+    }
+
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        for entry in self.entries.iter_mut().filter(|e| e.as_path() == from) {
+            *entry = to.to_path_buf();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn back_and_forward() {
+        let mut history = History::default();
+        for name in ["a", "b", "c"] {
+            history.visit(Path::new(name));
+        }
+        assert_eq!(history.back(), Some(Path::new("b")));
+        assert_eq!(history.back(), Some(Path::new("a")));
+        assert_eq!(history.back(), None);
+        assert_eq!(history.forward(), Some(Path::new("b")));
+        // a new file replaces what was ahead
+        history.visit(Path::new("d"));
+        assert_eq!(history.forward(), None);
+        assert_eq!(history.back(), Some(Path::new("b")));
+    }
+}
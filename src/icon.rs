@@ -0,0 +1,174 @@
+// the images of different sizes in Windows and macOS icon files
+
+// listed by default next to `scan::EXTENSIONS`, the macOS format only there
+#[cfg(target_os = "macos")]
+pub const EXTENSIONS: [&str; 2] = ["ico", "icns"];
+#[cfg(not(target_os = "macos"))]
+pub const EXTENSIONS: [&str; 1] = ["ico"];
+
+pub fn is_icon(bytes: &[u8]) -> bool {
+    is_ico(bytes) || (cfg!(target_os = "macos") && bytes.starts_with(b"icns"))
+}
+
+fn is_ico(bytes: &[u8]) -> bool {
+    bytes.starts_with(&[0, 0, 1, 0])
+}
+
+struct Entry {
+    size: [u32; 2],
+    bits: u16,
+    // the directory entry and the BMP or PNG data it points to
+    header: [u8; 16],
+    data: std::ops::Range<usize>,
+}
+
+// the images of an ICO file, largest first
+fn ico_entries(bytes: &[u8]) -> Vec<Entry> {
+    let u16_at = |at: usize| {
+        bytes
+            .get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    };
+    let u32_at = |at: usize| {
+        bytes
+            .get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let count = u16_at(4).unwrap_or(0);
+    let mut entries: Vec<Entry> = (0..usize::from(count))
+        .filter_map(|i| {
+            let at = 6 + i * 16;
+            let header: [u8; 16] = bytes.get(at..at + 16)?.try_into().ok()?;
+            let (len, offset) = (u32_at(at + 8)? as usize, u32_at(at + 12)? as usize);
+            let data = offset..offset.checked_add(len)?;
+            let image = bytes.get(data.clone())?;
+            // a side of 256 is stored as 0, PNG entries know their real size
+            let mut size = [header[0], header[1]].map(|s| if s == 0 { 256 } else { u32::from(s) });
+            if image.starts_with(b"\x89PNG") && image.len() >= 24 {
+                let be = |at: usize| u32::from_be_bytes(image[at..at + 4].try_into().unwrap());
+                size = [be(16), be(20)];
+            }
+            Some(Entry {
+                size,
+                bits: u16_at(at + 6)?,
+                header,
+                data,
+            })
+        })
+        .collect();
+    entries.sort_by_key(|e| std::cmp::Reverse((e.size[0] * e.size[1], e.bits)));
+    entries
+}
+
+// pixel sizes of the images in an icon file, largest first like the indices of `extract`
+pub fn sizes(bytes: &[u8]) -> Vec<[u32; 2]> {
+    if is_ico(bytes) {
+        return ico_entries(bytes).iter().map(|e| e.size).collect();
+    }
+    #[cfg(target_os = "macos")]
+    if let Some((_, types)) = apple::family(bytes) {
+        return types
+            .iter()
+            .map(|t| [t.pixel_width(), t.pixel_height()])
+            .collect();
+    }
+    Vec::new()
+}
+
+// an icon file holding only the image at `index`
+pub fn extract(bytes: &[u8], index: usize) -> Option<Vec<u8>> {
+    if is_ico(bytes) {
+        let entry = ico_entries(bytes).into_iter().nth(index)?;
+        let mut ico = vec![0, 0, 1, 0, 1, 0];
+        ico.extend_from_slice(&entry.header[..12]);
+        // the image follows the six byte header and the only directory entry
+        ico.extend_from_slice(&22u32.to_le_bytes());
+        ico.extend_from_slice(&bytes[entry.data]);
+        return Some(ico);
+    }
+    #[cfg(target_os = "macos")]
+    if let Some(icns) = apple::extract(bytes, index) {
+        return Some(icns);
+    }
+    None
+}
+
+#[cfg(target_os = "macos")]
+pub use apple::decode as decode_icns;
+
+#[cfg(target_os = "macos")]
+mod apple {
+    use ::icns::{IconFamily, IconType, PixelFormat};
+    use image::{DynamicImage, RgbaImage};
+
+    // the icon types of an ICNS file, one per pixel size and largest first
+    pub fn family(bytes: &[u8]) -> Option<(IconFamily, Vec<IconType>)> {
+        if !bytes.starts_with(b"icns") {
+            return None;
+        }
+        let family = IconFamily::read(bytes).ok()?;
+        let mut types = family.available_icons();
+        types.sort_by_key(|t| std::cmp::Reverse(t.pixel_width() * t.pixel_height()));
+        types.dedup_by_key(|t| (t.pixel_width(), t.pixel_height()));
+        Some((family, types))
+    }
+
+    pub fn extract(bytes: &[u8], index: usize) -> Option<Vec<u8>> {
+        let (family, types) = family(bytes)?;
+        let icon_type = *types.get(index)?;
+        let image = family.get_icon_with_type(icon_type).ok()?;
+        let mut single = IconFamily::new();
+        single.add_icon_with_type(&image, icon_type).ok()?;
+        let mut icns = Vec::new();
+        single.write(&mut icns).ok()?;
+        Some(icns)
+    }
+
+    // the largest image
+    pub fn decode(bytes: &[u8]) -> Result<DynamicImage, String> {
+        let (family, types) = family(bytes).ok_or("Not an ICNS file")?;
+        let icon_type = *types.first().ok_or("The icon file is empty")?;
+        let image = family
+            .get_icon_with_type(icon_type)
+            .map_err(|e| e.to_string())?
+            .convert_to(PixelFormat::RGBA);
+        RgbaImage::from_raw(image.width(), image.height(), image.into_data().into_vec())
+            .map(DynamicImage::ImageRgba8)
+            .ok_or_else(|| "The icon is shorter than its size".to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an ICO directory entry for a `side`² image of `len` bytes at `offset`
+    fn entry(side: u8, bits: u16, len: u32, offset: u32) -> Vec<u8> {
+        let mut entry = vec![side, side, 0, 0, 1, 0];
+        entry.extend_from_slice(&bits.to_le_bytes());
+        entry.extend_from_slice(&len.to_le_bytes());
+        entry.extend_from_slice(&offset.to_le_bytes());
+        entry
+    }
+
+    #[test]
+    fn entries_are_sorted_and_extracted() {
+        let mut ico = vec![0, 0, 1, 0, 2, 0];
+        ico.extend(entry(16, 32, 4, 38));
+        // a PNG of 300×200 in an entry that claims 256
+        ico.extend(entry(0, 32, 24, 42));
+        ico.extend_from_slice(b"BMP!");
+        ico.extend_from_slice(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR");
+        ico.extend_from_slice(&300u32.to_be_bytes());
+        ico.extend_from_slice(&200u32.to_be_bytes());
+        assert!(is_icon(&ico));
+        assert_eq!(sizes(&ico), [[300, 200], [16, 16]]);
+
+        let small = extract(&ico, 1).unwrap();
+        assert_eq!(&small[..6], [0, 0, 1, 0, 1, 0]);
+        assert_eq!(&small[6..18], &entry(16, 32, 4, 0)[..12]);
+        assert_eq!(&small[18..22], 22u32.to_le_bytes());
+        assert_eq!(&small[22..], b"BMP!");
+        assert!(extract(&ico, 2).is_none());
+    }
+}
@@ -0,0 +1,225 @@
+// one window for all the files opened from the file manager: a later start hands its paths to
+// the running instance and exits
+//
+// a message is the number of paths followed by each path, every part prefixed with its length
+// as a little-endian u32; unix uses a socket in the runtime directory, Windows a localhost port
+// that is written to a file in the cache directory along with a token the message starts with
+
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use directories::ProjectDirs;
+use eframe::egui;
+use log::{debug, info, warn};
+
+// a path or a list of them, more is not a file manager opening files
+const LIMIT: u32 = 1 << 20;
+
+// a stuck sender doesn't keep the others waiting for long
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct Instance {
+    // where other instances find this one, removed on exit
+    address: PathBuf,
+    pub opened: Receiver<Vec<PathBuf>>,
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        debug!("Removing {:?}", self.address);
+        let _ = std::fs::remove_file(&self.address);
+    }
+}
+
+fn address() -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("", "", "view-rs")?;
+    let dir = dirs.runtime_dir().unwrap_or(dirs.cache_dir());
+    let name = if cfg!(unix) {
+        "instance.sock"
+    } else {
+        "instance"
+    };
+    Some(dir.join(name))
+}
+
+// send `paths` to a running instance, false if there is none
+pub fn forward(paths: &[PathBuf]) -> bool {
+    let Some(address) = address() else {
+        return false;
+    };
+    match connect(&address).and_then(|mut stream| stream.write_all(&message(paths))) {
+        Ok(()) => {
+            info!("Handed {:?} to the running instance", paths);
+            true
+        }
+        Err(err) => {
+            debug!("No running instance at {:?}: {}", address, err);
+            false
+        }
+    }
+}
+
+// take messages of later instances, `ctx` is woken up for each
+pub fn listen(ctx: &egui::Context) -> Option<Instance> {
+    let address = address()?;
+    if let Some(dir) = address.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let (tx, opened) = mpsc::channel();
+    let ctx = ctx.clone();
+    let accept = match bind(&address) {
+        Ok(accept) => accept,
+        Err(err) => {
+            warn!("Failed to listen at {:?}: {}", address, err);
+            return None;
+        }
+    };
+    thread::spawn(move || {
+        for stream in accept {
+            match stream.and_then(|mut s| receive(&mut s)) {
+                Ok(paths) => {
+                    if tx.send(paths).is_err() {
+                        return;
+                    }
+                    ctx.request_repaint();
+                }
+                Err(err) => warn!("Failed to read a message of another instance: {}", err),
+            }
+        }
+    });
+    info!("Listening for other instances at {:?}", address);
+    Some(Instance { address, opened })
+}
+
+fn message(paths: &[PathBuf]) -> Vec<u8> {
+    let mut message = Vec::new();
+    put(&mut message, &(paths.len() as u32).to_le_bytes());
+    for path in paths {
+        put(&mut message, &encode(path));
+    }
+    message
+}
+
+fn put(message: &mut Vec<u8>, part: &[u8]) {
+    message.extend_from_slice(&(part.len() as u32).to_le_bytes());
+    message.extend_from_slice(part);
+}
+
+fn take(stream: &mut impl Read) -> std::io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len);
+    if len > LIMIT {
+        return Err(std::io::Error::other("message too long"));
+    }
+    let mut part = vec![0; len as usize];
+    stream.read_exact(&mut part)?;
+    Ok(part)
+}
+
+fn receive(stream: &mut impl Read) -> std::io::Result<Vec<PathBuf>> {
+    let count: [u8; 4] = take(stream)?
+        .try_into()
+        .map_err(|_| std::io::Error::other("no count"))?;
+    (0..u32::from_le_bytes(count).min(LIMIT))
+        .map(|_| take(stream).map(decode))
+        .collect()
+}
+
+#[cfg(unix)]
+fn encode(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+fn decode(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn encode(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(not(unix))]
+fn decode(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(unix)]
+fn connect(address: &Path) -> std::io::Result<std::os::unix::net::UnixStream> {
+    std::os::unix::net::UnixStream::connect(address)
+}
+
+#[cfg(unix)]
+fn bind(
+    address: &Path,
+) -> std::io::Result<
+    impl Iterator<Item = std::io::Result<std::os::unix::net::UnixStream>> + Send + use<>,
+> {
+    use std::os::unix::net::UnixListener;
+    // left behind by an instance that crashed, since nothing answered `forward`
+    let _ = std::fs::remove_file(address);
+    let listener = UnixListener::bind(address)?;
+    Ok(std::iter::from_fn(move || {
+        Some(listener.accept().and_then(|(stream, _)| {
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            Ok(stream)
+        }))
+    }))
+}
+
+#[cfg(not(unix))]
+fn connect(address: &Path) -> std::io::Result<std::net::TcpStream> {
+    let text = std::fs::read_to_string(address)?;
+    let (port, token) = text
+        .trim()
+        .split_once(' ')
+        .ok_or_else(|| std::io::Error::other("bad address file"))?;
+    let port: u16 = port.parse().map_err(std::io::Error::other)?;
+    let mut stream = std::net::TcpStream::connect(("127.0.0.1", port))?;
+    let mut message = Vec::new();
+    put(&mut message, token.as_bytes());
+    stream.write_all(&message)?;
+    Ok(stream)
+}
+
+#[cfg(not(unix))]
+fn bind(
+    address: &Path,
+) -> std::io::Result<impl Iterator<Item = std::io::Result<std::net::TcpStream>> + Send + use<>> {
+    let listener = std::net::TcpListener::bind(("127.0.0.1", 0))?;
+    let port = listener.local_addr()?.port();
+    // other programs can reach the port too, only those that can read the file get in
+    let token = format!("{:016x}", rand::random::<u64>());
+    std::fs::write(address, format!("{} {}", port, token))?;
+    Ok(std::iter::from_fn(move || {
+        Some(listener.accept().and_then(|(mut stream, _)| {
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            if take(&mut stream)? != token.as_bytes() {
+                return Err(std::io::Error::other("wrong token"));
+            }
+            Ok(stream)
+        }))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_survive_the_message() {
+        let paths = [PathBuf::from("/photos/a b.jpg"), "/photos/ü.png".into()];
+        let message = message(&paths);
+        assert_eq!(receive(&mut message.as_slice()).unwrap(), paths);
+        assert!(receive(&mut &message[..message.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use image::{DynamicImage, ImageBuffer};
+use jxl_oxide::{
+    EnumColourEncoding, JxlImage, RenderingIntent,
+    color::{ColourSpace, Primaries, TransferFunction, WhitePoint},
+};
+
+use crate::hdr::{self, Hdr, Transfer};
+
+// a bare codestream or the ISO container, the image crate does not know either
+pub fn is_jxl_data(bytes: &[u8]) -> bool {
+    bytes.starts_with(&[0xff, 0x0a]) || bytes.starts_with(b"\0\0\0\x0cJXL \r\n\x87\n")
+}
+
+// the first frame of an animation, HDR images are tone-mapped to sRGB
+pub fn decode(bytes: &[u8]) -> Result<DynamicImage, String> {
+    render(bytes, false).map(|(image, _)| image)
+}
+
+// the same with HDR images coded in PQ for `hdr::to_sdr`, which tells what they look like
+pub fn decode_hdr(bytes: &[u8]) -> Result<(DynamicImage, Option<Hdr>), String> {
+    render(bytes, true)
+}
+
+fn render(bytes: &[u8], signal: bool) -> Result<(DynamicImage, Option<Hdr>), String> {
+    let mut image = JxlImage::builder().read(bytes).map_err(|e| e.to_string())?;
+    if image.pixel_format().has_black() {
+        return Err("CMYK JPEG XL images are not supported".to_owned());
+    }
+    let hdr = image.hdr_type().is_some() && signal;
+    if hdr {
+        // HLG too, jxl-oxide converts between the two
+        image.request_color_encoding(EnumColourEncoding {
+            colour_space: ColourSpace::Rgb,
+            white_point: WhitePoint::D65,
+            primaries: Primaries::Bt2100,
+            tf: TransferFunction::Pq,
+            rendering_intent: RenderingIntent::Relative,
+        });
+    } else if image.hdr_type().is_some() {
+        // PQ and HLG values shown as they are come out washed out and clipped
+        image.request_color_encoding(EnumColourEncoding::srgb(RenderingIntent::Relative));
+    }
+    // a file cut short still shows the part that arrived
+    let render = if image.num_loaded_keyframes() > 0 {
+        image.render_frame(0)
+    } else {
+        image.render_loading_frame()
+    }
+    .map_err(|e| e.to_string())?;
+
+    let mut stream = render.stream();
+    let (width, height) = (stream.width(), stream.height());
+    let channels = stream.channels();
+    let mut buf = vec![0u16; width as usize * height as usize * channels as usize];
+    stream.write_to_buffer(&mut buf);
+    let decoded = match channels {
+        1 => ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLuma16),
+        2 => ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLumaA16),
+        3 => ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgb16),
+        4 => ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgba16),
+        _ => None,
+    };
+    let decoded =
+        decoded.ok_or_else(|| format!("Unexpected JPEG XL image with {} channels", channels))?;
+    let hdr = hdr.then_some(Hdr::Signal {
+        transfer: Transfer::Pq,
+        primaries: hdr::Primaries::Bt2020,
+    });
+    Ok((decoded, hdr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_a_codestream() {
+        // the smallest file in the jxl-oxide documentation
+        let bytes = [
+            0xff, 0x0a, 0x30, 0x54, 0x10, 0x09, 0x08, 0x06, 0x01, 0x00, 0x78, 0x00, 0x4b, 0x38,
+            0x41, 0x3c, 0xb6, 0x3a, 0x51, 0xfe, 0x00, 0x47, 0x1e, 0xa0, 0x85, 0xb8, 0x27, 0x1a,
+            0x48, 0x45, 0x84, 0x1b, 0x71, 0x4f, 0xa8, 0x3e, 0x8e, 0x30, 0x03, 0x92, 0x84, 0x01,
+        ];
+        assert!(is_jxl_data(&bytes));
+        assert!(!is_jxl_data(b"\x89PNG"));
+        let image = decode(&bytes).map(|i| i.to_rgba8());
+        assert!(image.is_ok_and(|i| i.dimensions() == (240, 135)));
+    }
+}
@@ -0,0 +1,817 @@
+use std::collections::BTreeMap;
+
+use eframe::egui::{Event, InputState, Key, KeyboardShortcut, ModifierNames, Modifiers};
+use log::warn;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// everything a key can be bound to
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Action {
+    NextImage,
+    PrevImage,
+    HistoryBack,
+    FirstImage,
+    LastImage,
+    PageDown,
+    PageUp,
+    GoTo,
+    RandomJump,
+    // the pan actions take over their keys while the image is larger than the window
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    ZoomIn,
+    ZoomOut,
+    ZoomFit,
+    ActualSize,
+    ToggleFullscreen,
+    LeaveFullscreen,
+    ToggleSlideshow,
+    OpenFile,
+    DeleteFile,
+    DeleteFileNow,
+    UndoDelete,
+    ShowHelp,
+    ToggleFilmstrip,
+    ToggleGrid,
+    OpenSelected,
+    ToggleTree,
+    NextFolder,
+    PrevFolder,
+    Reload,
+    Rescan,
+    RotateClockwise,
+    RotateCounterClockwise,
+    FlipHorizontal,
+    FlipVertical,
+    RotateFile,
+    Export,
+    ToggleInfo,
+    Eyedropper,
+    ToggleHistogram,
+    ToggleNearest,
+    PlayPause,
+    NextFrame,
+    PrevFrame,
+    SaveFrame,
+    NextPage,
+    PrevPage,
+    ToggleSpread,
+    ToggleWebtoon,
+    ToggleCompare,
+    FlipCompare,
+    EditExternally,
+    RevealFile,
+    SetWallpaper,
+    Rename,
+    CopyTo,
+    MoveTo,
+    RepeatMove,
+    Rate1,
+    Rate2,
+    Rate3,
+    Rate4,
+    Rate5,
+    ClearRating,
+    ToggleBookmark,
+    NextBookmark,
+    PrevBookmark,
+    ExportBookmarks,
+    FindDuplicates,
+    SavePlaylist,
+    ToggleMinimalUi,
+    ToggleOnTop,
+    ToggleBorderless,
+    ToggleLockZoom,
+    CycleFit,
+    ScrollDown,
+    ScrollUp,
+    CycleOverlay,
+}
+
+impl Action {
+    pub const ALL: [Action; 80] = [
+        Action::NextImage,
+        Action::PrevImage,
+        Action::HistoryBack,
+        Action::FirstImage,
+        Action::LastImage,
+        Action::PageDown,
+        Action::PageUp,
+        Action::GoTo,
+        Action::RandomJump,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::PanUp,
+        Action::PanDown,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ZoomFit,
+        Action::ActualSize,
+        Action::ToggleFullscreen,
+        Action::LeaveFullscreen,
+        Action::ToggleSlideshow,
+        Action::OpenFile,
+        Action::DeleteFile,
+        Action::DeleteFileNow,
+        Action::UndoDelete,
+        Action::ShowHelp,
+        Action::ToggleFilmstrip,
+        Action::ToggleGrid,
+        Action::OpenSelected,
+        Action::ToggleTree,
+        Action::NextFolder,
+        Action::PrevFolder,
+        Action::Reload,
+        Action::Rescan,
+        Action::RotateClockwise,
+        Action::RotateCounterClockwise,
+        Action::FlipHorizontal,
+        Action::FlipVertical,
+        Action::RotateFile,
+        Action::Export,
+        Action::ToggleInfo,
+        Action::Eyedropper,
+        Action::ToggleHistogram,
+        Action::ToggleNearest,
+        Action::PlayPause,
+        Action::NextFrame,
+        Action::PrevFrame,
+        Action::SaveFrame,
+        Action::NextPage,
+        Action::PrevPage,
+        Action::ToggleSpread,
+        Action::ToggleWebtoon,
+        Action::ToggleCompare,
+        Action::FlipCompare,
+        Action::EditExternally,
+        Action::RevealFile,
+        Action::SetWallpaper,
+        Action::Rename,
+        Action::CopyTo,
+        Action::MoveTo,
+        Action::RepeatMove,
+        Action::Rate1,
+        Action::Rate2,
+        Action::Rate3,
+        Action::Rate4,
+        Action::Rate5,
+        Action::ClearRating,
+        Action::ToggleBookmark,
+        Action::NextBookmark,
+        Action::PrevBookmark,
+        Action::ExportBookmarks,
+        Action::FindDuplicates,
+        Action::SavePlaylist,
+        Action::ToggleMinimalUi,
+        Action::ToggleOnTop,
+        Action::ToggleBorderless,
+        Action::ToggleLockZoom,
+        Action::CycleFit,
+        Action::ScrollDown,
+        Action::ScrollUp,
+        Action::CycleOverlay,
+    ];
+
+    // headings of the help overlay, in order
+    pub const GROUPS: [&'static str; 6] = [
+        "Navigation",
+        "View",
+        "File operations",
+        "Rating",
+        "Slideshow",
+        "Animation",
+    ];
+
+    // key in the settings file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::NextImage => "next_image",
+            Action::PrevImage => "prev_image",
+            Action::HistoryBack => "history_back",
+            Action::FirstImage => "first_image",
+            Action::LastImage => "last_image",
+            Action::PageDown => "page_down",
+            Action::PageUp => "page_up",
+            Action::GoTo => "go_to",
+            Action::RandomJump => "random_jump",
+            Action::PanLeft => "pan_left",
+            Action::PanRight => "pan_right",
+            Action::PanUp => "pan_up",
+            Action::PanDown => "pan_down",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::ZoomFit => "zoom_fit",
+            Action::ActualSize => "actual_size",
+            Action::ToggleFullscreen => "toggle_fullscreen",
+            Action::LeaveFullscreen => "leave_fullscreen",
+            Action::ToggleSlideshow => "toggle_slideshow",
+            Action::OpenFile => "open_file",
+            Action::DeleteFile => "delete_file",
+            Action::DeleteFileNow => "delete_file_now",
+            Action::UndoDelete => "undo_delete",
+            Action::ShowHelp => "show_help",
+            Action::ToggleFilmstrip => "toggle_filmstrip",
+            Action::ToggleGrid => "toggle_grid",
+            Action::OpenSelected => "open_selected",
+            Action::ToggleTree => "toggle_tree",
+            Action::NextFolder => "next_folder",
+            Action::PrevFolder => "prev_folder",
+            Action::Reload => "reload",
+            Action::Rescan => "rescan",
+            Action::RotateClockwise => "rotate_clockwise",
+            Action::RotateCounterClockwise => "rotate_counter_clockwise",
+            Action::FlipHorizontal => "flip_horizontal",
+            Action::FlipVertical => "flip_vertical",
+            Action::RotateFile => "rotate_file",
+            Action::Export => "export",
+            Action::ToggleInfo => "toggle_info",
+            Action::Eyedropper => "eyedropper",
+            Action::ToggleHistogram => "toggle_histogram",
+            Action::ToggleNearest => "toggle_nearest",
+            Action::PlayPause => "play_pause",
+            Action::NextFrame => "next_frame",
+            Action::PrevFrame => "prev_frame",
+            Action::SaveFrame => "save_frame",
+            Action::NextPage => "next_page",
+            Action::PrevPage => "prev_page",
+            Action::ToggleSpread => "toggle_spread",
+            Action::ToggleWebtoon => "toggle_webtoon",
+            Action::ToggleCompare => "toggle_compare",
+            Action::FlipCompare => "flip_compare",
+            Action::EditExternally => "edit_externally",
+            Action::RevealFile => "reveal_file",
+            Action::SetWallpaper => "set_wallpaper",
+            Action::Rename => "rename",
+            Action::CopyTo => "copy_to",
+            Action::MoveTo => "move_to",
+            Action::RepeatMove => "repeat_move",
+            Action::Rate1 => "rate_1",
+            Action::Rate2 => "rate_2",
+            Action::Rate3 => "rate_3",
+            Action::Rate4 => "rate_4",
+            Action::Rate5 => "rate_5",
+            Action::ClearRating => "clear_rating",
+            Action::ToggleBookmark => "toggle_bookmark",
+            Action::NextBookmark => "next_bookmark",
+            Action::PrevBookmark => "prev_bookmark",
+            Action::ExportBookmarks => "export_bookmarks",
+            Action::FindDuplicates => "find_duplicates",
+            Action::SavePlaylist => "save_playlist",
+            Action::ToggleMinimalUi => "toggle_minimal_ui",
+            Action::ToggleOnTop => "toggle_on_top",
+            Action::ToggleBorderless => "toggle_borderless",
+            Action::ToggleLockZoom => "toggle_lock_zoom",
+            Action::CycleFit => "cycle_fit",
+            Action::ScrollDown => "scroll_down",
+            Action::ScrollUp => "scroll_up",
+            Action::CycleOverlay => "cycle_overlay",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::NextImage => "Next image",
+            Action::PrevImage => "Previous image",
+            Action::HistoryBack => "Back in history",
+            Action::FirstImage => "First image",
+            Action::LastImage => "Last image",
+            Action::PageDown => "Skip forward",
+            Action::PageUp => "Skip back",
+            Action::GoTo => "Go to image…",
+            Action::RandomJump => "Random image",
+            Action::PanLeft => "Pan left",
+            Action::PanRight => "Pan right",
+            Action::PanUp => "Pan up",
+            Action::PanDown => "Pan down",
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+            Action::ZoomFit => "Fit to window",
+            Action::ActualSize => "Toggle actual size",
+            Action::ToggleFullscreen => "Toggle fullscreen",
+            Action::LeaveFullscreen => "Leave fullscreen",
+            Action::ToggleSlideshow => "Toggle slideshow",
+            Action::OpenFile => "Open file",
+            Action::DeleteFile => "Move to trash",
+            Action::DeleteFileNow => "Move to trash without asking",
+            Action::UndoDelete => "Undo move to trash",
+            Action::ShowHelp => "Keyboard shortcuts",
+            Action::ToggleFilmstrip => "Toggle filmstrip",
+            Action::ToggleGrid => "Toggle grid view",
+            Action::OpenSelected => "Open the selected thumbnail",
+            Action::ToggleTree => "Show the folder tree",
+            Action::NextFolder => "Next folder",
+            Action::PrevFolder => "Previous folder",
+            Action::Reload => "Reload the image",
+            Action::Rescan => "Rescan the folder",
+            Action::RotateClockwise => "Rotate clockwise",
+            Action::RotateCounterClockwise => "Rotate counter-clockwise",
+            Action::FlipHorizontal => "Flip horizontally",
+            Action::FlipVertical => "Flip vertically",
+            Action::RotateFile => "Rotate the file clockwise",
+            Action::Export => "Save a copy as…",
+            Action::ToggleInfo => "Show the file details",
+            Action::Eyedropper => "Show the color under the pointer",
+            Action::ToggleHistogram => "Show the histogram",
+            Action::ToggleNearest => "Show pixels as sharp squares when zoomed in",
+            Action::PlayPause => "Pause or play an animation",
+            Action::NextFrame => "Next frame of an animation",
+            Action::PrevFrame => "Previous frame of an animation",
+            Action::SaveFrame => "Save the frame of an animation as PNG",
+            Action::NextPage => "Next page or icon size",
+            Action::PrevPage => "Previous page or icon size",
+            Action::ToggleSpread => "Show two pages side by side",
+            Action::ToggleWebtoon => "Scroll through all images",
+            Action::ToggleCompare => "Pin the image to compare others with",
+            Action::FlipCompare => "Flip between the compared images",
+            Action::EditExternally => "Edit in another program",
+            Action::RevealFile => "Show in folder",
+            Action::SetWallpaper => "Set as wallpaper",
+            Action::Rename => "Rename",
+            Action::CopyTo => "Copy to…",
+            Action::MoveTo => "Move to…",
+            Action::RepeatMove => "Move to the last folder again",
+            Action::Rate1 => "Rate with 1 star",
+            Action::Rate2 => "Rate with 2 stars",
+            Action::Rate3 => "Rate with 3 stars",
+            Action::Rate4 => "Rate with 4 stars",
+            Action::Rate5 => "Rate with 5 stars",
+            Action::ClearRating => "Clear the rating",
+            Action::ToggleBookmark => "Bookmark the image",
+            Action::NextBookmark => "Next bookmarked image",
+            Action::PrevBookmark => "Previous bookmarked image",
+            Action::ExportBookmarks => "Export bookmarks as a file list",
+            Action::FindDuplicates => "Find duplicates…",
+            Action::SavePlaylist => "Save the list as a playlist…",
+            Action::ToggleMinimalUi => "Hide the toolbar and panels",
+            Action::ToggleOnTop => "Keep the window on top",
+            Action::ToggleBorderless => "Window without a border",
+            Action::ToggleLockZoom => "Keep the zoom and position for the next images",
+            Action::CycleFit => "Fit the whole image, its width or its height",
+            Action::ScrollDown => "Scroll down a screen, then show the next image",
+            Action::ScrollUp => "Scroll up a screen, then show the end of the previous image",
+            Action::CycleOverlay => "Show the thirds, a grid or a crosshair over the image",
+        }
+    }
+
+    pub fn group(self) -> &'static str {
+        match self {
+            Action::NextImage
+            | Action::PrevImage
+            | Action::HistoryBack
+            | Action::FirstImage
+            | Action::LastImage
+            | Action::PageDown
+            | Action::PageUp
+            | Action::GoTo
+            | Action::RandomJump
+            | Action::NextBookmark
+            | Action::PrevBookmark
+            | Action::NextFolder
+            | Action::PrevFolder
+            | Action::NextPage
+            | Action::PrevPage => "Navigation",
+            Action::PanLeft
+            | Action::PanRight
+            | Action::PanUp
+            | Action::PanDown
+            | Action::ScrollUp
+            | Action::ScrollDown
+            | Action::RotateClockwise
+            | Action::RotateCounterClockwise
+            | Action::FlipHorizontal
+            | Action::FlipVertical
+            | Action::ZoomIn
+            | Action::ZoomOut
+            | Action::ZoomFit
+            | Action::CycleFit
+            | Action::ActualSize
+            | Action::ToggleLockZoom
+            | Action::ToggleFullscreen
+            | Action::LeaveFullscreen
+            | Action::ShowHelp
+            | Action::ToggleFilmstrip
+            | Action::ToggleBorderless
+            | Action::ToggleOnTop
+            | Action::ToggleMinimalUi
+            | Action::ToggleSpread
+            | Action::ToggleGrid
+            | Action::ToggleWebtoon
+            | Action::OpenSelected
+            | Action::ToggleTree
+            | Action::ToggleInfo
+            | Action::Eyedropper
+            | Action::ToggleHistogram
+            | Action::ToggleCompare
+            | Action::ToggleBookmark
+            | Action::FlipCompare
+            | Action::ToggleNearest
+            | Action::CycleOverlay
+            | Action::Reload
+            | Action::Rescan => "View",
+            Action::OpenFile
+            | Action::DeleteFile
+            | Action::DeleteFileNow
+            | Action::UndoDelete
+            | Action::RotateFile
+            | Action::Export
+            | Action::EditExternally
+            | Action::RevealFile
+            | Action::SetWallpaper
+            | Action::Rename
+            | Action::CopyTo
+            | Action::MoveTo
+            | Action::RepeatMove
+            | Action::ExportBookmarks
+            | Action::FindDuplicates
+            | Action::SavePlaylist => "File operations",
+            Action::Rate1
+            | Action::Rate2
+            | Action::Rate3
+            | Action::Rate4
+            | Action::Rate5
+            | Action::ClearRating => "Rating",
+            Action::ToggleSlideshow => "Slideshow",
+            Action::PlayPause | Action::NextFrame | Action::PrevFrame | Action::SaveFrame => {
+                "Animation"
+            }
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    fn is_pan(self) -> bool {
+        matches!(
+            self,
+            Action::PanLeft
+                | Action::PanRight
+                | Action::PanUp
+                | Action::PanDown
+                | Action::ScrollDown
+                | Action::ScrollUp
+        )
+    }
+
+    // only fire while an animation is shown, taking the key from the other actions
+    pub fn is_playback(self) -> bool {
+        matches!(
+            self,
+            Action::PlayPause | Action::NextFrame | Action::PrevFrame | Action::SaveFrame
+        )
+    }
+
+    fn defaults(self) -> Vec<Binding> {
+        let key = |key| Binding::from(KeyboardShortcut::new(Modifiers::NONE, key));
+        let with = |modifiers, key| Binding::from(KeyboardShortcut::new(modifiers, key));
+        match self {
+            // vim-style letters, Space and Backspace work next to the arrows
+            Action::NextImage => vec![
+                key(Key::ArrowRight),
+                key(Key::Space),
+                key(Key::L),
+                key(Key::J),
+            ],
+            Action::PrevImage => vec![key(Key::ArrowLeft), key(Key::H), key(Key::K)],
+            Action::PanRight => vec![key(Key::ArrowRight)],
+            Action::PanLeft => vec![key(Key::ArrowLeft)],
+            Action::PanUp => vec![key(Key::ArrowUp)],
+            Action::PanDown => vec![key(Key::ArrowDown)],
+            Action::HistoryBack => vec![key(Key::Backspace)],
+            Action::FirstImage => vec![
+                key(Key::Home),
+                Binding {
+                    prefix: Some(KeyboardShortcut::new(Modifiers::NONE, Key::G)),
+                    key: KeyboardShortcut::new(Modifiers::NONE, Key::G),
+                },
+            ],
+            Action::LastImage => vec![key(Key::End), with(Modifiers::SHIFT, Key::G)],
+            Action::PageDown => vec![key(Key::PageDown)],
+            Action::PageUp => vec![key(Key::PageUp)],
+            Action::GoTo => vec![with(Modifiers::COMMAND, Key::G), key(Key::Colon)],
+            Action::RandomJump => vec![key(Key::R)],
+            Action::ZoomIn => vec![key(Key::Plus), key(Key::Equals)],
+            Action::ZoomOut => vec![key(Key::Minus)],
+            Action::ZoomFit => vec![key(Key::Num0)],
+            Action::ActualSize => vec![key(Key::Num1)],
+            Action::ToggleFullscreen => vec![key(Key::F11)],
+            Action::LeaveFullscreen => vec![key(Key::Escape)],
+            Action::ToggleSlideshow => vec![key(Key::S)],
+            Action::OpenFile => vec![with(Modifiers::COMMAND, Key::O)],
+            Action::DeleteFile => vec![key(Key::Delete)],
+            Action::DeleteFileNow => vec![with(Modifiers::SHIFT, Key::Delete)],
+            Action::UndoDelete => vec![with(Modifiers::COMMAND, Key::Z)],
+            Action::ShowHelp => vec![key(Key::Questionmark), key(Key::F1)],
+            Action::ToggleFilmstrip => vec![key(Key::T)],
+            Action::ToggleGrid => vec![key(Key::Tab)],
+            Action::OpenSelected => vec![key(Key::Enter)],
+            Action::ToggleTree => vec![key(Key::F9)],
+            Action::NextFolder => vec![with(Modifiers::COMMAND, Key::ArrowRight)],
+            Action::PrevFolder => vec![with(Modifiers::COMMAND, Key::ArrowLeft)],
+            Action::Reload => vec![key(Key::F5)],
+            Action::Rescan => vec![with(Modifiers::SHIFT, Key::F5)],
+            // `R` jumps to a random image and `H` goes back, vim-style
+            Action::RotateClockwise => vec![key(Key::CloseBracket)],
+            Action::RotateCounterClockwise => {
+                vec![key(Key::OpenBracket), with(Modifiers::SHIFT, Key::R)]
+            }
+            Action::FlipHorizontal => vec![with(Modifiers::SHIFT, Key::H)],
+            Action::FlipVertical => vec![key(Key::V)],
+            Action::RotateFile => vec![with(Modifiers::COMMAND, Key::R)],
+            Action::Export => vec![with(Modifiers::COMMAND | Modifiers::SHIFT, Key::S)],
+            Action::ToggleInfo => vec![key(Key::I)],
+            Action::Eyedropper => vec![key(Key::P)],
+            Action::ToggleHistogram => vec![with(Modifiers::SHIFT, Key::I)],
+            Action::ToggleNearest => vec![key(Key::N)],
+            Action::PlayPause => vec![key(Key::Space)],
+            Action::NextFrame => vec![key(Key::Period)],
+            Action::PrevFrame => vec![key(Key::Comma)],
+            Action::SaveFrame => vec![with(Modifiers::COMMAND, Key::S)],
+            Action::NextPage => vec![with(Modifiers::COMMAND, Key::PageDown)],
+            Action::PrevPage => vec![with(Modifiers::COMMAND, Key::PageUp)],
+            Action::ToggleSpread => vec![key(Key::D)],
+            Action::ToggleWebtoon => vec![key(Key::W)],
+            Action::ToggleCompare => vec![key(Key::C)],
+            Action::FlipCompare => vec![key(Key::A)],
+            Action::EditExternally => vec![with(Modifiers::COMMAND, Key::E)],
+            Action::RevealFile => vec![with(Modifiers::COMMAND | Modifiers::SHIFT, Key::E)],
+            Action::SetWallpaper => Vec::new(),
+            Action::Rename => vec![key(Key::F2)],
+            Action::CopyTo => vec![key(Key::F7)],
+            Action::MoveTo => vec![key(Key::F6)],
+            Action::RepeatMove => vec![key(Key::M)],
+            Action::Rate1 => vec![with(Modifiers::COMMAND, Key::Num1)],
+            Action::Rate2 => vec![with(Modifiers::COMMAND, Key::Num2)],
+            Action::Rate3 => vec![with(Modifiers::COMMAND, Key::Num3)],
+            Action::Rate4 => vec![with(Modifiers::COMMAND, Key::Num4)],
+            Action::Rate5 => vec![with(Modifiers::COMMAND, Key::Num5)],
+            // next to Ctrl+1, Ctrl+0 would reset the zoom of the whole UI as well
+            Action::ClearRating => vec![with(Modifiers::COMMAND, Key::Backtick)],
+            Action::ToggleBookmark => vec![key(Key::B)],
+            Action::NextBookmark => vec![with(Modifiers::SHIFT, Key::B)],
+            Action::PrevBookmark => Vec::new(),
+            Action::ExportBookmarks => Vec::new(),
+            Action::FindDuplicates => Vec::new(),
+            Action::SavePlaylist => Vec::new(),
+            Action::ToggleMinimalUi => vec![with(Modifiers::SHIFT, Key::Tab)],
+            Action::ToggleOnTop => vec![with(Modifiers::COMMAND, Key::T)],
+            Action::ToggleBorderless => vec![with(Modifiers::COMMAND, Key::B)],
+            Action::ToggleLockZoom => vec![with(Modifiers::SHIFT, Key::L)],
+            Action::CycleFit => vec![key(Key::F)],
+            Action::ScrollDown => vec![key(Key::Space)],
+            Action::ScrollUp => vec![with(Modifiers::SHIFT, Key::Space)],
+            Action::CycleOverlay => vec![key(Key::O)],
+        }
+    }
+}
+
+// a key, optionally pressed right after another one like `G G`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Binding {
+    pub prefix: Option<KeyboardShortcut>,
+    pub key: KeyboardShortcut,
+}
+
+impl From<KeyboardShortcut> for Binding {
+    fn from(key: KeyboardShortcut) -> Self {
+        Self { prefix: None, key }
+    }
+}
+
+// the keys bound to each action, stored in the settings file as `action = ["Ctrl+O", "G G", …]`
+#[derive(Clone, PartialEq)]
+pub struct Keybindings(BTreeMap<Action, Vec<Binding>>);
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self(Action::ALL.into_iter().map(|a| (a, a.defaults())).collect())
+    }
+}
+
+impl Keybindings {
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn get_mut(&mut self, action: Action) -> &mut Vec<Binding> {
+        self.0.entry(action).or_default()
+    }
+
+    // whether a single key bound to `action` was pressed in this frame
+    pub fn pressed(&self, i: &InputState, action: Action) -> bool {
+        presses(i).any(|(key, modifiers)| {
+            self.get(action)
+                .iter()
+                .any(|b| b.prefix.is_none() && self.fires(&b.key, key, modifiers))
+        })
+    }
+
+    // the actions whose keys were pressed in this frame, once per press so held keys repeat,
+    // `pending` keeps the first key of a sequence between frames
+    pub fn triggered_actions(
+        &self,
+        i: &InputState,
+        pending: &mut Option<KeyboardShortcut>,
+    ) -> Vec<Action> {
+        self.actions_for(presses(i), pending)
+    }
+
+    fn actions_for(
+        &self,
+        presses: impl Iterator<Item = (Key, Modifiers)>,
+        pending: &mut Option<KeyboardShortcut>,
+    ) -> Vec<Action> {
+        let mut actions = Vec::new();
+        for (key, modifiers) in presses {
+            let mut prefix = None;
+            let mut completed = false;
+            for (&action, bindings) in &self.0 {
+                for b in bindings {
+                    match b.prefix {
+                        None if self.fires(&b.key, key, modifiers) => actions.push(action),
+                        Some(p) if *pending == Some(p) && self.fires(&b.key, key, modifiers) => {
+                            actions.push(action);
+                            completed = true;
+                        }
+                        Some(p) if self.fires(&p, key, modifiers) => prefix = Some(p),
+                        _ => {}
+                    }
+                }
+            }
+            // the second `G` of `G G` doesn't start the sequence again
+            *pending = if completed { None } else { prefix };
+        }
+        actions
+    }
+
+    // Shift+Delete must not also count as Delete
+    fn fires(&self, shortcut: &KeyboardShortcut, key: Key, modifiers: Modifiers) -> bool {
+        let matches =
+            |s: &KeyboardShortcut| s.logical_key == key && modifiers.matches_logically(s.modifiers);
+        matches(shortcut)
+            && !self
+                .0
+                .values()
+                .flatten()
+                .flat_map(|b| b.prefix.into_iter().chain([b.key]))
+                .any(|other| {
+                    other.modifiers != shortcut.modifiers
+                        && other.modifiers.contains(shortcut.modifiers)
+                        && matches(&other)
+                })
+    }
+
+    // the other actions bound to `binding`, panning and playback may share their keys with the
+    // other actions
+    pub fn conflicts(&self, action: Action, binding: &Binding) -> Vec<Action> {
+        self.0
+            .iter()
+            .filter(|&(&other, keys)| {
+                other != action
+                    && other.is_pan() == action.is_pan()
+                    && other.is_playback() == action.is_playback()
+                    && keys.contains(binding)
+            })
+            .map(|(&other, _)| other)
+            .collect()
+    }
+}
+
+// key presses of this frame in order, including repeats of held keys
+fn presses(i: &InputState) -> impl Iterator<Item = (Key, Modifiers)> + '_ {
+    i.events.iter().filter_map(|e| match e {
+        Event::Key {
+            key,
+            pressed: true,
+            modifiers,
+            ..
+        } => Some((*key, *modifiers)),
+        _ => None,
+    })
+}
+
+pub fn format(binding: &Binding) -> String {
+    let key = |s: &KeyboardShortcut| s.format(&ModifierNames::NAMES, false);
+    match &binding.prefix {
+        Some(prefix) => format!("{} {}", key(prefix), key(&binding.key)),
+        None => key(&binding.key),
+    }
+}
+
+// "Ctrl+Shift+O", "Right", "Plus" or a sequence like "G G", Ctrl stands for Cmd on macOS
+pub fn parse(text: &str) -> Option<Binding> {
+    let mut keys = text.split_whitespace().map(parse_key);
+    let first = keys.next()??;
+    match (keys.next(), keys.next()) {
+        (None, _) => Some(first.into()),
+        (Some(second), None) => Some(Binding {
+            prefix: Some(first),
+            key: second?,
+        }),
+        _ => None,
+    }
+}
+
+fn parse_key(text: &str) -> Option<KeyboardShortcut> {
+    let (mods, key) = text.rsplit_once('+').unwrap_or(("", text));
+    let mut modifiers = Modifiers::NONE;
+    for m in mods.split('+').filter(|m| !m.is_empty()) {
+        modifiers |= match m {
+            "Ctrl" | "Cmd" => Modifiers::COMMAND,
+            "Shift" => Modifiers::SHIFT,
+            "Alt" | "Option" => Modifiers::ALT,
+            _ => return None,
+        };
+    }
+    Some(KeyboardShortcut::new(modifiers, Key::from_name(key)?))
+}
+
+impl Serialize for Keybindings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let map: BTreeMap<&str, Vec<String>> = self
+            .0
+            .iter()
+            .map(|(action, keys)| (action.name(), keys.iter().map(format).collect()))
+            .collect();
+        map.serialize(serializer)
+    }
+}
+
+// actions missing from the file keep their default keys, unknown actions and keys are skipped
+impl<'de> Deserialize<'de> for Keybindings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let map = BTreeMap::<String, Vec<String>>::deserialize(deserializer)?;
+        let mut bindings = Keybindings::default();
+        for (name, keys) in map {
+            let Some(action) = Action::from_name(&name) else {
+                warn!("Ignoring unknown action {:?} in the key bindings", name);
+                continue;
+            };
+            let keys = keys
+                .iter()
+                .filter_map(|k| {
+                    let binding = parse(k);
+                    if binding.is_none() {
+                        warn!("Ignoring unknown key {:?} for {}", k, name);
+                    }
+                    binding
+                })
+                .collect();
+            bindings.0.insert(action, keys);
+        }
+        Ok(bindings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_round_trip() {
+        for action in Action::ALL {
+            for binding in action.defaults() {
+                assert_eq!(parse(&format(&binding)), Some(binding));
+            }
+        }
+        assert_eq!(
+            parse("Ctrl+Shift+O"),
+            Some(KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::O).into())
+        );
+        assert_eq!(parse("Hyper+O"), None);
+        assert_eq!(parse("G G G"), None);
+    }
+
+    #[test]
+    fn missing_actions_keep_their_defaults() {
+        let bindings: Keybindings = toml::from_str("next_image = [\"L\"]\nfly = [\"F\"]").unwrap();
+        assert_eq!(
+            bindings.get(Action::NextImage),
+            [KeyboardShortcut::new(Modifiers::NONE, Key::L).into()]
+        );
+        assert_eq!(bindings.get(Action::GoTo), Action::GoTo.defaults());
+    }
+
+    #[test]
+    fn sequences_and_shifted_keys() {
+        let bindings = Keybindings::default();
+        let mut pending = None;
+        let g = (Key::G, Modifiers::NONE);
+        let shift_g = (Key::G, Modifiers::SHIFT);
+        assert_eq!(bindings.actions_for([g].into_iter(), &mut pending), []);
+        assert_eq!(
+            bindings.actions_for([g].into_iter(), &mut pending),
+            [Action::FirstImage]
+        );
+        assert_eq!(
+            bindings.actions_for([shift_g].into_iter(), &mut pending),
+            [Action::LastImage]
+        );
+        assert_eq!(
+            bindings.actions_for([(Key::Delete, Modifiers::SHIFT)].into_iter(), &mut pending),
+            [Action::DeleteFileNow]
+        );
+    }
+}
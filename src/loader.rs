@@ -0,0 +1,497 @@
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+    },
+    task::Poll,
+    thread,
+    time::{Duration, Instant},
+};
+
+use eframe::egui::{
+    self, ColorImage,
+    load::{
+        Bytes, BytesLoadResult, BytesLoader, BytesPoll, ImageLoadResult, ImageLoader, ImagePoll,
+        LoadError, SizeHint,
+    },
+    mutex::Mutex,
+};
+use image::{DynamicImage, ImageReader, RgbaImage};
+use log::{debug, trace, warn};
+
+use crate::hdr::{self, Hdr, ToneMap};
+use crate::{animation, archive, color, heic, icon, jxl, pages, path::to_path, raw};
+
+type Entry = Poll<Result<Arc<[u8]>, String>>;
+
+// the most a decoder may allocate, a gigapixel of 8 bit RGBA; the default of the image crate
+// rejects large panoramas, and without one a made up header could abort the process
+const MAX_ALLOC: u64 = 4 << 30;
+
+// reads file:// URLs made by `to_url`, the egui_extras file loader does not percent-decode them,
+// and the archive entries of `archive::to_url`
+#[derive(Default)]
+pub struct FileLoader {
+    cache: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+impl FileLoader {
+    pub const ID: &'static str = egui::generate_loader_id!(FileLoader);
+}
+
+impl BytesLoader for FileLoader {
+    fn id(&self) -> &str {
+        Self::ID
+    }
+
+    fn load(&self, ctx: &egui::Context, uri: &str) -> BytesLoadResult {
+        let entry = archive::to_path(uri);
+        let Some(path) = to_path(uri).or_else(|| entry.clone()) else {
+            return Err(LoadError::NotSupported);
+        };
+
+        let mut cache = self.cache.lock();
+        if let Some(entry) = cache.get(uri).cloned() {
+            return match entry {
+                Poll::Ready(Ok(bytes)) => Ok(BytesPoll::Ready {
+                    size: None,
+                    bytes: Bytes::Shared(bytes),
+                    mime: None,
+                }),
+                Poll::Ready(Err(err)) => Err(LoadError::Loading(err)),
+                Poll::Pending => Ok(BytesPoll::Pending { size: None }),
+            };
+        }
+
+        cache.insert(uri.to_owned(), Poll::Pending);
+        drop(cache);
+
+        // read on a separate thread so large files don't block the frame
+        let ctx = ctx.clone();
+        let cache = self.cache.clone();
+        let uri = uri.to_owned();
+        thread::spawn(move || {
+            trace!("Loading {:?}", path);
+            let result = match entry {
+                Some(entry) => archive::read(&entry),
+                None => std::fs::read(&path).map_err(|e| e.to_string()),
+            }
+            .map(Arc::from);
+            // the entry is gone when the image was forgotten while loading
+            let repaint = match cache.lock().get_mut(&uri) {
+                Some(entry) => {
+                    *entry = Poll::Ready(result);
+                    true
+                }
+                None => false,
+            };
+            // don't hold the cache lock while locking the context
+            if repaint {
+                ctx.request_repaint();
+            }
+        });
+
+        Ok(BytesPoll::Pending { size: None })
+    }
+
+    fn forget(&self, uri: &str) {
+        self.cache.lock().remove(uri);
+    }
+
+    fn forget_all(&self) {
+        self.cache.lock().clear();
+    }
+
+    fn byte_size(&self) -> usize {
+        self.cache
+            .lock()
+            .values()
+            .map(|entry| match entry {
+                Poll::Ready(Ok(bytes)) => bytes.len(),
+                Poll::Ready(Err(err)) => err.len(),
+                Poll::Pending => 0,
+            })
+            .sum()
+    }
+
+    fn has_pending(&self) -> bool {
+        self.cache.lock().values().any(Poll::is_pending)
+    }
+}
+
+struct Decoded {
+    image: Arc<ColorImage>,
+    // intrinsic size when the texture is smaller than the file
+    original: Option<[usize; 2]>,
+    // name of the embedded ICC profile the colors were converted from
+    profile: Option<String>,
+    hdr: Option<Hdr>,
+    // spent on the decoding thread, reading the file not included
+    took: Duration,
+}
+
+// what the diagnostics overlay shows of a decoded image
+#[derive(Clone, Copy, Debug)]
+pub struct Stats {
+    // of the texture, smaller than the file when it was downscaled
+    pub size: [usize; 2],
+    pub took: Duration,
+}
+
+impl Stats {
+    pub fn bytes(&self) -> usize {
+        self.size[0] * self.size[1] * 4
+    }
+}
+
+type DecodedEntry = Poll<Result<Decoded, String>>;
+
+// decodes the local images itself so images larger than the GPU allows can be downscaled before
+// the texture is created, the full resolution file stays in the `FileLoader` cache
+pub struct DownscaleLoader {
+    // largest texture side in pixels, lowered further to what the backend supports
+    limit: usize,
+    // convert images with an ICC profile to sRGB
+    color_managed: AtomicBool,
+    // how HDR images are brought to SDR
+    tone_map: Mutex<ToneMap>,
+    cache: Arc<Mutex<HashMap<String, DecodedEntry>>>,
+}
+
+impl DownscaleLoader {
+    pub const ID: &'static str = egui::generate_loader_id!(DownscaleLoader);
+
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            color_managed: AtomicBool::new(true),
+            tone_map: Mutex::new(ToneMap::default()),
+            cache: Default::default(),
+        }
+    }
+
+    // takes effect for the images decoded from now on
+    pub fn set_color_managed(&self, on: bool) {
+        self.color_managed.store(on, Ordering::Relaxed);
+    }
+
+    // takes effect for the images decoded from now on
+    pub fn set_tone_map(&self, tone_map: ToneMap) {
+        *self.tone_map.lock() = tone_map;
+    }
+
+    pub fn hdr(&self, uri: &str) -> Option<Hdr> {
+        match self.cache.lock().get(uri) {
+            Some(Poll::Ready(Ok(decoded))) => decoded.hdr,
+            _ => None,
+        }
+    }
+
+    // the embedded profile `uri` was converted from
+    pub fn profile(&self, uri: &str) -> Option<String> {
+        match self.cache.lock().get(uri) {
+            Some(Poll::Ready(Ok(decoded))) => decoded.profile.clone(),
+            _ => None,
+        }
+    }
+
+    pub fn stats(&self, uri: &str) -> Option<Stats> {
+        match self.cache.lock().get(uri) {
+            Some(Poll::Ready(Ok(decoded))) => Some(Stats {
+                size: decoded.image.size,
+                took: decoded.took,
+            }),
+            _ => None,
+        }
+    }
+
+    // the images decoded and kept, as opposed to pending or failed ones
+    pub fn resident(&self) -> usize {
+        self.cache
+            .lock()
+            .values()
+            .filter(|entry| matches!(entry, Poll::Ready(Ok(_))))
+            .count()
+    }
+
+    // intrinsic size of `uri` if it was downscaled to fit into a texture
+    pub fn original_size(&self, uri: &str) -> Option<[usize; 2]> {
+        match self.cache.lock().get(uri) {
+            Some(Poll::Ready(Ok(decoded))) => decoded.original,
+            _ => None,
+        }
+    }
+}
+
+impl ImageLoader for DownscaleLoader {
+    fn id(&self) -> &str {
+        Self::ID
+    }
+
+    fn load(&self, ctx: &egui::Context, uri: &str, _: SizeHint) -> ImageLoadResult {
+        // TIFF pages (`uri#page2`) are read from the bytes of their file, animation frames
+        // (`uri#0`) and formats the image crate can't read go to egui_extras
+        let (file, page) = pages::split(uri).unwrap_or((uri, 0));
+        let path = to_path(file).or_else(|| archive::to_path(file));
+        let Some(path) = path.filter(|_| !file.contains('#')) else {
+            return Err(LoadError::NotSupported);
+        };
+
+        if let Some(entry) = self.cache.lock().get(uri) {
+            return match entry {
+                Poll::Ready(Ok(decoded)) => Ok(ImagePoll::Ready {
+                    image: decoded.image.clone(),
+                }),
+                Poll::Ready(Err(err)) => Err(LoadError::Loading(err.clone())),
+                Poll::Pending => Ok(ImagePoll::Pending { size: None }),
+            };
+        }
+
+        let bytes = match ctx.try_load_bytes(file)? {
+            BytesPoll::Ready { bytes, .. } => bytes,
+            BytesPoll::Pending { size } => return Ok(ImagePoll::Pending { size }),
+        };
+        // the format is guessed from the content, so misnamed files load too
+        if !raw::is_raw(&path) && !readable(&bytes) {
+            return Err(LoadError::NotSupported);
+        }
+        let limit = ctx.input(|i| i.max_texture_side).min(self.limit);
+        let color_managed = self.color_managed.load(Ordering::Relaxed);
+        let tone_map = *self.tone_map.lock();
+        self.cache.lock().insert(uri.to_owned(), Poll::Pending);
+
+        let ctx = ctx.clone();
+        let cache = self.cache.clone();
+        let uri = uri.to_owned();
+        thread::spawn(move || {
+            trace!("Decoding {}", uri);
+            let start = Instant::now();
+            let result = match page {
+                0 => decode(&path, &bytes, limit, color_managed, tone_map),
+                // a copy of the whole file, so not made on the UI thread
+                _ => pages::extract(&bytes, page)
+                    .ok_or_else(|| format!("No page {} in the file", page + 1))
+                    .and_then(|page| decode(&path, &page, limit, color_managed, tone_map)),
+            }
+            .map(|decoded| Decoded {
+                took: start.elapsed(),
+                ..decoded
+            });
+            if let Ok(Decoded {
+                original: Some(original),
+                image,
+                ..
+            }) = &result
+            {
+                debug!(
+                    "Downscaled {} from {}×{} to {}×{}",
+                    uri,
+                    original[0],
+                    original[1],
+                    image.width(),
+                    image.height()
+                );
+            }
+            let repaint = match cache.lock().get_mut(&uri) {
+                Some(entry) => {
+                    *entry = Poll::Ready(result);
+                    true
+                }
+                None => false,
+            };
+            if repaint {
+                ctx.request_repaint();
+            }
+        });
+
+        Ok(ImagePoll::Pending { size: None })
+    }
+
+    fn forget(&self, uri: &str) {
+        self.cache.lock().remove(uri);
+    }
+
+    fn forget_all(&self) {
+        self.cache.lock().clear();
+    }
+
+    fn byte_size(&self) -> usize {
+        self.cache
+            .lock()
+            .values()
+            .map(|entry| match entry {
+                Poll::Ready(Ok(decoded)) => decoded.image.pixels.len() * 4,
+                Poll::Ready(Err(err)) => err.len(),
+                Poll::Pending => 0,
+            })
+            .sum()
+    }
+
+    fn has_pending(&self) -> bool {
+        self.cache.lock().values().any(Poll::is_pending)
+    }
+}
+
+// decode `bytes`, shrinking the image so neither side exceeds `limit` and turning it into sRGB
+// if it is HDR, or has a profile and `color_managed` says so
+fn decode(
+    path: &Path,
+    bytes: &[u8],
+    limit: usize,
+    color_managed: bool,
+    tone_map: ToneMap,
+) -> Result<Decoded, String> {
+    let (mut image, hdr) = if jxl::is_jxl_data(bytes) {
+        jxl::decode_hdr(bytes)?
+    } else {
+        (load_file(path, bytes)?, hdr::detect(bytes))
+    };
+
+    let size = [image.width() as usize, image.height() as usize];
+    let mut original = None;
+    if size[0] > limit || size[1] > limit {
+        image = image.thumbnail(limit as u32, limit as u32);
+        original = Some(size);
+    }
+    let start = Instant::now();
+    if let Some(sdr) = hdr.and_then(|hdr| hdr::to_sdr(&image, hdr, tone_map)) {
+        image = sdr;
+        debug!("Tone-mapped {:?} in {:?}", path, start.elapsed());
+    }
+    // after downscaling, fewer pixels to convert; the HDR curves aren't described by profiles
+    let signal = matches!(hdr, Some(Hdr::Signal { .. }));
+    let icc = (color_managed && !signal)
+        .then(|| color::embedded(bytes))
+        .flatten();
+    let profile = icc.and_then(|icc| {
+        color::to_srgb(&mut image, &icc)
+            .inspect_err(|e| warn!("Ignoring the color profile of {:?}: {}", path, e))
+            .ok()
+            .flatten()
+    });
+
+    let rgba = image.to_rgba8();
+    let image = ColorImage::from_rgba_unmultiplied(
+        [rgba.width() as usize, rgba.height() as usize],
+        rgba.as_flat_samples().as_slice(),
+    )
+    // lay it out at its intrinsic size, zoom levels stay relative to the file
+    .with_source_size(egui::vec2(size[0] as f32, size[1] as f32));
+    Ok(Decoded {
+        image: Arc::new(image),
+        original,
+        profile,
+        hdr,
+        took: Duration::ZERO,
+    })
+}
+
+// whether `bytes` start like a format `load_from_memory` reads, HEIC also without libheif so the
+// error says what is missing
+fn readable(bytes: &[u8]) -> bool {
+    jxl::is_jxl_data(bytes)
+        || heic::is_heif_data(bytes)
+        || icon::is_icon(bytes)
+        || image::guess_format(bytes).is_ok_and(|f| f.reading_enabled())
+}
+
+// decode `bytes` in any format the viewer reads, the format is guessed from the content
+pub fn load_from_memory(bytes: &[u8]) -> Result<DynamicImage, String> {
+    if jxl::is_jxl_data(bytes) {
+        return jxl::decode(bytes);
+    }
+    if heic::is_heif_data(bytes) {
+        return heic::decode(bytes);
+    }
+    #[cfg(target_os = "macos")]
+    if bytes.starts_with(b"icns") {
+        return icon::decode_icns(bytes);
+    }
+    let mut reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+    reader.limits(limits());
+    reader.decode().map_err(|e| e.to_string())
+}
+
+pub fn limits() -> image::Limits {
+    let mut limits = image::Limits::default();
+    limits.max_alloc = Some(MAX_ALLOC);
+    limits
+}
+
+// decode the `bytes` read from `path`, camera RAW files are told apart by their extension
+pub fn load_file(path: &Path, bytes: &[u8]) -> Result<DynamicImage, String> {
+    if raw::is_raw(path) {
+        return raw::decode(bytes);
+    }
+    load_from_memory(bytes)
+}
+
+// decode the file at `path` at full size
+pub fn open(path: &Path) -> Result<DynamicImage, String> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        // entries of an archive have no file of their own
+        Err(_) if archive::contains(path) => archive::read(path)?,
+        Err(err) => return Err(err.to_string()),
+    };
+    load_file(path, &bytes)
+}
+
+// the full size pixels of `path`, or of its `frame` composited as it was shown, decoded on a
+// thread so large files don't stall the window; `ctx` is woken once they are there
+pub fn open_in_background(
+    ctx: &egui::Context,
+    path: &Path,
+    frame: Option<usize>,
+) -> Receiver<Result<RgbaImage, String>> {
+    let (tx, done) = mpsc::channel();
+    let (ctx, path) = (ctx.clone(), path.to_path_buf());
+    thread::spawn(move || {
+        let pixels = match frame {
+            Some(index) => animation::frame(&path, index),
+            None => open(&path).map(|image| image.to_rgba8()),
+        };
+        if tx.send(pixels).is_ok() {
+            ctx.request_repaint();
+        }
+    });
+    done
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_frame_on_screen_is_copied() {
+        let dir = std::env::temp_dir().join(format!("view-rs-loader-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.gif");
+        let filled = |rgba| image::Frame::new(RgbaImage::from_pixel(2, 2, image::Rgba(rgba)));
+        let mut encoder =
+            image::codecs::gif::GifEncoder::new(std::fs::File::create(&path).unwrap());
+        encoder
+            .encode_frames([filled([255, 0, 0, 255]), filled([0, 0, 255, 255])])
+            .unwrap();
+        drop(encoder);
+
+        let ctx = egui::Context::default();
+        let copied = |frame| {
+            open_in_background(&ctx, &path, frame)
+                .recv()
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(copied(Some(1)).get_pixel(1, 1).0, [0, 0, 255, 255]);
+        assert_eq!(copied(Some(1)), animation::frame(&path, 1).unwrap());
+        // a still copy of an animation is its first frame
+        assert_eq!(copied(None).get_pixel(1, 1).0, [255, 0, 0, 255]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,92 @@
+// the log on stderr, Info unless RUST_LOG or `-v`/`-q` say otherwise, and also in a file with
+// `--log-file` for launches without a terminal
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+use log::{LevelFilter, warn};
+
+const LEVELS: [LevelFilter; 6] = [
+    LevelFilter::Off,
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+// Info moved up a level for each `-v` and down for each `-q`
+fn level(verbose: u8, quiet: u8) -> LevelFilter {
+    // the place of Info in `LEVELS`
+    let info = 3;
+    let at = (info + i32::from(verbose) - i32::from(quiet)).clamp(0, LEVELS.len() as i32 - 1);
+    LEVELS[at as usize]
+}
+
+// every record goes to stderr as before and to the file
+struct Tee(File);
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let _ = io::stderr().write_all(buf);
+        self.0.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _ = io::stderr().flush();
+        self.0.flush()
+    }
+}
+
+// RUST_LOG picks the level and the modules, the flags override its level when given
+pub fn init(verbose: u8, quiet: u8, file: Option<&Path>) {
+    let mut logger = env_logger::Builder::new();
+    logger.filter_level(LevelFilter::Info).parse_default_env();
+    if verbose > 0 || quiet > 0 {
+        logger.filter_level(level(verbose, quiet));
+    }
+    // appended to, a second launch handing its files over doesn't empty it
+    let opened = file.map(|path| {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("{:?}: {}", path, e))
+    });
+    let failed = match opened {
+        Some(Ok(file)) => {
+            logger
+                .target(env_logger::Target::Pipe(Box::new(Tee(file))))
+                .format_timestamp_millis();
+            None
+        }
+        Some(Err(err)) => Some(err),
+        None => None,
+    };
+    logger.init();
+    if let Some(err) = failed {
+        warn!(
+            "Logging to the terminal only, the log file could not be opened: {}",
+            err
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_move_the_level_from_info() {
+        assert_eq!(level(0, 0), LevelFilter::Info);
+        assert_eq!(level(1, 0), LevelFilter::Debug);
+        assert_eq!(level(5, 0), LevelFilter::Trace);
+        assert_eq!(level(0, 2), LevelFilter::Error);
+        assert_eq!(level(0, 9), LevelFilter::Off);
+        assert_eq!(level(2, 1), LevelFilter::Debug);
+    }
+}
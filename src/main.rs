@@ -1,13 +1,134 @@
-use log::{LevelFilter, error, info, warn};
-use std::{ops::Deref, path::PathBuf};
+use log::{error, info, warn};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        mpsc::{Receiver, TryRecvError},
+    },
+    time::Instant,
+};
 
+use clap::Parser;
 use eframe::{
     NativeOptions,
     egui::{self, DroppedFile},
 };
 
+mod animation;
+mod archive;
+mod bookmarks;
+mod cache;
+mod cli;
+mod color;
+mod compare;
+mod crop;
+mod diagnostics;
+mod diff;
+mod duplicates;
+mod export;
+mod external;
+mod fileops;
+mod filter;
+mod hdr;
+mod heic;
+mod histogram;
+mod history;
+mod icon;
+mod instance;
+mod jxl;
+mod keys;
+mod loader;
+mod logging;
+mod metadata;
+mod overlay;
+mod pages;
 mod path;
-use crate::path::{PathSortable, to_path, to_url};
+mod pixels;
+mod playlist;
+mod prefetch;
+mod ratings;
+mod raw;
+mod rotate;
+mod scan;
+mod settings;
+mod shuffle;
+mod spread;
+mod state;
+mod svg;
+mod thumbs;
+mod toast;
+mod tree;
+mod undo;
+mod view;
+mod wallpaper;
+mod watch;
+mod webtoon;
+mod window;
+use crate::animation::Animation;
+use crate::cache::Cache;
+use crate::cli::Args;
+use crate::compare::Compare;
+use crate::crop::Selection;
+use crate::diagnostics::Load;
+use crate::diff::{Diff, DiffView};
+use crate::duplicates::{DuplicateScan, Likeness};
+use crate::fileops::Transfer;
+use crate::filter::Filter;
+use crate::hdr::{Hdr, Operator};
+use crate::histogram::Histogram;
+use crate::history::History;
+use crate::instance::Instance;
+use crate::keys::Action;
+use crate::metadata::{DateScan, DetailsCache, format_time, human_size};
+use crate::overlay::Overlay;
+use crate::pages::Document;
+use crate::path::{PathSortable, SortKey, file_name, short_display, sort, to_path, to_url};
+use crate::pixels::Pixels;
+use crate::prefetch::Prefetch;
+use crate::ratings::{RatingStore, Ratings, stars};
+use crate::rotate::Rotated;
+use crate::scan::{Scan, ScanEvent, ScanOptions};
+use crate::settings::{Backdrop, Settings, Theme, Tool};
+use crate::shuffle::ShuffleBag;
+use crate::state::State;
+use crate::thumbs::Thumbnails;
+use crate::toast::{Level, Toasts};
+use crate::tree::{DirTree, sibling};
+use crate::undo::UndoStack;
+use crate::view::{
+    DisplayMode, Fit, PAN_STEP, Transform, View, ZOOM_STEP, paint_checkerboard, paint_pixel_grid,
+};
+use crate::wallpaper::WallpaperMode;
+use crate::watch::{DirWatch, FileWatch};
+use crate::window::Geometry;
+
+// seconds without pointer movement before the cursor is hidden in fullscreen
+const CURSOR_HIDE_DELAY: f64 = 2.0;
+// distance from the top of the screen that reveals the toolbar in fullscreen
+const TOOLBAR_REVEAL_EDGE: f32 = 4.0;
+// seconds an image that failed to load stays on screen before it is skipped
+const AUTO_SKIP_DELAY: f64 = 2.0;
+// seconds a file that was reloaded is tried again while it doesn't decode, editors may still be
+// writing it
+const RELOAD_RETRY: f64 = 2.0;
+// smallest side in points of a rectangle that is zoomed into, smaller ones were meant as clicks
+const MIN_BAND: f32 = 5.0;
+// seconds the name of the image shows with the chrome hidden, and of fading in and out
+const HUD_DURATION: f64 = 1.5;
+const HUD_FADE: f64 = 0.3;
+// files shown on each side of the current one in the filmstrip
+const FILMSTRIP_RADIUS: usize = 20;
+// size of a filmstrip thumbnail in points
+const FILMSTRIP_THUMB: f32 = 64.0;
+// range of the grid cell size in points, changed with Ctrl+wheel
+const GRID_CELL: std::ops::RangeInclusive<f32> = 48.0..=384.0;
+// zoom from which sharp pixels get a grid between them
+const PIXEL_GRID_ZOOM: f32 = 8.0;
+
+// the full size pixels of a file, decoded on a thread
+type Decoding = Receiver<Result<image::RgbaImage, String>>;
 
 struct ImageViewer {
     // current image source as a URL or file:// URI that egui_extras can handle
@@ -17,6 +138,210 @@ struct ImageViewer {
     files: Vec<PathSortable>,
     index: usize,
     randomize: bool,
+    // files shown since the last shuffle
+    bag: ShuffleBag,
+    // files in the order they were shown, for Prev and Next
+    history: History,
+    // every file shown in this session, random jumps prefer the others
+    visited: HashSet<PathBuf>,
+    // only files whose name matches are visited
+    filter: Filter,
+    // file displayed before the filter was typed, shown again when it is cleared
+    before_filter: Option<PathBuf>,
+    // order of the files while randomize is off
+    sort: SortKey,
+    // reverse the sort order, e.g. newest first
+    descending: bool,
+    // include images in subdirectories
+    recursive: bool,
+    // directory the files were read from
+    dir: Option<PathBuf>,
+    // scan of `dir` that is still running
+    scan: Option<Scan>,
+    // scan of `dir` that is merged into the list once it is done, and the files found so far
+    rescan: Option<(Scan, Vec<PathBuf>)>,
+    // notices files added to or removed from `dir`
+    watch: Option<DirWatch>,
+    // notices when the displayed file is saved
+    file_watch: Option<FileWatch>,
+    // input time when the displayed file was last reloaded
+    reloaded_at: Option<f64>,
+    // capture dates being read before sorting by them
+    dates: Option<DateScan>,
+    prefetch: Prefetch,
+    cache: Cache,
+    view: View,
+    // rotation and mirroring of the displayed image chosen by hand
+    transform: Transform,
+    // what the EXIF orientation of the displayed file asks for, applied before `transform`
+    orientation: Transform,
+    // size of the central panel in the last frame
+    viewport: egui::Vec2,
+    // keep the zoom factor and position when navigating to another image, until the folder changes
+    lock_zoom: bool,
+    fullscreen: bool,
+    // input time of the last pointer movement, used to hide the cursor in fullscreen
+    last_pointer_move: f64,
+    toolbar_revealed: bool,
+    // size and place of the window, restored at startup and followed from then on
+    window: Option<Geometry>,
+    // whether a restored window was checked to be on the monitor
+    window_checked: bool,
+    // input time the image last changed, for the name shown over it
+    hud_since: f64,
+    band: Option<Band>,
+    // a part of the image marked to be cropped
+    selection: Option<Selection>,
+    toolbar_height: f32,
+    slideshow: bool,
+    // seconds between two slides
+    slideshow_interval: f32,
+    // input time when the current slide was shown
+    slideshow_since: f64,
+    // input time of the current frame
+    time: f64,
+    // input times of the frames drawn in the last second, while the repaint overlay is on
+    frames: VecDeque<f64>,
+    // timing of the image navigated to last, and whether it is shown in a corner
+    load: Option<Load>,
+    debug_overlay: bool,
+    // window title sent to the viewport
+    title: String,
+    // name of the playlist the list was read from, shown in the title
+    playlist: Option<String>,
+    // paths piped in with `-`, and whether they are shuffled
+    stdin: Option<(Receiver<Vec<PathBuf>>, bool)>,
+    // paths handed over by instances started later
+    instance: Option<Instance>,
+    // path given on the command line, opened in the first frame
+    pending_open: Option<PathBuf>,
+    // shown in a banner when a file or directory could not be opened
+    error: Option<String>,
+    // input time when loading the current image failed
+    failed_since: Option<f64>,
+    // advance past images that fail to load
+    auto_skip: bool,
+    // the delete confirmation dialog is open
+    deleting: bool,
+    // files moved to the trash, restored with Ctrl+Z
+    undo: UndoStack,
+    // copies of the files rotated in this session as they were before, by the file they are of,
+    // deleted on exit
+    backups: HashMap<PathBuf, PathBuf>,
+    settings: Settings,
+    // where the last session stopped, saved again on exit
+    session: State,
+    // the move destinations window is open
+    destinations_open: bool,
+    // the settings window is open
+    settings_open: bool,
+    // the "Save a copy" window is open
+    export_open: bool,
+    // settings were edited in the settings window and are saved when it closes
+    settings_changed: bool,
+    // binding waiting for a key press in the settings window, an index past the end adds one
+    rebinding: Option<(Action, usize)>,
+    // first key of a sequence like `G G` that was pressed last
+    key_prefix: Option<egui::KeyboardShortcut>,
+    // the keyboard shortcut overlay is shown
+    help_open: bool,
+    // show thumbnails of the neighbouring files along the bottom
+    filmstrip: bool,
+    // show the next file next to the current one, like the pages of a book
+    spread: bool,
+    // started when the first thumbnail is needed
+    thumbs: Option<Thumbnails>,
+    // the thumbnail grid replaces the single image
+    grid: bool,
+    // all images are scrolled through one below the other instead
+    webtoon: bool,
+    strip: webtoon::Strip,
+    // grid cell size in points
+    grid_cell: f32,
+    // file the grid selection is on, opened with Enter
+    grid_selected: usize,
+    // grid columns and the rows that were visible in the last frame
+    grid_columns: usize,
+    grid_rows: std::ops::Range<usize>,
+    // the selection moved and has to be scrolled into view
+    grid_scroll: bool,
+    // show the folder tree on the left
+    tree_open: bool,
+    // started when the folder tree is first shown
+    tree: Option<DirTree>,
+    // show the details of the current file on the right
+    info_open: bool,
+    // started when the details panel is first shown
+    details: Option<DetailsCache>,
+    // show the color of the pixel under the pointer
+    eyedropper: bool,
+    // show the histogram over the image
+    histogram_open: bool,
+    // which of red, green, blue and luminance are drawn
+    histogram_shown: [bool; 4],
+    histogram_log: bool,
+    // the last one counted, shown faded while the next image is decoded
+    histogram: Option<Histogram>,
+    // decoded copy of the current image while the eyedropper or the histogram is on
+    pixels: Option<Pixels>,
+    // frames of the current image if it is a GIF or WebP
+    animation: Option<Animation>,
+    // the page shown of the current file
+    document: Option<Document>,
+    // image pinned to compare the current one with
+    compare: Option<Compare>,
+    // difference of the pinned and the current image, kept until either one changes
+    diff: Option<Diff>,
+    // external commands still running that reload the file they were run on when done
+    running: Vec<(PathBuf, Receiver<bool>)>,
+    // the desktop background being set
+    wallpaper: Option<Receiver<Result<(), String>>>,
+    // the file being decoded for Ctrl+C, with the animation frame that was on screen
+    copying: Option<(PathBuf, Option<usize>, Decoding)>,
+    // the window of duplicate files is open while there are groups, even none
+    duplicates: Option<Vec<Vec<PathBuf>>>,
+    duplicate_scan: Option<DuplicateScan>,
+    likeness: Likeness,
+    ratings: Ratings,
+    // short notices over the image
+    toasts: Toasts,
+    // opened on first use, it has to stay alive for the copied data to remain available on X11
+    clipboard: Option<arboard::Clipboard>,
+    // set while showing an image that is not one of `files`
+    detached: Option<Detached>,
+    // the ZIP or CBZ file whose images are listed
+    archive: Option<PathBuf>,
+    // text of the "Open URL" dialog while it is open
+    url_input: Option<String>,
+    // new name of the current file while the rename dialog is open, and whether the part
+    // before the extension was selected yet
+    renaming: Option<(String, bool)>,
+    // the file being copied or moved to another folder
+    transfer: Option<Transfer>,
+    // a copy or move waiting to be told what to do about a file of the same name in the folder:
+    // the file, the folder and whether it is moved
+    collision: Option<(PathBuf, PathBuf, bool)>,
+    // text of the "Go to image" dialog while it is open
+    goto_input: Option<String>,
+    // the extension list being edited in the Files menu
+    extensions_input: String,
+    ctx: egui::Context,
+    images: Arc<loader::DownscaleLoader>,
+}
+
+// a rectangle dragged over the image to zoom into, `live` until Escape cancels it
+struct Band {
+    // in screen points, on the image
+    start: egui::Pos2,
+    live: bool,
+}
+
+// image pasted from the clipboard or opened from a URL
+struct Detached {
+    // shown in the title and toolbar
+    name: String,
+    // encoded clipboard bitmap for "Save as…"
+    png: Option<Arc<[u8]>>,
 }
 
 impl Default for ImageViewer {
@@ -27,236 +352,5973 @@ impl Default for ImageViewer {
             files: Vec::new(),
             index: 0,
             randomize: true,
+            bag: ShuffleBag::default(),
+            history: History::default(),
+            visited: HashSet::new(),
+            filter: Filter::default(),
+            before_filter: None,
+            sort: SortKey::Name,
+            descending: false,
+            recursive: false,
+            dir: None,
+            scan: None,
+            rescan: None,
+            watch: None,
+            file_watch: None,
+            reloaded_at: None,
+            dates: None,
+            prefetch: Prefetch::new(1),
+            cache: Cache::new(1024 * 1024 * 1024),
+            view: View::default(),
+            transform: Transform::default(),
+            orientation: Transform::default(),
+            viewport: egui::Vec2::ZERO,
+            lock_zoom: false,
+            fullscreen: false,
+            last_pointer_move: 0.0,
+            toolbar_revealed: false,
+            window: None,
+            window_checked: false,
+            hud_since: f64::NEG_INFINITY,
+            band: None,
+            selection: None,
+            toolbar_height: 0.0,
+            slideshow: false,
+            slideshow_interval: 5.0,
+            slideshow_since: 0.0,
+            time: 0.0,
+            frames: VecDeque::new(),
+            load: None,
+            debug_overlay: false,
+            title: "view-rs".to_owned(),
+            playlist: None,
+            stdin: None,
+            instance: None,
+            pending_open: None,
+            error: None,
+            failed_since: None,
+            auto_skip: false,
+            deleting: false,
+            undo: UndoStack::default(),
+            backups: HashMap::new(),
+            settings: Settings::default(),
+            session: State::default(),
+            destinations_open: false,
+            settings_open: false,
+            export_open: false,
+            settings_changed: false,
+            rebinding: None,
+            key_prefix: None,
+            help_open: false,
+            filmstrip: false,
+            spread: false,
+            thumbs: None,
+            grid: false,
+            webtoon: false,
+            strip: webtoon::Strip::default(),
+            grid_cell: 128.0,
+            grid_selected: 0,
+            grid_columns: 1,
+            grid_rows: 0..0,
+            grid_scroll: false,
+            tree_open: false,
+            tree: None,
+            info_open: false,
+            details: None,
+            eyedropper: false,
+            histogram_open: false,
+            histogram_shown: [true; 4],
+            histogram_log: false,
+            histogram: None,
+            pixels: None,
+            animation: None,
+            document: None,
+            compare: None,
+            diff: None,
+            running: Vec::new(),
+            wallpaper: None,
+            copying: None,
+            duplicates: None,
+            duplicate_scan: None,
+            likeness: Likeness::default(),
+            ratings: Ratings::default(),
+            toasts: Toasts::default(),
+            clipboard: None,
+            detached: None,
+            archive: None,
+            url_input: None,
+            renaming: None,
+            transfer: None,
+            collision: None,
+            goto_input: None,
+            extensions_input: String::new(),
+            ctx: egui::Context::default(),
+            images: Arc::new(loader::DownscaleLoader::new(16384)),
         }
     }
 }
 
 impl eframe::App for ImageViewer {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                if ui.button("Open directory").clicked() {
-                    if let Some(dir) = rfd::FileDialog::new().pick_folder() {
-                        if let Err(err) = self.open_dir(&dir) {
-                            error!("Failed to open directory: {}", err);
-                        }
-                    }
-                }
+    // shows through where the image area is less than opaque
+    fn clear_color(&self, visuals: &egui::Visuals) -> [f32; 4] {
+        let opacity = self.settings.opacity.clamp(0.0, 1.0);
+        visuals
+            .panel_fill
+            .gamma_multiply(opacity)
+            .to_normalized_gamma_f32()
+    }
 
-                if ui.button("Prev").clicked() {
-                    self.prev();
-                }
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if self.settings_changed {
+            self.save_settings();
+        }
+        self.save_session();
+        // the socket goes away with it
+        self.instance = None;
+        for backup in self.backups.values() {
+            if let Err(err) = std::fs::remove_file(backup) {
+                warn!("Failed to remove {:?}: {}", backup, err);
+            }
+        }
+    }
 
-                if ui.button("Next").clicked() {
-                    self.next();
-                }
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // changes when the window is moved to a monitor with other scaling
+        self.view.pixels_per_point = ctx.pixels_per_point();
+        self.poll_scan();
+        self.poll_watch();
+        self.poll_rescan();
+        self.poll_file_watch();
+        self.poll_commands();
+        self.poll_wallpaper();
+        self.poll_copy();
+        self.poll_transfer();
+        self.poll_dates();
+        self.poll_duplicates();
+        self.poll_stdin();
+        self.poll_instance(ctx);
+        if !self.eyedropper && !self.histogram_open {
+            // only kept while something reads it
+            self.pixels = None;
+        }
+        if let Some(path) = self.pending_open.take() {
+            if let Err(err) = self.open_path(&path) {
+                error!("Failed to open {:?}: {}", path, err);
+                self.push_toast(
+                    Level::Error,
+                    format!("Could not open {}: {}", short_display(&path), err),
+                );
+            }
+        }
+
+        // fullscreen: F11 toggles, Escape leaves
+        let keys = &self.settings.keys;
+        let capturing = self.rebinding.is_some();
+        let (f11, escape, moved, time, pointer) = ctx.input(|i| {
+            (
+                !capturing && keys.pressed(i, Action::ToggleFullscreen),
+                !capturing && keys.pressed(i, Action::LeaveFullscreen),
+                i.pointer.delta() != egui::Vec2::ZERO || i.pointer.any_down(),
+                i.time,
+                i.pointer.hover_pos(),
+            )
+        });
+        // a developer aid, left out of the shortcuts that can be changed
+        let debug = egui::Modifiers::CTRL | egui::Modifiers::SHIFT;
+        if !capturing && ctx.input_mut(|i| i.consume_key(debug, egui::Key::F12)) {
+            self.debug_overlay = !self.debug_overlay;
+        }
+        if f11 {
+            self.set_fullscreen(ctx, !self.fullscreen);
+        } else if escape && egui::Popup::is_any_open(ctx) {
+            // it closes the menu that is open
+        } else if let Some(band) = self.band.as_mut().filter(|b| escape && b.live) {
+            band.live = false;
+        } else if escape && self.selection.is_some() {
+            self.selection = None;
+        } else if escape && self.help_open {
+            self.help_open = false;
+        } else if escape && self.compare.is_some() {
+            self.compare = None;
+            self.diff = None;
+        } else if escape && self.fullscreen {
+            self.set_fullscreen(ctx, false);
+        }
+        self.time = time;
+        if self.settings.repaint_overlay {
+            self.frames.push_back(time);
+            while self.frames.front().is_some_and(|&t| time - t > 1.0) {
+                self.frames.pop_front();
+            }
+        }
+        if moved {
+            self.last_pointer_move = time;
+        }
 
-                // detect toggle change so we can reorder files while keeping the current file visible
-                let prev_random = self.randomize;
-                ui.toggle_value(&mut self.randomize, "Randomize");
+        // a window restored onto a monitor that is gone, or smaller, is brought back onto it
+        let monitor = ctx.input(|i| i.viewport().monitor_size);
+        if let Some(monitor) = monitor.filter(|_| !self.window_checked) {
+            self.window_checked = true;
+            if let Some(position) = self.window.and_then(|w| w.moved_onto(monitor)) {
+                info!("Moving the window onto the screen at {:?}", position);
+                ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(position));
+            }
+        }
+        ctx.input(|i| Geometry::follow(&mut self.window, i.viewport()));
 
-                if prev_random != self.randomize && !self.files.is_empty() {
-                    if self.randomize {
-                        use rand::seq::SliceRandom;
-                        let mut rng = rand::rng();
-                        self.files.shuffle(&mut rng);
-                    } else {
-                        self.files.sort();
+        // with the chrome hidden the toolbar only shows up while the pointer is at the top edge
+        let minimal = self.minimal_ui();
+        let show_toolbar = !minimal
+            || pointer.is_some_and(|p| {
+                p.y <= TOOLBAR_REVEAL_EDGE || (self.toolbar_revealed && p.y <= self.toolbar_height)
+            });
+        self.toolbar_revealed = minimal && show_toolbar;
+        if show_toolbar {
+            let panel = egui::TopBottomPanel::top("top_panel").show(ctx, |ui| self.toolbar(ui));
+            self.toolbar_height = panel.response.rect.bottom();
+        }
+        if let Some(err) = self.error.clone() {
+            egui::TopBottomPanel::top("error_banner").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                    if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                        self.error = None;
                     }
-                    self.reindex();
-                    // reset image_size so runtime loader can supply intrinsic size again
-                    self.image_size = [0, 0];
-                }
-
-                if let Some(src) = &self.current_src {
-                    ui.label(src);
-                }
+                });
             });
-        });
+        }
+
+        // hide the idle cursor in fullscreen
+        if self.fullscreen && !show_toolbar && !egui::Popup::is_any_open(ctx) {
+            let idle = time - self.last_pointer_move;
+            if idle >= CURSOR_HIDE_DELAY {
+                ctx.set_cursor_icon(egui::CursorIcon::None);
+            } else {
+                ctx.request_repaint_after(std::time::Duration::from_secs_f64(
+                    CURSOR_HIDE_DELAY - idle,
+                ));
+            }
+        }
 
         // drag & drop: open directory or file
-        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        let (dropped, append) = ctx.input(|i| (i.raw.dropped_files.clone(), i.modifiers.command));
         if !dropped.is_empty() {
-            self.open_dropped_path(dropped);
+            self.open_dropped_path(dropped, append);
         }
 
-        // keyboard navigation
-        if ctx.input(|i| i.key_pressed(egui::Key::ArrowRight)) {
-            self.next();
+        // shortcuts are off while typing into a text field or choosing a new key
+        if !ctx.wants_keyboard_input() && self.rebinding.is_none() {
+            self.shortcuts(ctx);
         }
-        if ctx.input(|i| i.key_pressed(egui::Key::ArrowLeft)) {
-            self.prev();
+        if self.deleting {
+            self.delete_dialog(ctx);
+        }
+        if self.destinations_open {
+            self.destinations_window(ctx);
+        }
+        if self.settings_open {
+            self.settings_window(ctx);
+        }
+        if self.export_open {
+            self.export_window(ctx);
+        }
+        if self.duplicates.is_some() {
+            self.duplicates_window(ctx);
+        }
+        if self.url_input.is_some() {
+            self.url_dialog(ctx);
+        }
+        if self.renaming.is_some() {
+            self.rename_dialog(ctx);
+        }
+        if self.collision.is_some() {
+            self.collision_dialog(ctx);
+        }
+        if self.goto_input.is_some() {
+            self.goto_dialog(ctx);
+        }
+        if self.help_open {
+            self.help_overlay(ctx);
         }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            if let Some(src) = &self.current_src {
-                // determine display size
-                egui::ScrollArea::both()
-                    .auto_shrink([false; 2])
-                    .show(ui, |ui| {
-                        let avail = ui.available_size();
-                        let disp_size = egui::vec2(avail.x, avail.y);
-                        // Use egui Image widget with runtime source (egui_extras provides loaders)
-                        ui.add_sized(disp_size, egui::Image::new(src));
-                    });
+        if self.slideshow && !self.files.is_empty() {
+            let interval = f64::from(self.slideshow_interval);
+            let elapsed = self.time - self.slideshow_since;
+            if elapsed >= interval {
+                self.next();
+            }
+            // keep running while the window is idle
+            let remaining = interval - (self.time - self.slideshow_since);
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(remaining.max(0.0)));
+        }
+
+        let title = match (&self.detached, self.files.get(self.index)) {
+            (Some(detached), _) => format!("{} — view-rs", detached.name),
+            (None, Some(p)) => match &self.playlist {
+                Some(name) => format!(
+                    "{} — {} — {} — view-rs",
+                    file_name(p),
+                    self.position(),
+                    name
+                ),
+                None => format!("{} — {} — view-rs", file_name(p), self.position()),
+            },
+            (None, None) => "view-rs".to_owned(),
+        };
+        if title != self.title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.title = title;
+            self.hud_since = time;
+        }
+
+        // hidden along with the toolbar, below everything else at the bottom
+        if show_toolbar && self.current_src.is_some() {
+            egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| self.status_bar(ui));
+        }
+
+        // out of the way during a fullscreen slideshow
+        let presenting = self.fullscreen && self.slideshow;
+        if self.filmstrip && !self.grid && !self.files.is_empty() && !presenting && !minimal {
+            egui::TopBottomPanel::bottom("filmstrip").show(ctx, |ui| self.filmstrip(ui));
+        }
+        if self.tree_open && !presenting {
+            let options = self.scan_options();
+            let tree = self.tree.get_or_insert_with(|| DirTree::new(ctx));
+            let clicked = egui::SidePanel::left("tree")
+                .resizable(true)
+                .default_width(200.0)
+                .show(ctx, |ui| tree.show(ui, self.dir.as_deref(), &options))
+                .inner;
+            if let Some(dir) = clicked {
+                self.open_dir(&dir);
+            }
+        }
+        if self.info_open && !presenting {
+            let panel = egui::SidePanel::right("info")
+                .resizable(true)
+                .default_width(self.session.info_width.unwrap_or(240.0))
+                .show(ctx, |ui| self.info_panel(ui));
+            self.session.info_width = Some(panel.response.rect.width());
+        }
+
+        let mut frame = egui::Frame::central_panel(&ctx.style());
+        if self.fullscreen {
+            // use the entire screen for the image
+            frame = frame.inner_margin(0.0);
+        }
+        let dark = ctx.style().visuals.dark_mode;
+        if let Some([r, g, b]) = self.settings.background(dark) {
+            frame = frame.fill(egui::Color32::from_rgb(r, g, b));
+        }
+        let opacity = self.settings.opacity.clamp(0.0, 1.0);
+        frame.fill = frame.fill.gamma_multiply(opacity);
+        let central = egui::CentralPanel::default().frame(frame);
+        let central = central.show(ctx, |ui| {
+            ui.set_opacity(opacity);
+            if self.grid && !self.files.is_empty() {
+                self.show_grid(ui);
+            } else if self.webtoon && self.detached.is_none() && !self.files.is_empty() {
+                self.show_webtoon(ui);
+            } else if let Some(src) = self.current_src.clone() {
+                let src = self.page_src(&src);
+                match self.spread_partner() {
+                    _ if self.compare.is_some() => self.show_compare(ui, &src),
+                    Some(partner) => self.show_spread(ui, &src, &partner),
+                    None => self.show_image(ui, &src),
+                }
+                if self.histogram_open {
+                    self.histogram_overlay(ui, &src);
+                }
+                if let Some(Hdr::Signal { .. }) = self.images.hdr(&src) {
+                    self.tone_map_controls(ui);
+                }
+                if self.current_file().is_some_and(|p| raw::is_raw(p)) {
+                    raw_badge(ui);
+                }
+                self.animation_controls(ui);
             } else {
                 ui.centered_and_justified(|ui| {
-                    ui.label("No image loaded. Click Open to choose an image.");
+                    if self.scan.is_some() || self.stdin.is_some() {
+                        ui.spinner();
+                    } else if self.filter.is_active() && !self.files.is_empty() {
+                        ui.label("No file names match the filter.");
+                    } else if self.playlist.as_deref() == Some("stdin") && self.files.is_empty() {
+                        ui.label(
+                            "No image paths were read from stdin. Click Open to choose an image.",
+                        );
+                    } else if let Some(dir) =
+                        self.session.last_dir.clone().filter(|_| self.dir.is_none())
+                    {
+                        ui.vertical_centered(|ui| {
+                            ui.add_space(ui.available_height() / 2.0 - 30.0);
+                            ui.label("No image loaded. Click Open to choose an image.");
+                            if ui
+                                .button(format!("Reopen {}", short_display(&dir)))
+                                .clicked()
+                            {
+                                self.reopen_session();
+                            }
+                        });
+                    } else {
+                        ui.label("No image loaded. Click Open to choose an image.");
+                    }
                 });
             }
         });
+
+        if !show_toolbar {
+            self.hud(ctx, central.response.rect);
+        }
+        if self.settings.repaint_overlay {
+            self.repaint_overlay(ctx, central.response.rect);
+        }
+        if self.debug_overlay {
+            self.diagnostics_overlay(ctx, central.response.rect);
+        }
+        if self.settings.borderless && !self.fullscreen {
+            self.move_frameless(ctx);
+        }
+        self.toasts.show(ctx, central.response.rect, self.time);
+
+        if let Some(thumbs) = &mut self.thumbs {
+            thumbs.end_frame();
+        }
     }
 }
 
 impl ImageViewer {
-    fn open_dir(&mut self, dir: &PathBuf) -> Result<(), String> {
-        let mut entries = std::fs::read_dir(dir)
-            .map_err(|e| e.to_string())?
-            .filter_map(Result::ok)
-            .map(|e| e.path())
-            .filter(|p| {
-                p.is_file()
-                    && p.file_name()
-                        .is_some_and(|n| !n.to_string_lossy().starts_with('.'))
-            })
-            .map(PathSortable::from)
-            .collect::<Vec<_>>();
-
-        let exts = [
-            "jpg", "jpeg", "png", "bmp", "gif", "webp", "avif", "tif", "tiff",
-        ];
-        entries.retain(|p| {
-            p.extension()
-                .and_then(|s| s.to_str())
-                .map(|s| exts.contains(&s.to_ascii_lowercase().as_str()))
-                .unwrap_or(false)
-        });
+    fn new(
+        ctx: &egui::Context,
+        args: Args,
+        mut settings: Settings,
+        session: State,
+        images: Arc<loader::DownscaleLoader>,
+    ) -> Self {
+        let defaults = Self::default();
+        settings.sniff |= args.sniff;
+        let restore = settings.restore_session && args.path.is_none();
+        let stdin = args.path.as_deref() == Some(Path::new("-"));
+        let shuffle_stdin = args.random;
+        let listen = settings.single_instance && !args.new_window;
+        let randomize = if args.no_random || args.sort.is_some() {
+            false
+        } else if args.random {
+            true
+        } else {
+            session.randomize.unwrap_or(settings.randomize)
+        };
+        ctx.set_theme(settings.theme.preference());
+        let window = args.geometry.or(session.window);
+        let mut viewer = Self {
+            ctx: ctx.clone(),
+            extensions_input: settings.extensions.join(", "),
+            slideshow_interval: args.slideshow.unwrap_or(settings.slideshow_interval),
+            ratings: Ratings::load(settings.rating_store),
+            settings,
+            images,
+            // resolve relative paths against the working directory before they end up in file:// URLs
+            pending_open: args
+                .path
+                .filter(|_| !stdin)
+                .map(|p| std::path::absolute(&p).unwrap_or(p)),
+            randomize,
+            sort: args.sort.unwrap_or(defaults.sort),
+            descending: args.descending,
+            recursive: args.recursive,
+            prefetch: Prefetch::new(args.prefetch),
+            cache: Cache::new(args.cache_mb * 1024 * 1024),
+            auto_skip: args.auto_skip,
+            debug_overlay: args.debug_overlay,
+            slideshow: args.slideshow.is_some(),
+            window,
+            fullscreen: window.is_some_and(|w| w.fullscreen),
+            session,
+            ..defaults
+        };
+        viewer.set_fit(viewer.session.fit);
+        if restore {
+            viewer.reopen_session();
+        }
+        if stdin {
+            let list = playlist::read_stdin(ctx, viewer.scan_options());
+            viewer.stdin = Some((list, shuffle_stdin));
+        }
+        if listen {
+            viewer.instance = instance::listen(ctx);
+        }
+        viewer
+    }
 
-        if entries.is_empty() {
-            return Err("No image files found in directory".into());
+    // keyboard shortcuts that act on the list or the current image, see `keys::Keybindings`
+    fn shortcuts(&mut self, ctx: &egui::Context) {
+        // number keys with a destination move the file there, the others keep their zoom bindings
+        for (key, number) in [
+            egui::Key::Num1,
+            egui::Key::Num2,
+            egui::Key::Num3,
+            egui::Key::Num4,
+            egui::Key::Num5,
+            egui::Key::Num6,
+            egui::Key::Num7,
+            egui::Key::Num8,
+            egui::Key::Num9,
+        ]
+        .into_iter()
+        .zip(1..)
+        {
+            if self.settings.destination(number).is_some()
+                && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key))
+            {
+                self.move_current(number);
+            }
         }
 
-        if self.randomize {
-            use rand::seq::SliceRandom;
-            let mut rng = rand::rng();
-            entries.shuffle(&mut rng);
+        // the keys of external commands are taken from the actions too
+        let run: Vec<usize> = (0..self.settings.commands.len())
+            .filter(|&i| {
+                keys::parse(&self.settings.commands[i].key)
+                    .filter(|b| b.prefix.is_none())
+                    .is_some_and(|b| ctx.input_mut(|i| i.consume_shortcut(&b.key)))
+            })
+            .collect();
+        for i in run {
+            self.run_command(i);
+        }
+
+        let mut actions = ctx.input(|i| {
+            self.settings
+                .keys
+                .triggered_actions(i, &mut self.key_prefix)
+        });
+        if self.grid {
+            self.grid_shortcuts(ctx, actions);
+            return;
+        }
+        if self.webtoon && self.detached.is_none() {
+            self.webtoon_shortcuts(ctx, actions);
+            return;
+        }
+        // playback keys are taken from the other actions while an animation is shown
+        let playback = self
+            .animation
+            .as_ref()
+            .is_some_and(|a| a.is_animated() == Some(true));
+        if playback && actions.iter().any(|a| a.is_playback()) {
+            actions.retain(|a| a.is_playback());
         } else {
-            entries.sort();
+            actions.retain(|a| !a.is_playback());
+        }
+        // the arrow keys pan instead of navigating while the image is larger than the window
+        let panning = self.current_src.is_some()
+            && self.image_size[0] > 0
+            && self.view.pannable(self.viewport, self.image_vec());
+        let step = self.viewport * PAN_STEP;
+        let mut pan = egui::Vec2::ZERO;
+        for action in &actions {
+            match action {
+                Action::PanLeft => pan.x -= step.x,
+                Action::PanRight => pan.x += step.x,
+                Action::PanUp => pan.y -= step.y,
+                Action::PanDown => pan.y += step.y,
+                _ => {}
+            }
         }
+        // Space scrolls through a tall image before it goes on to the next one
+        let mut paged = false;
+        for action in &actions {
+            if let Action::ScrollDown | Action::ScrollUp = action {
+                let down = *action == Action::ScrollDown;
+                paged |= panning && self.view.page(down, self.viewport, self.image_vec());
+            }
+        }
+        let arrows = panning && pan != egui::Vec2::ZERO;
+        if arrows {
+            self.view.pan_by(pan, self.viewport, self.image_vec());
+        }
+        let panned = arrows || paged;
 
-        self.files = entries;
-        self.index = 0;
-        let p = self.files[0].clone();
+        let len = self.files.len();
+        let stride = self.settings.page_stride.max(1);
+        let pos = self.filter.position(self.index) - 1;
+        let zoomable = self.current_src.is_some() && self.image_size[0] > 0;
+        let (viewport, size) = (self.viewport, self.image_vec());
+        let center = viewport * 0.5;
+        // reading right to left, the arrow keys turn the pages the way they lie
+        let flipped = self.spread
+            && self.settings.right_to_left
+            && ctx.input(|i| {
+                i.key_pressed(egui::Key::ArrowLeft) || i.key_pressed(egui::Key::ArrowRight)
+            });
+        for action in actions {
+            match action {
+                Action::NextImage | Action::PrevImage if self.spread && !panned => {
+                    self.turn_spread((action == Action::NextImage) != flipped);
+                }
+                Action::NextImage if !panned => self.next(),
+                // Space is bound to the next image as well, Shift+Space goes back by itself
+                Action::ScrollUp if !panned && self.spread => self.turn_spread(flipped),
+                Action::ScrollUp if !panned => {
+                    self.prev();
+                    self.view.to_end = true;
+                }
+                Action::PrevImage | Action::HistoryBack if !panned => self.prev(),
+                Action::FirstImage | Action::LastImage | Action::PageDown | Action::PageUp => {
+                    // Home/End and Page Up/Down jump within the visible files
+                    let target = match action {
+                        Action::FirstImage => 0,
+                        Action::LastImage => usize::MAX,
+                        Action::PageDown => pos.saturating_add(stride),
+                        _ => pos.saturating_sub(stride),
+                    };
+                    if let Some(i) = self.filter.nth(target, len) {
+                        self.goto(i);
+                    }
+                }
+                Action::GoTo if !self.files.is_empty() => {
+                    // the typed letter must not end up in the text field that gets the focus
+                    ctx.input_mut(|i| i.events.retain(|e| !matches!(e, egui::Event::Text(_))));
+                    self.goto_input = Some(String::new());
+                }
+                Action::RandomJump => self.random_jump(),
+                Action::NextBookmark => self.step_bookmark(true),
+                Action::PrevBookmark => self.step_bookmark(false),
+                Action::NextFolder => self.step_folder(true),
+                Action::PrevFolder => self.step_folder(false),
+                Action::ZoomIn if zoomable => self.view.zoom_by(ZOOM_STEP, viewport, size, center),
+                Action::ZoomOut if zoomable => {
+                    self.view.zoom_by(1.0 / ZOOM_STEP, viewport, size, center)
+                }
+                Action::ZoomFit if zoomable => self.view.reset(),
+                Action::CycleFit => self.set_fit(self.view.fit.next()),
+                Action::ActualSize if zoomable => self.toggle_actual_size(viewport, size, center),
+                Action::ToggleSlideshow => self.toggle_slideshow(),
+                Action::OpenFile => self.pick_file(),
+                Action::DeleteFile | Action::DeleteFileNow if self.current_file().is_some() => {
+                    if action == Action::DeleteFileNow || !self.settings.confirm_delete {
+                        self.delete_current();
+                    } else {
+                        self.deleting = true;
+                    }
+                }
+                Action::UndoDelete => self.undo_delete(),
+                Action::ShowHelp => self.help_open = !self.help_open,
+                Action::ToggleFilmstrip => self.filmstrip = !self.filmstrip,
+                Action::ToggleMinimalUi => self.toggle_minimal_ui(),
+                Action::ToggleLockZoom => self.lock_zoom = !self.lock_zoom,
+                Action::ToggleOnTop => {
+                    self.settings.always_on_top = !self.settings.always_on_top;
+                    self.apply_window_style();
+                }
+                Action::ToggleBorderless => {
+                    self.settings.borderless = !self.settings.borderless;
+                    self.apply_window_style();
+                }
+                Action::ToggleSpread => self.spread = !self.spread,
+                Action::ToggleGrid => self.toggle_grid(ctx),
+                Action::ToggleWebtoon => self.toggle_webtoon(),
+                Action::ToggleTree => self.tree_open = !self.tree_open,
+                Action::ToggleInfo => self.info_open = !self.info_open,
+                Action::Eyedropper => self.eyedropper = !self.eyedropper,
+                Action::ToggleHistogram => self.histogram_open = !self.histogram_open,
+                Action::ToggleCompare => self.pin_current(),
+                Action::ToggleBookmark => self.toggle_bookmark(),
+                Action::ExportBookmarks => self.export_bookmarks(),
+                Action::FindDuplicates => self.find_duplicates(),
+                Action::SavePlaylist => self.save_playlist(),
+                Action::FlipCompare => {
+                    if let Some(compare) = &mut self.compare {
+                        compare.flip();
+                    }
+                }
+                Action::SaveFrame => self.save_frame(),
+                Action::NextPage => self.turn_page(true),
+                Action::PrevPage => self.turn_page(false),
+                Action::PlayPause | Action::NextFrame | Action::PrevFrame => {
+                    if let Some(animation) = &mut self.animation {
+                        match action {
+                            Action::PlayPause => animation.toggle(self.time),
+                            _ => animation.step(action == Action::NextFrame, self.time),
+                        }
+                    }
+                }
+                Action::ToggleNearest => {
+                    self.settings.nearest = !self.settings.nearest;
+                    self.settings_changed = true;
+                }
+                Action::CycleOverlay => {
+                    self.settings.overlay = self.settings.overlay.next();
+                    self.settings_changed = true;
+                    let label = self.settings.overlay.label();
+                    self.push_toast(Level::Info, format!("Overlay: {}", label.to_lowercase()));
+                }
+                Action::Reload => self.reload(),
+                Action::RotateFile => self.rotate_file(),
+                Action::Export => self.export_open = self.current_src.is_some(),
+                Action::EditExternally => self.edit_externally(),
+                Action::RevealFile => self.reveal_file(),
+                Action::SetWallpaper => self.set_wallpaper(),
+                Action::CopyTo => self.transfer_to(None, false),
+                Action::MoveTo => self.transfer_to(None, true),
+                Action::RepeatMove => match self.settings.last_move.clone() {
+                    Some(dir) => self.transfer_to(Some(dir), true),
+                    None => self.push_toast(
+                        Level::Warn,
+                        "Choose a folder with Move to… first".to_owned(),
+                    ),
+                },
+                Action::Rate1 => self.rate_current(1),
+                Action::Rate2 => self.rate_current(2),
+                Action::Rate3 => self.rate_current(3),
+                Action::Rate4 => self.rate_current(4),
+                Action::Rate5 => self.rate_current(5),
+                Action::ClearRating => self.rate_current(0),
+                Action::Rename => {
+                    self.renaming = self
+                        .current_file()
+                        .filter(|p| p.is_file())
+                        .map(|p| (file_name(p), false));
+                }
+                Action::RotateClockwise => self.transform_by(|t| t.rotate(true)),
+                Action::RotateCounterClockwise => self.transform_by(|t| t.rotate(false)),
+                Action::FlipHorizontal => self.transform_by(Transform::flip_horizontal),
+                Action::FlipVertical => self.transform_by(Transform::flip_vertical),
+                Action::Rescan => self.rescan(),
+                _ => {}
+            }
+        }
 
-        self.current_src = Some(to_url(&p));
-        self.image_size = [0, 0];
+        // the mouse back/forward buttons walk through the history
+        let (back, forward) = ctx.input(|i| {
+            (
+                i.pointer.button_pressed(egui::PointerButton::Extra1),
+                i.pointer.button_pressed(egui::PointerButton::Extra2),
+            )
+        });
+        if back {
+            self.prev();
+        }
+        if forward {
+            self.next();
+        }
 
-        info!(
-            "Opened directory: {:?}, {} image files detected.",
-            dir,
-            self.files.len()
-        );
-        Ok(())
+        // Ctrl+C copies the image, Ctrl+Shift+C its path; both arrive as events and can't be rebound
+        let (copy, shift) = ctx.input(|i| {
+            (
+                i.events.iter().any(|e| matches!(e, egui::Event::Copy)),
+                i.modifiers.shift,
+            )
+        });
+        if copy && self.current_file().is_some() {
+            if shift {
+                self.copy_path();
+            } else {
+                self.copy_image();
+            }
+        }
+
+        // Ctrl+V only arrives when the clipboard holds text, bitmaps are pasted with the button
+        let pasted = ctx.input(|i| {
+            i.events.iter().find_map(|e| match e {
+                egui::Event::Paste(text) => Some(text.clone()),
+                _ => None,
+            })
+        });
+        if let Some(text) = pasted {
+            self.paste(Some(text));
+        }
     }
 
-    fn open_dropped_path(&mut self, dropped: Vec<DroppedFile>) {
-        for f in dropped {
-            let Some(dropped_path) = f.path.as_ref().map(|p| p.to_path_buf()) else {
-                warn!("Dropped file has no path: {:?}", f.path);
-                continue;
-            };
-            let dir_path = if dropped_path.is_dir() {
-                dropped_path.clone()
-            } else {
-                let Some(parent) = dropped_path.parent() else {
-                    warn!(
-                        "Could not get parent directory of dropped file: {:?}",
-                        dropped_path
-                    );
-                    continue;
-                };
-                parent.to_path_buf()
-            };
+    fn toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Open file").clicked() {
+                self.pick_file();
+            }
+
+            if ui.button("Open directory").clicked() {
+                if let Some(dir) = self.file_dialog().pick_folder() {
+                    self.open_dir(&dir);
+                }
+            }
+            ui.add_enabled_ui(!self.settings.recent_dirs.is_empty(), |ui| {
+                ui.menu_button("⏷", |ui| self.recent_menu(ui))
+                    .response
+                    .on_hover_text("Recent directories");
+            });
+
+            if ui.button("Open URL…").clicked() {
+                self.url_input = Some(String::new());
+            }
+
+            if ui.button("Prev").clicked() {
+                self.prev();
+            }
+
+            if ui.button("Next").clicked() {
+                self.next();
+            }
 
-            let _ = self.open_dir(&dir_path);
-            info!(
-                "Opened directory: {:?}, {} image files detected.",
-                dir_path,
-                self.files.len()
+            // detect toggle change so we can reorder files while keeping the current file visible
+            let prev_random = self.randomize;
+            ui.toggle_value(&mut self.randomize, "Randomize");
+
+            if prev_random != self.randomize && !self.files.is_empty() {
+                if self.randomize {
+                    self.shuffle_files();
+                    self.dates = None;
+                } else {
+                    self.sort_files();
+                }
+                self.reindex();
+                // reset image_size so runtime loader can supply intrinsic size again
+                self.image_size = [0, 0];
+            }
+            if self.randomize {
+                if ui.button("Reshuffle now").clicked() {
+                    self.reshuffle();
+                }
+                ui.label(format!(
+                    "seen {}/{} this cycle",
+                    thousands(self.bag.seen().min(self.files.len())),
+                    thousands(self.files.len())
+                ));
+            }
+
+            let (prev_sort, prev_descending) = (self.sort, self.descending);
+            egui::ComboBox::from_id_salt("sort")
+                .selected_text(self.sort.label())
+                .show_ui(ui, |ui| {
+                    for key in SortKey::ALL {
+                        ui.selectable_value(&mut self.sort, key, key.label());
+                    }
+                });
+            let arrow = if self.descending { "⬇" } else { "⬆" };
+            ui.toggle_value(&mut self.descending, arrow)
+                .on_hover_text("Descending order");
+            if (prev_sort, prev_descending) != (self.sort, self.descending) {
+                // picking an order ends the shuffle, like --sort does
+                self.randomize = false;
+                if !self.files.is_empty() {
+                    self.sort_files();
+                    self.reindex();
+                }
+            }
+
+            if ui.selectable_label(self.slideshow, "Slideshow").clicked() {
+                self.toggle_slideshow();
+            }
+            ui.add(
+                egui::DragValue::new(&mut self.slideshow_interval)
+                    .range(0.5..=3600.0)
+                    .speed(0.1)
+                    .suffix(" s"),
             );
 
-            if dropped_path.is_file() {
-                // display dropped file
-                for (i, p) in self.files.iter().enumerate() {
-                    if p.deref() == &dropped_path {
-                        self.index = i;
-                        self.current_src = Some(to_url(p.deref()));
-                        break;
+            if ui
+                .checkbox(&mut self.recursive, "Include subfolders")
+                .changed()
+            {
+                self.reopen_dir();
+            }
+
+            ui.checkbox(&mut self.auto_skip, "Skip broken images");
+
+            if ui.button("Destinations…").clicked() {
+                self.destinations_open = true;
+            }
+            if ui
+                .button("Duplicates…")
+                .on_hover_text("Find identical or similar files in the list")
+                .clicked()
+            {
+                self.find_duplicates();
+            }
+
+            ui.menu_button("Files", |ui| {
+                let save = egui::Button::new("Save list as playlist…");
+                if ui.add_enabled(!self.files.is_empty(), save).clicked() {
+                    ui.close();
+                    self.save_playlist();
+                }
+                ui.separator();
+                self.files_menu(ui);
+            });
+
+            ui.menu_button("Window", |ui| self.window_menu(ui));
+
+            if ui.button("Settings…").clicked() {
+                self.settings_open = true;
+            }
+            if ui.button("?").on_hover_text("Keyboard shortcuts").clicked() {
+                self.help_open = !self.help_open;
+            }
+
+            if ui.button("Clear cache").clicked() {
+                self.clear_cache();
+            }
+
+            if ui
+                .button("Hide")
+                .on_hover_text("Only the image, the toolbar comes back at the top edge")
+                .clicked()
+            {
+                self.toggle_minimal_ui();
+            }
+            let mut fit = self.view.fit;
+            egui::ComboBox::from_id_salt("fit")
+                .selected_text(fit.label())
+                .show_ui(ui, |ui| {
+                    for option in Fit::ALL {
+                        ui.selectable_value(&mut fit, option, option.label());
                     }
+                });
+            if fit != self.view.fit {
+                self.set_fit(fit);
+            }
+            ui.toggle_value(&mut self.lock_zoom, "Lock view")
+                .on_hover_text("Keep the zoom and position for the next images");
+            ui.toggle_value(&mut self.filmstrip, "Filmstrip");
+            ui.toggle_value(&mut self.spread, "Spread")
+                .on_hover_text("Two pages side by side");
+            ui.toggle_value(&mut self.tree_open, "Folders");
+            ui.toggle_value(&mut self.info_open, "Info");
+            ui.toggle_value(&mut self.histogram_open, "Histogram");
+            if ui.selectable_label(self.grid, "Grid").clicked() {
+                self.toggle_grid(ui.ctx());
+            }
+            if ui
+                .selectable_label(self.webtoon, "Scroll")
+                .on_hover_text("All images one below the other")
+                .clicked()
+            {
+                self.toggle_webtoon();
+            }
+            if self.current_src.is_some() {
+                let zoom = self.view.zoom(self.viewport, self.image_vec());
+                ui.label(format!("{:.0}%", zoom * 100.0));
+                let transform = self.transform.label();
+                if !transform.is_empty() {
+                    ui.label(transform);
                 }
-                info!("Opened dropped file: {:?}", dropped_path);
-                break;
             }
-        }
+            if let Some([w, h]) = self
+                .current_src
+                .as_deref()
+                .and_then(|src| self.images.original_size(src))
+            {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("downscaled from {w}×{h}"),
+                )
+                .on_hover_text("The image is larger than the maximum texture size");
+            }
+            if let Some(profile) = self
+                .current_src
+                .as_deref()
+                .and_then(|src| self.images.profile(src))
+            {
+                ui.label(format!("🎨 {}", profile))
+                    .on_hover_text("Converted to sRGB from the embedded color profile");
+            }
+            if let Some(hdr) = self
+                .current_src
+                .as_deref()
+                .and_then(|src| self.images.hdr(src))
+            {
+                ui.label("HDR (tone-mapped)").on_hover_text(hdr.describe());
+            }
+
+            let edit = ui.add(
+                egui::TextEdit::singleline(&mut self.filter.pattern)
+                    .hint_text("Filter, e.g. *.png")
+                    .desired_width(120.0),
+            );
+            if edit.changed() {
+                self.apply_filter();
+            }
+            let min = self.filter.rated.as_ref().map_or(0, |(min, _)| *min);
+            let mut chosen = min;
+            let label = |n: u8| match n {
+                0 => "★ any".to_owned(),
+                n => format!("≥ {}", stars(n)),
+            };
+            egui::ComboBox::from_id_salt("rating filter")
+                .selected_text(label(min))
+                .show_ui(ui, |ui| {
+                    for n in 0..=5 {
+                        ui.selectable_value(&mut chosen, n, label(n));
+                    }
+                })
+                .response
+                .on_hover_text("Only show files rated with at least this many stars");
+            if chosen != min {
+                self.filter_rating(chosen);
+            }
+            self.bookmarks_menu(ui);
+
+            if let Some(dates) = &self.dates {
+                ui.spinner();
+                ui.label(format!(
+                    "Reading dates… {}/{}",
+                    thousands(dates.progress()),
+                    thousands(dates.total)
+                ));
+            }
+            if self.scan.is_some() {
+                ui.spinner();
+                ui.label(format!("Scanning… {} files", thousands(self.files.len())));
+            }
+            if let Some(transfer) = &self.transfer {
+                ui.spinner();
+                let verb = if transfer.moving { "Moving" } else { "Copying" };
+                ui.label(format!(
+                    "{} {}… {:.0}%",
+                    verb,
+                    file_name(&transfer.file),
+                    transfer.progress() * 100.0
+                ));
+            }
+
+            if ui
+                .button("Paste")
+                .on_hover_text("View the image, path or URL in the clipboard")
+                .clicked()
+            {
+                self.paste(None);
+            }
+            if self.detached.as_ref().is_some_and(|d| d.png.is_some())
+                && ui.button("Save as…").clicked()
+            {
+                self.save_detached();
+            }
+
+            if self.current_src.is_some() && ui.button("Save a copy…").clicked() {
+                self.export_open = true;
+            }
+
+            if let Some(detached) = &self.detached {
+                ui.label(&detached.name);
+            } else if let Some(p) = self.files.get(self.index) {
+                ui.label(format!("{} — {}", file_name(p), self.position()))
+                    .on_hover_text(p.display().to_string());
+            }
+        });
     }
 
-    fn next(&mut self) {
-        if self.files.is_empty() {
-            return;
+    // directories that no longer exist are greyed out and can be removed
+    fn recent_menu(&mut self, ui: &mut egui::Ui) {
+        let mut remove = None;
+        for dir in self.settings.recent_dirs.clone() {
+            let label = short_display(&dir);
+            if dir.is_dir() {
+                if ui
+                    .button(label)
+                    .on_hover_text(dir.display().to_string())
+                    .clicked()
+                {
+                    self.open_dir(&dir);
+                }
+            } else {
+                ui.horizontal(|ui| {
+                    ui.add_enabled(false, egui::Button::new(label))
+                        .on_disabled_hover_text("No longer exists");
+                    if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                        remove = Some(dir.clone());
+                    }
+                });
+            }
+        }
+        ui.separator();
+        let clear = ui.button("Clear recent").clicked();
+        if clear {
+            self.settings.recent_dirs.clear();
+        } else if let Some(dir) = &remove {
+            self.settings.recent_dirs.retain(|d| d != dir);
+        }
+        if clear || remove.is_some() {
+            if let Err(err) = self.settings.save() {
+                warn!("Failed to save settings: {}", err);
+            }
         }
-        self.index = (self.index + 1) % self.files.len();
-        let p = self.files[self.index].clone();
-        self.current_src = Some(to_url(&p));
-        self.image_size = [0, 0];
     }
 
-    fn prev(&mut self) {
-        if self.files.is_empty() {
-            return;
+    // which files are listed, changes rescan the open directory
+    fn files_menu(&mut self, ui: &mut egui::Ui) {
+        let mut changed = ui
+            .checkbox(&mut self.settings.show_hidden, "Show hidden files")
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.settings.sniff, "Detect images by content")
+            .on_hover_text("Also list files with a missing or wrong extension, reads every file")
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.settings.raw, "Camera RAW files")
+            .on_hover_text(format!(
+                "Show the preview embedded in {}",
+                raw::EXTENSIONS.join(", ")
+            ))
+            .changed();
+        ui.label("Extensions:");
+        let edit = ui.add(
+            egui::TextEdit::singleline(&mut self.extensions_input)
+                .hint_text("jpg, png, …")
+                .desired_width(200.0),
+        );
+        if edit.lost_focus() {
+            let extensions = parse_extensions(&self.extensions_input);
+            if !extensions.is_empty() && extensions != self.settings.extensions {
+                self.settings.extensions = extensions;
+                changed = true;
+            }
+            self.extensions_input = self.settings.extensions.join(", ");
         }
-        if self.index == 0 {
-            self.index = self.files.len() - 1;
-        } else {
-            self.index -= 1;
+        if changed {
+            if let Err(err) = self.settings.save() {
+                warn!("Failed to save settings: {}", err);
+            }
+            self.reopen_dir();
         }
-        let p = self.files[self.index].clone();
-        self.current_src = Some(to_url(&p));
-        self.image_size = [0, 0];
     }
 
-    fn reindex(&mut self) {
-        let cur_path = self.current_src.as_ref().and_then(|s| to_path(s));
-        if let Some(cur) = cur_path {
-            let pos = self
+    // edits apply right away, the file is written when the window is closed
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.settings_open;
+        let mut changed = false;
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("settings").num_columns(2).show(ui, |ui| {
+                    ui.label("Startup");
+                    ui.vertical(|ui| {
+                        changed |= ui
+                            .checkbox(&mut self.settings.randomize, "Randomize the file order")
+                            .changed();
+                        changed |= ui
+                            .checkbox(
+                                &mut self.settings.restore_session,
+                                "Reopen the last directory",
+                            )
+                            .changed();
+                    });
+                    ui.end_row();
+
+                    ui.label("Slideshow interval");
+                    let interval = ui.add(
+                        egui::DragValue::new(&mut self.settings.slideshow_interval)
+                            .range(0.5..=3600.0)
+                            .speed(0.1)
+                            .suffix(" s"),
+                    );
+                    if interval.changed() {
+                        self.slideshow_interval = self.settings.slideshow_interval;
+                        changed = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Theme");
+                    egui::ComboBox::from_id_salt("theme")
+                        .selected_text(self.settings.theme.label())
+                        .show_ui(ui, |ui| {
+                            for theme in Theme::ALL {
+                                let option = ui.selectable_value(
+                                    &mut self.settings.theme,
+                                    theme,
+                                    theme.label(),
+                                );
+                                if option.changed() {
+                                    ctx.set_theme(theme.preference());
+                                    changed = true;
+                                }
+                            }
+                        });
+                    ui.end_row();
+
+                    // each theme has its own, white behind images in the light one stays there
+                    let dark = ui.visuals().dark_mode;
+                    ui.label("Background");
+                    ui.horizontal(|ui| {
+                        let background = self.settings.background_mut(dark);
+                        let mut custom = background.is_some();
+                        if ui.checkbox(&mut custom, "Custom").changed() {
+                            let start = if dark { [0, 0, 0] } else { [255, 255, 255] };
+                            *background = custom.then_some(start);
+                            changed = true;
+                        }
+                        if let Some(rgb) = background {
+                            changed |= ui.color_edit_button_srgb(rgb).changed();
+                        }
+                        let theme = if dark { "dark" } else { "light" };
+                        ui.weak(format!("with the {} theme", theme));
+                    });
+                    ui.end_row();
+
+                    ui.label("Transparency");
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt("transparency")
+                            .selected_text(self.settings.transparency.label())
+                            .show_ui(ui, |ui| {
+                                for backdrop in Backdrop::ALL {
+                                    changed |= ui
+                                        .selectable_value(
+                                            &mut self.settings.transparency,
+                                            backdrop,
+                                            backdrop.label(),
+                                        )
+                                        .changed();
+                                }
+                            });
+                        match self.settings.transparency {
+                            Backdrop::Checkerboard => {
+                                changed |= ui
+                                    .add(
+                                        egui::DragValue::new(&mut self.settings.checker_size)
+                                            .range(2.0..=64.0)
+                                            .suffix(" pt"),
+                                    )
+                                    .changed();
+                            }
+                            Backdrop::Custom => {
+                                changed |= ui
+                                    .color_edit_button_srgb(&mut self.settings.transparency_color)
+                                    .changed();
+                            }
+                            _ => {}
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Magnification");
+                    changed |= ui
+                        .checkbox(
+                            &mut self.settings.nearest,
+                            "Sharp pixels above 100%, with a grid from 800%",
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Color profiles");
+                    if ui
+                        .checkbox(
+                            &mut self.settings.color_managed,
+                            "Convert images with an embedded profile to sRGB",
+                        )
+                        .changed()
+                    {
+                        self.images.set_color_managed(self.settings.color_managed);
+                        // decoded again in the other colors
+                        self.ctx.forget_all_images();
+                        self.reload();
+                        changed = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Overlay");
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt("overlay")
+                            .selected_text(self.settings.overlay.label())
+                            .show_ui(ui, |ui| {
+                                for overlay in Overlay::ALL {
+                                    changed |= ui
+                                        .selectable_value(
+                                            &mut self.settings.overlay,
+                                            overlay,
+                                            overlay.label(),
+                                        )
+                                        .changed();
+                                }
+                            });
+                        if self.settings.overlay == Overlay::Grid {
+                            let [columns, rows] = &mut self.settings.overlay_grid;
+                            changed |= ui
+                                .add(egui::DragValue::new(columns).range(2..=64))
+                                .changed();
+                            ui.label("×");
+                            changed |= ui.add(egui::DragValue::new(rows).range(2..=64)).changed();
+                        }
+                        changed |= ui
+                            .color_edit_button_srgba_unmultiplied(&mut self.settings.overlay_color)
+                            .changed();
+                    });
+                    ui.end_row();
+
+                    ui.label("Page Up/Down");
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut self.settings.page_stride)
+                                .range(1..=1000)
+                                .suffix(" images"),
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Rotation");
+                    ui.vertical(|ui| {
+                        if ui
+                            .checkbox(
+                                &mut self.settings.auto_orient,
+                                "Turn photos upright by their EXIF orientation",
+                            )
+                            .changed()
+                        {
+                            self.orient();
+                            changed = true;
+                        }
+                        changed |= ui
+                            .checkbox(
+                                &mut self.settings.sticky_transform,
+                                "Keep rotation and flips for the next image",
+                            )
+                            .changed();
+                    });
+                    ui.end_row();
+
+                    ui.label("Spread");
+                    ui.vertical(|ui| {
+                        changed |= ui
+                            .checkbox(&mut self.settings.right_to_left, "Right to left, for manga")
+                            .changed();
+                        changed |= ui
+                            .checkbox(
+                                &mut self.settings.spread_cover,
+                                "Show the first page on its own",
+                            )
+                            .changed();
+                    });
+                    ui.end_row();
+
+                    ui.label("New files");
+                    changed |= ui
+                        .checkbox(
+                            &mut self.settings.follow_new,
+                            "Show images as soon as they appear in the folder",
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Window");
+                    let single = ui
+                        .checkbox(
+                            &mut self.settings.single_instance,
+                            "Open files in the window that is already open",
+                        )
+                        .on_hover_text("--new-window opens one of its own anyway");
+                    if single.changed() {
+                        changed = true;
+                        self.instance = self
+                            .settings
+                            .single_instance
+                            .then(|| instance::listen(ctx))
+                            .flatten();
+                    }
+                    ui.end_row();
+
+                    ui.label("Fullscreen");
+                    changed |= ui
+                        .checkbox(
+                            &mut self.settings.fullscreen_minimal,
+                            "Hide the toolbar and panels",
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Delete");
+                    changed |= ui
+                        .checkbox(
+                            &mut self.settings.confirm_delete,
+                            "Ask before moving to the trash",
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Editor");
+                    changed |= ui
+                        .add(
+                            egui::TextEdit::singleline(&mut self.settings.editor)
+                                .hint_text("default application")
+                                .desired_width(240.0),
+                        )
+                        .on_hover_text("A command like gimp \"{path}\", used by Ctrl+E")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Wallpaper");
+                    egui::ComboBox::from_id_salt("wallpaper mode")
+                        .selected_text(self.settings.wallpaper_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in WallpaperMode::ALL {
+                                changed |= ui
+                                    .selectable_value(
+                                        &mut self.settings.wallpaper_mode,
+                                        mode,
+                                        mode.label(),
+                                    )
+                                    .changed();
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "How \"Set as wallpaper\" covers the screen, not on macOS and KDE",
+                        );
+                    ui.end_row();
+
+                    ui.label("Ratings");
+                    egui::ComboBox::from_id_salt("rating store")
+                        .selected_text(self.settings.rating_store.label())
+                        .show_ui(ui, |ui| {
+                            for store in RatingStore::ALL {
+                                changed |= ui
+                                    .selectable_value(
+                                        &mut self.settings.rating_store,
+                                        store,
+                                        store.label(),
+                                    )
+                                    .changed();
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "Where stars are kept, sidecars are shared with other photo tools",
+                        );
+                    ui.end_row();
+
+                    ui.label("Debug");
+                    changed |= ui
+                        .checkbox(
+                            &mut self.settings.repaint_overlay,
+                            "Show frames per second and why the window was redrawn",
+                        )
+                        .changed();
+                    ui.end_row();
+                });
+                ui.separator();
+                ui.label("Files");
+                self.files_menu(ui);
+                ui.separator();
+                egui::CollapsingHeader::new("External commands")
+                    .show(ui, |ui| changed |= self.commands_page(ui));
+                egui::CollapsingHeader::new("Keyboard shortcuts")
+                    .show(ui, |ui| changed |= self.keys_page(ui));
+            });
+        if !open {
+            self.rebinding = None;
+        }
+        if self.settings.rating_store != self.ratings.store() {
+            self.ratings = Ratings::load(self.settings.rating_store);
+            if let Some((min, _)) = self.filter.rated {
+                self.filter_rating(min);
+            }
+        }
+        self.settings_changed |= changed;
+        self.settings_open = open;
+        if !open && self.settings_changed {
+            self.save_settings();
+        }
+    }
+
+    // the current file in the middle of its visible neighbours
+    fn filmstrip(&mut self, ui: &mut egui::Ui) {
+        let len = self.files.len();
+        let count = self.filter.count(len);
+        if count == 0 {
+            return;
+        }
+        let pos = self.filter.position(self.index) - 1;
+        let range = pos.saturating_sub(FILMSTRIP_RADIUS)..=(pos + FILMSTRIP_RADIUS).min(count - 1);
+        let shown: Vec<usize> = range.filter_map(|p| self.filter.nth(p, len)).collect();
+        let mut clicked = None;
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            ui.horizontal(|ui| {
+                for i in shown {
+                    let selected = i == self.index && self.detached.is_none();
+                    let response = self.thumbnail(ui, i, FILMSTRIP_THUMB, selected);
+                    if i == self.index {
+                        response.scroll_to_me(Some(egui::Align::Center));
+                    }
+                    if response.clicked() {
+                        clicked = Some(i);
+                    }
+                }
+            });
+        });
+        if let Some(i) = clicked {
+            self.goto(i);
+        }
+    }
+
+    // the thumbnail of `files[index]` once it is decoded, fitted into a square cell
+    fn thumbnail(
+        &mut self,
+        ui: &mut egui::Ui,
+        index: usize,
+        size: f32,
+        selected: bool,
+    ) -> egui::Response {
+        let path = self.files[index].to_path_buf();
+        let modified = self.files[index].meta().modified;
+        let ctx = self.ctx.clone();
+        let thumbs = self.thumbs.get_or_insert_with(|| Thumbnails::new(&ctx));
+        let texture = thumbs.get(&ctx, &path, modified);
+
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::click());
+        let visuals = ui.style().interact_selectable(&response, selected);
+        if selected || response.hovered() {
+            ui.painter()
+                .rect_filled(rect, visuals.corner_radius, visuals.weak_bg_fill);
+        }
+        let inner = rect.shrink(4.0);
+        match texture {
+            Some(texture) => {
+                let image = texture.size_vec2();
+                let scale = (inner.size() / image).min_elem();
+                let fitted = egui::Rect::from_center_size(inner.center(), image * scale);
+                let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+                ui.painter()
+                    .image(texture.id(), fitted, uv, egui::Color32::WHITE);
+            }
+            None => {
+                ui.painter()
+                    .rect_filled(inner, 2.0, ui.visuals().faint_bg_color);
+            }
+        }
+        if self.is_bookmarked(&path) {
+            let corner = inner.right_top();
+            let marker = vec![
+                corner,
+                corner - egui::vec2(10.0, 0.0),
+                corner + egui::vec2(0.0, 10.0),
+            ];
+            ui.painter().add(egui::Shape::convex_polygon(
+                marker,
+                ui.visuals().selection.bg_fill,
+                egui::Stroke::NONE,
+            ));
+        }
+        response.on_hover_text(file_name(&path))
+    }
+
+    fn toggle_grid(&mut self, ctx: &egui::Context) {
+        self.grid = !self.grid;
+        if self.grid {
+            self.grid_selected = self.index;
+            self.grid_scroll = true;
+        }
+        // Tab also moves the keyboard focus, which would let Space press a toolbar button
+        if let Some(id) = ctx.memory(|m| m.focused()) {
+            ctx.memory_mut(|m| m.surrender_focus(id));
+        }
+    }
+
+    fn toggle_webtoon(&mut self) {
+        self.webtoon = !self.webtoon;
+        if self.webtoon {
+            self.strip.scroll_to = Some(self.filter.position(self.index).saturating_sub(1));
+        } else {
+            self.strip.clear(&self.ctx);
+            // the strip only moved the index, the single view takes over from there
+            if self.index < self.files.len() {
+                self.show_current();
+            }
+        }
+    }
+
+    // Space and Page Down scroll by a screen, the other arrows by a step
+    fn webtoon_shortcuts(&mut self, ctx: &egui::Context, actions: Vec<Action>) {
+        let height = self.viewport.y;
+        for action in actions {
+            match action {
+                Action::NextImage | Action::PageDown => self.strip.scroll_by += height,
+                Action::PrevImage | Action::PageUp => self.strip.scroll_by -= height,
+                Action::PanDown => self.strip.scroll_by += height * PAN_STEP,
+                Action::PanUp => self.strip.scroll_by -= height * PAN_STEP,
+                Action::FirstImage => self.strip.scroll_to = Some(0),
+                Action::LastImage => self.strip.scroll_to = Some(usize::MAX),
+                Action::ToggleWebtoon => self.toggle_webtoon(),
+                Action::ToggleGrid => {
+                    self.toggle_webtoon();
+                    self.toggle_grid(ctx);
+                }
+                Action::ShowHelp => self.help_open = !self.help_open,
+                Action::ToggleTree => self.tree_open = !self.tree_open,
+                _ => {}
+            }
+        }
+    }
+
+    // the visible files fit to the width of the window in one scroll area, only the ones near
+    // the viewport are laid out and loaded, `index` follows the topmost one
+    fn show_webtoon(&mut self, ui: &mut egui::Ui) {
+        let len = self.files.len();
+        let count = self.filter.count(len);
+        let width = ui.available_width();
+        let gap = ui.spacing().item_spacing.y;
+        self.viewport = ui.available_size();
+        let files: Vec<usize> = (0..count)
+            .filter_map(|pos| self.filter.nth(pos, len))
+            .collect();
+        let mut tops = Vec::with_capacity(count + 1);
+        let mut top = 0.0;
+        for &i in &files {
+            tops.push(top);
+            top += self.strip.ratio(&self.files[i]) * width + gap;
+        }
+        tops.push(top);
+
+        let mut area = egui::ScrollArea::vertical()
+            .id_salt("webtoon")
+            .auto_shrink([false; 2]);
+        if let Some(pos) = self.strip.scroll_to.take() {
+            area = area.vertical_scroll_offset(tops[pos.min(count)]);
+        }
+        let scroll_by = std::mem::take(&mut self.strip.scroll_by);
+        let options = self.texture_options();
+        let mut wanted = HashSet::new();
+        // images above the viewport that turned out taller or shorter than estimated
+        let mut shift = 0.0;
+        let output = area.show_viewport(ui, |ui, viewport| {
+            ui.set_height(top);
+            if scroll_by != 0.0 {
+                ui.scroll_with_delta(egui::vec2(0.0, -scroll_by));
+            }
+            let shown = webtoon::visible(&tops, viewport.min.y, viewport.height());
+            let near = shown.start.saturating_sub(webtoon::MARGIN)
+                ..(shown.end + webtoon::MARGIN).min(count);
+            let origin = ui.max_rect().min;
+            for pos in near {
+                let path = &self.files[files[pos]];
+                let uri = self.src_of(path);
+                let image = egui::Image::new(&uri).texture_options(options);
+                let rect = egui::Rect::from_min_size(
+                    origin + egui::vec2(0.0, tops[pos]),
+                    egui::vec2(width, tops[pos + 1] - tops[pos] - gap),
+                );
+                match image.load_for_size(ui.ctx(), rect.size()) {
+                    Ok(poll) => {
+                        if let Some(size) = poll.size() {
+                            let grown = self.strip.measured(path, size, width);
+                            if pos < shown.start {
+                                shift += grown;
+                            }
+                        }
+                        if shown.contains(&pos) {
+                            self.paint_backdrop(ui, rect);
+                            image.paint_at(ui, rect);
+                        }
+                    }
+                    Err(err) => {
+                        ui.painter().text(
+                            rect.center(),
+                            egui::Align2::CENTER_CENTER,
+                            format!("{}: {}", file_name(path), err),
+                            egui::TextStyle::Body.resolve(ui.style()),
+                            ui.visuals().error_fg_color,
+                        );
+                    }
+                }
+                wanted.insert(uri);
+            }
+            shown.start
+        });
+        self.strip.keep(ui.ctx(), &wanted);
+        // the images in view stay where they are while the ones above get their real height
+        if shift != 0.0 {
+            self.strip.scroll_by += shift;
+            ui.ctx().request_repaint();
+        }
+        if let Some(&i) = files.get(output.inner) {
+            self.index = i;
+        }
+    }
+
+    // the arrows move the grid selection, Enter opens it
+    fn grid_shortcuts(&mut self, ctx: &egui::Context, actions: Vec<Action>) {
+        let len = self.files.len();
+        let columns = self.grid_columns.max(1) as isize;
+        let pos = self.filter.position(self.grid_selected) as isize - 1;
+        for action in actions {
+            let target = match action {
+                Action::NextImage | Action::PanRight => pos + 1,
+                Action::PrevImage | Action::PanLeft => pos - 1,
+                Action::PanDown | Action::PageDown => pos + columns,
+                Action::PanUp | Action::PageUp => pos - columns,
+                Action::FirstImage => 0,
+                Action::LastImage => isize::MAX,
+                Action::OpenSelected => {
+                    self.grid = false;
+                    self.goto(self.grid_selected);
+                    return;
+                }
+                Action::ToggleGrid => {
+                    self.toggle_grid(ctx);
+                    return;
+                }
+                Action::ShowHelp => {
+                    self.help_open = !self.help_open;
+                    continue;
+                }
+                Action::ToggleTree => {
+                    self.tree_open = !self.tree_open;
+                    continue;
+                }
+                _ => continue,
+            };
+            if let Some(i) = self.filter.nth(target.max(0) as usize, len) {
+                self.grid_selected = i;
+                self.grid_scroll = true;
+            }
+        }
+    }
+
+    // thumbnails of all visible files, only the rows on screen are laid out and decoded
+    fn show_grid(&mut self, ui: &mut egui::Ui) {
+        let zoom = ui.input(|i| i.zoom_delta());
+        if zoom != 1.0 {
+            self.grid_cell = (self.grid_cell * zoom).clamp(*GRID_CELL.start(), *GRID_CELL.end());
+        }
+        let len = self.files.len();
+        let count = self.filter.count(len);
+        let spacing = ui.spacing().item_spacing;
+        let cell = self.grid_cell;
+        let columns = (((ui.available_width() + spacing.x) / (cell + spacing.x)) as usize).max(1);
+        self.grid_columns = columns;
+        let rows = count.div_ceil(columns);
+
+        let mut area = egui::ScrollArea::vertical()
+            .id_salt("grid")
+            .auto_shrink([false; 2]);
+        let selected_row = (self.filter.position(self.grid_selected) - 1) / columns;
+        if std::mem::take(&mut self.grid_scroll) && !self.grid_rows.contains(&selected_row) {
+            // bring the selection to the nearest edge of the view
+            let row_height = cell + spacing.y;
+            let visible = self.grid_rows.len().max(1).saturating_sub(1);
+            let top = if selected_row < self.grid_rows.start {
+                selected_row
+            } else {
+                selected_row.saturating_sub(visible)
+            };
+            area = area.vertical_scroll_offset(top as f32 * row_height);
+        }
+        let mut clicked = None;
+        let output = area.show_rows(ui, cell, rows, |ui, range| {
+            self.grid_rows = range.clone();
+            for row in range {
+                ui.horizontal(|ui| {
+                    for col in 0..columns {
+                        let Some(i) = self.filter.nth(row * columns + col, len) else {
+                            break;
+                        };
+                        if row * columns + col >= count {
+                            break;
+                        }
+                        let selected = i == self.grid_selected;
+                        if self.thumbnail(ui, i, cell, selected).clicked() {
+                            clicked = Some(i);
+                        }
+                    }
+                });
+            }
+        });
+        // rows that are only partly visible don't count
+        let fully = (output.inner_rect.height() / (cell + spacing.y)) as usize;
+        self.grid_rows.end = self.grid_rows.end.min(self.grid_rows.start + fully.max(1));
+        if let Some(i) = clicked {
+            self.grid = false;
+            self.goto(i);
+        }
+    }
+
+    // file attributes and EXIF fields of the current file
+    fn info_panel(&mut self, ui: &mut egui::Ui) {
+        let Some(file) = self.current_file() else {
+            ui.weak("No file");
+            return;
+        };
+        let path = file.to_path_buf();
+        let meta = *file.meta();
+        let row = |ui: &mut egui::Ui, label: &str, value: String| {
+            ui.label(label);
+            ui.add(egui::Label::new(value).selectable(true).wrap());
+            ui.end_row();
+        };
+        let ctx = self.ctx.clone();
+        let details = self
+            .details
+            .get_or_insert_with(|| DetailsCache::new(&ctx))
+            .get(&path);
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("info")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    row(ui, "Name", file_name(&path));
+                    let [w, h] = self.image_size;
+                    if w > 0 {
+                        row(ui, "Dimensions", format!("{}×{}", w, h));
+                    }
+                    if let Some(size) = meta.size {
+                        row(ui, "File size", human_size(size));
+                    }
+                    if let Some(modified) = meta.modified {
+                        row(ui, "Modified", format_time(modified));
+                    }
+                    for (label, value) in details.iter().flat_map(|d| &d.fields) {
+                        row(ui, label, value.clone());
+                    }
+                    if let Some((lat, lon)) = details.and_then(|d| d.gps) {
+                        row(ui, "GPS", format!("{:.6}, {:.6}", lat, lon));
+                    }
+                });
+            match details {
+                None => {
+                    ui.spinner();
+                }
+                Some(details) => {
+                    if let Some((lat, lon)) = details.gps {
+                        if ui.button("Copy coordinates").clicked() {
+                            ui.ctx().copy_text(format!("{:.6}, {:.6}", lat, lon));
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    // every bound action with its keys, built from the key bindings
+    fn help_overlay(&mut self, ctx: &egui::Context) {
+        // nearly opaque so it stays readable over bright and dark images alike
+        let fill = ctx.style().visuals.window_fill.gamma_multiply(0.92);
+        egui::Area::new(egui::Id::new("help"))
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).fill(fill).show(ui, |ui| {
+                    ui.horizontal_top(|ui| {
+                        for group in Action::GROUPS {
+                            ui.vertical(|ui| {
+                                ui.heading(group);
+                                egui::Grid::new(group).num_columns(2).show(ui, |ui| {
+                                    for action in
+                                        Action::ALL.into_iter().filter(|a| a.group() == group)
+                                    {
+                                        let keys = self.settings.keys.get(action);
+                                        if keys.is_empty() {
+                                            continue;
+                                        }
+                                        ui.label(action.label());
+                                        let keys: Vec<_> = keys.iter().map(keys::format).collect();
+                                        ui.strong(keys.join(", "));
+                                        ui.end_row();
+                                    }
+                                });
+                            });
+                            ui.add_space(16.0);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Close").clicked() {
+                            self.help_open = false;
+                        }
+                        ui.weak("or press Escape");
+                    });
+                });
+            });
+    }
+
+    // e.g. "1920×1080 • 2.4 MB • 87% zoom • 17/244 • cat_03.jpg"
+    fn status_bar(&mut self, ui: &mut egui::Ui) {
+        let mut parts = Vec::new();
+        if let Some(compare) = &self.compare {
+            let size = match self.page_size(&compare.src) {
+                Some(Some(size)) => format!(" {}×{}", size.x, size.y),
+                _ => String::new(),
+            };
+            parts.push(format!("pinned {}{} ↔", compare.name, size));
+        }
+        let mut chosen_size = None;
+        let [w, h] = self.image_size;
+        if w > 0 {
+            parts.push(format!("{}×{}", w, h));
+        }
+        if let Some(size) = self.current_file().and_then(|p| p.meta().size) {
+            parts.push(human_size(size));
+        }
+        if let Some(file) = self.current_file().map(|p| p.to_path_buf()) {
+            let rating = self.ratings.get(&file);
+            if rating > 0 {
+                parts.push(stars(rating));
+            }
+            if self.is_bookmarked(&file) {
+                parts.push("🔖".to_owned());
+            }
+        }
+        // icon files get a selector of their sizes instead
+        let mut icon_sizes = None;
+        if let Some((page, count)) = self.document.as_ref().and_then(Document::position) {
+            match self.document.as_ref().filter(|d| d.is_icon()) {
+                Some(document) => icon_sizes = Some((page, document.sizes().to_vec())),
+                None => parts.push(format!("page {}/{}", page + 1, count)),
+            }
+        }
+        if w > 0 {
+            let zoom = self.view.zoom(self.viewport, self.image_vec());
+            let fit = match self.view.mode {
+                DisplayMode::Fit(fit) if fit != Fit::Whole => {
+                    format!(", {}", fit.label().to_lowercase())
+                }
+                _ => String::new(),
+            };
+            let locked = if self.lock_zoom { ", locked" } else { "" };
+            parts.push(format!("{:.0}% zoom{}{}", zoom * 100.0, fit, locked));
+        }
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            ui.label(parts.join(" • "));
+            if let Some((shown, sizes)) = &icon_sizes {
+                ui.label(" • ");
+                let size = |[w, h]: [u32; 2]| format!("{}×{} icon", w, h);
+                egui::ComboBox::from_id_salt("icon size")
+                    .selected_text(sizes.get(*shown).map(|&s| size(s)).unwrap_or_default())
+                    .show_ui(ui, |ui| {
+                        for (i, &s) in sizes.iter().enumerate() {
+                            if ui.selectable_label(i == *shown, size(s)).clicked() {
+                                chosen_size = Some(i);
+                            }
+                        }
+                    });
+            }
+            let name = match (&self.detached, self.files.get(self.index)) {
+                (Some(detached), _) => detached.name.clone(),
+                (None, Some(p)) => {
+                    if !parts.is_empty() {
+                        ui.label(" • ");
+                    }
+                    let position = ui
+                        .add(egui::Label::new(self.position()).sense(egui::Sense::click()))
+                        .on_hover_text("Go to image…");
+                    if position.clicked() {
+                        self.goto_input = Some(String::new());
+                    }
+                    file_name(p)
+                }
+                (None, None) => return,
+            };
+            if ui.cursor().min.x > ui.max_rect().min.x {
+                ui.label(" • ");
+            }
+            ui.label(name);
+        });
+        if let Some(index) = chosen_size {
+            self.show_page(index);
+        }
+    }
+
+    // the programs of the right-click menu, returns whether any changed
+    fn commands_page(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        ui.label("{path}, {dir} and {filename} are replaced by the current file, its folder and its name.");
+        let mut removed = None;
+        egui::Grid::new("commands").num_columns(5).show(ui, |ui| {
+            ui.strong("Name");
+            ui.strong("Command");
+            ui.strong("Key");
+            ui.strong("Reload");
+            ui.end_row();
+            for (i, tool) in self.settings.commands.iter_mut().enumerate() {
+                changed |= ui
+                    .add(egui::TextEdit::singleline(&mut tool.name).desired_width(120.0))
+                    .changed();
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut tool.command)
+                            .hint_text("oxipng \"{path}\"")
+                            .desired_width(220.0),
+                    )
+                    .changed();
+                // sequences like `G G` are left to the actions
+                let valid = tool.key.is_empty()
+                    || keys::parse(&tool.key).is_some_and(|b| b.prefix.is_none());
+                let mut key = egui::TextEdit::singleline(&mut tool.key)
+                    .hint_text("none")
+                    .desired_width(80.0);
+                if !valid {
+                    key = key.text_color(ui.visuals().error_fg_color);
+                }
+                changed |= ui
+                    .add(key)
+                    .on_hover_text("A key like Ctrl+Shift+O")
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut tool.refresh, "")
+                    .on_hover_text("Load the image again once the program is done")
+                    .changed();
+                if ui.button("✖").on_hover_text("Remove").clicked() {
+                    removed = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = removed {
+            self.settings.commands.remove(i);
+            changed = true;
+        }
+        if ui.button("Add command").clicked() {
+            self.settings.commands.push(Tool::default());
+            changed = true;
+        }
+        changed
+    }
+
+    // click a key to change it, right-click to remove it
+    fn keys_page(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        if let Some((action, n)) = self.rebinding {
+            let pressed = ui.input(|i| {
+                i.events.iter().find_map(|e| match e {
+                    egui::Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => Some((*key, *modifiers)),
+                    _ => None,
+                })
+            });
+            match pressed {
+                Some((egui::Key::Escape, m)) if m.is_none() => self.rebinding = None,
+                Some((key, m)) => {
+                    let mut modifiers = egui::Modifiers::NONE;
+                    if m.command {
+                        modifiers |= egui::Modifiers::COMMAND;
+                    }
+                    if m.shift {
+                        modifiers |= egui::Modifiers::SHIFT;
+                    }
+                    if m.alt {
+                        modifiers |= egui::Modifiers::ALT;
+                    }
+                    let shortcut = egui::KeyboardShortcut::new(modifiers, key).into();
+                    let keys = self.settings.keys.get_mut(action);
+                    match keys.get_mut(n) {
+                        Some(old) => *old = shortcut,
+                        None => keys.push(shortcut),
+                    }
+                    self.rebinding = None;
+                    changed = true;
+                }
+                None => {}
+            }
+        }
+
+        egui::ScrollArea::vertical()
+            .max_height(320.0)
+            .show(ui, |ui| {
+                egui::Grid::new("keys")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for action in Action::ALL {
+                            ui.label(action.label());
+                            ui.horizontal(|ui| {
+                                let keys = self.settings.keys.get(action).to_vec();
+                                for (n, shortcut) in keys.iter().enumerate() {
+                                    let capturing = self.rebinding == Some((action, n));
+                                    let text = if capturing {
+                                        "Press a key…".to_owned()
+                                    } else {
+                                        keys::format(shortcut)
+                                    };
+                                    let conflicts = self.settings.keys.conflicts(action, shortcut);
+                                    let mut button = egui::Button::new(text).selected(capturing);
+                                    let hint = if conflicts.is_empty() {
+                                        "Click to change, right-click to remove".to_owned()
+                                    } else {
+                                        button = button.stroke((1.0, ui.visuals().error_fg_color));
+                                        let names: Vec<_> =
+                                            conflicts.iter().map(|a| a.label()).collect();
+                                        format!("Also bound to {}", names.join(", "))
+                                    };
+                                    let response = ui.add(button).on_hover_text(hint);
+                                    if response.clicked() {
+                                        self.rebinding = Some((action, n));
+                                    }
+                                    if response.secondary_clicked() {
+                                        self.settings.keys.get_mut(action).remove(n);
+                                        self.rebinding = None;
+                                        changed = true;
+                                    }
+                                }
+                                if self.rebinding == Some((action, keys.len())) {
+                                    ui.label("Press a key…");
+                                } else if ui.small_button("+").on_hover_text("Add a key").clicked()
+                                {
+                                    self.rebinding = Some((action, keys.len()));
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+        if ui.button("Reset to defaults").clicked() {
+            self.settings.keys = keys::Keybindings::default();
+            self.rebinding = None;
+            changed = true;
+        }
+        changed
+    }
+
+    fn save_settings(&mut self) {
+        self.settings_changed = false;
+        if let Err(err) = self.settings.save() {
+            error!("Failed to save settings: {}", err);
+            self.error = Some(format!("Could not save settings: {}", err));
+        }
+    }
+
+    // drop every decoded image and texture, the current one is loaded again
+    fn clear_cache(&mut self) {
+        self.cache.clear(&self.ctx);
+        self.prefetch.clear();
+        if !self.files.is_empty() {
+            self.show_current();
+        }
+    }
+
+    fn toggle_slideshow(&mut self) {
+        self.slideshow = !self.slideshow;
+        self.slideshow_since = self.time;
+    }
+
+    // whether only the image is shown, as chosen or because of fullscreen
+    fn minimal_ui(&self) -> bool {
+        self.settings.minimal_ui || (self.fullscreen && self.settings.fullscreen_minimal)
+    }
+
+    fn toggle_minimal_ui(&mut self) {
+        if self.minimal_ui() {
+            self.settings.minimal_ui = false;
+            // brought back in fullscreen, they stay there the next time too
+            if self.fullscreen {
+                self.settings.fullscreen_minimal = false;
+            }
+        } else {
+            self.settings.minimal_ui = true;
+        }
+        self.toolbar_revealed = false;
+        self.settings_changed = true;
+    }
+
+    // for a reference image over a drawing: above other windows, without a frame, see-through
+    fn window_menu(&mut self, ui: &mut egui::Ui) {
+        let on_top = egui::Checkbox::new(&mut self.settings.always_on_top, "Always on top");
+        let on_top = ui.add(on_top);
+        let borderless = egui::Checkbox::new(&mut self.settings.borderless, "Borderless");
+        let borderless = ui
+            .add(borderless)
+            .on_hover_text("Alt and drag the image to move the window, drag its edges to resize");
+        if on_top.changed() || borderless.changed() {
+            self.apply_window_style();
+        }
+        ui.horizontal(|ui| {
+            ui.label("Opacity");
+            let opacity = egui::Slider::new(&mut self.settings.opacity, 0.1..=1.0)
+                .custom_formatter(|v, _| format!("{:.0}%", v * 100.0));
+            self.settings_changed |= ui
+                .add(opacity)
+                .on_hover_text("Of the image area, where the system supports it")
+                .changed();
+        });
+    }
+
+    fn apply_window_style(&mut self) {
+        let level = if self.settings.always_on_top {
+            egui::WindowLevel::AlwaysOnTop
+        } else {
+            egui::WindowLevel::Normal
+        };
+        self.ctx
+            .send_viewport_cmd(egui::ViewportCommand::WindowLevel(level));
+        self.ctx
+            .send_viewport_cmd(egui::ViewportCommand::Decorations(
+                !self.settings.borderless,
+            ));
+        self.settings_changed = true;
+    }
+
+    // a window without a frame is moved with Alt and a drag, and resized at its edges
+    fn move_frameless(&self, ctx: &egui::Context) {
+        let (pos, pressed, alt, maximized) = ctx.input(|i| {
+            (
+                i.pointer.hover_pos(),
+                i.pointer.primary_pressed(),
+                i.modifiers.alt,
+                i.viewport().maximized == Some(true),
+            )
+        });
+        let Some(pos) = pos else {
+            return;
+        };
+        match window::edge(ctx.content_rect(), pos).filter(|_| !maximized) {
+            Some(direction) => {
+                ctx.set_cursor_icon(window::resize_cursor(direction));
+                if pressed {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::BeginResize(direction));
+                }
+            }
+            None if pressed && alt => ctx.send_viewport_cmd(egui::ViewportCommand::StartDrag),
+            None => {}
+        }
+    }
+
+    fn set_fullscreen(&mut self, ctx: &egui::Context, fullscreen: bool) {
+        self.fullscreen = fullscreen;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(fullscreen));
+    }
+
+    fn open_dir(&mut self, dir: &Path) {
+        self.leave_dir(dir);
+        self.remember_dir(dir);
+        self.scan_dir(dir, None);
+    }
+
+    // put `dir` on top of the recent directories
+    fn remember_dir(&mut self, dir: &Path) {
+        if self.settings.recent_dirs.first().map(PathBuf::as_path) == Some(dir) {
+            return;
+        }
+        self.settings.add_recent_dir(dir);
+        if let Err(err) = self.settings.save() {
+            warn!("Failed to save settings: {}", err);
+        }
+    }
+
+    // forget what belongs to the previous directory when switching to `dir`
+    fn leave_dir(&mut self, dir: &Path) {
+        self.playlist = None;
+        if self.dir.as_deref() != Some(dir) {
+            // the next folder holds other kinds of images
+            self.lock_zoom = false;
+            self.undo.clear();
+            self.bag.clear();
+            self.history.clear();
+        }
+    }
+
+    fn scan_options(&self) -> ScanOptions {
+        ScanOptions {
+            recursive: self.recursive,
+            hidden: self.settings.show_hidden,
+            extensions: if self.settings.raw {
+                let raw = raw::EXTENSIONS.iter().map(|e| e.to_string());
+                self.settings
+                    .extensions
+                    .iter()
+                    .cloned()
+                    .chain(raw)
+                    .collect()
+            } else {
+                self.settings.extensions.clone()
+            },
+            sniff: self.settings.sniff,
+        }
+    }
+
+    // read the open directory again, staying on the current file if it is still there
+    fn reopen_dir(&mut self) {
+        let Some(dir) = self.dir.clone() else {
+            return;
+        };
+        let current = self.current_src.as_deref().and_then(src_path);
+        self.scan_dir(&dir, current);
+    }
+
+    // open a directory, or a file together with its siblings
+    fn open_path(&mut self, path: &Path) -> Result<(), String> {
+        if path.is_dir() {
+            self.open_dir(path);
+            Ok(())
+        } else if path.is_file() && archive::is_archive(path) {
+            self.open_archive(path)
+        } else if path.is_file() {
+            self.open_file(path)
+        } else {
+            Err(format!("No such file or directory: {}", path.display()))
+        }
+    }
+
+    // open `file` together with the other images in its directory
+    fn open_file(&mut self, file: &Path) -> Result<(), String> {
+        if archive::is_archive(file) {
+            return self.open_archive(file);
+        }
+        if playlist::is_playlist(file) {
+            return self.open_playlist(file);
+        }
+        let dir = file
+            .parent()
+            .ok_or_else(|| format!("Could not get parent directory of {:?}", file))?;
+        self.leave_dir(dir);
+
+        if self.scan_options().accepts(file) {
+            self.remember_dir(dir);
+            self.scan_dir(dir, Some(file.to_path_buf()));
+        } else {
+            // not a supported extension: view it on its own
+            self.scan = None;
+            self.rescan = None;
+            self.watch = None;
+            self.dir = None;
+            self.files = vec![PathSortable::from(file.to_path_buf())];
+            self.filter.update(&self.files);
+            self.index = 0;
+            self.show_current();
+        }
+        info!("Opened file: {:?}", file);
+        Ok(())
+    }
+
+    // list the images inside a ZIP or CBZ file, subdirectories flattened in the order of their paths
+    fn open_archive(&mut self, file: &Path) -> Result<(), String> {
+        let options = self.scan_options();
+        let mut files: Vec<PathSortable> = archive::list(file, |name| options.accepts(name))?
+            .into_iter()
+            .map(PathSortable::from)
+            .collect();
+        if files.is_empty() {
+            return Err(format!("No images in {}", file_name(file)));
+        }
+        sort(&mut files, SortKey::Name, false);
+        self.leave_dir(file);
+        self.scan = None;
+        self.rescan = None;
+        self.watch = None;
+        self.dir = None;
+        // pages are read in order
+        self.randomize = false;
+        self.archive = Some(file.to_path_buf());
+        self.files = files;
+        self.filter.update(&self.files);
+        self.index = 0;
+        self.show_current();
+        info!("Opened archive: {:?}", file);
+        Ok(())
+    }
+
+    // the files of a playlist in its order, entries that are gone are left out
+    fn open_playlist(&mut self, file: &Path) -> Result<(), String> {
+        let entries = playlist::read(file)?;
+        let name = file_name(file);
+        self.open_list(&name, entries);
+        info!("Opened playlist: {:?}", file);
+        Ok(())
+    }
+
+    // `files` as the list in this order, named `name` in the title
+    fn open_list(&mut self, name: &str, entries: Vec<PathBuf>) {
+        let total = entries.len();
+        let files: Vec<PathSortable> = entries
+            .into_iter()
+            .filter(|p| p.is_file())
+            .map(PathSortable::from)
+            .collect();
+        let missing = total - files.len();
+        if missing > 0 {
+            warn!("{} entries of {} are missing", missing, name);
+            let entries = if missing == 1 { "entry" } else { "entries" };
+            self.push_toast(Level::Warn, format!("{} {} missing", missing, entries));
+        }
+        self.leave_dir(Path::new(name));
+        self.scan = None;
+        self.rescan = None;
+        self.watch = None;
+        self.dir = None;
+        self.archive = None;
+        self.detached = None;
+        // the order is what the list is for
+        self.randomize = false;
+        self.playlist = Some(name.to_owned());
+        self.files = files;
+        self.filter.update(&self.files);
+        self.index = 0;
+        match self.filter.nearest(0, self.files.len()) {
+            Some(i) => {
+                self.index = i;
+                self.show_current();
+            }
+            None => self.current_src = None,
+        }
+    }
+
+    // files opened with another start of the program, shown here with the window brought up
+    fn poll_instance(&mut self, ctx: &egui::Context) {
+        let Some(paths) = self
+            .instance
+            .as_ref()
+            .and_then(|i| i.opened.try_recv().ok())
+        else {
+            return;
+        };
+        info!("Opening {:?} from another instance", paths);
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        // where the system doesn't let a window take the focus, it can at least flash
+        ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(
+            egui::UserAttentionType::Informational,
+        ));
+        self.open_paths(paths, false);
+    }
+
+    // the list piped in, in its order unless --random asked for a shuffle
+    fn poll_stdin(&mut self) {
+        let Some((list, shuffle)) = &self.stdin else {
+            return;
+        };
+        let shuffle = *shuffle;
+        match list.try_recv() {
+            Ok(files) => {
+                self.stdin = None;
+                self.open_list("stdin", files);
+                if shuffle {
+                    self.randomize = true;
+                    self.reshuffle();
+                }
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => self.stdin = None,
+        }
+    }
+
+    // write the list in its current order, shuffled or not, to a playlist file
+    fn save_playlist(&mut self) {
+        let files: Vec<PathBuf> = self.files.iter().map(|p| p.to_path_buf()).collect();
+        if files.is_empty() {
+            return;
+        }
+        let name = match &self.playlist {
+            Some(name) => name.clone(),
+            None => "playlist.m3u".to_owned(),
+        };
+        let Some(path) = self
+            .file_dialog()
+            .set_file_name(name)
+            .add_filter("Playlist", &playlist::EXTENSIONS)
+            .save_file()
+        else {
+            return;
+        };
+        match playlist::write(&path, &files) {
+            Ok(()) => {
+                info!("Saved {} files to {:?}", files.len(), path);
+                self.push_toast(Level::Info, format!("Saved {}", short_display(&path)));
+            }
+            Err(err) => {
+                error!("Failed to save {:?}: {}", path, err);
+                self.error = Some(format!("Could not save {}: {}", path.display(), err));
+            }
+        }
+    }
+
+    // start reading `dir` in the background, `keep` is one of its files that is displayed meanwhile
+    fn scan_dir(&mut self, dir: &Path, keep: Option<PathBuf>) {
+        self.archive = None;
+        self.files = keep.iter().cloned().map(PathSortable::from).collect();
+        // the file that was asked for is shown even if the filter hides it
+        self.filter.update(&self.files);
+        self.index = 0;
+        if self.files.is_empty() {
+            self.current_src = None;
+        } else {
+            self.show_current();
+        }
+        self.dir = Some(dir.to_path_buf());
+        self.watch = match DirWatch::start(&self.ctx, dir, self.recursive) {
+            Ok(watch) => Some(watch),
+            Err(err) => {
+                warn!("Not watching {:?} for changes: {}", dir, err);
+                None
+            }
+        };
+        // replacing a running scan cancels it
+        self.rescan = None;
+        self.scan = Some(Scan::start(&self.ctx, dir, self.scan_options(), keep));
+    }
+
+    fn poll_scan(&mut self) {
+        let Some(mut scan) = self.scan.take() else {
+            return;
+        };
+        for event in scan.poll() {
+            match event {
+                ScanEvent::Found(batch) => self.add_scanned(&mut scan, batch),
+                ScanEvent::Failed(err) => {
+                    error!("Failed to open {}: {}", scan.label, err);
+                    self.push_toast(
+                        Level::Error,
+                        format!("Could not open {}: {}", scan.label, err),
+                    );
+                    return;
+                }
+                ScanEvent::Done => {
+                    self.finish_scan(scan);
+                    return;
+                }
+            }
+        }
+        self.scan = Some(scan);
+    }
+
+    // add the files that appeared in the watched directory and drop the ones that vanished
+    fn poll_watch(&mut self) {
+        // changes wait until the scan is done, it may or may not have seen them
+        if self.scan.is_some() {
+            return;
+        }
+        let (Some(changed), Some(dir)) = (
+            self.watch.as_mut().and_then(DirWatch::poll),
+            self.dir.clone(),
+        ) else {
+            return;
+        };
+        let options = self.scan_options();
+        let listed: HashSet<&Path> = self.files.iter().map(|p| p.as_path()).collect();
+        let (listed, unlisted): (Vec<_>, Vec<_>) = changed
+            .into_iter()
+            .partition(|p| listed.contains(p.as_path()));
+        let gone: HashSet<PathBuf> = listed.into_iter().filter(|p| !p.is_file()).collect();
+        let added: Vec<PathBuf> = unlisted
+            .into_iter()
+            .filter(|p| {
+                let hidden = p
+                    .strip_prefix(&dir)
+                    .is_ok_and(|rel| rel.iter().any(|c| c.to_string_lossy().starts_with('.')));
+                (options.hidden || !hidden) && p.is_file() && options.accepts(p)
+            })
+            .collect();
+        if gone.is_empty() && added.is_empty() {
+            return;
+        }
+        info!(
+            "{:?} changed: {} files added, {} removed",
+            dir,
+            added.len(),
+            gone.len()
+        );
+        self.merge(gone, added, self.settings.follow_new);
+    }
+
+    // finish a rescan of the open directory started with Shift+F5
+    fn poll_rescan(&mut self) {
+        let Some((mut scan, mut found)) = self.rescan.take() else {
+            return;
+        };
+        for event in scan.poll() {
+            match event {
+                ScanEvent::Found(batch) => found.extend(batch.iter().map(|p| p.to_path_buf())),
+                ScanEvent::Failed(err) => {
+                    error!("Failed to rescan {}: {}", scan.label, err);
+                    self.push_toast(
+                        Level::Error,
+                        format!("Could not read {} again: {}", scan.label, err),
+                    );
+                    return;
+                }
+                ScanEvent::Done => {
+                    let found: HashSet<PathBuf> = found.into_iter().collect();
+                    let gone: HashSet<PathBuf> = self
+                        .files
+                        .iter()
+                        .filter(|p| !found.contains(p.as_path()))
+                        .map(|p| p.to_path_buf())
+                        .collect();
+                    let listed: HashSet<&Path> = self.files.iter().map(|p| p.as_path()).collect();
+                    let added: Vec<PathBuf> = found
+                        .iter()
+                        .filter(|p| !listed.contains(p.as_path()))
+                        .cloned()
+                        .collect();
+                    let summary = format!("+{} new, −{} removed", added.len(), gone.len());
+                    info!("Rescanned {}: {}", scan.label, summary);
+                    self.merge(gone, added, false);
+                    self.push_toast(Level::Info, summary);
+                    return;
+                }
+            }
+        }
+        self.rescan = Some((scan, found));
+    }
+
+    // read the open directory again without starting over: new files are merged into the list in
+    // its current order and missing ones dropped
+    fn rescan(&mut self) {
+        let Some(dir) = self.dir.clone() else {
+            return;
+        };
+        if self.scan.is_some() {
+            return;
+        }
+        let scan = Scan::start(&self.ctx, &dir, self.scan_options(), None);
+        self.rescan = Some((scan, Vec::new()));
+    }
+
+    // drop the files in `gone` and add the ones in `added`, moving on to the next file if the
+    // displayed one is gone, or to the newest added one with `follow`
+    fn merge(&mut self, gone: HashSet<PathBuf>, added: Vec<PathBuf>, follow: bool) {
+        if gone.is_empty() && added.is_empty() {
+            return;
+        }
+        let current = self.current_src.as_deref().and_then(src_path);
+        let vanished = current.as_ref().is_some_and(|c| gone.contains(c));
+        // where the list continues if the current file is gone
+        let after = self.files[..self.index.min(self.files.len())]
+            .iter()
+            .filter(|p| !gone.contains(p.as_path()))
+            .count();
+        for path in &gone {
+            self.ctx.forget_image(&self.src_of(path));
+        }
+        self.files.retain(|p| !gone.contains(p.as_path()));
+        let newest = added
+            .iter()
+            .max_by_key(|p| p.metadata().and_then(|m| m.modified()).ok())
+            .cloned();
+        // in random order the new files join the part of the cycle that wasn't shown yet
+        self.files.extend(added.into_iter().map(PathSortable::from));
+        if !self.randomize {
+            self.sort_files();
+        }
+
+        match newest.filter(|_| follow && self.detached.is_none()) {
+            Some(newest) => {
+                self.filter.update(&self.files);
+                if let Some(i) = self.files.iter().position(|p| p.deref() == &newest) {
+                    self.goto(i);
+                }
+            }
+            None if vanished || self.current_src.is_none() => {
+                self.filter.update(&self.files);
+                self.show_near(after);
+            }
+            None => self.reindex(),
+        }
+    }
+
+    // reload the displayed file when it is saved, the watch follows navigation
+    fn poll_file_watch(&mut self) {
+        let Some(path) = self.current_file().map(|p| p.to_path_buf()) else {
+            self.file_watch = None;
+            return;
+        };
+        match &mut self.file_watch {
+            Some(watch) if watch.path() == path => {
+                if watch.changed() {
+                    info!("{:?} changed on disk", path);
+                    self.reload();
+                }
+            }
+            _ => {
+                self.file_watch = match FileWatch::start(&self.ctx, &path) {
+                    Ok(watch) => Some(watch),
+                    Err(err) => {
+                        warn!("Not watching {:?} for changes: {}", path, err);
+                        None
+                    }
+                };
+            }
+        }
+    }
+
+    // read the displayed file again, keeping zoom and pan
+    fn reload(&mut self) {
+        let Some(src) = self.current_src.clone() else {
+            return;
+        };
+        self.ctx.forget_image(&src);
+        if let Some(path) = src_path(&src) {
+            self.cache.visit(&self.ctx, &src, &path);
+        }
+        self.start_load(&src);
+        self.error = None;
+        self.failed_since = None;
+        self.reloaded_at = Some(self.time);
+        // counted again from the new bytes
+        self.pixels = None;
+        self.diff = None;
+        self.animation = None;
+        if let Some(document) = self.document.as_mut().filter(|d| d.src() == src) {
+            self.ctx.forget_image(&document.uri());
+            document.recount();
+        }
+    }
+
+    // the URL of the page of `src` that is shown, the first one of a file just opened
+    fn page_src(&mut self, src: &str) -> String {
+        if self.document.as_ref().is_none_or(|d| d.src() != src) {
+            // the cache only keeps the files themselves
+            if let Some(document) = self.document.take() {
+                self.forget_page(&document.uri());
+            }
+            self.document = Some(Document::new(src));
+        }
+        let document = self.document.as_mut().expect("document was just set");
+        let counted = document.poll(&self.ctx);
+        let uri = document.uri();
+        if counted && document.is_icon() && !self.lock_zoom {
+            self.icon_view();
+        }
+        uri
+    }
+
+    fn turn_page(&mut self, forward: bool) {
+        let shown = self.document.as_mut().and_then(|d| d.turn(forward));
+        self.page_shown(shown);
+    }
+
+    fn show_page(&mut self, index: usize) {
+        let shown = self.document.as_mut().and_then(|d| d.show(index));
+        self.page_shown(shown);
+    }
+
+    // another page replaced `shown`, if any
+    fn page_shown(&mut self, shown: Option<String>) {
+        let Some(shown) = shown else {
+            return;
+        };
+        self.forget_page(&shown);
+        self.image_size = [0, 0];
+        self.error = None;
+        self.failed_since = None;
+        if !self.lock_zoom {
+            self.view.reset();
+            if self.document.as_ref().is_some_and(Document::is_icon) {
+                self.icon_view();
+            }
+        }
+    }
+
+    // icons are drawn for their exact size, so they start out pixel for pixel
+    fn icon_view(&mut self) {
+        self.view.mode = DisplayMode::ActualSize;
+        self.view.moved = true;
+    }
+
+    // drop the texture of a page other than the first, which is left to the cache
+    fn forget_page(&self, uri: &str) {
+        if pages::split(uri).is_some() {
+            self.ctx.forget_image(uri);
+        }
+    }
+
+    fn add_scanned(&mut self, scan: &mut Scan, mut batch: Vec<PathSortable>) {
+        if let Some(keep) = &scan.keep {
+            let len = batch.len();
+            batch.retain(|p| p.deref() != keep);
+            scan.keep_found |= batch.len() != len;
+        }
+        if self.randomize {
+            use rand::seq::SliceRandom;
+            let mut rng = rand::rng();
+            batch.shuffle(&mut rng);
+        }
+
+        // start viewing as soon as the first visible files arrive, later batches don't move the index
+        self.files.extend(batch);
+        self.filter.update(&self.files);
+        if self.current_src.is_none() {
+            if let Some(i) = self.filter.nearest(0, self.files.len()) {
+                self.index = i;
+                self.show_current();
+            }
+        }
+    }
+
+    fn finish_scan(&mut self, scan: Scan) {
+        if let Some(keep) = scan.keep.as_ref().filter(|_| !scan.keep_found) {
+            // the displayed file is not part of the directory (anymore)
+            self.files.retain(|p| p.deref() != keep);
+        }
+        // dropped items can overlap, e.g. a folder and a file inside it
+        let mut seen = std::collections::HashSet::new();
+        self.files.retain(|p| seen.insert(p.to_path_buf()));
+
+        if self.files.is_empty() {
+            error!("Failed to open {}: No image files found", scan.label);
+            self.current_src = None;
+            self.push_toast(Level::Warn, format!("No image files in {}", scan.label));
+            return;
+        }
+
+        // order the complete list once, staying on the image that is currently displayed
+        let current = self.current_src.as_deref().and_then(src_path);
+        if self.randomize {
+            self.shuffle_files();
+        } else {
+            self.sort_files();
+        }
+        // sidecars of the files found since the rating filter was chosen
+        if let Some(min) = self.filter.rated.as_ref().map(|(min, _)| *min) {
+            self.filter.rated = Some((min, self.rated(min)));
+        }
+        self.filter.update(&self.files);
+        match current.and_then(|cur| self.files.iter().position(|p| p.deref() == &cur)) {
+            Some(i) => self.index = i,
+            None => match self.filter.nearest(0, self.files.len()) {
+                Some(i) => {
+                    self.index = i;
+                    self.show_current();
+                }
+                None => self.current_src = None,
+            },
+        }
+
+        info!(
+            "Opened {}, {} image files detected.",
+            scan.label,
+            self.files.len()
+        );
+    }
+
+    // shuffle the files not shown yet in this cycle, staying on the current one
+    fn shuffle_files(&mut self) {
+        let current = self.current_src.as_deref().and_then(src_path);
+        self.bag.arrange(&mut self.files, current.as_deref());
+    }
+
+    // start a new cycle now, the current image counts as its first
+    fn reshuffle(&mut self) {
+        self.bag.clear();
+        self.shuffle_files();
+        self.reindex();
+        if let Some(p) = self.current_file() {
+            let p = p.to_path_buf();
+            self.bag.visit(&p);
+        }
+    }
+
+    // order `files` by the chosen key, waiting for the capture dates in the background if needed
+    fn sort_files(&mut self) {
+        if self.sort == SortKey::Taken {
+            let missing = self
+                .files
+                .iter()
+                .filter(|p| !p.has_taken())
+                .map(|p| p.to_path_buf())
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                self.dates = Some(DateScan::start(&self.ctx, missing));
+                return;
+            }
+        }
+        self.dates = None;
+        sort(&mut self.files, self.sort, self.descending);
+    }
+
+    fn poll_dates(&mut self) {
+        let Some(dates) = self.dates.as_ref().and_then(|d| d.poll()) else {
+            return;
+        };
+        self.dates = None;
+        let dates = dates
+            .into_iter()
+            .collect::<std::collections::HashMap<_, _>>();
+        for p in &self.files {
+            if let Some(date) = dates.get(p.deref()) {
+                p.set_taken(*date);
+            }
+        }
+        if !self.randomize && !self.files.is_empty() {
+            // files added since the dates were requested are read on demand
+            sort(&mut self.files, self.sort, self.descending);
+            self.reindex();
+        }
+    }
+
+    // starts in the open directory, or the one of the last session
+    fn file_dialog(&self) -> rfd::FileDialog {
+        let dialog = rfd::FileDialog::new();
+        match self.dir.as_ref().or(self.session.last_dir.as_ref()) {
+            Some(dir) => dialog.set_directory(dir),
+            None => dialog,
+        }
+    }
+
+    // open the directory of the last session again, on the file that was shown
+    fn reopen_session(&mut self) {
+        let Some(dir) = self.session.last_dir.clone() else {
+            return;
+        };
+        if !dir.is_dir() {
+            warn!("Last directory {:?} no longer exists", dir);
+            self.push_toast(
+                Level::Warn,
+                format!("{} no longer exists", short_display(&dir)),
+            );
+            self.session.last_dir = None;
+            self.session.last_file = None;
+            return;
+        }
+        let file = self.session.last_file.clone().filter(|f| f.is_file());
+        self.leave_dir(&dir);
+        self.remember_dir(&dir);
+        self.scan_dir(&dir, file);
+        info!("Reopened directory: {:?}", dir);
+    }
+
+    // remember where this session stopped, an empty window keeps the previous state
+    fn save_session(&mut self) {
+        self.session.randomize = Some(self.randomize);
+        self.session.fit = self.view.fit;
+        self.session.window = self.window;
+        if let Some(dir) = &self.dir {
+            self.session.last_dir = Some(dir.clone());
+            self.session.last_file = self.current_file().map(|p| p.to_path_buf());
+        }
+        if let Err(err) = self.session.save() {
+            warn!("Failed to save session state: {}", err);
+        }
+    }
+
+    fn pick_file(&mut self) {
+        if let Some(file) = self.file_dialog().pick_file() {
+            if let Err(err) = self.open_file(&file) {
+                error!("Failed to open file: {}", err);
+                self.push_toast(
+                    Level::Error,
+                    format!("Could not open {}: {}", file_name(&file), err),
+                );
+            }
+        }
+    }
+
+    // a single item opens like Open file/directory, several become a list of their own,
+    // holding Ctrl appends them to the current list instead
+    fn open_dropped_path(&mut self, dropped: Vec<DroppedFile>, append: bool) {
+        let mut paths = Vec::new();
+        for f in dropped {
+            match f.path {
+                Some(path) => paths.push(path),
+                None => {
+                    warn!("Dropped file has no path: {:?}", f.name);
+                    self.push_toast(
+                        Level::Warn,
+                        "A dropped file without a path can't be opened".to_owned(),
+                    );
+                }
+            }
+        }
+        self.open_paths(paths, append);
+    }
+
+    fn open_paths(&mut self, paths: Vec<PathBuf>, append: bool) {
+        match paths.as_slice() {
+            [] => {}
+            [dir] if !append && dir.is_dir() => self.open_dir(&dir.clone()),
+            [file] if !append => {
+                // display the file along with its siblings
+                if let Err(err) = self.open_file(&file.clone()) {
+                    error!("Failed to open {:?}: {}", file, err);
+                    self.push_toast(
+                        Level::Error,
+                        format!("Could not open {}: {}", file_name(file), err),
+                    );
+                }
+            }
+            _ => {
+                if !append {
+                    self.undo.clear();
+                    self.playlist = None;
+                    self.files.clear();
+                    self.filter.update(&self.files);
+                    self.index = 0;
+                    self.current_src = None;
+                    self.detached = None;
+                }
+                // a list has no directory to read again
+                self.dir = None;
+                self.watch = None;
+                self.rescan = None;
+                self.scan = Some(Scan::start_list(&self.ctx, paths, self.scan_options()));
+            }
+        }
+    }
+
+    // forward through the history, or on to the next file
+    fn next(&mut self) {
+        if self.detached.is_none() {
+            while let Some(path) = self.history.forward().map(Path::to_path_buf) {
+                if self.show_visible(&path) {
+                    return;
+                }
+            }
+        }
+        self.step(1);
+    }
+
+    // back through the history, or on to the previous file once it is exhausted
+    fn prev(&mut self) {
+        if self.detached.is_some() {
+            self.step(-1);
+            return;
+        }
+        while let Some(path) = self.history.back().map(Path::to_path_buf) {
+            if self.show_visible(&path) {
+                return;
+            }
+        }
+        if let Some(i) = self.filter.step(self.index, -1, self.files.len()) {
+            self.history.visit_before(&self.files[i]);
+            self.goto(i);
+        }
+    }
+
+    // show the file at `index`, every jump within the list ends up here
+    fn goto(&mut self, index: usize) {
+        if index < self.files.len() {
+            self.index = index;
+            self.show_current();
+        }
+    }
+
+    // the next or previous sibling directory, or subdirectory of the list in recursive mode
+    fn step_folder(&mut self, forward: bool) {
+        if self.recursive {
+            self.step_subdir(forward);
+            return;
+        }
+        let Some(dir) = self.dir.clone() else {
+            return;
+        };
+        match sibling(&dir, forward, &self.scan_options()) {
+            Some(next) => {
+                self.open_dir(&next);
+                self.push_toast(Level::Info, file_name(&next));
+            }
+            None if forward => self.push_toast(Level::Info, "no next folder".to_owned()),
+            None => self.push_toast(Level::Info, "no previous folder".to_owned()),
+        }
+    }
+
+    // the first visible file of the next or previous directory in the list
+    fn step_subdir(&mut self, forward: bool) {
+        let len = self.files.len();
+        if len == 0 {
+            return;
+        }
+        let parent = |pos: usize| {
+            self.filter
+                .nth(pos, len)
+                .and_then(|i| self.files[i].parent())
+        };
+        // first position of the directory the file at `pos` is in
+        let start = |mut pos: usize| {
+            while pos > 0 && parent(pos - 1) == parent(pos) {
+                pos -= 1;
+            }
+            pos
+        };
+        let pos = self.filter.position(self.index) - 1;
+        let target = if forward {
+            (pos + 1..self.filter.count(len)).find(|&p| parent(p) != parent(pos))
+        } else {
+            Some(start(pos)).filter(|&p| p > 0).map(|p| start(p - 1))
+        };
+        let Some(i) = target.and_then(|p| self.filter.nth(p, len)) else {
+            let edge = if forward { "last" } else { "first" };
+            self.push_toast(Level::Info, format!("already in the {} folder", edge));
+            return;
+        };
+        let name = match (self.files[i].parent(), &self.dir) {
+            (Some(parent), Some(dir)) if parent != dir => parent
+                .strip_prefix(dir)
+                .unwrap_or(parent)
+                .display()
+                .to_string(),
+            _ => self.dir.as_deref().map(file_name).unwrap_or_default(),
+        };
+        self.goto(i);
+        self.push_toast(Level::Info, name);
+    }
+
+    // any visible file, preferring those not shown yet in this session
+    fn random_jump(&mut self) {
+        use rand::seq::IndexedRandom;
+        let visible: Vec<usize> = (0..self.files.len())
+            .filter(|&i| self.filter.contains(i) && i != self.index)
+            .collect();
+        let unseen: Vec<usize> = visible
+            .iter()
+            .copied()
+            .filter(|&i| !self.visited.contains(self.files[i].deref()))
+            .collect();
+        let pool = if unseen.is_empty() { &visible } else { &unseen };
+        if let Some(&i) = pool.choose(&mut rand::rng()) {
+            self.goto(i);
+            self.push_toast(Level::Info, format!("jumped to {}", self.position()));
+        }
+    }
+
+    // a 1-based position, clamped to the visible files, or part of a file name searched after the
+    // current file
+    fn goto_text(&mut self, text: &str) {
+        let len = self.files.len();
+        let target = match text.parse::<usize>() {
+            Ok(n) => self.filter.nth(n.saturating_sub(1), len),
+            Err(_) => {
+                let needle = text.to_lowercase();
+                (1..=self.filter.count(len))
+                    .filter_map(|d| self.filter.step(self.index, d as isize, len))
+                    .find(|&i| file_name(&self.files[i]).to_lowercase().contains(&needle))
+            }
+        };
+        match target {
+            Some(i) => self.goto(i),
+            None => self.push_toast(Level::Info, format!("No file name contains \"{}\"", text)),
+        }
+    }
+
+    fn goto_dialog(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut submit = None;
+        let count = self.filter.count(self.files.len());
+        let Some(input) = &mut self.goto_input else {
+            return;
+        };
+        egui::Window::new("Go to image")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let edit = ui.add(
+                    egui::TextEdit::singleline(input)
+                        .hint_text(format!("1–{} or part of a name", thousands(count)))
+                        .desired_width(240.0),
+                );
+                edit.request_focus();
+                if edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    submit = Some(input.trim().to_owned());
+                }
+            });
+        if let Some(text) = submit {
+            self.goto_input = None;
+            if !text.is_empty() {
+                self.goto_text(&text);
+            }
+        } else if !open || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.goto_input = None;
+        }
+    }
+
+    // show `path` if it is still in the list and matches the filter
+    fn show_visible(&mut self, path: &Path) -> bool {
+        match self.files.iter().position(|p| p.deref() == path) {
+            Some(i) if self.filter.contains(i) => {
+                self.goto(i);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // move `delta` visible files forward, wrapping around at the ends
+    fn step(&mut self, delta: isize) {
+        // leave a pasted image or URL for the list it replaced
+        let delta = if self.detached.is_some() { 0 } else { delta };
+        let len = self.files.len();
+        let Some(i) = self.filter.step(self.index, delta, len) else {
+            return;
+        };
+        if !(self.randomize && delta > 0 && i <= self.index) {
+            self.goto(i);
+            return;
+        }
+        // every file was visited, the next cycle gets a new order
+        let last = self.files[self.index].to_path_buf();
+        self.bag.reshuffle(&mut self.files, Some(&last));
+        self.filter.update(&self.files);
+        if let Some(mut i) = self.filter.nearest(0, len) {
+            if self.files[i].deref() == &last {
+                i = self.filter.step(i, 1, len).unwrap_or(i);
+            }
+            self.goto(i);
+        }
+    }
+
+    // the filter text changed
+    fn apply_filter(&mut self) {
+        let active = !self.filter.pattern.trim().is_empty() || self.filter.rated.is_some();
+        if active && self.before_filter.is_none() {
+            self.before_filter = self.current_file().map(|p| p.to_path_buf());
+        }
+        self.filter.update(&self.files);
+        if self.detached.is_some() {
+            return;
+        }
+
+        let mut index = self.filter.nearest(self.index, self.files.len());
+        if !active {
+            // back to the image from before filtering
+            if let Some(before) = self.before_filter.take() {
+                index = self
+                    .files
+                    .iter()
+                    .position(|p| p.deref() == &before)
+                    .or(index);
+            }
+        }
+        match index {
+            Some(i) if i != self.index || self.current_src.is_none() => {
+                self.index = i;
+                self.show_current();
+            }
+            Some(_) => {}
+            None => self.current_src = None,
+        }
+    }
+
+    // only show files with at least `min` stars, all of them again at 0
+    fn filter_rating(&mut self, min: u8) {
+        self.filter.rated = (min > 0).then(|| (min, self.rated(min)));
+        self.apply_filter();
+    }
+
+    fn rated(&mut self, min: u8) -> HashSet<PathBuf> {
+        self.ratings
+            .at_least(min, self.files.iter().map(|p| p.as_path()))
+    }
+
+    // give the current file `rating` stars, 0 takes them away
+    fn rate_current(&mut self, rating: u8) {
+        let Some(file) = self
+            .current_file()
+            .filter(|p| p.is_file())
+            .map(|p| p.to_path_buf())
+        else {
+            return;
+        };
+        if let Err(err) = self.ratings.set(&file, rating) {
+            error!("Failed to rate {:?}: {}", file, err);
+            self.push_toast(Level::Error, format!("Could not save the rating: {}", err));
+            return;
+        }
+        info!("Rated {:?} with {} stars", file, rating);
+        self.push_toast(
+            Level::Info,
+            match rating {
+                0 => "Rating cleared".to_owned(),
+                n => stars(n),
+            },
+        );
+        // a file rated below the filter is hidden like one renamed out of it
+        if let Some((min, rated)) = &mut self.filter.rated {
+            if rating >= *min {
+                rated.insert(file);
+            } else {
+                rated.remove(&file);
+            }
+            self.apply_filter();
+        }
+    }
+
+    // bookmarks belong to the folder or archive of the list, dropped files to the folder of the
+    // first one
+    fn bookmark_folder(&self) -> Option<PathBuf> {
+        self.dir
+            .clone()
+            .or_else(|| self.archive.clone())
+            .or_else(|| self.files.first()?.parent().map(Path::to_path_buf))
+    }
+
+    fn bookmarked(&self) -> &[PathBuf] {
+        match self.bookmark_folder() {
+            Some(folder) => self.session.bookmarks.marked(&folder),
+            None => &[],
+        }
+    }
+
+    fn is_bookmarked(&self, file: &Path) -> bool {
+        self.bookmark_folder()
+            .is_some_and(|folder| self.session.bookmarks.contains(&folder, file))
+    }
+
+    fn toggle_bookmark(&mut self) {
+        let (Some(folder), Some(file)) = (
+            self.bookmark_folder(),
+            self.current_file().map(|p| p.to_path_buf()),
+        ) else {
+            return;
+        };
+        let added = self.session.bookmarks.toggle(&folder, &file);
+        info!(
+            "{} {:?}",
+            if added { "Bookmarked" } else { "Unbookmarked" },
+            file
+        );
+        let toast = if added {
+            format!("Bookmarked, {} in this folder", self.bookmarked().len())
+        } else {
+            "Bookmark removed".to_owned()
+        };
+        self.push_toast(Level::Info, toast);
+        // a large folder is not browsed again for nothing after a crash
+        if let Err(err) = self.session.save() {
+            warn!("Failed to save session state: {}", err);
+        }
+    }
+
+    // the mark follows a renamed file, `to` is none when it left the folder
+    fn move_bookmark(&mut self, from: &Path, to: Option<&Path>) {
+        if let Some(folder) = self.bookmark_folder() {
+            self.session.bookmarks.rename(&folder, from, to);
+        }
+    }
+
+    // the next or previous bookmarked file in list order, among the visible ones
+    fn step_bookmark(&mut self, forward: bool) {
+        let marked: HashSet<&Path> = self.bookmarked().iter().map(PathBuf::as_path).collect();
+        if marked.is_empty() {
+            self.push_toast(
+                Level::Info,
+                "No bookmarks in this folder, B adds one".to_owned(),
+            );
+            return;
+        }
+        let len = self.files.len();
+        let target = (1..=self.filter.count(len) as isize)
+            .filter_map(|d| {
+                self.filter
+                    .step(self.index, if forward { d } else { -d }, len)
+            })
+            .find(|&i| marked.contains(self.files[i].as_path()));
+        match target {
+            Some(i) => self.goto(i),
+            None => self.push_toast(
+                Level::Info,
+                "The bookmarked files are filtered out".to_owned(),
+            ),
+        }
+    }
+
+    fn export_bookmarks(&mut self) {
+        // in list order like Shift+B visits them
+        let marked: HashSet<&Path> = self.bookmarked().iter().map(PathBuf::as_path).collect();
+        let files: Vec<PathBuf> = self
+            .files
+            .iter()
+            .filter(|p| marked.contains(p.as_path()))
+            .map(|p| p.to_path_buf())
+            .collect();
+        if files.is_empty() {
+            self.push_toast(
+                Level::Info,
+                "No bookmarks in this folder, B adds one".to_owned(),
+            );
+            return;
+        }
+        let Some(path) = self
+            .file_dialog()
+            .set_file_name("bookmarks.txt")
+            .add_filter("Text", &["txt"])
+            .save_file()
+        else {
+            return;
+        };
+        match bookmarks::export(&files, &path) {
+            Ok(()) => {
+                info!("Exported {} bookmarks to {:?}", files.len(), path);
+                self.push_toast(
+                    Level::Info,
+                    format!("Saved {} paths to {}", files.len(), short_display(&path)),
+                );
+            }
+            Err(err) => {
+                error!("Failed to export bookmarks to {:?}: {}", path, err);
+                self.error = Some(format!("Could not save {}: {}", path.display(), err));
+            }
+        }
+    }
+
+    // the bookmarks of the folder to jump to, only there while it has some
+    fn bookmarks_menu(&mut self, ui: &mut egui::Ui) {
+        let marked = self.bookmarked().to_vec();
+        if marked.is_empty() {
+            return;
+        }
+        let mut chosen = None;
+        let mut export = false;
+        ui.menu_button(format!("🔖 {}", marked.len()), |ui| {
+            for file in &marked {
+                let current = self.current_file().map(|p| p.as_path()) == Some(file.as_path());
+                if ui.selectable_label(current, file_name(file)).clicked() {
+                    chosen = Some(file.clone());
+                }
+            }
+            ui.separator();
+            export = ui.button("Export as file list…").clicked();
+        })
+        .response
+        .on_hover_text("Bookmarked images, Shift+B goes to the next one");
+        if let Some(file) = chosen {
+            match self.files.iter().position(|p| p.deref() == &file) {
+                Some(i) => self.goto(i),
+                None => self.push_toast(
+                    Level::Warn,
+                    format!("{} is not in the list", file_name(&file)),
+                ),
+            }
+        }
+        if export {
+            self.export_bookmarks();
+        }
+    }
+
+    // drop the current file from the list and show the next one, or the previous one if it was last
+    fn remove_current(&mut self) {
+        if self.files.is_empty() {
+            return;
+        }
+        let removed = self.files.remove(self.index);
+        self.ctx.forget_image(&self.src_of(&removed));
+        info!("Removed {:?} from the list", removed.deref());
+        self.filter.update(&self.files);
+        self.show_near(self.index);
+    }
+
+    // the visible file at `index` or after it, or the one before if nothing visible follows
+    fn show_near(&mut self, index: usize) {
+        if self.files.is_empty() {
+            self.current_src = None;
+            self.index = 0;
+            return;
+        }
+        let len = self.files.len();
+        let visible = (index..len)
+            .find(|&i| self.filter.contains(i))
+            .or_else(|| (0..index.min(len)).rev().find(|&i| self.filter.contains(i)));
+        match visible {
+            Some(i) => {
+                self.index = i;
+                self.show_current();
+            }
+            None => self.current_src = None,
+        }
+    }
+
+    // drop `file` from the list wherever it is, the current image only changes if it was `file`
+    fn remove_file(&mut self, file: &Path) {
+        let Some(i) = self.files.iter().position(|p| p.deref() == file) else {
+            return;
+        };
+        if i == self.index && self.detached.is_none() {
+            self.remove_current();
+        } else {
+            let removed = self.files.remove(i);
+            self.ctx.forget_image(&self.src_of(&removed));
+            if i < self.index {
+                self.index -= 1;
+            }
+            self.filter.update(&self.files);
+        }
+    }
+
+    // compare the files of the list in the background, the window shows the progress meanwhile
+    fn find_duplicates(&mut self) {
+        let paths: Vec<PathBuf> = self
+            .files
+            .iter()
+            .filter(|p| p.is_file())
+            .map(|p| p.to_path_buf())
+            .collect();
+        if paths.len() < 2 {
+            self.push_toast(Level::Info, "There are no files to compare".to_owned());
+            return;
+        }
+        info!("Looking for duplicates among {} files", paths.len());
+        self.duplicate_scan = Some(DuplicateScan::start(&self.ctx, paths, self.likeness));
+        self.duplicates = Some(Vec::new());
+    }
+
+    fn poll_duplicates(&mut self) {
+        if let Some(groups) = self.duplicate_scan.as_ref().and_then(DuplicateScan::poll) {
+            self.duplicate_scan = None;
+            self.duplicates = Some(groups);
+        }
+    }
+
+    fn duplicates_window(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let (mut search, mut cancel) = (false, false);
+        let (mut show, mut trash) = (None, None);
+        let current = self.current_file().map(|p| p.to_path_buf());
+        let groups = self.duplicates.as_deref().unwrap_or_default();
+        egui::Window::new("Duplicates")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let searching = self.duplicate_scan.is_some();
+                    ui.add_enabled_ui(!searching, |ui| {
+                        egui::ComboBox::from_id_salt("likeness")
+                            .selected_text(self.likeness.label())
+                            .show_ui(ui, |ui| {
+                                for likeness in Likeness::ALL {
+                                    ui.selectable_value(
+                                        &mut self.likeness,
+                                        likeness,
+                                        likeness.label(),
+                                    );
+                                }
+                            });
+                        search = ui.button("Search again").clicked();
+                    });
+                });
+                ui.separator();
+                if let Some(scan) = &self.duplicate_scan {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!(
+                            "Comparing… {}/{}",
+                            thousands(scan.progress()),
+                            thousands(scan.total)
+                        ));
+                        cancel = ui.button("Cancel").clicked();
+                    });
+                    return;
+                }
+                if groups.is_empty() {
+                    ui.label("No duplicates found.");
+                    return;
+                }
+                let files: usize = groups.iter().map(Vec::len).sum();
+                ui.label(format!(
+                    "{} groups, {} files",
+                    groups.len(),
+                    thousands(files)
+                ));
+                egui::ScrollArea::vertical()
+                    .max_height(420.0)
+                    .show(ui, |ui| {
+                        for (i, group) in groups.iter().enumerate() {
+                            if i > 0 {
+                                ui.separator();
+                            }
+                            for file in group {
+                                ui.horizontal(|ui| {
+                                    if ui
+                                        .small_button("🗑")
+                                        .on_hover_text("Move to the trash")
+                                        .clicked()
+                                    {
+                                        trash = Some(file.clone());
+                                    }
+                                    let shown = current.as_ref() == Some(file);
+                                    if ui
+                                        .selectable_label(shown, file_name(file))
+                                        .on_hover_text(file.display().to_string())
+                                        .clicked()
+                                    {
+                                        show = Some(file.clone());
+                                    }
+                                });
+                            }
+                        }
+                    });
+            });
+        if !open || cancel {
+            self.duplicates = None;
+            self.duplicate_scan = None;
+            return;
+        }
+        if search {
+            self.find_duplicates();
+        }
+        if let Some(file) = show {
+            match self.files.iter().position(|p| p.deref() == &file) {
+                Some(i) => self.goto(i),
+                None => self.push_toast(
+                    Level::Warn,
+                    format!("{} is not in the list", file_name(&file)),
+                ),
+            }
+        }
+        if let Some(file) = trash {
+            self.trash_duplicate(&file);
+        }
+    }
+
+    fn trash_duplicate(&mut self, file: &Path) {
+        if let Err(err) = trash::delete(file) {
+            error!("Failed to move {:?} to the trash: {}", file, err);
+            self.error = Some(format!(
+                "Could not move {} to the trash: {}",
+                file_name(file),
+                err
+            ));
+            return;
+        }
+        info!("Moved {:?} to the trash", file);
+        if let Some(i) = self.files.iter().position(|p| p.deref() == file) {
+            self.undo.push(file.to_path_buf(), i);
+        }
+        self.ratings.removed(file);
+        self.move_bookmark(file, None);
+        self.remove_file(file);
+        // a group of one is no group of duplicates anymore
+        if let Some(groups) = &mut self.duplicates {
+            for group in groups.iter_mut() {
+                group.retain(|p| p != file);
+            }
+            groups.retain(|g| g.len() > 1);
+        }
+    }
+
+    // move the current file to the system trash
+    fn delete_current(&mut self) {
+        let Some(path) = self.current_file().map(|p| p.to_path_buf()) else {
+            return;
+        };
+        match trash::delete(&path) {
+            Ok(()) => {
+                info!("Moved {:?} to the trash", path);
+                self.ratings.removed(&path);
+                self.move_bookmark(&path, None);
+                self.undo.push(path, self.index);
+                self.remove_current();
+            }
+            Err(err) => {
+                error!("Failed to move {:?} to the trash: {}", path, err);
+                self.error = Some(format!(
+                    "Could not move {} to the trash: {}",
+                    file_name(&path),
+                    err
+                ));
+            }
+        }
+    }
+
+    // move the current file to the folder bound to the number `key`
+    fn move_current(&mut self, key: u8) {
+        let (Some(file), Some(dir)) = (
+            self.current_file().map(|p| p.to_path_buf()),
+            self.settings.destination(key).cloned(),
+        ) else {
+            return;
+        };
+        match fileops::move_to(&file, &dir) {
+            Ok(target) => {
+                info!("Moved {:?} to {:?}", file, target);
+                self.ratings.moved(&file, &target);
+                self.move_bookmark(&file, None);
+                self.push_toast(Level::Info, format!("Moved to {}", short_display(&dir)));
+                self.remove_current();
+            }
+            Err(err) => {
+                error!("Failed to move {:?} to {:?}: {}", file, dir, err);
+                self.error = Some(format!(
+                    "Could not move {} to {}: {}",
+                    file_name(&file),
+                    dir.display(),
+                    err
+                ));
+            }
+        }
+    }
+
+    fn clipboard(&mut self) -> Result<&mut arboard::Clipboard, String> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(arboard::Clipboard::new().map_err(|e| e.to_string())?);
+        }
+        Ok(self.clipboard.as_mut().expect("clipboard was just opened"))
+    }
+
+    // copy the full resolution pixels of the current file, of animations the frame on screen;
+    // the clipboard is set by `poll_copy` once the file is decoded
+    fn copy_image(&mut self) {
+        let Some(path) = self.current_file().map(|p| p.to_path_buf()) else {
+            return;
+        };
+        let frame = self
+            .animation
+            .as_ref()
+            .filter(|a| a.is_animated() == Some(true) && a.path() == path)
+            .map(|a| a.index());
+        let done = loader::open_in_background(&self.ctx, &path, frame);
+        self.copying = Some((path, frame, done));
+    }
+
+    fn poll_copy(&mut self) {
+        let Some((_, _, done)) = &self.copying else {
+            return;
+        };
+        let pixels = match done.try_recv() {
+            Ok(pixels) => pixels,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err("the image was not decoded".to_owned()),
+        };
+        let (path, frame, _) = self.copying.take().expect("a copy is pending");
+        let result = pixels.and_then(|rgba| {
+            let data = arboard::ImageData {
+                width: rgba.width() as usize,
+                height: rgba.height() as usize,
+                bytes: rgba.into_raw().into(),
+            };
+            self.clipboard()?.set_image(data).map_err(|e| e.to_string())
+        });
+        match result {
+            Ok(()) => {
+                info!("Copied {:?} to the clipboard", path);
+                let copied = match frame {
+                    Some(index) => format!("Copied frame {}", index + 1),
+                    None => "Copied image".to_owned(),
+                };
+                self.push_toast(Level::Info, copied);
+            }
+            Err(err) => {
+                error!("Failed to copy {:?}: {}", path, err);
+                self.push_toast(Level::Error, format!("Could not copy the image: {}", err));
+            }
+        }
+    }
+
+    fn copy_path(&mut self) {
+        let Some(path) = self.current_file().map(|p| p.display().to_string()) else {
+            return;
+        };
+        let result = self
+            .clipboard()
+            .and_then(|c| c.set_text(path.clone()).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => self.push_toast(Level::Info, format!("Copied {}", path)),
+            Err(err) => {
+                error!("Failed to copy the path: {}", err);
+                self.push_toast(Level::Error, format!("Could not copy the path: {}", err));
+            }
+        }
+    }
+
+    // open what the clipboard holds: a path, an http(s) URL or a bitmap
+    fn paste(&mut self, text: Option<String>) {
+        let text = text.or_else(|| self.clipboard().ok()?.get_text().ok());
+        if let Some(line) = text
+            .as_deref()
+            .and_then(|t| t.lines().map(str::trim).find(|l| !l.is_empty()))
+        {
+            let line = line.to_owned();
+            if is_http_url(&line) {
+                self.open_url(&line);
+                return;
+            }
+            let path = to_path(&line).unwrap_or_else(|| PathBuf::from(&line));
+            if path.exists() {
+                if let Err(err) = self.open_path(&path) {
+                    self.push_toast(
+                        Level::Error,
+                        format!("Could not open {}: {}", path.display(), err),
+                    );
+                }
+            } else {
+                self.push_toast(
+                    Level::Warn,
+                    format!("Nothing to view in the clipboard: {}", line),
+                );
+            }
+            return;
+        }
+
+        let image = self
+            .clipboard()
+            .and_then(|c| c.get_image().map_err(|e| e.to_string()));
+        match image.and_then(|image| encode_png(&image)) {
+            Ok(png) => {
+                let uri = "bytes://clipboard.png";
+                // the bytes loader keeps the first image included under a URI
+                self.ctx.forget_image(uri);
+                self.ctx.include_bytes(uri, png.clone());
+                self.show_detached(uri.to_owned(), "Clipboard image".to_owned(), Some(png));
+            }
+            Err(err) => {
+                warn!("Nothing to paste: {}", err);
+                self.push_toast(
+                    Level::Warn,
+                    "The clipboard holds no image, path or URL".to_owned(),
+                );
+            }
+        }
+    }
+
+    // view a remote image, Prev/Next return to the file list
+    fn open_url(&mut self, url: &str) {
+        let name = url
+            .split(['?', '#'])
+            .next()
+            .and_then(|u| u.rsplit('/').find(|s| !s.is_empty()))
+            .unwrap_or(url)
+            .to_owned();
+        self.settings.add_recent_url(url);
+        if let Err(err) = self.settings.save() {
+            warn!("Failed to save settings: {}", err);
+        }
+        self.show_detached(url.to_owned(), name, None);
+    }
+
+    // copy or move the current file to `dir`, or to a folder chosen now
+    fn transfer_to(&mut self, dir: Option<PathBuf>, moving: bool) {
+        if self.transfer.is_some() {
+            self.push_toast(Level::Warn, "Another file is still being copied".to_owned());
+            return;
+        }
+        let Some(file) = self
+            .current_file()
+            .filter(|p| p.is_file())
+            .map(|p| p.to_path_buf())
+        else {
+            return;
+        };
+        let Some(dir) = dir.or_else(|| self.file_dialog().pick_folder()) else {
+            return;
+        };
+        if moving && self.settings.last_move.as_ref() != Some(&dir) {
+            self.settings.last_move = Some(dir.clone());
+            self.save_settings();
+        }
+        if file.file_name().is_some_and(|name| dir.join(name).exists()) {
+            self.collision = Some((file, dir, moving));
+        } else {
+            self.transfer = Some(Transfer::start(&self.ctx, &file, &dir, moving, false));
+        }
+    }
+
+    // overwrite, keep both or skip when the folder has a file of the same name
+    fn collision_dialog(&mut self, ctx: &egui::Context) {
+        let Some((file, dir, _)) = &self.collision else {
+            return;
+        };
+        let mut answer = None;
+        egui::Window::new("File exists")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} already has a file named {}.",
+                    short_display(dir),
+                    file_name(file)
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Replace it").clicked() {
+                        answer = Some(Some(true));
+                    }
+                    if ui
+                        .button("Keep both")
+                        .on_hover_text("Add a number to the new name")
+                        .clicked()
+                    {
+                        answer = Some(Some(false));
+                    }
+                    if ui.button("Skip").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape))
+                    {
+                        answer = Some(None);
+                    }
+                });
+            });
+        let Some(answer) = answer else {
+            return;
+        };
+        if let (Some(replace), Some((file, dir, moving))) = (answer, self.collision.take()) {
+            self.transfer = Some(Transfer::start(&self.ctx, &file, &dir, moving, replace));
+        }
+    }
+
+    // a moved file leaves the list, the next one is shown if it was the current one
+    fn poll_transfer(&mut self) {
+        let Some(result) = self.transfer.as_ref().and_then(Transfer::poll) else {
+            return;
+        };
+        let Some(transfer) = self.transfer.take() else {
+            return;
+        };
+        let verb = if transfer.moving { "move" } else { "copy" };
+        match result {
+            Ok(target) if !transfer.moving => {
+                info!("Copied {:?} to {:?}", transfer.file, target);
+                self.push_toast(Level::Info, format!("Copied to {}", short_display(&target)));
+            }
+            Ok(target) => {
+                info!("Moved {:?} to {:?}", transfer.file, target);
+                self.ratings.moved(&transfer.file, &target);
+                self.move_bookmark(&transfer.file, None);
+                let dir = target.parent().map(short_display).unwrap_or_default();
+                self.push_toast(Level::Info, format!("Moved to {}", dir));
+                self.remove_file(&transfer.file);
+            }
+            Err(err) => {
+                error!("Failed to {} {:?}: {}", verb, transfer.file, err);
+                self.error = Some(format!(
+                    "Could not {} {}: {}",
+                    verb,
+                    file_name(&transfer.file),
+                    err
+                ));
+            }
+        }
+    }
+
+    fn rename_dialog(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let (mut submit, mut cancel) = (false, false);
+        let Some(file) = self.current_file().map(|p| p.to_path_buf()) else {
+            self.renaming = None;
+            return;
+        };
+        let Some((name, selected)) = &mut self.renaming else {
+            return;
+        };
+        let old = file_name(&file);
+        egui::Window::new("Rename")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let mut output = egui::TextEdit::singleline(name)
+                    .desired_width(360.0)
+                    .show(ui);
+                output.response.request_focus();
+                // typing replaces the name and keeps the extension
+                if !*selected {
+                    let stem = Path::new(&old)
+                        .file_stem()
+                        .map_or(0, |s| s.to_string_lossy().chars().count());
+                    let range = egui::text::CCursorRange::two(
+                        egui::text::CCursor::new(0),
+                        egui::text::CCursor::new(stem),
+                    );
+                    output.state.cursor.set_char_range(Some(range));
+                    output.state.store(ui.ctx(), output.response.id);
+                    *selected = true;
+                }
+                let target = file.with_file_name(name.as_str());
+                let problem = fileops::check_name(name).err().or_else(|| {
+                    (*name != old && target.exists() && !name.eq_ignore_ascii_case(&old))
+                        .then(|| format!("{} already exists", name))
+                });
+                if let Some(problem) = &problem {
+                    ui.colored_label(ui.visuals().error_fg_color, problem);
+                }
+                let valid = problem.is_none() && *name != old;
+                if output.response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    submit = valid;
+                }
+                cancel = ui.input(|i| i.key_pressed(egui::Key::Escape));
+                if ui.add_enabled(valid, egui::Button::new("Rename")).clicked() {
+                    submit = true;
+                }
+            });
+        if submit {
+            let name = name.clone();
+            self.renaming = None;
+            self.rename_current(&file, &name);
+        } else if cancel || !open {
+            self.renaming = None;
+        }
+    }
+
+    // rename the current file, it keeps its place unless the list is sorted by name
+    fn rename_current(&mut self, file: &Path, name: &str) {
+        match fileops::rename(file, name) {
+            Ok(target) => {
+                info!("Renamed {:?} to {:?}", file, target);
+                self.ratings.moved(file, &target);
+                self.move_bookmark(file, Some(&target));
+                if let Some((_, rated)) = &mut self.filter.rated {
+                    if rated.remove(file) {
+                        rated.insert(target.clone());
+                    }
+                }
+                self.ctx.forget_image(&self.src_of(file));
+                self.files[self.index] = PathSortable::from(target.clone());
+                self.history.rename(file, &target);
+                self.bag.rename(file, &target);
+                if self.visited.remove(file) {
+                    self.visited.insert(target.clone());
+                }
+                self.current_src = Some(self.src_of(&target));
+                if !self.randomize {
+                    self.sort_files();
+                }
+                self.reindex();
+            }
+            Err(err) => {
+                error!("Failed to rename {:?} to {:?}: {}", file, name, err);
+                self.push_toast(
+                    Level::Error,
+                    format!("Could not rename {}: {}", file_name(file), err),
+                );
+            }
+        }
+    }
+
+    fn url_dialog(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut submit = None;
+        let recent = self.settings.recent_urls.clone();
+        let Some(input) = &mut self.url_input else {
+            return;
+        };
+        egui::Window::new("Open URL")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let edit = ui.add(
+                        egui::TextEdit::singleline(input)
+                            .hint_text("https://example.com/image.jpg")
+                            .desired_width(360.0),
+                    );
+                    edit.request_focus();
+                    if edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        submit = Some(input.trim().to_owned());
+                    }
+                    ui.add_enabled_ui(!recent.is_empty(), |ui| {
+                        ui.menu_button("Recent", |ui| {
+                            for url in &recent {
+                                if ui.button(url).clicked() {
+                                    submit = Some(url.clone());
+                                }
+                            }
+                        });
+                    });
+                });
+                let valid = is_http_url(input.trim());
+                if !valid && !input.trim().is_empty() {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        "Only http:// and https:// URLs are supported",
+                    );
+                }
+                if ui.add_enabled(valid, egui::Button::new("Open")).clicked() {
+                    submit = Some(input.trim().to_owned());
+                }
+            });
+        match submit.filter(|url| is_http_url(url)) {
+            Some(url) => {
+                self.url_input = None;
+                self.open_url(&url);
+            }
+            None if !open => self.url_input = None,
+            None => {}
+        }
+    }
+
+    // display an image that is not part of `files`
+    fn show_detached(&mut self, uri: String, name: String, png: Option<Arc<[u8]>>) {
+        info!("Viewing {}", uri);
+        self.start_load(&uri);
+        self.current_src = Some(uri);
+        self.detached = Some(Detached { name, png });
+        self.orientation = Transform::default();
+        self.image_size = [0, 0];
+        self.error = None;
+        self.failed_since = None;
+        self.reloaded_at = None;
+        if !self.settings.sticky_transform {
+            self.transform = Transform::default();
+        }
+        if !self.lock_zoom {
+            self.view.reset();
+        }
+        self.slideshow_since = self.time;
+    }
+
+    // format options for a copy of the displayed image, the format follows the chosen file name
+    fn export_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.export_open;
+        let mut changed = false;
+        let mut save = false;
+        egui::Window::new("Save a copy")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("export").num_columns(2).show(ui, |ui| {
+                    ui.label("JPEG quality");
+                    changed |= ui
+                        .add(egui::Slider::new(
+                            &mut self.settings.export_quality,
+                            1..=100,
+                        ))
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Size");
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .checkbox(&mut self.settings.export_resize, "Long edge at most")
+                            .changed();
+                        ui.add_enabled_ui(self.settings.export_resize, |ui| {
+                            changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut self.settings.export_max_edge)
+                                        .range(16..=65535)
+                                        .suffix(" px"),
+                                )
+                                .changed();
+                        });
+                    });
+                    ui.end_row();
+                });
+                save = ui.button("Save as…").clicked();
+            });
+        self.settings_changed |= changed;
+        if save && self.export_current() {
+            open = false;
+        }
+        self.export_open = open;
+        if !open && self.settings_changed {
+            self.save_settings();
+        }
+    }
+
+    // decode the displayed image at full size and save it as it is shown, returns whether it was
+    // saved
+    fn export_current(&mut self) -> bool {
+        let Some((name, image)) = self.decode_current() else {
+            return false;
+        };
+        let mut dialog = self.file_dialog().set_file_name(format!("{}.jpg", name));
+        for (format, extensions) in export::FORMATS {
+            dialog = dialog.add_filter(format, extensions);
+        }
+        let Some(path) = dialog.save_file() else {
+            return false;
+        };
+        let max_edge = self
+            .settings
+            .export_resize
+            .then_some(self.settings.export_max_edge);
+        let quality = self.settings.export_quality;
+        match export::export(image, self.full_transform(), &path, quality, max_edge) {
+            Ok(()) => {
+                info!("Saved a copy of {} as {:?}", name, path);
+                self.push_toast(Level::Info, format!("Saved {}", short_display(&path)));
+                true
+            }
+            Err(err) => {
+                error!("Failed to save {:?}: {}", path, err);
+                self.error = Some(format!("Could not save {}: {}", path.display(), err));
+                false
+            }
+        }
+    }
+
+    // the full resolution pixels of the displayed image with a name to save them under
+    fn decode_current(&mut self) -> Option<(String, image::DynamicImage)> {
+        let src = self.current_src.clone()?;
+        let (name, decoded) = match self.current_file() {
+            Some(path) => (
+                path.file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                loader::open(path),
+            ),
+            // a pasted or downloaded image is still held by egui
+            None => (
+                "image".to_owned(),
+                match self.ctx.try_load_bytes(&src) {
+                    Ok(egui::load::BytesPoll::Ready { bytes, .. }) => {
+                        loader::load_from_memory(&bytes)
+                    }
+                    Ok(egui::load::BytesPoll::Pending { .. }) => {
+                        Err("The image is still loading".to_owned())
+                    }
+                    Err(err) => Err(err.to_string()),
+                },
+            ),
+        };
+        match decoded {
+            Ok(image) => Some((name, image)),
+            Err(err) => {
+                error!("Failed to decode {} for saving: {}", src, err);
+                self.error = Some(format!("Could not read the image: {}", err));
+                None
+            }
+        }
+    }
+
+    fn save_detached(&mut self) {
+        let Some(png) = self.detached.as_ref().and_then(|d| d.png.clone()) else {
+            return;
+        };
+        let Some(path) = self
+            .file_dialog()
+            .set_file_name("clipboard.png")
+            .add_filter("PNG", &["png"])
+            .save_file()
+        else {
+            return;
+        };
+        match std::fs::write(&path, &png) {
+            Ok(()) => self.push_toast(Level::Info, format!("Saved {}", short_display(&path))),
+            Err(err) => {
+                error!("Failed to save {:?}: {}", path, err);
+                self.error = Some(format!("Could not save {}: {}", path.display(), err));
+            }
+        }
+    }
+
+    // the file behind the displayed image, none for pasted images and URLs
+    fn current_file(&self) -> Option<&PathSortable> {
+        match self.detached {
+            Some(_) => None,
+            None => self.files.get(self.index),
+        }
+    }
+
+    // the URL `path` is loaded from
+    fn src_of(&self, path: &Path) -> String {
+        match &self.archive {
+            Some(archive) if path.starts_with(archive) => archive::to_url(path),
+            _ => to_url(path),
+        }
+    }
+
+    fn push_toast(&mut self, level: Level, message: String) {
+        self.toasts.push(level, message, self.time);
+    }
+
+    // the file holding the current image, the archive for one inside it, None for URLs and
+    // pasted images
+    fn on_disk(&self) -> Option<&Path> {
+        let path: &Path = self.current_file()?;
+        path.ancestors().find(|p| p.is_file())
+    }
+
+    // the file manager with the current file selected
+    fn reveal_file(&mut self) {
+        let Some(path) = self.on_disk().map(Path::to_path_buf) else {
+            return;
+        };
+        if let Err(err) = external::reveal(&path) {
+            warn!("Failed to show {:?} in its folder: {}", path, err);
+            self.push_toast(
+                Level::Error,
+                format!("Could not open the file manager: {}", err),
+            );
+        }
+    }
+
+    // make the current image the desktop background, images that are not a file of their own
+    // are saved to the cache directory first
+    fn set_wallpaper(&mut self) {
+        let Some(src) = self.current_src.clone() else {
+            return;
+        };
+        let path = match self.current_file().filter(|p| p.is_file()) {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let saved = match self.ctx.try_load_bytes(&src) {
+                    Ok(egui::load::BytesPoll::Ready { bytes, .. }) => wallpaper::save(&bytes),
+                    Ok(egui::load::BytesPoll::Pending { .. }) => {
+                        Err("the image is still loading".to_owned())
+                    }
+                    Err(err) => Err(err.to_string()),
+                };
+                match saved {
+                    Ok(path) => path,
+                    Err(err) => {
+                        warn!("Failed to save {} as the wallpaper: {}", src, err);
+                        self.push_toast(
+                            Level::Error,
+                            format!("Could not set the wallpaper: {}", err),
+                        );
+                        return;
+                    }
+                }
+            }
+        };
+        self.wallpaper = Some(wallpaper::set(
+            &path,
+            self.settings.wallpaper_mode,
+            &self.ctx,
+        ));
+    }
+
+    fn poll_wallpaper(&mut self) {
+        let Some(setting) = &self.wallpaper else {
+            return;
+        };
+        let result = match setting.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err("the desktop was not told".to_owned()),
+        };
+        self.wallpaper = None;
+        match result {
+            Ok(()) => self.push_toast(Level::Info, "set as wallpaper".to_owned()),
+            Err(err) => {
+                warn!("Failed to set the wallpaper: {}", err);
+                self.push_toast(
+                    Level::Error,
+                    format!("Could not set the wallpaper: {}", err),
+                );
+            }
+        }
+    }
+
+    // run the external command at `index` of the settings on the current file
+    fn run_command(&mut self, index: usize) {
+        let Some(tool) = self.settings.commands.get(index).cloned() else {
+            return;
+        };
+        let Some(path) = self
+            .current_file()
+            .filter(|p| p.is_file())
+            .map(|p| p.to_path_buf())
+        else {
+            self.push_toast(Level::Warn, "Commands only run on files on disk".to_owned());
+            return;
+        };
+        match external::expand(&tool.command, &path)
+            .and_then(|words| external::spawn(&words, Some(&self.ctx)))
+        {
+            Ok(done) => {
+                if tool.refresh {
+                    self.running.push((path, done));
+                }
+                self.push_toast(Level::Info, format!("running {}", tool.name));
+            }
+            Err(err) => {
+                warn!("Failed to run {:?}: {}", tool.name, err);
+                self.push_toast(
+                    Level::Error,
+                    format!("Could not run {}: {}", tool.name, err),
+                );
+            }
+        }
+    }
+
+    // reload the current file once a command run on it is done
+    fn poll_commands(&mut self) {
+        if self.running.is_empty() {
+            return;
+        }
+        let mut finished = Vec::new();
+        self.running.retain(|(path, done)| match done.try_recv() {
+            Err(TryRecvError::Empty) => true,
+            _ => {
+                finished.push(path.clone());
+                false
+            }
+        });
+        if self
+            .current_file()
+            .is_some_and(|p| finished.iter().any(|f| f == p.deref()))
+        {
+            self.reload();
+        }
+    }
+
+    // open the current file in the editor from the settings, the file watch reloads it here
+    // once it is saved
+    fn edit_externally(&mut self) {
+        let Some(path) = self.current_file().filter(|p| p.is_file()) else {
+            self.push_toast(Level::Warn, "Only files on disk can be edited".to_owned());
+            return;
+        };
+        if let Err(err) = external::edit(&self.settings.editor, path) {
+            warn!("Failed to start the editor: {}", err);
+            self.push_toast(Level::Error, format!("Could not start the editor: {}", err));
+        }
+    }
+
+    fn destinations_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.destinations_open;
+        let mut changed = false;
+        egui::Window::new("Move destinations")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Press a number key to move the current image to its folder.");
+                egui::Grid::new("destinations")
+                    .num_columns(3)
+                    .show(ui, |ui| {
+                        for key in 1..=9 {
+                            ui.label(key.to_string());
+                            let dir = self.settings.destination(key).cloned();
+                            match &dir {
+                                Some(dir) => {
+                                    ui.label(short_display(dir))
+                                        .on_hover_text(dir.display().to_string());
+                                }
+                                None => {
+                                    ui.weak("—");
+                                }
+                            }
+                            ui.horizontal(|ui| {
+                                if ui.button("Choose…").clicked() {
+                                    if let Some(dir) = self.file_dialog().pick_folder() {
+                                        self.settings.set_destination(key, Some(dir));
+                                        changed = true;
+                                    }
+                                }
+                                if dir.is_some() && ui.button("✖").on_hover_text("Remove").clicked()
+                                {
+                                    self.settings.set_destination(key, None);
+                                    changed = true;
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+        self.destinations_open = open;
+        if changed {
+            self.save_settings();
+        }
+    }
+
+    // restore the file deleted last and show it at its old position
+    fn undo_delete(&mut self) {
+        match self.undo.restore() {
+            Some(Ok(deleted)) => {
+                info!("Restored {:?} from the trash", deleted.path);
+                self.index = deleted.index.min(self.files.len());
+                self.files
+                    .insert(self.index, PathSortable::from(deleted.path));
+                self.filter.update(&self.files);
+                self.show_current();
+            }
+            Some(Err(err)) => {
+                error!("{}", err);
+                self.error = Some(err);
+            }
+            None => {}
+        }
+    }
+
+    // turn the displayed file clockwise on disk, JPEGs only without loss through jpegtran
+    fn rotate_file(&mut self) {
+        let Some(path) = self.current_file().map(|p| p.to_path_buf()) else {
+            return;
+        };
+        // the first rotation keeps the original until the session ends
+        if !self.backups.contains_key(&path) {
+            let backup =
+                fileops::unique_path(&path.with_file_name(format!("{}.bak", file_name(&path))));
+            if let Err(err) = std::fs::copy(&path, &backup) {
+                error!("Failed to back up {:?}: {}", path, err);
+                self.error = Some(format!("Could not back up {}: {}", file_name(&path), err));
+                return;
+            }
+            self.backups.insert(path.clone(), backup);
+        }
+        match rotate::rotate_file(&path, true) {
+            Ok(Rotated::Lossless) => {
+                info!("Rotated {:?}", path);
+                self.push_toast(Level::Info, "rotated the file".to_owned());
+            }
+            Ok(Rotated::Reencoded) => {
+                warn!("Rotated {:?} by encoding it again", path);
+                self.push_toast(
+                    Level::Warn,
+                    "rotated the file, it was encoded again without its metadata".to_owned(),
+                );
+            }
+            Err(err) => {
+                error!("Failed to rotate {:?}: {}", path, err);
+                self.error = Some(format!("Could not rotate {}: {}", file_name(&path), err));
+                return;
+            }
+        }
+        self.orient();
+        self.reload();
+    }
+
+    fn delete_dialog(&mut self, ctx: &egui::Context) {
+        let name = self
+            .files
+            .get(self.index)
+            .map(file_name)
+            .unwrap_or_default();
+        let modal = egui::Modal::new(egui::Id::new("delete_dialog")).show(ctx, |ui| {
+            ui.heading("Move to trash?");
+            ui.label(name);
+            let mut dont_ask = !self.settings.confirm_delete;
+            if ui.checkbox(&mut dont_ask, "Don't ask again").changed() {
+                self.settings.confirm_delete = !dont_ask;
+                self.save_settings();
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Move to trash").clicked()
+                    || ui.input(|i| i.key_pressed(egui::Key::Enter))
+                {
+                    self.deleting = false;
+                    self.delete_current();
+                }
+                if ui.button("Cancel").clicked() {
+                    self.deleting = false;
+                }
+            });
+        });
+        if modal.should_close() {
+            self.deleting = false;
+        }
+    }
+
+    // "17/244", counting only the files that match the filter
+    fn position(&self) -> String {
+        let pos = self.filter.position(self.index);
+        let count = self.filter.count(self.files.len());
+        if self.spread_partner().is_some() {
+            return format!("pp. {}–{} / {}", pos, pos + 1, count);
+        }
+        format!("{}/{}", pos, count)
+    }
+
+    // the URL of the file shown next to the current one in a spread
+    fn spread_partner(&self) -> Option<String> {
+        if !self.spread || self.detached.is_some() {
+            return None;
+        }
+        let pos = self.filter.position(self.index).checked_sub(1)?;
+        self.pairs(pos)
+            .then(|| self.filter.nth(pos + 1, self.files.len()))
+            .flatten()
+            .map(|i| self.src_of(&self.files[i]))
+    }
+
+    // whether the visible files at `pos` and after it make up a spread, which a cover, a double
+    // page scan or a broken file on either side doesn't
+    fn pairs(&self, pos: usize) -> bool {
+        let len = self.files.len();
+        if self.settings.spread_cover && pos == 0 {
+            return false;
+        }
+        let wide = |pos: usize| {
+            self.filter.nth(pos, len).is_none_or(|i| {
+                self.page_size(&self.src_of(&self.files[i]))
+                    .is_none_or(|size| size.is_some_and(spread::is_wide))
+            })
+        };
+        !wide(pos) && !wide(pos + 1)
+    }
+
+    // intrinsic size of an image once the loaders know it, they start on it if they haven't,
+    // None if it can't be loaded
+    fn page_size(&self, uri: &str) -> Option<Option<egui::Vec2>> {
+        let poll =
+            self.ctx
+                .try_load_texture(uri, self.texture_options(), egui::load::SizeHint::default());
+        match poll {
+            Ok(egui::load::TexturePoll::Ready { texture }) => Some(Some(texture.size)),
+            Ok(egui::load::TexturePoll::Pending { size }) => Some(size),
+            Err(_) => None,
+        }
+    }
+
+    // go to the next or previous spread, a page shown on its own is one step
+    fn turn_spread(&mut self, forward: bool) {
+        let pos = self.filter.position(self.index).saturating_sub(1);
+        let shown = if forward {
+            self.pairs(pos)
+        } else {
+            pos >= 2 && self.pairs(pos - 2)
+        };
+        let step = if shown { 2 } else { 1 };
+        self.step(if forward { step } else { -step });
+    }
+
+    // display `files[index]`, called after every navigation
+    fn show_current(&mut self) {
+        let p = self.files[self.index].clone();
+        if self.randomize {
+            self.bag.visit(&p);
+        }
+        self.history.visit(&p);
+        self.visited.insert(p.clone());
+        self.current_src = Some(self.src_of(&p));
+        self.detached = None;
+        self.orient();
+        self.image_size = [0, 0];
+        self.error = None;
+        self.failed_since = None;
+        self.reloaded_at = None;
+        if !self.settings.sticky_transform {
+            self.transform = Transform::default();
+        }
+        if !self.lock_zoom {
+            self.view.reset();
+        }
+        // the slideshow waits a full interval after any navigation
+        self.slideshow_since = self.time;
+        let current = self.src_of(&p);
+        self.cache.visit(&self.ctx, &current, &p);
+        self.start_load(&current);
+        // archive entries are held in memory, only the ones next to the current one are kept
+        let (window, distance) = match self.archive {
+            Some(_) => (1, self.prefetch.distance.min(1)),
+            None => (
+                cache::WINDOW.max(self.prefetch.distance),
+                self.prefetch.distance,
+            ),
+        };
+        let keep = self.neighbours(window);
+        self.cache.trim(&self.ctx, &current, &keep);
+        let mut wanted = self.neighbours(distance);
+        // animations are decoded by `Animation` once they are shown
+        wanted.retain(|uri| !animation::is_animated_uri(uri));
+        self.prefetch.retarget(&self.ctx, &current, wanted);
+    }
+
+    // URLs of the visible files around the current one, nearest first
+    fn neighbours(&self, distance: usize) -> Vec<String> {
+        self.filter
+            .neighbours(self.index, distance, self.files.len())
+            .into_iter()
+            .map(|i| self.src_of(&self.files[i]))
+            .collect()
+    }
+
+    fn transform_by(&mut self, change: impl FnOnce(&mut Transform)) {
+        change(&mut self.transform);
+        // the scroll offset has to stay within the turned image
+        self.view
+            .pan_by(egui::Vec2::ZERO, self.viewport, self.image_vec());
+    }
+
+    // read the EXIF orientation of the displayed file
+    fn orient(&mut self) {
+        let orientation = self
+            .current_file()
+            .filter(|_| self.settings.auto_orient)
+            .and_then(|p| metadata::orientation(p));
+        self.orientation = orientation.map_or_else(Transform::default, Transform::from_orientation);
+    }
+
+    // the orientation correction and the rotation and flips chosen by hand
+    fn full_transform(&self) -> Transform {
+        self.orientation.then(self.transform)
+    }
+
+    // size of the image as it is shown, after rotating it
+    fn image_vec(&self) -> egui::Vec2 {
+        let size = egui::vec2(self.image_size[0] as f32, self.image_size[1] as f32);
+        self.full_transform().size(size)
+    }
+
+    // compare the next images with the current one
+    fn pin_current(&mut self) {
+        let Some(src) = self.current_src.clone() else {
+            return;
+        };
+        let name = match (&self.detached, self.current_file()) {
+            (Some(detached), _) => detached.name.clone(),
+            (None, Some(p)) => file_name(p),
+            (None, None) => return,
+        };
+        self.compare = Some(Compare::new(src, name, self.orientation));
+        self.diff = None;
+        self.push_toast(Level::Info, "pinned, Esc stops comparing".to_owned());
+    }
+
+    // the pinned image next to the current one, or either of them with A/B, with their
+    // difference on top or in place of the pinned one
+    fn show_compare(&mut self, ui: &mut egui::Ui, src: &str) {
+        let Some(compare) = &self.compare else {
+            return;
+        };
+        let (ab, swapped, view) = (compare.ab, compare.swapped, compare.diff);
+        let heatmap = self.heatmap(ui.ctx(), src);
+        let rect = ui.available_rect_before_wrap();
+        let overlay = view == DiffView::Overlay;
+        match ab {
+            Some(pinned) => {
+                self.show_image(ui, src);
+                if pinned && view == DiffView::Only {
+                    self.paint_heatmap(ui, rect, heatmap.as_ref(), true);
+                } else if pinned {
+                    self.paint_pinned(ui, rect, true);
+                }
+                if overlay {
+                    self.paint_heatmap(ui, rect, heatmap.as_ref(), false);
+                }
+            }
+            None => {
+                let gap = ui.spacing().item_spacing.x;
+                let [pinned, current] = compare::panes(rect, gap, swapped);
+                let mut pane = ui.new_child(egui::UiBuilder::new().max_rect(current));
+                self.show_image(&mut pane, src);
+                if overlay {
+                    self.paint_heatmap(ui, current, heatmap.as_ref(), false);
+                }
+                if view == DiffView::Only {
+                    self.paint_heatmap(ui, pinned, heatmap.as_ref(), true);
+                } else {
+                    self.paint_pinned(ui, pinned, false);
+                }
+            }
+        }
+        self.compare_bar(ui, rect);
+    }
+
+    // where the current image is in `pane` at the current zoom and pan
+    fn rect_in(&self, pane: egui::Rect) -> Option<egui::Rect> {
+        let size = self.image_vec();
+        if size.min_elem() <= 0.0 {
+            return None;
+        }
+        let viewport = pane.size();
+        let content = egui::Rect::from_min_size(
+            pane.min - self.view.offset,
+            self.view.content_size(viewport, size),
+        );
+        Some(self.view.image_rect(content, viewport, size))
+    }
+
+    // the pinned image laid out like the current one in `pane`, so the same parts line up even
+    // when their pixel sizes differ
+    fn paint_pinned(&self, ui: &mut egui::Ui, pane: egui::Rect, cover: bool) {
+        let (Some(compare), Some(rect)) = (&self.compare, self.rect_in(pane)) else {
+            return;
+        };
+        let mut ui = ui.new_child(egui::UiBuilder::new().max_rect(pane));
+        ui.set_clip_rect(pane.intersect(ui.clip_rect()));
+        if cover {
+            let fill = match self.settings.background(ui.visuals().dark_mode) {
+                Some([r, g, b]) => egui::Color32::from_rgb(r, g, b),
+                None => ui.visuals().panel_fill,
+            };
+            ui.painter().rect_filled(pane, 0.0, fill);
+        }
+        self.paint_backdrop(&ui, rect);
+        let image = egui::Image::new(&compare.src).texture_options(self.texture_options());
+        compare
+            .orientation
+            .then(self.transform)
+            .paint(image, &ui, rect);
+    }
+
+    // the heatmap of the difference, on its own over a dark `pane` with `cover`
+    fn paint_heatmap(
+        &self,
+        ui: &mut egui::Ui,
+        pane: egui::Rect,
+        heatmap: Option<&egui::TextureHandle>,
+        cover: bool,
+    ) {
+        let mut ui = ui.new_child(egui::UiBuilder::new().max_rect(pane));
+        ui.set_clip_rect(pane.intersect(ui.clip_rect()));
+        if cover {
+            ui.painter()
+                .rect_filled(pane, 0.0, egui::Color32::from_gray(16));
+        }
+        if let (Some(heatmap), Some(rect)) = (heatmap, self.rect_in(pane)) {
+            let image = egui::Image::from_texture(heatmap);
+            self.full_transform().paint(image, &ui, rect);
+        }
+    }
+
+    // the heatmap once the difference is computed, started when it is first wanted
+    fn heatmap(&mut self, ctx: &egui::Context, src: &str) -> Option<egui::TextureHandle> {
+        let compare = self.compare.as_ref().filter(|c| c.diff != DiffView::Off)?;
+        if self.diff.as_ref().is_none_or(|d| !d.of(&compare.src, src)) {
+            self.diff = Some(Diff::new(&compare.src, src));
+        }
+        match self.diff.as_mut()?.poll(ctx) {
+            diff::State::Done(_, heatmap) => Some(heatmap.clone()),
+            _ => None,
+        }
+    }
+
+    fn compare_bar(&mut self, ui: &egui::Ui, rect: egui::Rect) {
+        let mut close = false;
+        egui::Area::new(egui::Id::new("compare bar"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::CENTER_TOP)
+            .fixed_pos(rect.center_top() + egui::vec2(0.0, 8.0))
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    let Some(compare) = &mut self.compare else {
+                        return;
+                    };
+                    ui.horizontal(|ui| {
+                        let swap = ui.add_enabled(compare.ab.is_none(), egui::Button::new("Swap"));
+                        if swap.on_hover_text("Exchange the panes").clicked() {
+                            compare.swapped = !compare.swapped;
+                        }
+                        let ab = ui
+                            .selectable_label(compare.ab.is_some(), "A/B")
+                            .on_hover_text("One image at a time, A flips between them");
+                        if ab.clicked() {
+                            compare.ab = match compare.ab {
+                                Some(_) => None,
+                                None => Some(true),
+                            };
+                        }
+                        let showing = match compare.ab {
+                            Some(true) => "pinned",
+                            Some(false) => "current",
+                            None => "",
+                        };
+                        if !showing.is_empty() {
+                            ui.label(showing);
+                        }
+                        ui.separator();
+                        egui::ComboBox::from_id_salt("diff")
+                            .selected_text(compare.diff.label())
+                            .show_ui(ui, |ui| {
+                                for view in DiffView::ALL {
+                                    ui.selectable_value(&mut compare.diff, view, view.label());
+                                }
+                            });
+                        if let Some(diff) =
+                            self.diff.as_mut().filter(|_| compare.diff != DiffView::Off)
+                        {
+                            match diff.poll(ui.ctx()) {
+                                diff::State::Decoding => {
+                                    ui.spinner();
+                                }
+                                diff::State::Computing(done) => {
+                                    ui.spinner();
+                                    ui.label(format!("{:.0}%", done * 100.0));
+                                }
+                                diff::State::Done(stats, _) if stats.differing == 0 => {
+                                    ui.label("identical");
+                                }
+                                diff::State::Done(stats, _) => {
+                                    ui.label(format!(
+                                        "max {} • mean {:.2} • {} pixels differ",
+                                        stats.max,
+                                        stats.mean,
+                                        thousands(stats.differing)
+                                    ));
+                                }
+                                diff::State::Failed(err) => {
+                                    ui.colored_label(ui.visuals().error_fg_color, err);
+                                }
+                            }
+                        }
+                        ui.separator();
+                        close = ui
+                            .button("✖")
+                            .on_hover_text("Stop comparing (Esc)")
+                            .clicked();
+                    });
+                });
+            });
+        if close {
+            self.compare = None;
+            self.diff = None;
+        }
+    }
+
+    // the current page and `partner` side by side, fit to the window
+    fn show_spread(&mut self, ui: &mut egui::Ui, src: &str, partner: &str) {
+        let rect = ui.available_rect_before_wrap();
+        self.viewport = rect.size();
+        self.animation = None;
+        let (Some(Some(first)), Some(Some(second))) =
+            (self.page_size(src), self.page_size(partner))
+        else {
+            ui.centered_and_justified(|ui| ui.spinner());
+            return;
+        };
+        self.image_size = [first.x as usize, first.y as usize];
+        let options = self.texture_options();
+        let rects = spread::layout(rect, first, second, self.settings.right_to_left);
+        for (uri, rect) in [src, partner].into_iter().zip(rects) {
+            self.paint_backdrop(ui, rect);
+            egui::Image::new(uri)
+                .texture_options(options)
+                .paint_at(ui, rect);
+        }
+        ui.allocate_rect(rect, egui::Sense::hover());
+        self.prefetch.poll(ui.ctx(), options);
+    }
+
+    fn show_image(&mut self, ui: &mut egui::Ui, src: &str) {
+        // Use egui Image widget with runtime source (egui_extras provides loaders)
+        let options = self.texture_options();
+        let image = if animation::is_animated_uri(src) {
+            // decoded here so the frames can be paused and stepped through
+            if self
+                .animation
+                .as_ref()
+                .is_none_or(|a| a.src() != src || a.options() != options)
+            {
+                self.animation = Some(Animation::start(ui.ctx(), src, options, self.time));
+            }
+            let animation = self.animation.as_mut().expect("animation was just started");
+            animation.poll(ui.ctx());
+            if let Some(next) = animation.advance(self.time) {
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_secs_f64(next));
+            }
+            match (animation.is_animated(), animation.texture()) {
+                // a still image is left to the loaders
+                (Some(false), _) => egui::Image::new(src).texture_options(options),
+                (_, Some(texture)) => {
+                    egui::Image::from_texture(egui::load::SizedTexture::from_handle(texture))
+                }
+                (_, None) => {
+                    ui.centered_and_justified(|ui| ui.spinner());
+                    return;
+                }
+            }
+        } else {
+            self.animation = None;
+            egui::Image::new(src).texture_options(options)
+        };
+        let viewport = ui.available_size();
+        self.viewport = viewport;
+
+        // the intrinsic size is unknown until the loader has read the header,
+        // keep the last known size (reset on navigation) until then
+        match image.load_for_size(ui.ctx(), viewport) {
+            Ok(poll) => {
+                if let Some(size) = poll.size() {
+                    let size = [size.x as usize, size.y as usize];
+                    if size != self.image_size {
+                        self.cache.loaded(src, size);
+                    }
+                    self.image_size = size;
+                }
+                if let egui::load::TexturePoll::Ready { .. } = poll {
+                    self.prefetch.poll(ui.ctx(), self.texture_options());
+                    self.reloaded_at = None;
+                    if let Some(load) = self.load.as_mut().filter(|l| l.src == src) {
+                        if load.ready(Instant::now()) {
+                            let stats = self.images.stats(src);
+                            info!("Shown {}", load.log_line(stats, self.images.resident()));
+                        }
+                    }
+                }
+            }
+            // a file caught in the middle of being saved is read again shortly
+            Err(_)
+                if self
+                    .reloaded_at
+                    .is_some_and(|t| self.time - t < RELOAD_RETRY) =>
+            {
+                ui.ctx().forget_image(src);
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_millis(250));
+                ui.centered_and_justified(|ui| ui.spinner());
+                return;
+            }
+            Err(err) => {
+                self.load_failed(ui, &err.to_string());
+                return;
+            }
+        }
+
+        let mut area = egui::ScrollArea::both().auto_shrink([false; 2]);
+        if self.image_size.contains(&0) {
+            area.show(ui, |ui| {
+                let (rect, _) = ui.allocate_exact_size(viewport, egui::Sense::hover());
+                image.paint_at(ui, rect);
+            });
+            return;
+        }
+        let size = self.image_vec();
+
+        // zoom: ctrl+wheel (or pinch) anchors on the cursor, keys anchor on the viewport center
+        let origin = ui.max_rect().min;
+        let (zoom_delta, hover) = ui.input(|i| (i.zoom_delta(), i.pointer.hover_pos()));
+        if let Some(pos) = hover.filter(|p| ui.max_rect().contains(*p)) {
+            if zoom_delta != 1.0 {
+                self.view.zoom_by(zoom_delta, viewport, size, pos - origin);
+            }
+        }
+        let dt = ui.input(|i| i.stable_dt);
+        if self.view.glide(dt, viewport, size) {
+            ui.ctx().request_repaint();
+        }
+        if std::mem::take(&mut self.view.to_end) {
+            self.view.pan_by(egui::vec2(0.0, f32::MAX), viewport, size);
+        }
+        if std::mem::take(&mut self.view.moved) {
+            area = area.scroll_offset(self.view.offset);
+        }
+
+        // dragging is handled below so it can show a grab cursor and glide after release
+        let pannable = self.view.pannable(viewport, size);
+        let output = area
+            .scroll_source(egui::scroll_area::ScrollSource {
+                drag: false,
+                ..Default::default()
+            })
+            .show(ui, |ui| {
+                let content = self.view.content_size(viewport, size);
+                let (rect, response) =
+                    ui.allocate_exact_size(content, egui::Sense::click_and_drag());
+                let rect = self.view.image_rect(rect, viewport, size);
+                self.paint_backdrop(ui, rect);
+                self.full_transform().paint(image, ui, rect);
+                let scale = self.view.scale(viewport, size);
+                if self.settings.nearest && scale >= PIXEL_GRID_ZOOM {
+                    paint_pixel_grid(ui.painter(), rect, ui.clip_rect(), scale);
+                }
+                let [r, g, b, a] = self.settings.overlay_color;
+                overlay::paint(
+                    ui.painter(),
+                    self.settings.overlay,
+                    rect,
+                    ui.clip_rect(),
+                    self.settings.overlay_grid,
+                    egui::Color32::from_rgba_unmultiplied(r, g, b, a),
+                );
+                (response, rect)
+            });
+        self.view.offset = output.state.offset;
+
+        let (response, rect) = &output.inner;
+        response.context_menu(|ui| self.image_menu(ui));
+        if self.eyedropper {
+            self.pick_color(ui, src, *rect, response);
+        }
+        let banding = self.select_region(ui, src, response, *rect, output.inner_rect, size)
+            || self.zoom_band(ui, response, *rect, output.inner_rect, size);
+        if pannable && !banding {
+            if response.dragged() {
+                self.view.velocity = egui::Vec2::ZERO;
+                self.view.pan_by(-response.drag_delta(), viewport, size);
+                ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+            } else if response.hovered() {
+                ui.ctx().set_cursor_icon(egui::CursorIcon::Grab);
+            }
+            if response.drag_stopped() {
+                self.view.velocity = ui.input(|i| i.pointer.velocity());
+                ui.ctx().request_repaint();
+            }
+        }
+
+        // double-click toggles 100%, centering the view on the clicked point
+        if response.double_clicked() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                self.toggle_actual_size(viewport, size, pos - origin);
+                ui.ctx().request_repaint();
+            }
+        }
+    }
+
+    // Ctrl and a drag marks a part of the image to zoom into, true while a rectangle is drawn
+    fn zoom_band(
+        &mut self,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        image: egui::Rect,
+        visible: egui::Rect,
+        size: egui::Vec2,
+    ) -> bool {
+        let (command, origin, pointer) = ui.input(|i| {
+            (
+                i.modifiers.command,
+                i.pointer.press_origin(),
+                i.pointer.interact_pos(),
+            )
+        });
+        if response.drag_started() && command {
+            if let Some(origin) = origin {
+                self.band = Some(Band {
+                    start: image.clamp(origin),
+                    live: true,
+                });
+            }
+        }
+        if !response.dragged() && !response.drag_stopped() {
+            self.band = None;
+        }
+        let Some(band) = &self.band else {
+            return false;
+        };
+        let marked =
+            egui::Rect::from_two_pos(band.start, image.clamp(pointer.unwrap_or(band.start)));
+        if response.drag_stopped() {
+            let wanted = band.live && marked.width() >= MIN_BAND && marked.height() >= MIN_BAND;
+            self.band = None;
+            if wanted {
+                // from screen points to pixels of the image as it is shown
+                let scale = size / image.size();
+                let area = egui::Rect::from_min_max(
+                    ((marked.min - image.min) * scale).to_pos2(),
+                    ((marked.max - image.min) * scale).to_pos2(),
+                );
+                self.view.zoom_to(area, self.viewport, size);
+            }
+            return true;
+        }
+        if band.live {
+            crop::paint(ui.painter(), visible, marked);
+            ui.ctx().set_cursor_icon(egui::CursorIcon::Crosshair);
+        }
+        true
+    }
+
+    // Shift and a drag marks a part to crop, its edges are dragged to adjust it; true while the
+    // drag belongs to it
+    fn select_region(
+        &mut self,
+        ui: &egui::Ui,
+        src: &str,
+        response: &egui::Response,
+        image: egui::Rect,
+        visible: egui::Rect,
+        size: egui::Vec2,
+    ) -> bool {
+        let transform = self.full_transform();
+        if self
+            .selection
+            .as_ref()
+            .is_some_and(|s| s.src != src || s.transform != transform)
+        {
+            self.selection = None;
+        }
+        let (shift, origin, pointer) = ui.input(|i| {
+            (
+                i.modifiers.shift,
+                i.pointer.press_origin(),
+                i.pointer.interact_pos(),
+            )
+        });
+        if let Some(origin) = origin.filter(|_| response.drag_started()) {
+            let grabbed = self
+                .selection
+                .as_ref()
+                .and_then(|s| crop::grab(crop::on_screen(s.area(), image), origin));
+            match (&mut self.selection, grabbed) {
+                (Some(selection), Some(direction)) => {
+                    selection.rect = selection.area();
+                    selection.grabbed = Some(direction);
+                }
+                _ if shift => {
+                    let at = crop::on_image(origin, image);
+                    self.selection = Some(Selection::new(src, transform, at));
+                }
+                _ => {}
+            }
+        }
+        let Some(selection) = self.selection.as_mut() else {
+            return false;
+        };
+        let dragging = selection.grabbed.is_some();
+        if dragging {
+            if let Some(pointer) = pointer {
+                selection.drag(crop::on_image(pointer, image));
+            }
+            if !response.dragged() {
+                selection.grabbed = None;
+                selection.rect = selection.area();
+                // a click with Shift is not a selection
+                let marked = crop::on_screen(selection.area(), image);
+                if marked.width() < MIN_BAND || marked.height() < MIN_BAND {
+                    self.selection = None;
+                    return true;
+                }
+            }
+        }
+        let marked = crop::on_screen(selection.area(), image);
+        crop::paint(ui.painter(), visible, marked);
+        let [w, h] = selection.pixels(size);
+        ui.painter().with_clip_rect(visible).text(
+            marked.left_top() - egui::vec2(0.0, 4.0),
+            egui::Align2::LEFT_BOTTOM,
+            format!("{} × {}", w, h),
+            egui::FontId::proportional(13.0),
+            egui::Color32::WHITE,
+        );
+        let hovered = response.hover_pos().and_then(|p| crop::grab(marked, p));
+        if let Some(direction) = selection.grabbed.or(hovered) {
+            ui.ctx().set_cursor_icon(window::resize_cursor(direction));
+        }
+        if dragging {
+            return true;
+        }
+
+        let (mut save, mut copy) = (false, false);
+        egui::Area::new(egui::Id::new("selection"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::RIGHT_TOP)
+            .fixed_pos(marked.right_bottom() + egui::vec2(0.0, 6.0))
+            .constrain_to(visible)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        save = ui.button("Crop & Save As…").clicked();
+                        copy = ui.button("Copy region").clicked();
+                        if ui.button("✖").on_hover_text("Escape").clicked() {
+                            self.selection = None;
+                        }
+                    });
+                });
+            });
+        if save {
+            self.save_region();
+        } else if copy {
+            self.copy_region();
+        }
+        false
+    }
+
+    // write the marked part of the original pixels to a new file, turned like it is shown
+    fn save_region(&mut self) {
+        let Some((name, decoded)) = self.decode_current() else {
+            return;
+        };
+        let Some(selection) = &self.selection else {
+            return;
+        };
+        let (region, transform) = (selection.cut(&decoded), selection.transform);
+        let mut dialog = self
+            .file_dialog()
+            .set_file_name(format!("{}_crop.png", name));
+        for (format, extensions) in export::FORMATS {
+            dialog = dialog.add_filter(format, extensions);
+        }
+        let Some(path) = dialog.save_file() else {
+            return;
+        };
+        let quality = self.settings.export_quality;
+        match export::export(region, transform, &path, quality, None) {
+            Ok(()) => {
+                info!("Saved a part of {} as {:?}", name, path);
+                self.push_toast(Level::Info, format!("Saved {}", short_display(&path)));
+            }
+            Err(err) => {
+                error!("Failed to save {:?}: {}", path, err);
+                self.error = Some(format!("Could not save {}: {}", path.display(), err));
+            }
+        }
+    }
+
+    fn copy_region(&mut self) {
+        let Some((_, decoded)) = self.decode_current() else {
+            return;
+        };
+        let Some(selection) = &self.selection else {
+            return;
+        };
+        let rgba = selection
+            .transform
+            .apply(selection.cut(&decoded))
+            .to_rgba8();
+        let data = arboard::ImageData {
+            width: rgba.width() as usize,
+            height: rgba.height() as usize,
+            bytes: rgba.into_raw().into(),
+        };
+        match self
+            .clipboard()
+            .and_then(|c| c.set_image(data).map_err(|e| e.to_string()))
+        {
+            Ok(()) => self.push_toast(Level::Info, "Copied region".to_owned()),
+            Err(err) => {
+                error!("Failed to copy the region: {}", err);
+                self.push_toast(Level::Error, format!("Could not copy the region: {}", err));
+            }
+        }
+    }
+
+    // the first key bound to `action`, for menu entries
+    fn key_text(&self, action: Action) -> String {
+        let bindings = self.settings.keys.get(action);
+        bindings.first().map(keys::format).unwrap_or_default()
+    }
+
+    // name and position of the image for a moment after it changed, while the chrome is hidden
+    fn hud(&self, ctx: &egui::Context, area: egui::Rect) {
+        let shown = self.time - self.hud_since;
+        let opacity = (shown / HUD_FADE)
+            .min((HUD_DURATION - shown) / HUD_FADE)
+            .clamp(0.0, 1.0) as f32;
+        if opacity <= 0.0 || self.files.is_empty() {
+            return;
+        }
+        let Some(name) = self.current_file().map(file_name) else {
+            return;
+        };
+        egui::Area::new(egui::Id::new("hud"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::CENTER_TOP)
+            .fixed_pos(area.center_top() + egui::vec2(0.0, 12.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                ui.set_opacity(opacity);
+                egui::Frame::NONE
+                    .fill(egui::Color32::from_black_alpha(160))
+                    .corner_radius(6.0)
+                    .inner_margin(egui::Margin::symmetric(12, 6))
+                    .show(ui, |ui| {
+                        let text = format!("{}   {}", name, self.position());
+                        ui.label(egui::RichText::new(text).color(egui::Color32::WHITE));
+                    });
+            });
+        // every frame only while it fades in or out
+        let steady = HUD_DURATION - HUD_FADE - shown;
+        if shown < HUD_FADE || steady <= 0.0 {
+            ctx.request_repaint();
+        } else {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(steady));
+        }
+    }
+
+    // the clock starts for `src`, hit if the loader has it decoded already
+    fn start_load(&mut self, src: &str) {
+        let hit = self.images.stats(src).is_some();
+        self.load = Some(Load::new(src, Instant::now(), hit));
+    }
+
+    fn diagnostics_overlay(&self, ctx: &egui::Context, area: egui::Rect) {
+        let Some(load) = &self.load else {
+            return;
+        };
+        let rows = load.rows(self.images.stats(&load.src), self.images.resident());
+        egui::Area::new(egui::Id::new("diagnostics"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::RIGHT_BOTTOM)
+            .fixed_pos(area.right_bottom() + egui::vec2(-8.0, -8.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    egui::Grid::new("diagnostics rows")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            for (label, value) in rows {
+                                ui.label(label);
+                                ui.monospace(value);
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+    }
+
+    // frames drawn in the last second and what asked for this one, without a cause it was input
+    fn repaint_overlay(&self, ctx: &egui::Context, area: egui::Rect) {
+        let causes = ctx.repaint_causes();
+        let reason = if causes.is_empty() {
+            "input".to_owned()
+        } else {
+            causes
+                .iter()
+                .map(|c| {
+                    let file = c.file.rsplit(['/', '\\']).next().unwrap_or(c.file);
+                    format!("{}:{} {}", file, c.line, c.reason)
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        egui::Area::new(egui::Id::new("repaint overlay"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::LEFT_BOTTOM)
+            .fixed_pos(area.left_bottom() + egui::vec2(8.0, -8.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::NONE
+                    .fill(egui::Color32::from_black_alpha(160))
+                    .corner_radius(4.0)
+                    .inner_margin(egui::Margin::symmetric(8, 4))
+                    .show(ui, |ui| {
+                        let text = format!("{} fps   {}", self.frames.len(), reason.trim_end());
+                        ui.label(
+                            egui::RichText::new(text)
+                                .monospace()
+                                .color(egui::Color32::WHITE),
+                        );
+                    });
+            });
+    }
+
+    // the right-click menu of the image, entries that don't apply to it are greyed out
+    fn image_menu(&mut self, ui: &mut egui::Ui) {
+        let file = self.current_file().is_some();
+        let on_disk = self.on_disk().is_some();
+        // URLs and images inside archives have no file of their own to delete
+        let deletable = self.current_file().is_some_and(|p| p.is_file());
+        let copy = |modifiers| {
+            let shortcut = egui::KeyboardShortcut::new(modifiers, egui::Key::C);
+            ui.ctx().format_shortcut(&shortcut)
+        };
+        let (copy_image, copy_path) = (
+            copy(egui::Modifiers::COMMAND),
+            copy(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT),
+        );
+        let item = |ui: &mut egui::Ui, enabled: bool, label: &str, shortcut: String| {
+            let button = egui::Button::new(label).shortcut_text(shortcut);
+            let clicked = ui.add_enabled(enabled, button).clicked();
+            if clicked {
+                ui.close();
+            }
+            clicked
+        };
+        if item(ui, file, "Copy image", copy_image) {
+            self.copy_image();
+        }
+        if item(ui, file, "Copy path", copy_path) {
+            self.copy_path();
+        }
+        if item(
+            ui,
+            on_disk,
+            "Show in folder",
+            self.key_text(Action::RevealFile),
+        ) {
+            self.reveal_file();
+        }
+        ui.separator();
+        if item(
+            ui,
+            true,
+            "Rotate left",
+            self.key_text(Action::RotateCounterClockwise),
+        ) {
+            self.transform_by(|t| t.rotate(false));
+        }
+        if item(
+            ui,
+            true,
+            "Rotate right",
+            self.key_text(Action::RotateClockwise),
+        ) {
+            self.transform_by(|t| t.rotate(true));
+        }
+        if item(
+            ui,
+            deletable,
+            "Move to trash",
+            self.key_text(Action::DeleteFile),
+        ) {
+            if self.settings.confirm_delete {
+                self.deleting = true;
+            } else {
+                self.delete_current();
+            }
+        }
+        if item(
+            ui,
+            true,
+            "Set as wallpaper",
+            self.key_text(Action::SetWallpaper),
+        ) {
+            self.set_wallpaper();
+        }
+        if item(ui, true, "Properties", self.key_text(Action::ToggleInfo)) {
+            self.info_open = true;
+        }
+        if !self.settings.commands.is_empty() {
+            ui.separator();
+        }
+        let mut run = None;
+        for (i, tool) in self.settings.commands.iter().enumerate() {
+            let button = egui::Button::new(&tool.name).shortcut_text(&tool.key);
+            if ui.add_enabled(on_disk, button).clicked() {
+                ui.close();
+                run = Some(i);
+            }
+        }
+        if let Some(i) = run {
+            self.run_command(i);
+        }
+        let animated = self
+            .animation
+            .as_ref()
+            .is_some_and(|a| a.is_animated() == Some(true));
+        if animated && ui.button("Save frame as PNG…").clicked() {
+            ui.close();
+            self.save_frame();
+        }
+    }
+
+    // sharp pixels when magnified with the nearest setting, zoomed out images stay smooth
+    fn texture_options(&self) -> egui::TextureOptions {
+        let icon = self.document.as_ref().is_some_and(Document::is_icon);
+        if self.settings.nearest || icon {
+            egui::TextureOptions {
+                magnification: egui::TextureFilter::Nearest,
+                ..egui::TextureOptions::LINEAR
+            }
+        } else {
+            egui::TextureOptions::default()
+        }
+    }
+
+    // what shows through transparent pixels of the image in `rect`
+    fn paint_backdrop(&self, ui: &egui::Ui, rect: egui::Rect) {
+        let painter = ui.painter();
+        let fill = match self.settings.transparency {
+            Backdrop::Checkerboard => {
+                paint_checkerboard(painter, rect, ui.clip_rect(), self.settings.checker_size);
+                return;
+            }
+            Backdrop::Black => egui::Color32::BLACK,
+            Backdrop::White => egui::Color32::WHITE,
+            Backdrop::Grey => egui::Color32::from_gray(128),
+            Backdrop::Custom => {
+                let [r, g, b] = self.settings.transparency_color;
+                egui::Color32::from_rgb(r, g, b)
+            }
+        };
+        painter.rect_filled(rect, 0.0, fill);
+    }
+
+    // the decoded pixels of `src`, replacing those of another image
+    fn pixels(&mut self, src: &str) -> &mut Pixels {
+        if self.pixels.as_ref().is_none_or(|p| p.src() != src) {
+            self.pixels = Some(Pixels::new(src));
+        }
+        self.pixels.as_mut().expect("pixels were just set")
+    }
+
+    // play, pause, step and speed of an animation at the bottom of the image area
+    fn animation_controls(&mut self, ui: &egui::Ui) {
+        let time = self.time;
+        let mut save = false;
+        let Some(animation) = self
+            .animation
+            .as_mut()
+            .filter(|a| a.is_animated() == Some(true))
+        else {
+            return;
+        };
+        let bottom = ui.max_rect().center_bottom() + egui::vec2(0.0, -8.0);
+        egui::Area::new(egui::Id::new("animation"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::CENTER_BOTTOM)
+            .fixed_pos(bottom)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("⏮").on_hover_text("Previous frame").clicked() {
+                            animation.step(false, time);
+                        }
+                        let play = if animation.playing { "⏸" } else { "▶" };
+                        if ui.button(play).clicked() {
+                            animation.toggle(time);
+                        }
+                        if ui.button("⏭").on_hover_text("Next frame").clicked() {
+                            animation.step(true, time);
+                        }
+                        if ui
+                            .button("💾")
+                            .on_hover_text("Save frame as PNG…")
+                            .clicked()
+                        {
+                            save = true;
+                        }
+                        let more = if animation.loading() { "…" } else { "" };
+                        ui.label(format!(
+                            "{}/{}{}",
+                            animation.index() + 1,
+                            animation.len(),
+                            more
+                        ));
+                        egui::ComboBox::from_id_salt("speed")
+                            .width(60.0)
+                            .selected_text(format!("{}×", animation.speed))
+                            .show_ui(ui, |ui| {
+                                for speed in animation::SPEEDS {
+                                    ui.selectable_value(
+                                        &mut animation.speed,
+                                        speed,
+                                        format!("{}×", speed),
+                                    );
+                                }
+                            });
+                    });
+                });
+            });
+        if save {
+            self.save_frame();
+        }
+    }
+
+    // write the frame the animation is on as PNG, turned like it is shown
+    fn save_frame(&mut self) {
+        let Some(animation) = self
+            .animation
+            .as_mut()
+            .filter(|a| a.is_animated() == Some(true))
+        else {
+            return;
+        };
+        animation.playing = false;
+        let (path, index) = (animation.path().to_path_buf(), animation.index());
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let Some(out) = self
+            .file_dialog()
+            .set_file_name(format!("{}_frame{:04}.png", stem, index + 1))
+            .add_filter("PNG", &["png"])
+            .save_file()
+        else {
+            return;
+        };
+        let result = animation::frame(&path, index).and_then(|frame| {
+            self.full_transform()
+                .apply(image::DynamicImage::ImageRgba8(frame))
+                .save_with_format(&out, image::ImageFormat::Png)
+                .map_err(|e| e.to_string())
+        });
+        match result {
+            Ok(()) => {
+                info!("Saved frame {} of {:?} as {:?}", index + 1, path, out);
+                self.push_toast(Level::Info, format!("Saved {}", short_display(&out)));
+            }
+            Err(err) => {
+                error!("Failed to save frame {} of {:?}: {}", index + 1, path, err);
+                self.error = Some(format!("Could not save {}: {}", out.display(), err));
+            }
+        }
+    }
+
+    // operator and exposure of the tone mapping of an HDR image, in the top left corner
+    fn tone_map_controls(&mut self, ui: &egui::Ui) {
+        let mut tone = self.settings.tone_map;
+        let mut apply = false;
+        egui::Area::new(egui::Id::new("tone map"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(ui.max_rect().left_top() + egui::vec2(8.0, 8.0))
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.strong("HDR");
+                        egui::ComboBox::from_id_salt("tone map operator")
+                            .selected_text(tone.operator.label())
+                            .show_ui(ui, |ui| {
+                                for operator in Operator::ALL {
+                                    apply |= ui
+                                        .selectable_value(
+                                            &mut tone.operator,
+                                            operator,
+                                            operator.label(),
+                                        )
+                                        .changed();
+                                }
+                            });
+                        let slider = ui.add(
+                            egui::Slider::new(&mut tone.exposure, -4.0..=4.0)
+                                .step_by(0.1)
+                                .suffix(" EV"),
+                        );
+                        // decoded again, so only once the slider is let go
+                        apply |= slider.drag_stopped() || (slider.changed() && !slider.dragged());
+                    });
+                });
+            });
+        self.settings.tone_map.exposure = tone.exposure;
+        if apply {
+            self.settings.tone_map = tone;
+            self.settings_changed = true;
+            self.images.set_tone_map(tone);
+            self.ctx.forget_all_images();
+            self.reload();
+        }
+    }
+
+    // curves in the top right corner of the image area
+    fn histogram_overlay(&mut self, ui: &egui::Ui, src: &str) {
+        let ctx = ui.ctx().clone();
+        let pixels = self.pixels(src);
+        let failed = pixels.failed();
+        let stale = match pixels.histogram(&ctx) {
+            Some(histogram) => {
+                self.histogram = Some(histogram.clone());
+                false
+            }
+            None => true,
+        };
+        let corner = ui.max_rect().right_top() + egui::vec2(-8.0, 8.0);
+        egui::Area::new(egui::Id::new("histogram"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::RIGHT_TOP)
+            .fixed_pos(corner)
+            .show(&ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    match &self.histogram {
+                        Some(histogram) if !failed => histogram::show(
+                            ui,
+                            histogram,
+                            self.histogram_shown,
+                            self.histogram_log,
+                            stale,
+                        ),
+                        _ => {
+                            ui.allocate_ui(egui::vec2(256.0, 100.0), |ui| {
+                                ui.centered_and_justified(|ui| {
+                                    if failed {
+                                        ui.weak("No histogram for this image");
+                                    } else {
+                                        ui.spinner();
+                                    }
+                                });
+                            });
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        for ((name, _), shown) in
+                            histogram::CHANNELS.iter().zip(&mut self.histogram_shown)
+                        {
+                            ui.checkbox(shown, *name);
+                        }
+                        ui.checkbox(&mut self.histogram_log, "Log");
+                    });
+                });
+            });
+    }
+
+    // overlay with the pixel under the pointer, a click copies its color
+    fn pick_color(
+        &mut self,
+        ui: &egui::Ui,
+        src: &str,
+        rect: egui::Rect,
+        response: &egui::Response,
+    ) {
+        let Some(pos) = response.hover_pos().filter(|pos| rect.contains(*pos)) else {
+            return;
+        };
+        let transform = self.full_transform();
+        let Some(pixels) = self.pixels(src).get(ui.ctx()) else {
+            return;
+        };
+        // the pixel is found in the image as stored, before it was turned for display
+        let uv = transform.texture_uv((pos - rect.min) / rect.size());
+        let x = ((uv.x * pixels.width() as f32) as u32).min(pixels.width().saturating_sub(1));
+        let y = ((uv.y * pixels.height() as f32) as u32).min(pixels.height().saturating_sub(1));
+        let [r, g, b, a] = pixels.get_pixel(x, y).0;
+        let hex = format!("#{:02X}{:02X}{:02X}", r, g, b);
+        ui.ctx().set_cursor_icon(egui::CursorIcon::Crosshair);
+
+        egui::Area::new(egui::Id::new("eyedropper"))
+            .order(egui::Order::Tooltip)
+            .interactable(false)
+            .fixed_pos(pos + egui::vec2(16.0, 16.0))
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let (swatch, _) =
+                            ui.allocate_exact_size(egui::vec2(32.0, 32.0), egui::Sense::hover());
+                        ui.painter().rect_filled(
+                            swatch,
+                            2.0,
+                            egui::Color32::from_rgba_unmultiplied(r, g, b, a),
+                        );
+                        ui.vertical(|ui| {
+                            ui.label(format!("{}, {}", x, y));
+                            ui.label(format!("rgba({}, {}, {}, {})", r, g, b, a));
+                            ui.monospace(&hex);
+                        });
+                    });
+                });
+            });
+
+        if response.clicked() {
+            let result = self
+                .clipboard()
+                .and_then(|c| c.set_text(hex.clone()).map_err(|e| e.to_string()));
+            match result {
+                Ok(()) => self.push_toast(Level::Info, format!("Copied {}", hex)),
+                Err(err) => {
+                    error!("Failed to copy the color: {}", err);
+                    self.push_toast(Level::Error, format!("Could not copy the color: {}", err));
+                }
+            }
+        }
+    }
+
+    // shown in place of an image the loaders could not decode
+    fn load_failed(&mut self, ui: &mut egui::Ui, err: &str) {
+        let since = *self.failed_since.get_or_insert_with(|| {
+            warn!("Failed to load {:?}: {}", self.current_src, err);
+            self.time
+        });
+        let name = match &self.detached {
+            Some(detached) => Some(detached.name.clone()),
+            None => self.files.get(self.index).map(file_name),
+        };
+        let detached = self.detached.is_some();
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() / 3.0);
+            if let Some(name) = name {
+                ui.heading(name);
+            }
+            ui.colored_label(ui.visuals().error_fg_color, err);
+            ui.add_space(8.0);
+            if ui.button("Skip").clicked() {
+                self.next();
+            }
+            if !detached && ui.button("Remove from list").clicked() {
+                self.remove_current();
+            }
+
+            if self.auto_skip && self.filter.count(self.files.len()) > 1 {
+                let remaining = AUTO_SKIP_DELAY - (self.time - since);
+                if remaining <= 0.0 {
+                    self.next();
+                } else {
+                    ui.label(format!("Skipping in {:.0} s", remaining.ceil()));
+                    ui.ctx()
+                        .request_repaint_after(std::time::Duration::from_secs_f64(remaining));
+                }
+            }
+        });
+    }
+
+    // fit the whole image, its width or its height, for this and the next images
+    fn set_fit(&mut self, fit: Fit) {
+        self.view.fit = fit;
+        self.view.reset();
+    }
+
+    fn toggle_actual_size(&mut self, viewport: egui::Vec2, size: egui::Vec2, anchor: egui::Vec2) {
+        let mode = if self.view.mode == DisplayMode::ActualSize {
+            DisplayMode::Fit(self.view.fit)
+        } else {
+            DisplayMode::ActualSize
+        };
+        self.view
+            .set_mode(mode, viewport, size, anchor, viewport * 0.5);
+    }
+
+    fn reindex(&mut self) {
+        self.filter.update(&self.files);
+        // a pasted image or URL stays on screen, Next returns to the start of the list
+        if self.detached.is_some() {
+            self.index = 0;
+            return;
+        }
+        let cur_path = self.current_src.as_deref().and_then(src_path);
+        if let Some(cur) = cur_path {
+            let pos = self
                 .files
                 .iter()
                 .position(|p| p.deref() == &cur)
                 .unwrap_or(0);
             self.index = pos;
-            self.current_src = Some(to_url(&self.files[self.index]));
-        } else {
-            self.index = 0;
-            self.current_src = Some(to_url(&self.files[0]));
+            self.current_src = Some(self.src_of(&self.files[self.index]));
+        } else if let Some(i) = self.filter.nearest(0, self.files.len()) {
+            self.index = i;
+            self.current_src = Some(self.src_of(&self.files[i]));
+        }
+    }
+}
+
+// the file or archive entry shown from `uri`
+fn src_path(uri: &str) -> Option<PathBuf> {
+    to_path(uri).or_else(|| archive::to_path(uri))
+}
+
+// "JPG, .png,webp" -> ["jpg", "png", "webp"]
+fn parse_extensions(text: &str) -> Vec<String> {
+    let mut extensions = Vec::new();
+    for ext in text.split([',', ' ']) {
+        let ext = ext.trim().trim_start_matches('.').to_lowercase();
+        if !ext.is_empty() && !extensions.contains(&ext) {
+            extensions.push(ext);
+        }
+    }
+    extensions
+}
+
+// marks the camera's JPEG or a quick render of the sensor data, not a developed image
+fn raw_badge(ui: &egui::Ui) {
+    egui::Area::new(egui::Id::new("raw badge"))
+        .order(egui::Order::Foreground)
+        .fixed_pos(ui.max_rect().left_top() + egui::vec2(8.0, 8.0))
+        .interactable(false)
+        .show(ui.ctx(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(egui::RichText::new("RAW preview").small().strong());
+            });
+        });
+}
+
+fn is_http_url(s: &str) -> bool {
+    let s = s.to_ascii_lowercase();
+    (s.starts_with("http://") || s.starts_with("https://")) && s.len() > "https://".len()
+}
+
+fn encode_png(image: &arboard::ImageData) -> Result<Arc<[u8]>, String> {
+    let rgba = image::RgbaImage::from_raw(
+        image.width as u32,
+        image.height as u32,
+        image.bytes.to_vec(),
+    )
+    .ok_or("Invalid clipboard image")?;
+    let mut png = Vec::new();
+    rgba.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(png.into())
+}
+
+// 12400 -> "12,400"
+fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
         }
+        out.push(c);
     }
+    out
 }
 
 fn main() {
+    let args = Args::parse();
+
     // initialize logger so egui_extras and other crates can emit diagnostics
-    env_logger::builder()
-        .default_format()
-        .filter_level(LevelFilter::Debug)
-        .init();
+    logging::init(args.verbose, args.quiet, args.log_file.as_deref());
+
+    // a window that is already open takes the files instead, or is only brought up without any
+    let settings = Settings::load();
+    let stdin = args.path.as_deref() == Some(Path::new("-"));
+    if settings.single_instance && !args.new_window && !stdin {
+        let paths: Vec<PathBuf> = args
+            .path
+            .iter()
+            .map(|p| std::path::absolute(p).unwrap_or_else(|_| p.clone()))
+            .collect();
+        if instance::forward(&paths) {
+            return;
+        }
+    }
 
-    let options = NativeOptions::default();
+    // --geometry wins over the size and place of the last window
+    let session = State::load();
+    let mut options = NativeOptions::default();
+    if let Some(geometry) = args.geometry.or(session.window) {
+        options.viewport = geometry.apply(options.viewport);
+    }
+    // transparent so the opacity can be lowered while running
+    options.viewport = options
+        .viewport
+        .with_transparent(true)
+        .with_decorations(!settings.borderless);
+    if settings.always_on_top {
+        options.viewport = options.viewport.with_always_on_top();
+    }
     let _ = eframe::run_native(
         "view-rs",
         options,
         Box::new(|cc| {
             // install egui_extras image loaders so runtime image sources (file://, http://) work
             egui_extras::install_image_loaders(&cc.egui_ctx);
+            // registered last so it takes precedence over the egui_extras file loader
+            cc.egui_ctx
+                .add_bytes_loader(Arc::new(loader::FileLoader::default()));
+            let images = Arc::new(loader::DownscaleLoader::new(args.max_texture_size));
+            images.set_color_managed(settings.color_managed);
+            images.set_tone_map(settings.tone_map);
+            cc.egui_ctx.add_image_loader(images.clone());
+            cc.egui_ctx
+                .add_image_loader(Arc::new(svg::SvgLoader::new(args.max_texture_size)));
             info!("egui_extras image loaders installed");
-            Ok(Box::new(ImageViewer::default()))
+            Ok(Box::new(ImageViewer::new(
+                &cc.egui_ctx,
+                args,
+                settings,
+                session,
+                images,
+            )))
         }),
     );
 }
@@ -0,0 +1,340 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use eframe::egui;
+use exif::{In, Tag, Value};
+
+use crate::raw;
+
+// the EXIF block of `path`, also of the RAW formats with their own TIFF signature
+fn read_exif(path: &Path) -> Option<exif::Exif> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new().read_from_container(&mut BufReader::new(file));
+    if exif.is_ok() || !raw::is_raw(path) {
+        return exif.ok();
+    }
+    // Olympus and Panasonic only differ from TIFF in the magic number
+    let mut bytes = std::fs::read(path).ok()?;
+    if bytes.len() < 8 || !(bytes.starts_with(b"II") || bytes.starts_with(b"MM")) {
+        return None;
+    }
+    let magic = if bytes[0] == b'I' { b"*\0" } else { b"\0*" };
+    bytes[2..4].copy_from_slice(magic);
+    exif::Reader::new().read_raw(bytes).ok()
+}
+
+// EXIF DateTimeOriginal of `path` as seconds since 1970, in the zone OffsetTimeOriginal names or
+// else in the local one, so it sorts right among the modification times of other files
+pub fn date_taken(path: &Path) -> Option<i64> {
+    let exif = read_exif(path)?;
+    let ascii = |tag| match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values.first().cloned(),
+        _ => None,
+    };
+    let mut dt = exif::DateTime::from_ascii(&ascii(Tag::DateTimeOriginal)?).ok()?;
+    if let Some(offset) = ascii(Tag::OffsetTimeOriginal) {
+        // a blank or malformed offset leaves the date in local time
+        let _ = dt.parse_offset(&offset);
+    }
+    Some(timestamp(&dt, local_offset))
+}
+
+// seconds since 1970 of the wall clock time `dt`, `local` gives the offset from UTC in seconds
+// when it has none of its own
+fn timestamp(dt: &exif::DateTime, local: impl Fn(i64) -> i64) -> i64 {
+    let days = days_from_civil(i64::from(dt.year), dt.month.into(), dt.day.into());
+    let wall =
+        days * 86400 + i64::from(dt.hour) * 3600 + i64::from(dt.minute) * 60 + i64::from(dt.second);
+    let offset = dt.offset.map_or_else(|| local(wall), |m| i64::from(m) * 60);
+    wall - offset
+}
+
+// how far the local zone was ahead of UTC at the wall clock time `wall`, DST included
+fn local_offset(wall: i64) -> i64 {
+    use chrono::{Local, Offset, TimeZone};
+    chrono::DateTime::from_timestamp(wall, 0)
+        .and_then(|utc| {
+            Local
+                .offset_from_local_datetime(&utc.naive_utc())
+                .earliest()
+        })
+        .map_or(0, |offset| offset.fix().local_minus_utc().into())
+}
+
+// EXIF Orientation of `path`, 1-8, None if the file has none or it can't be read
+pub fn orientation(path: &Path) -> Option<u32> {
+    let exif = read_exif(path)?;
+    exif.get_field(Tag::Orientation, In::PRIMARY)?
+        .value
+        .get_uint(0)
+}
+
+// EXIF fields shown in the details panel, in this order
+const DETAILS: [(Tag, &str); 8] = [
+    (Tag::Make, "Make"),
+    (Tag::Model, "Camera"),
+    (Tag::LensModel, "Lens"),
+    (Tag::ExposureTime, "Exposure"),
+    (Tag::FNumber, "Aperture"),
+    (Tag::PhotographicSensitivity, "ISO"),
+    (Tag::FocalLength, "Focal length"),
+    (Tag::DateTimeOriginal, "Taken"),
+];
+// details kept in memory before they are read again
+const DETAILS_LIMIT: usize = 1000;
+
+// the EXIF fields of a file worth showing
+#[derive(Default)]
+pub struct Details {
+    // label and value
+    pub fields: Vec<(&'static str, String)>,
+    // latitude and longitude in degrees, negative south and west
+    pub gps: Option<(f64, f64)>,
+}
+
+// read the details of `path`, a file without EXIF or with a broken block has none
+pub fn details(path: &Path) -> Details {
+    let Some(exif) = read_exif(path) else {
+        return Details::default();
+    };
+    let fields = DETAILS
+        .iter()
+        .filter_map(|&(tag, label)| {
+            let field = exif.get_field(tag, In::PRIMARY)?;
+            let value = field.display_value().with_unit(&exif).to_string();
+            Some((label, value.trim_matches('"').to_owned()))
+        })
+        .collect();
+    let coordinate = |tag, reference, negative: &[u8]| {
+        let Value::Rational(dms) = &exif.get_field(tag, In::PRIMARY)?.value else {
+            return None;
+        };
+        let [d, m, s] = dms.get(..3)? else {
+            return None;
+        };
+        let degrees = d.to_f64() + m.to_f64() / 60.0 + s.to_f64() / 3600.0;
+        let negative = match &exif.get_field(reference, In::PRIMARY)?.value {
+            Value::Ascii(values) => values.first().is_some_and(|v| v.as_slice() == negative),
+            _ => false,
+        };
+        Some(if negative { -degrees } else { degrees })
+    };
+    let gps = coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, b"S").zip(coordinate(
+        Tag::GPSLongitude,
+        Tag::GPSLongitudeRef,
+        b"W",
+    ));
+    Details { fields, gps }
+}
+
+// EXIF details read on a background thread as files are shown, kept for when they come up again
+pub struct DetailsCache {
+    // None while the file is being read
+    entries: HashMap<PathBuf, Option<Details>>,
+    requests: Sender<PathBuf>,
+    results: Receiver<(PathBuf, Details)>,
+}
+
+impl DetailsCache {
+    pub fn new(ctx: &egui::Context) -> Self {
+        let (requests, queue) = mpsc::channel::<PathBuf>();
+        let (tx, results) = mpsc::channel();
+        let ctx = ctx.clone();
+        // ends when the cache is dropped together with the sender
+        thread::spawn(move || {
+            for path in queue {
+                let details = details(&path);
+                if tx.send((path, details)).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            }
+        });
+        Self {
+            entries: HashMap::new(),
+            requests,
+            results,
+        }
+    }
+
+    // the details of `path` if they were read, otherwise they are requested
+    pub fn get(&mut self, path: &Path) -> Option<&Details> {
+        for (path, details) in self.results.try_iter() {
+            self.entries.insert(path, Some(details));
+        }
+        if !self.entries.contains_key(path) {
+            if self.entries.len() >= DETAILS_LIMIT {
+                self.entries.retain(|_, details| details.is_none());
+            }
+            self.entries.insert(path.to_path_buf(), None);
+            let _ = self.requests.send(path.to_path_buf());
+        }
+        self.entries.get(path)?.as_ref()
+    }
+}
+
+// e.g. "2.4 MB"
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["bytes", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit + 1 < UNITS.len() {
+        size /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+// e.g. "2024-02-29 13:05 UTC"
+pub fn format_time(time: SystemTime) -> String {
+    let secs = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+    let minutes = secs.rem_euclid(86400) / 60;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+// the date of the proleptic Gregorian calendar that is `days` after 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// days between 1970-01-01 and the given date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// reads the capture dates of many files on a background thread, cancelled when dropped
+pub struct DateScan {
+    pub total: usize,
+    done: Arc<AtomicUsize>,
+    result: Receiver<Vec<(PathBuf, Option<i64>)>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl DateScan {
+    pub fn start(ctx: &egui::Context, paths: Vec<PathBuf>) -> Self {
+        let (tx, result) = mpsc::channel();
+        let done = Arc::new(AtomicUsize::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let total = paths.len();
+
+        let ctx = ctx.clone();
+        let (counter, flag) = (done.clone(), cancel.clone());
+        thread::spawn(move || {
+            let mut dates = Vec::with_capacity(paths.len());
+            for (i, path) in paths.into_iter().enumerate() {
+                if flag.load(Ordering::Relaxed) {
+                    return;
+                }
+                let date = date_taken(&path);
+                dates.push((path, date));
+                counter.store(i + 1, Ordering::Relaxed);
+                // keep the progress display moving, a few times a second
+                if i % 64 == 0 {
+                    ctx.request_repaint_after(Duration::from_millis(100));
+                }
+            }
+            if tx.send(dates).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+
+        Self {
+            total,
+            done,
+            result,
+            cancel,
+        }
+    }
+
+    pub fn progress(&self) -> usize {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    // the dates once all files were read
+    pub fn poll(&self) -> Option<Vec<(PathBuf, Option<i64>)>> {
+        self.result.try_recv().ok()
+    }
+}
+
+impl Drop for DateScan {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn civil_days() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(days_from_civil(2024, 2, 29), 19782);
+    }
+
+    #[test]
+    fn days_round_trip() {
+        for days in [-719468, -1, 0, 11017, 19782, 2932896] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn dates_taken_keep_their_zone() {
+        let mut dt = exif::DateTime::from_ascii(b"2024:02:29 12:30:00").unwrap();
+        let noon = 19782 * 86400 + 12 * 3600 + 30 * 60;
+        // without an offset of its own the date is in the local zone, two hours ahead here
+        assert_eq!(timestamp(&dt, |_| 7200), noon - 7200);
+        dt.parse_offset(b"-05:00").unwrap();
+        assert_eq!(timestamp(&dt, |_| 7200), noon + 5 * 3600);
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(human_size(999), "999 bytes");
+        assert_eq!(human_size(2_400_000), "2.4 MB");
+    }
+}
@@ -0,0 +1,101 @@
+// composition guides drawn over the image, laid out on its rect so they follow zoom, pan and turns
+
+use eframe::egui::{self, Rect};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Default, Debug, Serialize, Deserialize)]
+pub enum Overlay {
+    #[default]
+    None,
+    Thirds,
+    // `overlay_grid` cells across and down
+    Grid,
+    Crosshair,
+}
+
+impl Overlay {
+    pub const ALL: [Overlay; 4] = [
+        Overlay::None,
+        Overlay::Thirds,
+        Overlay::Grid,
+        Overlay::Crosshair,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Overlay::None => "None",
+            Overlay::Thirds => "Rule of thirds",
+            Overlay::Grid => "Grid",
+            Overlay::Crosshair => "Center lines",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&o| o == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    // where the vertical and the horizontal lines are, 0-1 across the image
+    fn lines(self, columns: u32, rows: u32) -> (Vec<f32>, Vec<f32>) {
+        let split = |n: u32| (1..n).map(|i| i as f32 / n as f32).collect();
+        match self {
+            Overlay::None => (Vec::new(), Vec::new()),
+            Overlay::Thirds => (split(3), split(3)),
+            Overlay::Grid => (split(columns.max(1)), split(rows.max(1))),
+            Overlay::Crosshair => (vec![0.5], vec![0.5]),
+        }
+    }
+}
+
+// the lines of `overlay` over the image drawn at `image`, only the part inside `clip`
+pub fn paint(
+    painter: &egui::Painter,
+    overlay: Overlay,
+    image: Rect,
+    clip: Rect,
+    [columns, rows]: [u32; 2],
+    color: egui::Color32,
+) {
+    let visible = image.intersect(clip);
+    if !visible.is_positive() {
+        return;
+    }
+    let painter = painter.with_clip_rect(visible);
+    let stroke = egui::Stroke::new(1.0, color);
+    let (verticals, horizontals) = overlay.lines(columns, rows);
+    for x in verticals {
+        painter.vline(
+            image.lerp_inside(egui::vec2(x, 0.0)).x,
+            image.y_range(),
+            stroke,
+        );
+    }
+    for y in horizontals {
+        painter.hline(
+            image.x_range(),
+            image.lerp_inside(egui::vec2(0.0, y)).y,
+            stroke,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlays_cycle_and_split_the_image() {
+        let mut overlay = Overlay::None;
+        for _ in Overlay::ALL {
+            overlay = overlay.next();
+        }
+        assert_eq!(overlay, Overlay::None);
+        assert_eq!(Overlay::None.next(), Overlay::Thirds);
+        assert_eq!(Overlay::Thirds.lines(4, 2).0, [1.0 / 3.0, 2.0 / 3.0]);
+        assert_eq!(
+            Overlay::Grid.lines(4, 2),
+            (vec![0.25, 0.5, 0.75], vec![0.5])
+        );
+        assert_eq!(Overlay::Crosshair.lines(4, 2), (vec![0.5], vec![0.5]));
+    }
+}
@@ -0,0 +1,242 @@
+// pages of multi-page TIFF files and the sizes in icon files, each shown through the loaders
+// like a file of its own
+
+use eframe::egui::{self, load::BytesPoll};
+
+use crate::icon;
+
+// appended to the URL of the file for the pages after the first
+const SUFFIX: &str = "#page";
+// a broken file could link its directories in a loop
+const MAX_PAGES: usize = 10_000;
+
+pub fn is_tiff(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"II*\0")
+        || bytes.starts_with(b"MM\0*")
+        || bytes.starts_with(b"II+\0")
+        || bytes.starts_with(b"MM\0+")
+}
+
+// URL of page `index` (from 0) of the file at `uri`
+pub fn page_uri(uri: &str, index: usize) -> String {
+    match index {
+        0 => uri.to_owned(),
+        _ => format!("{}{}{}", uri, SUFFIX, index + 1),
+    }
+}
+
+// the URL of the file and the page index of a `page_uri`
+pub fn split(uri: &str) -> Option<(&str, usize)> {
+    let (file, page) = uri.rsplit_once(SUFFIX)?;
+    let page: usize = page.parse().ok()?;
+    Some((file, page.checked_sub(1)?))
+}
+
+// where each image file directory starts, one per page
+fn directories(bytes: &[u8]) -> Vec<usize> {
+    let big_endian = bytes.starts_with(b"MM");
+    let read = |at: usize, len: usize| -> Option<u64> {
+        let b = bytes.get(at..at + len)?;
+        Some(b.iter().enumerate().fold(0, |n, (i, &byte)| {
+            let shift = if big_endian { len - 1 - i } else { i } * 8;
+            n | u64::from(byte) << shift
+        }))
+    };
+    // classic TIFF has 32-bit offsets and 12-byte entries, BigTIFF 64-bit ones and 20 bytes
+    let big = is_tiff(bytes) && bytes[2..4].contains(&b'+');
+    let (offset, count, entry) = if big { (8, 8, 20) } else { (4, 2, 12) };
+    let mut next = match read(if big { 8 } else { 4 }, offset) {
+        Some(first) if is_tiff(bytes) => first as usize,
+        _ => return Vec::new(),
+    };
+    let mut found = Vec::new();
+    while next != 0 && found.len() < MAX_PAGES && !found.contains(&next) {
+        let Some(entries) = read(next, count) else {
+            break;
+        };
+        found.push(next);
+        match read(next + count + entries as usize * entry, offset) {
+            Some(n) => next = n as usize,
+            None => break,
+        }
+    }
+    found
+}
+
+pub fn count(bytes: &[u8]) -> usize {
+    if icon::is_icon(bytes) {
+        return icon::sizes(bytes).len();
+    }
+    directories(bytes).len()
+}
+
+// a copy of the file whose first page is page `index`, so any TIFF decoder reads that one
+pub fn extract(bytes: &[u8], index: usize) -> Option<Vec<u8>> {
+    if icon::is_icon(bytes) {
+        return icon::extract(bytes, index);
+    }
+    let start = *directories(bytes).get(index)?;
+    let mut copy = bytes.to_vec();
+    let big_endian = bytes.starts_with(b"MM");
+    if bytes[2..4].contains(&b'+') {
+        let start = start as u64;
+        let start = if big_endian {
+            start.to_be_bytes()
+        } else {
+            start.to_le_bytes()
+        };
+        copy[8..16].copy_from_slice(&start);
+    } else {
+        let start = u32::try_from(start).ok()?;
+        let start = if big_endian {
+            start.to_be_bytes()
+        } else {
+            start.to_le_bytes()
+        };
+        copy[4..8].copy_from_slice(&start);
+    }
+    Some(copy)
+}
+
+// the page shown of the current file, all files start out with one
+pub struct Document {
+    src: String,
+    index: usize,
+    // None until the bytes of the file were read
+    count: Option<usize>,
+    // the size of each page of an icon file, largest first
+    sizes: Vec<[u32; 2]>,
+}
+
+impl Document {
+    pub fn new(src: &str) -> Self {
+        Self {
+            src: src.to_owned(),
+            index: 0,
+            count: None,
+            sizes: Vec::new(),
+        }
+    }
+
+    // the URL of the file, not of the page
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    pub fn uri(&self) -> String {
+        page_uri(&self.src, self.index)
+    }
+
+    // the page shown and the number of pages, once the file has more than one
+    pub fn position(&self) -> Option<(usize, usize)> {
+        self.count.filter(|&n| n > 1).map(|n| (self.index, n))
+    }
+
+    pub fn is_icon(&self) -> bool {
+        !self.sizes.is_empty()
+    }
+
+    pub fn sizes(&self) -> &[[u32; 2]] {
+        &self.sizes
+    }
+
+    // count the pages as soon as the loaders have read the file, returns whether it just did
+    pub fn poll(&mut self, ctx: &egui::Context) -> bool {
+        if self.count.is_some() {
+            return false;
+        }
+        match ctx.try_load_bytes(&self.src) {
+            Ok(BytesPoll::Ready { bytes, .. }) => {
+                self.sizes = if icon::is_icon(&bytes) {
+                    icon::sizes(&bytes)
+                } else {
+                    Vec::new()
+                };
+                // a file shortened since keeps the last page it still has
+                let count = count(&bytes).max(1);
+                self.index = self.index.min(count - 1);
+                self.count = Some(count);
+                true
+            }
+            Ok(BytesPoll::Pending { .. }) => false,
+            Err(_) => {
+                self.count = Some(1);
+                true
+            }
+        }
+    }
+
+    // the file was read again, it may have a different number of pages now
+    pub fn recount(&mut self) {
+        self.count = None;
+    }
+
+    // show the next or previous page, returns the URL of the one that was shown if it changed
+    pub fn turn(&mut self, forward: bool) -> Option<String> {
+        let index = if forward {
+            self.index + 1
+        } else {
+            self.index.checked_sub(1)?
+        };
+        self.show(index)
+    }
+
+    // like `turn` for any page
+    pub fn show(&mut self, index: usize) -> Option<String> {
+        let count = self.count?;
+        if index >= count || index == self.index {
+            return None;
+        }
+        let shown = self.uri();
+        self.index = index;
+        Some(shown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(width: u8, next: u32) -> Vec<u8> {
+        // ImageWidth, ImageLength, BitsPerSample, PhotometricInterpretation, StripOffsets,
+        // RowsPerStrip and StripByteCounts of a 1×1 8-bit grey image whose pixel is at 0
+        let mut ifd = 7u16.to_le_bytes().to_vec();
+        for (tag, value) in [
+            (256u16, u32::from(width)),
+            (257, 1),
+            (258, 8),
+            (262, 1),
+            (273, 0),
+            (278, 1),
+            (279, 1),
+        ] {
+            ifd.extend_from_slice(&tag.to_le_bytes());
+            ifd.extend_from_slice(&3u16.to_le_bytes());
+            ifd.extend_from_slice(&1u32.to_le_bytes());
+            ifd.extend_from_slice(&value.to_le_bytes());
+        }
+        ifd.extend_from_slice(&next.to_le_bytes());
+        ifd
+    }
+
+    #[test]
+    fn pages_are_found_and_extracted() {
+        let mut tiff = b"II*\0\x08\0\0\0".to_vec();
+        let second = 8 + page(1, 0).len() as u32;
+        tiff.extend(page(1, second));
+        tiff.extend(page(2, 0));
+        assert_eq!(count(&tiff), 2);
+        assert_eq!(directories(&tiff), [8, second as usize]);
+        let copy = extract(&tiff, 1).unwrap();
+        assert_eq!(directories(&copy)[0], second as usize);
+        let decoded = image::load_from_memory(&copy).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (2, 1));
+        assert!(extract(&tiff, 2).is_none());
+        assert_eq!(count(b"\x89PNG"), 0);
+
+        let uri = page_uri("file:///scan.tif", 6);
+        assert_eq!(uri, "file:///scan.tif#page7");
+        assert_eq!(split(&uri), Some(("file:///scan.tif", 6)));
+        assert_eq!(split("file:///a.gif#3"), None);
+    }
+}
@@ -1,46 +1,398 @@
+#[cfg(not(windows))]
+use std::borrow::Cow;
 use std::{
+    cmp::Ordering,
     ops::Deref,
     path::{Path, PathBuf},
+    sync::OnceLock,
+    time::SystemTime,
 };
 
-#[derive(Eq, PartialEq)]
-pub struct PathSortable(PathBuf);
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, percent_encode};
+
+use crate::metadata::date_taken;
+
+pub struct PathSortable {
+    path: PathBuf,
+    // read on first use and kept, so sorting doesn't stat a file for every comparison
+    meta: OnceLock<Meta>,
+    // EXIF capture date, see `metadata::date_taken`
+    taken: OnceLock<Option<i64>>,
+}
+
+// the file attributes the list can be sorted by
+#[derive(Clone, Copy, Default)]
+pub struct Meta {
+    pub modified: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+    pub size: Option<u64>,
+}
+
+impl PathSortable {
+    pub fn meta(&self) -> &Meta {
+        self.meta.get_or_init(|| match self.path.metadata() {
+            Ok(m) => Meta {
+                modified: m.modified().ok(),
+                created: m.created().ok(),
+                size: Some(m.len()),
+            },
+            Err(_) => Meta::default(),
+        })
+    }
+
+    pub fn taken(&self) -> Option<i64> {
+        *self.taken.get_or_init(|| date_taken(&self.path))
+    }
+
+    // whether `taken` can answer without reading the file
+    pub fn has_taken(&self) -> bool {
+        self.taken.get().is_some()
+    }
+
+    pub fn set_taken(&self, taken: Option<i64>) {
+        let _ = self.taken.set(taken);
+    }
+}
 
 impl From<PathBuf> for PathSortable {
-    fn from(p: PathBuf) -> Self {
-        PathSortable(p)
+    fn from(path: PathBuf) -> Self {
+        PathSortable {
+            path,
+            meta: OnceLock::new(),
+            taken: OnceLock::new(),
+        }
     }
 }
 
 impl Deref for PathSortable {
     type Target = PathBuf;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.path
     }
 }
 
 impl AsRef<Path> for PathSortable {
     fn as_ref(&self) -> &Path {
-        self.0.as_ref()
+        self.path.as_ref()
     }
 }
 
+impl PartialEq for PathSortable {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Eq for PathSortable {}
+
+// compares component by component so files in the same directory stay together
 impl Ord for PathSortable {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        natural_sort_rs::natural_cmp(&self.0.to_string_lossy(), &other.0.to_string_lossy())
+    fn cmp(&self, other: &Self) -> Ordering {
+        let mut a = self.path.components();
+        let mut b = other.path.components();
+        loop {
+            match (a.next(), b.next()) {
+                (Some(x), Some(y)) => {
+                    let ord = natural_sort_rs::natural_cmp(
+                        &x.as_os_str().to_string_lossy(),
+                        &y.as_os_str().to_string_lossy(),
+                    );
+                    if ord.is_ne() {
+                        return ord;
+                    }
+                }
+                (x, y) => return x.is_some().cmp(&y.is_some()),
+            }
+        }
     }
 }
 
 impl PartialOrd for PathSortable {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum SortKey {
+    // natural order of the path
+    Name,
+    // modification time, oldest first
+    Mtime,
+    // creation time, oldest first
+    Created,
+    // EXIF capture date, the modification time for files without one
+    Taken,
+    // file size, smallest first
+    Size,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 5] = [
+        SortKey::Name,
+        SortKey::Mtime,
+        SortKey::Created,
+        SortKey::Taken,
+        SortKey::Size,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "Name",
+            SortKey::Mtime => "Modified",
+            SortKey::Created => "Created",
+            SortKey::Taken => "Date taken",
+            SortKey::Size => "Size",
+        }
+    }
+}
+
+pub fn sort(files: &mut [PathSortable], key: SortKey, descending: bool) {
+    if key == SortKey::Name {
+        files.sort_by(|a, b| if descending { b.cmp(a) } else { a.cmp(b) });
+        return;
+    }
+    // sort by name first so files with equal keys stay in natural order
+    files.sort();
+    files.sort_by(|a, b| {
+        let ord = match key {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Mtime => a.meta().modified.cmp(&b.meta().modified),
+            SortKey::Created => a.meta().created.cmp(&b.meta().created),
+            SortKey::Taken => taken_or_modified(a).cmp(&taken_or_modified(b)),
+            SortKey::Size => a.meta().size.cmp(&b.meta().size),
+        };
+        if descending { ord.reverse() } else { ord }
+    });
+}
+
+fn taken_or_modified(p: &PathSortable) -> Option<i64> {
+    p.taken().or_else(|| {
+        let modified = p.meta().modified?;
+        let secs = modified
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()?
+            .as_secs();
+        i64::try_from(secs).ok()
+    })
+}
+
+// bytes that can't appear verbatim in the path of a file:// URL, non-ASCII is always encoded
+const PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+#[cfg(not(windows))]
 pub fn to_url<P: AsRef<Path>>(path: P) -> String {
-    format!("file://{}", path.as_ref().display())
+    format!(
+        "file://{}",
+        percent_encode(&path_bytes(path.as_ref()), PATH)
+    )
 }
 
+#[cfg(not(windows))]
 pub fn to_path(url: &str) -> Option<PathBuf> {
-    url.strip_prefix("file://").map(PathBuf::from)
+    let encoded = url.strip_prefix("file://")?;
+    Some(path_from_bytes(percent_decode_str(encoded).collect()))
+}
+
+// `C:\dir\a.png` becomes `file:///C:/dir/a.png`, `\\server\share\a.png` becomes `file://server/share/a.png`
+#[cfg(windows)]
+pub fn to_url<P: AsRef<Path>>(path: P) -> String {
+    use std::path::{Component, Prefix};
+
+    let encode =
+        |s: &std::ffi::OsStr| percent_encode(s.to_string_lossy().as_bytes(), PATH).to_string();
+    let mut host = String::new();
+    let mut segments = Vec::new();
+    let mut components = path.as_ref().components().peekable();
+    while let Some(component) = components.next() {
+        match component {
+            Component::Prefix(prefix) => match prefix.kind() {
+                Prefix::Disk(drive) | Prefix::VerbatimDisk(drive) => {
+                    segments.push(format!("{}:", drive as char))
+                }
+                Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => {
+                    host = server.to_string_lossy().into_owned();
+                    segments.push(encode(share));
+                }
+                Prefix::Verbatim(s) | Prefix::DeviceNS(s) => segments.push(encode(s)),
+            },
+            // a bare drive or share root keeps its trailing slash
+            Component::RootDir if components.peek().is_none() => segments.push(String::new()),
+            Component::RootDir => {}
+            Component::CurDir => segments.push(".".to_owned()),
+            Component::ParentDir => segments.push("..".to_owned()),
+            Component::Normal(s) => segments.push(encode(s)),
+        }
+    }
+    format!("file://{}/{}", host, segments.join("/"))
+}
+
+#[cfg(windows)]
+pub fn to_path(url: &str) -> Option<PathBuf> {
+    let rest = url.strip_prefix("file://")?;
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let path = percent_decode_str(path)
+        .decode_utf8_lossy()
+        .replace('/', "\\");
+    if host.is_empty() || host.eq_ignore_ascii_case("localhost") {
+        // `\C:\dir\a.png`, drop the separator in front of the drive letter
+        Some(PathBuf::from(path.strip_prefix('\\').unwrap_or(&path)))
+    } else {
+        Some(PathBuf::from(format!("\\\\{host}{path}")))
+    }
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(path.as_os_str().as_bytes())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        Cow::Borrowed(s) => Cow::Borrowed(s.as_bytes()),
+        Cow::Owned(s) => Cow::Owned(s.into_bytes()),
+    }
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub fn file_name<P: AsRef<Path>>(path: P) -> String {
+    path.as_ref()
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.as_ref().display().to_string())
+}
+
+// `/home/me/Pictures/keep` -> `~/Pictures/keep`
+pub fn short_display(path: &Path) -> String {
+    let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    match home
+        .as_deref()
+        .and_then(|home| path.strip_prefix(home).ok())
+    {
+        Some(rest) => Path::new("~").join(rest).display().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn url_escapes_spaces() {
+        assert_eq!(
+            to_url("/home/me/Screenshot 2024-01-01.png"),
+            "file:///home/me/Screenshot%202024-01-01.png"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn url_escapes_reserved_characters() {
+        assert_eq!(
+            to_url("/pics/#2 100%?.png"),
+            "file:///pics/%232%20100%25%3F.png"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn url_escapes_non_ascii() {
+        assert_eq!(
+            to_url("/写真/猫.jpg"),
+            "file:///%E5%86%99%E7%9C%9F/%E7%8C%AB.jpg"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn path_round_trips() {
+        for p in [
+            "/home/me/pic.jpg",
+            "/home/me/Screenshot 2024-01-01 #2.png",
+            "/写真/猫 (1).jpg",
+            "/a/%20literal/b?c.png",
+        ] {
+            assert_eq!(to_path(&to_url(p)), Some(PathBuf::from(p)));
+        }
+    }
+
+    #[test]
+    fn non_file_url_has_no_path() {
+        assert_eq!(to_path("https://example.com/a.png"), None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_drive_url() {
+        assert_eq!(
+            to_url(r"C:\Users\me\My Pictures\pic.jpg"),
+            "file:///C:/Users/me/My%20Pictures/pic.jpg"
+        );
+        assert_eq!(to_url(r"d:\pic.jpg"), "file:///d:/pic.jpg");
+        assert_eq!(to_url(r"C:\"), "file:///C:/");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_unc_url() {
+        assert_eq!(
+            to_url(r"\\server\share\img 1.png"),
+            "file://server/share/img%201.png"
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_url_to_path() {
+        assert_eq!(
+            to_path("file:///C:/Users/me/My%20Pictures/pic.jpg"),
+            Some(PathBuf::from(r"C:\Users\me\My Pictures\pic.jpg"))
+        );
+        assert_eq!(
+            to_path("file://server/share/img.png"),
+            Some(PathBuf::from(r"\\server\share\img.png"))
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_path_round_trips() {
+        for p in [
+            r"C:\Users\me\pic.jpg",
+            r"c:\Users\me\Screenshot 2024-01-01 #2.png",
+            r"E:\写真\猫 (1).jpg",
+            r"\\server\share\dir\img 1.png",
+        ] {
+            assert_eq!(to_path(&to_url(p)), Some(PathBuf::from(p)));
+        }
+    }
 }
@@ -0,0 +1,107 @@
+use std::{
+    borrow::Cow,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use eframe::egui::{self, load::BytesPoll};
+use image::RgbaImage;
+use log::debug;
+
+use crate::{histogram::Histogram, loader, pages, path::to_path};
+
+// the decoded pixels of an image, the texture egui keeps can't be read back and may be downscaled
+pub struct Pixels {
+    src: String,
+    decoding: Option<Receiver<Option<Decoded>>>,
+    // None until it was decoded, then None inside if that failed
+    decoded: Option<Option<Decoded>>,
+}
+
+struct Decoded {
+    image: RgbaImage,
+    histogram: Histogram,
+}
+
+impl Pixels {
+    pub fn new(src: &str) -> Self {
+        Self {
+            src: src.to_owned(),
+            decoding: None,
+            decoded: None,
+        }
+    }
+
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    // the pixels once they are decoded on a background thread from the bytes egui has read
+    pub fn get(&mut self, ctx: &egui::Context) -> Option<&RgbaImage> {
+        self.poll(ctx).map(|d| &d.image)
+    }
+
+    // counted on the same thread right after decoding
+    pub fn histogram(&mut self, ctx: &egui::Context) -> Option<&Histogram> {
+        self.poll(ctx).map(|d| &d.histogram)
+    }
+
+    // whether the image could not be decoded
+    pub fn failed(&self) -> bool {
+        matches!(self.decoded, Some(None))
+    }
+
+    fn poll(&mut self, ctx: &egui::Context) -> Option<&Decoded> {
+        if self.decoded.is_none() {
+            match &self.decoding {
+                Some(decoding) => {
+                    if let Ok(decoded) = decoding.try_recv() {
+                        self.decoded = Some(decoded);
+                    }
+                }
+                None => {
+                    match ctx.try_load_bytes(pages::split(&self.src).map_or(&self.src, |p| p.0)) {
+                        Ok(BytesPoll::Ready { bytes, .. }) => {
+                            let (tx, decoding) = mpsc::channel();
+                            let ctx = ctx.clone();
+                            let src = self.src.clone();
+                            thread::spawn(move || {
+                                let (file, page) = pages::split(&src).unwrap_or((&src, 0));
+                                // RAW files are recognized by the extension in the URL
+                                let path = to_path(file).unwrap_or_default();
+                                let bytes = match page {
+                                    0 => Some(Cow::Borrowed(bytes.as_ref())),
+                                    _ => pages::extract(&bytes, page).map(Cow::Owned),
+                                };
+                                let decoded = match bytes
+                                    .ok_or_else(|| "No such page".to_owned())
+                                    .and_then(|b| loader::load_file(&path, &b))
+                                {
+                                    Ok(image) => {
+                                        let image = image.into_rgba8();
+                                        let histogram = Histogram::new(&image);
+                                        Some(Decoded { image, histogram })
+                                    }
+                                    Err(err) => {
+                                        debug!("Failed to decode pixels of {:?}: {}", src, err);
+                                        None
+                                    }
+                                };
+                                if tx.send(decoded).is_ok() {
+                                    ctx.request_repaint();
+                                }
+                            });
+                            self.decoding = Some(decoding);
+                        }
+                        Ok(BytesPoll::Pending { .. }) => {}
+                        Err(err) => {
+                            debug!("No pixels of {:?}: {}", self.src, err);
+                            self.decoded = Some(None);
+                        }
+                    }
+                }
+            }
+        }
+        self.decoded.as_ref()?.as_ref()
+    }
+}
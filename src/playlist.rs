@@ -0,0 +1,103 @@
+// ordered lists of images across folders: one path per line, or an M3U file whose `#` lines
+// are skipped
+
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use eframe::egui;
+use log::{error, info};
+
+use crate::path::to_path;
+use crate::scan::ScanOptions;
+
+pub const EXTENSIONS: [&str; 3] = ["m3u", "m3u8", "txt"];
+
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EXTENSIONS.iter().any(|x| e.eq_ignore_ascii_case(x)))
+}
+
+// the entries in their order, relative ones resolved against the folder of the playlist
+pub fn read(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    Ok(parse(&text, dir))
+}
+
+fn parse(text: &str, dir: &Path) -> Vec<PathBuf> {
+    text.trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| to_path(line).unwrap_or_else(|| dir.join(line)))
+        .collect()
+}
+
+// the paths piped in as with `find … | view-rs -`, read on a thread until the producer is done
+// and left out unless they are images by `options`
+pub fn read_stdin(ctx: &egui::Context, options: ScanOptions) -> Receiver<Vec<PathBuf>> {
+    let (tx, done) = mpsc::channel();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        let mut text = String::new();
+        if let Err(err) = std::io::stdin().read_to_string(&mut text) {
+            error!("Failed to read the file list from stdin: {}", err);
+        }
+        let dir = std::env::current_dir().unwrap_or_default();
+        let entries = parse(&text, &dir);
+        let total = entries.len();
+        // `find .` prints `./a.png`
+        let files: Vec<PathBuf> = entries
+            .into_iter()
+            .map(|p| std::path::absolute(&p).unwrap_or(p))
+            .filter(|p| p.is_file() && options.accepts(p))
+            .collect();
+        info!("Read {} of {} paths from stdin", files.len(), total);
+        if tx.send(files).is_ok() {
+            ctx.request_repaint();
+        }
+    });
+    done
+}
+
+// `files` with absolute paths, M3U files get their header
+pub fn write(path: &Path, files: &[PathBuf]) -> Result<(), String> {
+    let m3u = path
+        .extension()
+        .is_some_and(|e| !e.eq_ignore_ascii_case("txt"));
+    let mut list = String::new();
+    if m3u {
+        list.push_str("#EXTM3U\n");
+    }
+    for file in files {
+        let file = std::path::absolute(file).unwrap_or_else(|_| file.clone());
+        list.push_str(&file.to_string_lossy());
+        list.push('\n');
+    }
+    std::fs::write(path, list).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_read_in_order() {
+        let text = "\u{feff}#EXTM3U\n/photos/b.jpg\r\n\n#EXTINF:-1,a\nsub/a.png\nfile:///photos/c%20d.jpg\n";
+        assert_eq!(
+            parse(text, Path::new("/lists")),
+            [
+                PathBuf::from("/photos/b.jpg"),
+                "/lists/sub/a.png".into(),
+                "/photos/c d.jpg".into()
+            ]
+        );
+        assert!(is_playlist(Path::new("best.M3U")));
+        assert!(!is_playlist(Path::new("best.jpg")));
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::HashSet;
+
+use eframe::egui::{
+    self, TextureOptions,
+    load::{SizeHint, TexturePoll},
+};
+use log::{debug, warn};
+
+// loads the neighbours of the current image so navigating to them doesn't wait for the decoder
+pub struct Prefetch {
+    // number of images to load in each direction
+    pub distance: usize,
+    // neighbours that are not loaded yet, nearest first
+    pending: Vec<String>,
+    // neighbours whose texture is resident
+    loaded: HashSet<String>,
+}
+
+impl Prefetch {
+    pub fn new(distance: usize) -> Self {
+        Self {
+            distance,
+            pending: Vec::new(),
+            loaded: HashSet::new(),
+        }
+    }
+
+    // drop all bookkeeping after the loader caches were cleared
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.loaded.clear();
+    }
+
+    // switch to the neighbours of the image at `current`, forgetting earlier ones that are out of
+    // range now
+    pub fn retarget(&mut self, ctx: &egui::Context, current: &str, mut wanted: Vec<String>) {
+        // loads that were started for an earlier position and are not needed anymore
+        let stale = self
+            .loaded
+            .iter()
+            .chain(&self.pending)
+            .filter(|uri| *uri != current && !wanted.contains(uri))
+            .cloned()
+            .collect::<Vec<_>>();
+        for uri in stale {
+            debug!("Forgetting prefetched {}", uri);
+            ctx.forget_image(&uri);
+            self.loaded.remove(&uri);
+        }
+        // the current image is owned by the display from now on
+        self.loaded.remove(current);
+
+        wanted.retain(|uri| !self.loaded.contains(uri));
+        self.pending = wanted;
+    }
+
+    // advance the pending loads with the `options` the display asks for, called every frame once
+    // the current image is ready so fast navigation doesn't pile up loads for skipped images
+    pub fn poll(&mut self, ctx: &egui::Context, options: TextureOptions) {
+        self.pending.retain(
+            |uri| match ctx.try_load_texture(uri, options, SizeHint::default()) {
+                Ok(TexturePoll::Pending { .. }) => true,
+                Ok(TexturePoll::Ready { .. }) => {
+                    self.loaded.insert(uri.clone());
+                    false
+                }
+                Err(err) => {
+                    warn!("Failed to prefetch {}: {}", uri, err);
+                    false
+                }
+            },
+        );
+    }
+}
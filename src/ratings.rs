@@ -0,0 +1,302 @@
+// star ratings from 1 to 5, kept in a file in the data directory or in XMP sidecars next to the
+// images where other photo tools read them
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use directories::ProjectDirs;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{read_toml, write_toml};
+
+const XMP: &str = "http://ns.adobe.com/xap/1.0/";
+
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum RatingStore {
+    #[default]
+    Central,
+    // `photo.jpg.xmp`, `photo.xmp` is read too
+    Sidecar,
+}
+
+impl RatingStore {
+    pub const ALL: [RatingStore; 2] = [RatingStore::Central, RatingStore::Sidecar];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RatingStore::Central => "In the data folder",
+            RatingStore::Sidecar => "XMP sidecar files",
+        }
+    }
+}
+
+// `ratings.toml`, by path
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Central {
+    ratings: BTreeMap<String, u8>,
+}
+
+#[derive(Default)]
+pub struct Ratings {
+    store: RatingStore,
+    central: Central,
+    // sidecars read so far, 0 where there is none
+    read: HashMap<PathBuf, u8>,
+}
+
+impl Ratings {
+    pub fn load(store: RatingStore) -> Self {
+        let central = match (store, central_path()) {
+            (RatingStore::Central, Some(path)) => read_toml(&path),
+            _ => Central::default(),
+        };
+        Self {
+            store,
+            central,
+            read: HashMap::new(),
+        }
+    }
+
+    pub fn store(&self) -> RatingStore {
+        self.store
+    }
+
+    // 0 for files that are not rated
+    pub fn get(&mut self, file: &Path) -> u8 {
+        match self.store {
+            RatingStore::Central => self
+                .central
+                .ratings
+                .get(&*file.to_string_lossy())
+                .copied()
+                .unwrap_or(0),
+            RatingStore::Sidecar => *self.read.entry(file.to_path_buf()).or_insert_with(|| {
+                existing_sidecar(file)
+                    .and_then(|p| std::fs::read_to_string(p).ok())
+                    .and_then(|text| read_rating(&text))
+                    .unwrap_or(0)
+            }),
+        }
+    }
+
+    // the files with at least `min` stars, all of the rated ones when they are kept centrally so
+    // files found later are included
+    pub fn at_least<'a>(
+        &mut self,
+        min: u8,
+        files: impl Iterator<Item = &'a Path>,
+    ) -> HashSet<PathBuf> {
+        match self.store {
+            RatingStore::Central => self
+                .central
+                .ratings
+                .iter()
+                .filter(|&(_, &rating)| rating >= min)
+                .map(|(path, _)| PathBuf::from(path))
+                .collect(),
+            RatingStore::Sidecar => files
+                .filter(|&file| self.get(file) >= min)
+                .map(Path::to_path_buf)
+                .collect(),
+        }
+    }
+
+    // 0 clears the rating
+    pub fn set(&mut self, file: &Path, rating: u8) -> Result<(), String> {
+        let rating = rating.min(5);
+        match self.store {
+            RatingStore::Central => {
+                let key = file.to_string_lossy().into_owned();
+                if rating == 0 {
+                    self.central.ratings.remove(&key);
+                } else {
+                    self.central.ratings.insert(key, rating);
+                }
+                self.save()
+            }
+            RatingStore::Sidecar => {
+                let sidecar = existing_sidecar(file).unwrap_or_else(|| sidecar(file));
+                let old = std::fs::read_to_string(&sidecar).ok();
+                // a sidecar is not created just to say there is no rating
+                if old.is_some() || rating > 0 {
+                    std::fs::write(&sidecar, write_rating(old.as_deref(), rating))
+                        .map_err(|e| e.to_string())?;
+                }
+                self.read.insert(file.to_path_buf(), rating);
+                Ok(())
+            }
+        }
+    }
+
+    // the rating follows a file that was moved or renamed
+    pub fn moved(&mut self, from: &Path, to: &Path) {
+        match self.store {
+            RatingStore::Central => {
+                let Some(rating) = self.central.ratings.remove(&*from.to_string_lossy()) else {
+                    return;
+                };
+                self.central
+                    .ratings
+                    .insert(to.to_string_lossy().into_owned(), rating);
+                if let Err(err) = self.save() {
+                    warn!("Failed to save ratings: {}", err);
+                }
+            }
+            RatingStore::Sidecar => {
+                self.read.remove(from);
+                let Some(old) = existing_sidecar(from) else {
+                    return;
+                };
+                let new = sidecar(to);
+                debug!("Moving sidecar {:?} to {:?}", old, new);
+                let moved = std::fs::rename(&old, &new).or_else(|_| {
+                    std::fs::copy(&old, &new).and_then(|_| std::fs::remove_file(&old))
+                });
+                if let Err(err) = moved {
+                    warn!("Failed to move {:?} along: {}", old, err);
+                }
+            }
+        }
+    }
+
+    // a file was deleted, its sidecar goes to the trash with it
+    pub fn removed(&mut self, file: &Path) {
+        match self.store {
+            RatingStore::Central => {
+                if self
+                    .central
+                    .ratings
+                    .remove(&*file.to_string_lossy())
+                    .is_some()
+                {
+                    if let Err(err) = self.save() {
+                        warn!("Failed to save ratings: {}", err);
+                    }
+                }
+            }
+            RatingStore::Sidecar => {
+                self.read.remove(file);
+                if let Some(sidecar) = existing_sidecar(file) {
+                    if let Err(err) = trash::delete(&sidecar) {
+                        warn!("Failed to move {:?} to the trash: {}", sidecar, err);
+                    }
+                }
+            }
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let path = central_path().ok_or("No data directory")?;
+        write_toml(&path, &self.central)
+    }
+}
+
+// 3 -> "★★★☆☆"
+pub fn stars(rating: u8) -> String {
+    (1..=5)
+        .map(|i| if i <= rating { '★' } else { '☆' })
+        .collect()
+}
+
+fn central_path() -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("", "", "view-rs")?;
+    Some(dirs.data_local_dir().join("ratings.toml"))
+}
+
+fn sidecar(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(".xmp");
+    PathBuf::from(name)
+}
+
+fn existing_sidecar(file: &Path) -> Option<PathBuf> {
+    [sidecar(file), file.with_extension("xmp")]
+        .into_iter()
+        .find(|p| p.is_file())
+}
+
+// `xmp:Rating` as an attribute or an element, -1 for rejected photos counts as none
+fn read_rating(xmp: &str) -> Option<u8> {
+    let doc = roxmltree::Document::parse(xmp).ok()?;
+    let value = doc.descendants().find_map(|node| {
+        node.attribute((XMP, "Rating")).or_else(|| {
+            (node.tag_name().namespace() == Some(XMP) && node.tag_name().name() == "Rating")
+                .then(|| node.text())
+                .flatten()
+        })
+    })?;
+    let rating: f32 = value.trim().parse().ok()?;
+    Some(rating.clamp(0.0, 5.0) as u8)
+}
+
+// `xmp` with the rating changed, written by other tools or a new sidecar
+fn write_rating(xmp: Option<&str>, rating: u8) -> String {
+    let Some(xmp) = xmp.filter(|x| x.contains("<rdf:Description")) else {
+        return format!(
+            concat!(
+                "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n",
+                " <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+                "  <rdf:Description rdf:about=\"\" xmlns:xmp=\"{}\" xmp:Rating=\"{}\"/>\n",
+                " </rdf:RDF>\n",
+                "</x:xmpmeta>\n"
+            ),
+            XMP, rating
+        );
+    };
+    for (open, close) in [("xmp:Rating=\"", "\""), ("<xmp:Rating>", "</xmp:Rating>")] {
+        if let Some(start) = xmp.find(open).map(|i| i + open.len()) {
+            if let Some(len) = xmp[start..].find(close) {
+                return format!("{}{}{}", &xmp[..start], rating, &xmp[start + len..]);
+            }
+        }
+    }
+    // added to the first description, with the namespace if it is not declared
+    let at = xmp.find("<rdf:Description").unwrap_or(0) + "<rdf:Description".len();
+    let namespace = if xmp.contains("xmlns:xmp=") {
+        String::new()
+    } else {
+        format!(" xmlns:xmp=\"{}\"", XMP)
+    };
+    format!(
+        "{}{} xmp:Rating=\"{}\"{}",
+        &xmp[..at],
+        namespace,
+        rating,
+        &xmp[at..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sidecars_are_read_and_updated() {
+        let new = write_rating(None, 4);
+        assert_eq!(read_rating(&new), Some(4));
+        assert_eq!(read_rating(&write_rating(Some(&new), 2)), Some(2));
+
+        // a sidecar written by another tool keeps what it holds
+        let other = concat!(
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF ",
+            "xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">",
+            "<rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">",
+            "<dc:title>Harbour</dc:title></rdf:Description></rdf:RDF></x:xmpmeta>"
+        );
+        assert_eq!(read_rating(other), None);
+        let rated = write_rating(Some(other), 5);
+        assert_eq!(read_rating(&rated), Some(5));
+        assert!(rated.contains("<dc:title>Harbour</dc:title>"));
+
+        let element = rated
+            .replace(" xmp:Rating=\"5\"", "")
+            .replace("<dc:title>", "<xmp:Rating>-1</xmp:Rating><dc:title>");
+        assert_eq!(read_rating(&element), Some(0));
+        assert_eq!(read_rating(&write_rating(Some(&element), 3)), Some(3));
+        assert_eq!(stars(3), "★★★☆☆");
+    }
+}
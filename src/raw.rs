@@ -0,0 +1,274 @@
+use std::{io::Cursor, path::Path};
+
+use image::{DynamicImage, ImageFormat, RgbImage};
+
+// TIFF-based camera formats, listed only when enabled in the Files menu
+pub const EXTENSIONS: [&str; 9] = [
+    "cr2", "nef", "nrw", "arw", "dng", "orf", "rw2", "pef", "srw",
+];
+// embedded previews smaller than this are only used when the sensor data can't be read
+const MIN_PREVIEW: u32 = 1024;
+// image file directories followed per file
+const MAX_DIRECTORIES: usize = 64;
+
+pub fn is_raw(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+}
+
+// the largest JPEG the camera embedded, else the sensor data at half size without any processing
+// but white balance and the color matrix
+pub fn decode(bytes: &[u8]) -> Result<DynamicImage, String> {
+    let preview = previews(bytes)
+        .into_iter()
+        .max_by_key(|p| p.width * p.height);
+    if let Some(preview) = &preview {
+        if preview.width.max(preview.height) >= MIN_PREVIEW {
+            return load_preview(bytes, preview);
+        }
+    }
+    match (demosaic(bytes), preview) {
+        (Ok(image), _) => Ok(image),
+        (Err(err), None) => Err(err),
+        (Err(_), Some(preview)) => load_preview(bytes, &preview),
+    }
+}
+
+fn load_preview(bytes: &[u8], preview: &Preview) -> Result<DynamicImage, String> {
+    image::load_from_memory_with_format(&bytes[preview.range.clone()], ImageFormat::Jpeg)
+        .map_err(|e| e.to_string())
+}
+
+struct Preview {
+    range: std::ops::Range<usize>,
+    width: u32,
+    height: u32,
+}
+
+// the JPEGs pointed to by the directories of the TIFF structure most RAW formats use
+fn previews(bytes: &[u8]) -> Vec<Preview> {
+    let big_endian = bytes.starts_with(b"MM");
+    if !big_endian && !bytes.starts_with(b"II") {
+        return Vec::new();
+    }
+    let read = |at: usize, len: usize| -> Option<u32> {
+        let b = bytes.get(at..at.checked_add(len)?)?;
+        Some(b.iter().enumerate().fold(0, |n, (i, &byte)| {
+            let shift = if big_endian { len - 1 - i } else { i } * 8;
+            n | u32::from(byte) << shift
+        }))
+    };
+
+    let mut found = Vec::new();
+    let mut pending = read(4, 4).into_iter().collect::<Vec<_>>();
+    let mut visited = Vec::new();
+    while let Some(ifd) = pending.pop() {
+        let ifd = ifd as usize;
+        if ifd == 0 || visited.contains(&ifd) || visited.len() >= MAX_DIRECTORIES {
+            continue;
+        }
+        visited.push(ifd);
+        let Some(count) = read(ifd, 2) else {
+            continue;
+        };
+        // tag to (type, count, value or offset)
+        let entries = (0..count as usize)
+            .filter_map(|i| {
+                let at = ifd + 2 + i * 12;
+                Some((read(at, 2)?, (read(at + 2, 2)?, read(at + 4, 4)?, at + 8)))
+            })
+            .collect::<std::collections::HashMap<_, _>>();
+        // SHORT and LONG values, stored in the entry itself when a single one fits
+        let value = |tag: u32| -> Option<u32> {
+            let &(ty, _, at) = entries.get(&tag)?;
+            match ty {
+                3 => read(at, 2),
+                4 => read(at, 4),
+                _ => None,
+            }
+        };
+
+        // JPEGInterchangeFormat and its length, the preview strip of CR2 and DNG, JpgFromRaw of RW2
+        let mut ranges = Vec::new();
+        if let (Some(start), Some(len)) = (value(0x201), value(0x202)) {
+            ranges.push((start, len));
+        }
+        if matches!(value(0x103), Some(6 | 7)) && entries.get(&0x111).is_some_and(|e| e.1 == 1) {
+            if let (Some(start), Some(len)) = (value(0x111), value(0x117)) {
+                ranges.push((start, len));
+            }
+        }
+        if let Some(&(7, len, at)) = entries.get(&0x2e) {
+            ranges.extend(read(at, 4).map(|start| (start, len)));
+        }
+        for (start, len) in ranges {
+            let range = start as usize..start as usize + len as usize;
+            if let Some((width, height)) = bytes.get(range.clone()).and_then(jpeg_size) {
+                found.push(Preview {
+                    range,
+                    width,
+                    height,
+                });
+            }
+        }
+
+        // SubIFDs hold the previews of NEF and DNG files
+        if let Some(&(4 | 13, n, at)) = entries.get(&0x14a) {
+            let at = if n == 1 {
+                at
+            } else {
+                read(at, 4).unwrap_or(0) as usize
+            };
+            pending.extend((0..n.min(8) as usize).filter_map(|i| read(at + i * 4, 4)));
+        }
+        pending.extend(read(ifd + 2 + count as usize * 12, 4));
+    }
+    found
+}
+
+// the size in the frame header of a JPEG the image crate can decode, raw sensor data is often
+// stored as lossless JPEG which it can't
+fn jpeg_size(jpeg: &[u8]) -> Option<(u32, u32)> {
+    if !jpeg.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+    let mut at = 2;
+    loop {
+        let marker = *jpeg.get(at + 1)?;
+        let len = usize::from(u16::from_be_bytes([*jpeg.get(at + 2)?, *jpeg.get(at + 3)?]));
+        match marker {
+            // baseline, extended and progressive Huffman frames
+            0xc0..=0xc2 => {
+                let frame = jpeg.get(at + 5..at + 9)?;
+                let height = u16::from_be_bytes([frame[0], frame[1]]);
+                let width = u16::from_be_bytes([frame[2], frame[3]]);
+                return Some((u32::from(width), u32::from(height)));
+            }
+            0xc3..=0xcf if marker != 0xc4 && marker != 0xc8 && marker != 0xcc => return None,
+            0xda => return None,
+            _ => at += 2 + len,
+        }
+    }
+}
+
+// XYZ (D65) to linear sRGB
+const XYZ_TO_SRGB: [[f32; 3]; 3] = [
+    [3.2404542, -1.5371385, -0.4985314],
+    [-0.969266, 1.8760108, 0.041556],
+    [0.0556434, -0.2040259, 1.0572252],
+];
+
+// every 2×2 block of the sensor becomes one pixel, so no interpolation is needed
+fn demosaic(bytes: &[u8]) -> Result<DynamicImage, String> {
+    // rawloader panics on some files it does not expect
+    let raw = std::panic::catch_unwind(|| rawloader::decode(&mut Cursor::new(bytes)))
+        .map_err(|_| "The sensor data could not be read".to_owned())?
+        .map_err(|e| e.to_string())?;
+    let data: Vec<f32> = match &raw.data {
+        rawloader::RawImageData::Integer(data) => data.iter().map(|&v| f32::from(v)).collect(),
+        rawloader::RawImageData::Float(data) => data.clone(),
+    };
+    let [top, right, bottom, left] = raw.crops;
+    let width = raw.width.saturating_sub(left + right);
+    let height = raw.height.saturating_sub(top + bottom);
+    let cfa = raw.cropped_cfa();
+
+    let mut wb = raw.wb_coeffs;
+    if !wb[0].is_normal() || !wb[1].is_normal() || !wb[2].is_normal() {
+        wb = raw.neutralwb();
+    }
+    let wb = [wb[0] / wb[1], 1.0, wb[2] / wb[1], 1.0];
+    // cameras missing from the rawloader database have no matrix
+    let cam_to_xyz = raw.cam_to_xyz_normalized();
+    let matrix = cam_to_xyz.iter().flatten().all(|v| v.is_finite())
+        && cam_to_xyz.iter().flatten().any(|&v| v != 0.0);
+    let channel = |c: usize, v: f32| {
+        let black = f32::from(raw.blacklevels[c]);
+        let white = f32::from(raw.whitelevels[c]);
+        ((v - black) / (white - black).max(1.0)).max(0.0) * wb[c]
+    };
+
+    let mut image = RgbImage::new((width / 2) as u32, (height / 2) as u32);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let (x, y) = (x as usize * 2, y as usize * 2);
+        let mut sums = [0.0; 4];
+        let mut counts = [0.0f32; 4];
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let c = if raw.cpp == 1 {
+                cfa.color_at(y + dy, x + dx)
+            } else {
+                1
+            };
+            let at = (top + y + dy) * raw.width + left + x + dx;
+            if raw.cpp == 1 {
+                sums[c] += channel(c, data[at]);
+                counts[c] += 1.0;
+            } else {
+                for (c, sum) in sums.iter_mut().enumerate().take(3) {
+                    *sum += channel(c, data[at * raw.cpp + c]);
+                    counts[c] += 1.0;
+                }
+            }
+        }
+        let mut cam = [0.0; 4];
+        for c in 0..4 {
+            cam[c] = sums[c] / counts[c].max(1.0);
+        }
+        // the fourth color of CYGM and RGBE sensors is counted as green when not present
+        if counts[3] == 0.0 {
+            cam[3] = cam[1];
+        }
+        let rgb = if matrix {
+            let xyz = cam_to_xyz.map(|row| (0..4).map(|c| row[c] * cam[c]).sum::<f32>());
+            XYZ_TO_SRGB.map(|row| (0..3).map(|c| row[c] * xyz[c]).sum::<f32>())
+        } else {
+            [cam[0], cam[1], cam[2]]
+        };
+        pixel.0 = rgb.map(encode_srgb);
+    }
+    Ok(DynamicImage::ImageRgb8(image))
+}
+
+fn encode_srgb(linear: f32) -> u8 {
+    let v = linear.clamp(0.0, 1.0);
+    let v = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (v * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_embedded_jpeg() {
+        // a TIFF whose only directory points at a 2×1 baseline JPEG frame header
+        let mut raw = b"II*\0\x08\0\0\0".to_vec();
+        raw.extend_from_slice(&2u16.to_le_bytes());
+        for (tag, value) in [(0x201u16, 38u32), (0x202, 21)] {
+            raw.extend_from_slice(&tag.to_le_bytes());
+            raw.extend_from_slice(&4u16.to_le_bytes());
+            raw.extend_from_slice(&1u32.to_le_bytes());
+            raw.extend_from_slice(&value.to_le_bytes());
+        }
+        raw.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(raw.len(), 38);
+        raw.extend_from_slice(&[0xff, 0xd8, 0xff, 0xc0, 0, 17, 8, 0, 1, 0, 2, 3]);
+        raw.extend_from_slice(&[1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1]);
+        let found = previews(&raw);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].width, found[0].height), (2, 1));
+        assert_eq!(found[0].range, 38..59);
+
+        // lossless JPEG holds sensor data, not a picture
+        assert_eq!(
+            jpeg_size(&[0xff, 0xd8, 0xff, 0xc3, 0, 11, 8, 0, 1, 0, 2]),
+            None
+        );
+        assert!(is_raw(Path::new("a/DSC_0001.NEF")));
+    }
+}
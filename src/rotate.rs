@@ -0,0 +1,182 @@
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
+    process::Command,
+};
+
+use image::{ImageFormat, ImageReader};
+
+use crate::{loader, metadata, path::file_name, raw, view::Transform};
+
+pub enum Rotated {
+    // the pixels were moved without decoding them
+    Lossless,
+    // decoded, turned and encoded again, never done to JPEGs
+    Reencoded,
+}
+
+// turn the pixels of `path` by a quarter, with its EXIF orientation applied and reset to 1
+pub fn rotate_file(path: &Path, clockwise: bool) -> Result<Rotated, String> {
+    let mut turn = Transform::default();
+    turn.rotate(clockwise);
+    let stored =
+        metadata::orientation(path).map_or_else(Transform::default, Transform::from_orientation);
+    let transform = stored.then(turn);
+    if raw::is_raw(path) {
+        return Err("RAW files can't be rotated".to_owned());
+    }
+
+    // written next to the file and moved over it, so a failure leaves the original alone
+    let tmp = path.with_file_name(format!(".{}.rotating", file_name(path)));
+    let reader = ImageReader::open(path)
+        .and_then(|r| r.with_guessed_format())
+        .map_err(|e| e.to_string())?;
+    let result = match reader.format() {
+        // encoding them again would lose quality and the EXIF data the image crate drops
+        Some(ImageFormat::Jpeg) => jpegtran(path, &tmp, transform)
+            .map(|()| Rotated::Lossless)
+            .map_err(|err| format!("JPEGs are only rotated without loss by jpegtran, {}", err)),
+        _ => reencode(reader, &tmp, transform).map(|()| Rotated::Reencoded),
+    };
+    match result.and_then(|rotated| {
+        fs::rename(&tmp, path)
+            .map(|()| rotated)
+            .map_err(|e| e.to_string())
+    }) {
+        Ok(rotated) => Ok(rotated),
+        Err(err) => {
+            let _ = fs::remove_file(&tmp);
+            Err(err)
+        }
+    }
+}
+
+// transform the DCT blocks with jpegtran, which refuses images whose size isn't a multiple of
+// the block size instead of dropping the edge
+fn jpegtran(path: &Path, out: &Path, transform: Transform) -> Result<(), String> {
+    let op: &[&str] = match transform.orientation() {
+        2 => &["-flip", "horizontal"],
+        3 => &["-rotate", "180"],
+        4 => &["-flip", "vertical"],
+        5 => &["-transpose"],
+        6 => &["-rotate", "90"],
+        7 => &["-transverse"],
+        8 => &["-rotate", "270"],
+        _ => &[],
+    };
+    let output = Command::new("jpegtran")
+        .args(["-copy", "all", "-perfect"])
+        .args(op)
+        .arg("-outfile")
+        .arg(out)
+        .arg(path)
+        .output()
+        .map_err(|e| format!("jpegtran: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+    // the orientation was copied along with the other metadata
+    let mut bytes = fs::read(out).map_err(|e| e.to_string())?;
+    if reset_orientation(&mut bytes) {
+        fs::write(out, bytes).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn reencode(
+    mut reader: ImageReader<std::io::BufReader<File>>,
+    out: &Path,
+    transform: Transform,
+) -> Result<(), String> {
+    let format = reader.format().ok_or("Unknown image format")?;
+    reader.limits(loader::limits());
+    let image = transform.apply(reader.decode().map_err(|e| e.to_string())?);
+    let mut file = BufWriter::new(File::create(out).map_err(|e| e.to_string())?);
+    image.write_to(&mut file, format).map_err(|e| e.to_string())
+}
+
+// set the EXIF orientation of the JPEG in `bytes` to 1, returns whether it had one
+fn reset_orientation(bytes: &mut [u8]) -> bool {
+    // the segments after the start of image marker, until the image data starts
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF && bytes[pos + 1] != 0xDA {
+        let len = usize::from(u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]));
+        let end = (pos + 2 + len).min(bytes.len());
+        let marker = bytes[pos + 1];
+        let body = &mut bytes[pos + 4..end];
+        if marker == 0xE1 && body.starts_with(b"Exif\0\0") {
+            return reset_in_tiff(&mut body[6..]);
+        }
+        pos += 2 + len;
+    }
+    false
+}
+
+// the Orientation entry of the first IFD in a TIFF structure
+fn reset_in_tiff(tiff: &mut [u8]) -> bool {
+    let big = match tiff.get(..2) {
+        Some(b"MM") => true,
+        Some(b"II") => false,
+        _ => return false,
+    };
+    let u16_at = |tiff: &[u8], i: usize| {
+        let b = tiff.get(i..i + 2)?;
+        Some(if big {
+            u16::from_be_bytes([b[0], b[1]])
+        } else {
+            u16::from_le_bytes([b[0], b[1]])
+        })
+    };
+    let Some(ifd) = tiff.get(4..8).map(|b| {
+        let b = [b[0], b[1], b[2], b[3]];
+        if big {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        }
+    }) else {
+        return false;
+    };
+    let ifd = ifd as usize;
+    let count = u16_at(tiff, ifd).unwrap_or(0);
+    for i in 0..usize::from(count) {
+        let entry = ifd + 2 + i * 12;
+        // tag 0x0112 of type SHORT holds its value inline
+        if u16_at(tiff, entry) == Some(0x0112) && u16_at(tiff, entry + 2) == Some(3) {
+            let one = if big { [0, 1] } else { [1, 0] };
+            return match tiff.get_mut(entry + 8..entry + 10) {
+                Some(value) => {
+                    value.copy_from_slice(&one);
+                    true
+                }
+                None => false,
+            };
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orientation_is_reset() {
+        let mut tiff = b"II*\0\x08\0\0\0\x01\0".to_vec();
+        // Orientation, SHORT, 1 value: 6
+        tiff.extend_from_slice(b"\x12\x01\x03\0\x01\0\0\0\x06\0\0\0");
+        tiff.extend_from_slice(b"\0\0\0\0");
+        let mut jpeg = b"\xFF\xD8\xFF\xE1".to_vec();
+        jpeg.extend_from_slice(&(2 + 6 + tiff.len() as u16).to_be_bytes());
+        jpeg.extend_from_slice(b"Exif\0\0");
+        jpeg.extend_from_slice(&tiff);
+        jpeg.extend_from_slice(b"\xFF\xDA");
+
+        let value = 4 + 2 + 6 + 10 + 8;
+        assert_eq!(jpeg[value], 6);
+        assert!(reset_orientation(&mut jpeg));
+        assert_eq!(jpeg[value], 1);
+        assert!(!reset_orientation(&mut b"\xFF\xD8\xFF\xDA".to_vec()));
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    collections::HashSet,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use eframe::egui;
+use log::warn;
+
+use crate::{jxl, path::PathSortable};
+
+pub const EXTENSIONS: [&str; 11] = [
+    "jpg", "jpeg", "png", "bmp", "gif", "webp", "avif", "tif", "tiff", "svg", "jxl",
+];
+
+// found files are sent once a batch is this large or this old
+const BATCH_SIZE: usize = 512;
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+// which files a scan picks up
+#[derive(Clone)]
+pub struct ScanOptions {
+    // descend into subdirectories
+    pub recursive: bool,
+    // include files and directories starting with a dot
+    pub hidden: bool,
+    // lowercase, without the dot
+    pub extensions: Vec<String>,
+    // also accept files whose first bytes look like an image, whatever their name
+    pub sniff: bool,
+}
+
+impl ScanOptions {
+    pub fn accepts(&self, path: &Path) -> bool {
+        let known = path
+            .extension()
+            .and_then(|s| s.to_str())
+            .is_some_and(|ext| self.extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)));
+        known || (self.sniff && sniff(path))
+    }
+}
+
+// whether the file starts with the signature of an image format
+fn sniff(path: &Path) -> bool {
+    let mut head = [0; 32];
+    let Ok(n) = std::fs::File::open(path).and_then(|mut f| f.read(&mut head)) else {
+        return false;
+    };
+    let head = &head[..n];
+    // ISO base media files (AVIF, HEIF) name their brand in the `ftyp` box
+    let bmff = head.len() >= 12
+        && &head[4..8] == b"ftyp"
+        && [
+            &b"avif"[..],
+            b"avis",
+            b"heic",
+            b"heix",
+            b"heim",
+            b"heis",
+            b"mif1",
+            b"msf1",
+        ]
+        .contains(&&head[8..12]);
+    bmff || jxl::is_jxl_data(head) || image::guess_format(head).is_ok()
+}
+
+pub enum ScanEvent {
+    Found(Vec<PathSortable>),
+    Failed(String),
+    Done,
+}
+
+// a directory scan running on a background thread, cancelled when dropped
+pub struct Scan {
+    // what is being read, for log messages
+    pub label: String,
+    // file that is already displayed and must not be added twice
+    pub keep: Option<PathBuf>,
+    pub keep_found: bool,
+    events: Receiver<ScanEvent>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Scan {
+    pub fn start(
+        ctx: &egui::Context,
+        dir: &Path,
+        options: ScanOptions,
+        keep: Option<PathBuf>,
+    ) -> Self {
+        let root = dir.to_path_buf();
+        Self::spawn(ctx, format!("{:?}", dir), keep, move |flag, batch| {
+            walk(&root, &options, flag, |p| batch.push(p))
+        })
+    }
+
+    // read a mix of files and directories, e.g. a drop from a file manager, skipping the ones
+    // that can't be read
+    pub fn start_list(ctx: &egui::Context, paths: Vec<PathBuf>, options: ScanOptions) -> Self {
+        let label = format!("{} dropped items", paths.len());
+        Self::spawn(ctx, label, None, move |flag, batch| {
+            for path in paths {
+                if path.is_dir() {
+                    // the directories themselves are always expanded
+                    if let Err(err) = walk(&path, &options, flag, |p| batch.push(p)) {
+                        warn!("Skipping {:?}: {}", path, err);
+                    }
+                } else if path.is_file() && options.accepts(&path) {
+                    batch.push(path);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn spawn(
+        ctx: &egui::Context,
+        label: String,
+        keep: Option<PathBuf>,
+        run: impl FnOnce(&AtomicBool, &mut Batch) -> Result<(), String> + Send + 'static,
+    ) -> Self {
+        let (tx, events) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let ctx = ctx.clone();
+        let flag = cancel.clone();
+        thread::spawn(move || {
+            let mut batch = Batch {
+                tx,
+                ctx,
+                files: Vec::new(),
+                since: Instant::now(),
+            };
+            let result = run(&flag, &mut batch);
+            batch.flush();
+            let event = match result {
+                Ok(()) => ScanEvent::Done,
+                Err(err) => ScanEvent::Failed(err),
+            };
+            batch.send(event);
+        });
+
+        Self {
+            label,
+            keep,
+            keep_found: false,
+            events,
+            cancel,
+        }
+    }
+
+    // events that arrived since the last call
+    pub fn poll(&mut self) -> Vec<ScanEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+impl Drop for Scan {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+struct Batch {
+    tx: Sender<ScanEvent>,
+    ctx: egui::Context,
+    files: Vec<PathSortable>,
+    since: Instant,
+}
+
+impl Batch {
+    fn push(&mut self, path: PathBuf) {
+        self.files.push(PathSortable::from(path));
+        if self.files.len() >= BATCH_SIZE || self.since.elapsed() >= BATCH_INTERVAL {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if !self.files.is_empty() {
+            let files = std::mem::take(&mut self.files);
+            self.send(ScanEvent::Found(files));
+        }
+        self.since = Instant::now();
+    }
+
+    fn send(&self, event: ScanEvent) {
+        // the receiver is gone when the scan was replaced, nothing left to do then
+        if self.tx.send(event).is_ok() {
+            self.ctx.request_repaint();
+        }
+    }
+}
+
+// call `found` for every image file in `root` that `options` accepts
+fn walk(
+    root: &Path,
+    options: &ScanOptions,
+    cancel: &AtomicBool,
+    mut found: impl FnMut(PathBuf),
+) -> Result<(), String> {
+    let mut pending = vec![root.to_path_buf()];
+    // canonical paths of the directories already read, so symlink loops are only entered once
+    let mut visited = HashSet::new();
+
+    while let Some(dir) = pending.pop() {
+        if let Ok(canonical) = dir.canonicalize() {
+            if !visited.insert(canonical) {
+                continue;
+            }
+        }
+
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            // only the directory that was asked for has to be readable
+            Err(e) if dir == root => return Err(e.to_string()),
+            Err(e) => {
+                warn!("Skipping unreadable directory {:?}: {}", dir, e);
+                continue;
+            }
+        };
+
+        let entries = entries
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| {
+                options.hidden
+                    || p.file_name()
+                        .is_some_and(|n| !n.to_string_lossy().starts_with('.'))
+            });
+        for p in entries {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            if p.is_dir() {
+                if options.recursive {
+                    pending.push(p);
+                }
+            } else if p.is_file() && options.accepts(&p) {
+                found(p);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heic_photos_are_listed_by_default() {
+        let dir = std::env::temp_dir().join(format!("view-rs-scan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["IMG_0001.HEIC", "IMG_0002.heif", "notes.txt"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let options = ScanOptions {
+            recursive: false,
+            hidden: false,
+            extensions: crate::settings::Settings::default().extensions,
+            sniff: false,
+        };
+        let mut found = Vec::new();
+        walk(&dir, &options, &AtomicBool::new(false), |p| found.push(p)).unwrap();
+        found.sort();
+        // decoded or not, the error screen tells when the build can't
+        assert_eq!(
+            found,
+            [dir.join("IMG_0001.HEIC"), dir.join("IMG_0002.heif")]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
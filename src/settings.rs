@@ -0,0 +1,348 @@
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+use eframe::egui;
+use log::{info, warn};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::hdr::ToneMap;
+use crate::keys::Keybindings;
+use crate::overlay::Overlay;
+use crate::ratings::RatingStore;
+use crate::scan::EXTENSIONS;
+use crate::wallpaper::WallpaperMode;
+use crate::{heic, icon};
+
+// a folder the current file can be moved to with a number key
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Destination {
+    // 1-9
+    pub key: u8,
+    pub dir: PathBuf,
+}
+
+// a program run on the current file from the right-click menu, see `external::expand`
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tool {
+    pub name: String,
+    // like `oxipng "{path}"`
+    pub command: String,
+    // a shortcut like `Ctrl+U`, none if empty
+    pub key: String,
+    // load the image again once the program is done
+    pub refresh: bool,
+}
+
+// light or dark widgets, the system's follows it when it changes
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Light, Theme::Dark];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::System => "Follow the system",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+        }
+    }
+
+    pub fn preference(self) -> egui::ThemePreference {
+        match self {
+            Theme::System => egui::ThemePreference::System,
+            Theme::Light => egui::ThemePreference::Light,
+            Theme::Dark => egui::ThemePreference::Dark,
+        }
+    }
+}
+
+// what is drawn behind the image where it is transparent
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Backdrop {
+    #[default]
+    Checkerboard,
+    Black,
+    White,
+    Grey,
+    // `transparency_color`
+    Custom,
+}
+
+impl Backdrop {
+    pub const ALL: [Backdrop; 5] = [
+        Backdrop::Checkerboard,
+        Backdrop::Black,
+        Backdrop::White,
+        Backdrop::Grey,
+        Backdrop::Custom,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Backdrop::Checkerboard => "Checkerboard",
+            Backdrop::Black => "Black",
+            Backdrop::White => "White",
+            Backdrop::Grey => "50% grey",
+            Backdrop::Custom => "Custom",
+        }
+    }
+}
+
+// number of URLs kept in `recent_urls`
+const RECENT_URLS: usize = 8;
+// number of directories kept in `recent_dirs`
+const RECENT_DIRS: usize = 10;
+
+// stored in `settings.toml` in the platform config directory, keys missing from the file keep
+// their defaults and unknown keys are ignored
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub destinations: Vec<Destination>,
+    // the folder chosen with "Move to…" last, offered again by "Move to the last folder again"
+    pub last_move: Option<PathBuf>,
+    // URLs opened with "Open URL…", most recent first
+    pub recent_urls: Vec<String>,
+    // directories that were opened, most recent first
+    pub recent_dirs: Vec<PathBuf>,
+    // list files starting with a dot
+    pub show_hidden: bool,
+    // extensions of the files that are listed, lowercase without the dot
+    pub extensions: Vec<String>,
+    // detect images by their content, see `ScanOptions::sniff`
+    pub sniff: bool,
+    // list camera RAW files too, in addition to `extensions`
+    pub raw: bool,
+    // reopen the directory of the last session at startup instead of offering it
+    pub restore_session: bool,
+    // shuffle the files when neither --sort nor the last session says otherwise
+    pub randomize: bool,
+    // seconds between two slides unless --slideshow gives one
+    pub slideshow_interval: f32,
+    pub theme: Theme,
+    // fill behind the image as RGB with the dark theme, the theme's panel color if unset
+    pub background: Option<[u8; 3]>,
+    // the same with the light theme
+    pub light_background: Option<[u8; 3]>,
+    // behind transparent parts of the image, covering only the image
+    pub transparency: Backdrop,
+    pub transparency_color: [u8; 3],
+    // side of a checkerboard square in points
+    pub checker_size: f32,
+    // magnify with nearest-neighbor sampling, for pixel art and screenshots
+    pub nearest: bool,
+    // convert images with an embedded ICC profile to sRGB
+    pub color_managed: bool,
+    // how HDR photos are shown on the SDR display
+    pub tone_map: ToneMap,
+    // guides drawn over the image, with the cells of the grid across and down
+    pub overlay: Overlay,
+    pub overlay_grid: [u32; 2],
+    // RGBA, not premultiplied
+    pub overlay_color: [u8; 4],
+    // ask before moving a file to the trash
+    pub confirm_delete: bool,
+    // files skipped by Page Up and Page Down
+    pub page_stride: usize,
+    // show files as soon as they appear in the open directory
+    pub follow_new: bool,
+    // apply the EXIF orientation of photos
+    pub auto_orient: bool,
+    // keep rotation and flips when moving to another image
+    pub sticky_transform: bool,
+    // spreads start on the right and the arrow keys follow, for manga
+    pub right_to_left: bool,
+    // the first page of a spread view is a cover shown on its own
+    pub spread_cover: bool,
+    // quality of JPEGs saved with "Save a copy as…", 1-100
+    pub export_quality: u8,
+    // scale saved copies down to `export_max_edge` pixels on their long edge
+    pub export_resize: bool,
+    pub export_max_edge: u32,
+    // command the current file is edited with, `{path}` stands for it, the system's default
+    // application if empty
+    pub editor: String,
+    // listed in the right-click menu in this order
+    pub commands: Vec<Tool>,
+    pub wallpaper_mode: WallpaperMode,
+    pub rating_store: RatingStore,
+    // files opened later go to the window that is already open
+    pub single_instance: bool,
+    // only the image is shown, the toolbar comes back at the top edge
+    pub minimal_ui: bool,
+    // fullscreen hides them the same way
+    pub fullscreen_minimal: bool,
+    // above other windows, for a reference image next to a drawing
+    pub always_on_top: bool,
+    // without the title bar and frame, dragged around with Alt
+    pub borderless: bool,
+    // of the image area, 1 is opaque
+    pub opacity: f32,
+    // frames per second and why the last one was drawn, in a corner
+    pub repaint_overlay: bool,
+    pub keys: Keybindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            destinations: Vec::new(),
+            last_move: None,
+            recent_urls: Vec::new(),
+            recent_dirs: Vec::new(),
+            show_hidden: false,
+            extensions: EXTENSIONS
+                .iter()
+                .chain(&heic::EXTENSIONS)
+                .chain(&icon::EXTENSIONS)
+                .map(|e| e.to_string())
+                .collect(),
+            sniff: false,
+            raw: false,
+            restore_session: false,
+            randomize: true,
+            slideshow_interval: 5.0,
+            theme: Theme::default(),
+            background: None,
+            light_background: None,
+            transparency: Backdrop::default(),
+            transparency_color: [255, 0, 255],
+            checker_size: 8.0,
+            nearest: false,
+            color_managed: true,
+            tone_map: ToneMap::default(),
+            overlay: Overlay::default(),
+            overlay_grid: [4, 4],
+            overlay_color: [255, 255, 255, 160],
+            confirm_delete: true,
+            page_stride: 10,
+            follow_new: false,
+            auto_orient: true,
+            sticky_transform: false,
+            right_to_left: false,
+            spread_cover: true,
+            export_quality: 90,
+            export_resize: false,
+            export_max_edge: 1600,
+            editor: String::new(),
+            commands: Vec::new(),
+            wallpaper_mode: WallpaperMode::default(),
+            rating_store: RatingStore::default(),
+            single_instance: false,
+            minimal_ui: false,
+            fullscreen_minimal: true,
+            always_on_top: false,
+            borderless: false,
+            opacity: 1.0,
+            repaint_overlay: false,
+            keys: Keybindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    // the fill behind the image chosen for the theme that is shown
+    pub fn background(&self, dark: bool) -> Option<[u8; 3]> {
+        if dark {
+            self.background
+        } else {
+            self.light_background
+        }
+    }
+
+    pub fn background_mut(&mut self, dark: bool) -> &mut Option<[u8; 3]> {
+        if dark {
+            &mut self.background
+        } else {
+            &mut self.light_background
+        }
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "view-rs").map(|dirs| dirs.config_dir().join("settings.toml"))
+    }
+
+    // the saved settings, or the defaults if there are none or they can't be read
+    pub fn load() -> Self {
+        Self::path()
+            .map(|path| read_toml(&path))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No configuration directory")?;
+        write_toml(&path, self)?;
+        info!("Saved settings to {:?}", path);
+        Ok(())
+    }
+
+    pub fn destination(&self, key: u8) -> Option<&PathBuf> {
+        self.destinations
+            .iter()
+            .find(|d| d.key == key)
+            .map(|d| &d.dir)
+    }
+
+    pub fn add_recent_url(&mut self, url: &str) {
+        self.recent_urls.retain(|u| u != url);
+        self.recent_urls.insert(0, url.to_owned());
+        self.recent_urls.truncate(RECENT_URLS);
+    }
+
+    pub fn add_recent_dir(&mut self, dir: &Path) {
+        self.recent_dirs.retain(|d| d != dir);
+        self.recent_dirs.insert(0, dir.to_path_buf());
+        self.recent_dirs.truncate(RECENT_DIRS);
+    }
+
+    pub fn set_destination(&mut self, key: u8, dir: Option<PathBuf>) {
+        self.destinations.retain(|d| d.key != key);
+        if let Some(dir) = dir {
+            self.destinations.push(Destination { key, dir });
+            self.destinations.sort_by_key(|d| d.key);
+        }
+    }
+}
+
+// the contents of a TOML file, the defaults if it is missing or can't be read
+pub fn read_toml<T: DeserializeOwned + Default>(path: &Path) -> T {
+    match std::fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text).unwrap_or_else(|err| {
+            warn!("Ignoring invalid file {:?}: {}", path, err);
+            T::default()
+        }),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => T::default(),
+        Err(err) => {
+            warn!("Failed to read {:?}: {}", path, err);
+            T::default()
+        }
+    }
+}
+
+pub fn write_toml<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let text = toml::to_string_pretty(value).map_err(|e| e.to_string())?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(path, text).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_keys_are_ignored() {
+        let settings: Settings =
+            toml::from_str("sniff = true\nfrom_a_newer_version = 1\n").unwrap();
+        assert!(settings.sniff);
+        assert!(settings.confirm_delete);
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use rand::{Rng, seq::SliceRandom};
+
+use crate::path::PathSortable;
+
+// files shown since the order was last shuffled, every file comes up once per cycle
+#[derive(Default)]
+pub struct ShuffleBag {
+    // file -> how many files were shown before it in this cycle
+    seen: HashMap<PathBuf, usize>,
+}
+
+impl ShuffleBag {
+    pub fn visit(&mut self, path: &Path) {
+        let n = self.seen.len();
+        self.seen.entry(path.to_path_buf()).or_insert(n);
+    }
+
+    pub fn clear(&mut self) {
+        self.seen.clear();
+    }
+
+    // a renamed file keeps its place in the cycle
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        if let Some(n) = self.seen.remove(from) {
+            self.seen.insert(to.to_path_buf(), n);
+        }
+    }
+
+    pub fn seen(&self) -> usize {
+        self.seen.len()
+    }
+
+    // order `files` for the rest of the cycle: the files shown so far in the order they were
+    // shown, then `current`, then the others at random
+    pub fn arrange(&self, files: &mut [PathSortable], current: Option<&Path>) {
+        let rank = |p: &PathSortable| match self.seen.get(p.deref()) {
+            Some(&n) => n,
+            None if current == Some(p.as_path()) => self.seen.len(),
+            None => self.seen.len() + 1,
+        };
+        files.sort_by_cached_key(rank);
+        let unseen = files
+            .iter()
+            .position(|p| rank(p) > self.seen.len())
+            .unwrap_or(files.len());
+        files[unseen..].shuffle(&mut rand::rng());
+    }
+
+    // start a new cycle in a fresh order that doesn't begin with `last`
+    pub fn reshuffle(&mut self, files: &mut [PathSortable], last: Option<&Path>) {
+        self.clear();
+        let mut rng = rand::rng();
+        files.shuffle(&mut rng);
+        if files.len() > 1 && last == Some(files[0].as_path()) {
+            let other = rng.random_range(1..files.len());
+            files.swap(0, other);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(names: &[&str]) -> Vec<PathSortable> {
+        names
+            .iter()
+            .map(|n| PathSortable::from(PathBuf::from(n)))
+            .collect()
+    }
+
+    #[test]
+    fn arrange_keeps_the_seen_files_in_order() {
+        let mut bag = ShuffleBag::default();
+        for name in ["c", "a", "e"] {
+            bag.visit(Path::new(name));
+        }
+        let mut list = files(&["a", "b", "c", "d", "e", "f", "g"]);
+        bag.arrange(&mut list, Some(Path::new("g")));
+        let names: Vec<_> = list
+            .iter()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names[..4], ["c", "a", "e", "g"]);
+        let mut rest = names[4..].to_vec();
+        rest.sort();
+        assert_eq!(rest, ["b", "d", "f"]);
+    }
+
+    #[test]
+    fn reshuffle_does_not_repeat_the_last_file() {
+        let mut bag = ShuffleBag::default();
+        for _ in 0..50 {
+            let mut list = files(&["a", "b"]);
+            bag.reshuffle(&mut list, Some(Path::new("a")));
+            assert_eq!(list[0].as_path(), Path::new("b"));
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// two consecutive pages side by side, for reading comics
+
+use eframe::egui::{Rect, Vec2, vec2};
+
+// pages wider than tall are scans of a double page, shown on their own
+pub fn is_wide(size: Vec2) -> bool {
+    size.x > size.y
+}
+
+// where `first` and `second` go in `viewport`: the same height, fit together and centered, the
+// first page on the right when reading right to left
+pub fn layout(viewport: Rect, first: Vec2, second: Vec2, right_to_left: bool) -> [Rect; 2] {
+    let aspect = |size: Vec2| size.x / size.y.max(1.0);
+    let height = viewport
+        .height()
+        .min(viewport.width() / (aspect(first) + aspect(second)).max(f32::EPSILON));
+    let [first, second] = [first, second].map(|size| vec2(aspect(size) * height, height));
+    let (left, right) = if right_to_left {
+        (second, first)
+    } else {
+        (first, second)
+    };
+    let min = viewport.center() - vec2(left.x + right.x, height) * 0.5;
+    let left = Rect::from_min_size(min, left);
+    let right = Rect::from_min_size(left.right_top(), right);
+    if right_to_left {
+        [right, left]
+    } else {
+        [left, right]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui::pos2;
+
+    use super::*;
+
+    #[test]
+    fn pages_share_the_height() {
+        let viewport = Rect::from_min_size(pos2(0.0, 0.0), vec2(1000.0, 500.0));
+        let page = vec2(700.0, 1000.0);
+        let [first, second] = layout(viewport, page, page * 2.0, false);
+        assert_eq!(
+            first,
+            Rect::from_min_max(pos2(150.0, 0.0), pos2(500.0, 500.0))
+        );
+        assert_eq!(
+            second,
+            Rect::from_min_max(pos2(500.0, 0.0), pos2(850.0, 500.0))
+        );
+
+        // a narrow window leaves room above and below
+        let viewport = Rect::from_min_size(pos2(0.0, 0.0), vec2(700.0, 1000.0));
+        let [first, second] = layout(viewport, page, vec2(1400.0, 1000.0), true);
+        assert_eq!(second.left(), 0.0);
+        assert!((first.right() - 700.0).abs() < 0.01);
+        assert!((first.height() - 700.0 / 2.1).abs() < 0.01);
+        assert!(is_wide(vec2(1400.0, 1000.0)) && !is_wide(page));
+    }
+}
@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::bookmarks::Bookmarks;
+use crate::settings::{read_toml, write_toml};
+use crate::view::Fit;
+use crate::window::Geometry;
+
+// where the last session stopped, stored in `state.toml` in the platform state directory
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    pub last_dir: Option<PathBuf>,
+    pub last_file: Option<PathBuf>,
+    pub randomize: Option<bool>,
+    // width of the details panel in points
+    pub info_width: Option<f32>,
+    // by folder or archive
+    pub bookmarks: Bookmarks,
+    pub window: Option<Geometry>,
+    pub fit: Fit,
+}
+
+impl State {
+    pub fn path() -> Option<PathBuf> {
+        let dirs = ProjectDirs::from("", "", "view-rs")?;
+        let dir = dirs.state_dir().unwrap_or(dirs.data_local_dir());
+        Some(dir.join("state.toml"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .map(|path| read_toml(&path))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No state directory")?;
+        write_toml(&path, self)?;
+        info!("Saved session state to {:?}", path);
+        Ok(())
+    }
+}
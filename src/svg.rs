@@ -0,0 +1,253 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicU64, Ordering::Relaxed},
+    },
+};
+
+use eframe::egui::{
+    self, ColorImage,
+    load::{BytesPoll, ImageLoadResult, ImageLoader, ImagePoll, LoadError, SizeHint},
+    mutex::Mutex,
+};
+use log::debug;
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{self, Size, Tree},
+};
+
+use crate::path::to_path;
+
+// size of an SVG without width, height and viewBox
+const DEFAULT_SIZE: f32 = 512.0;
+
+pub fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+}
+
+// the system fonts are only loaded once the first SVG is opened
+fn options() -> &'static usvg::Options<'static> {
+    static OPTIONS: OnceLock<usvg::Options<'static>> = OnceLock::new();
+    OPTIONS.get_or_init(|| {
+        let mut options = usvg::Options::default();
+        options.fontdb_mut().load_system_fonts();
+        options
+    })
+}
+
+pub struct Svg {
+    tree: Tree,
+    // intrinsic size in pixels
+    size: Size,
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Svg, String> {
+    let tree = Tree::from_data(bytes, options()).map_err(|e| e.to_string())?;
+    // usvg makes those 100×100, which is tiny on screen
+    let sized = std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|text| roxmltree::Document::parse(text).ok())
+        .is_none_or(|doc| {
+            let root = doc.root_element();
+            ["width", "height", "viewBox"]
+                .iter()
+                .any(|a| root.has_attribute(*a))
+        });
+    let size = if sized {
+        tree.size()
+    } else {
+        Size::from_wh(DEFAULT_SIZE, DEFAULT_SIZE).expect("size is positive")
+    };
+    Ok(Svg { tree, size })
+}
+
+// draw `svg` into an image of `size` pixels, it stays at its intrinsic size for layout
+pub fn render(svg: &Svg, size: [u32; 2]) -> Result<ColorImage, String> {
+    let mut pixmap = Pixmap::new(size[0].max(1), size[1].max(1))
+        .ok_or_else(|| format!("Can't render an SVG of {}×{}", size[0], size[1]))?;
+    let drawn = svg.tree.size();
+    let transform = Transform::from_scale(
+        pixmap.width() as f32 / drawn.width(),
+        pixmap.height() as f32 / drawn.height(),
+    );
+    resvg::render(&svg.tree, transform, &mut pixmap.as_mut());
+    let image = ColorImage::from_rgba_premultiplied(
+        [pixmap.width() as usize, pixmap.height() as usize],
+        pixmap.data(),
+    );
+    Ok(image.with_source_size(egui::vec2(svg.size.width(), svg.size.height())))
+}
+
+// rendered to fit into a square of `side` pixels
+pub fn thumbnail(path: &Path, side: u32) -> Result<ColorImage, String> {
+    let svg = parse(&std::fs::read(path).map_err(|e| e.to_string())?)?;
+    let scale = side as f32 / svg.size.width().max(svg.size.height());
+    render(&svg, pixel_size(svg.size, scale))
+}
+
+fn pixel_size(size: Size, scale: f32) -> [u32; 2] {
+    [
+        (size.width() * scale).round().max(1.0) as u32,
+        (size.height() * scale).round().max(1.0) as u32,
+    ]
+}
+
+// pixels to render `size` at for `hint`, no side longer than `limit`
+fn hinted_size(size: Size, hint: SizeHint, limit: u32) -> [u32; 2] {
+    let scale = match hint {
+        SizeHint::Size {
+            width,
+            height,
+            maintain_aspect_ratio: false,
+        } => {
+            // exactly what was asked for, unless that is too large
+            let over = (width.max(height) as f32 / limit as f32).max(1.0);
+            return [
+                (width as f32 / over).round().max(1.0) as u32,
+                (height as f32 / over).round().max(1.0) as u32,
+            ];
+        }
+        SizeHint::Size { width, height, .. } => {
+            (width as f32 / size.width()).min(height as f32 / size.height())
+        }
+        SizeHint::Width(width) => width as f32 / size.width(),
+        SizeHint::Height(height) => height as f32 / size.height(),
+        SizeHint::Scale(scale) => scale.into_inner(),
+    };
+    let scale = scale.min(limit as f32 / size.width().max(size.height()));
+    pixel_size(size, scale)
+}
+
+struct Entry {
+    // parsed once, rendered at every size the image is shown at
+    svg: Result<Arc<Svg>, String>,
+    renders: HashMap<SizeHint, (Arc<ColorImage>, AtomicU64)>,
+}
+
+// renders SVG files at the size they are painted at, so they stay sharp when zoomed in; unlike
+// the egui_extras loader it also reads `.SVG` and won't allocate more than a texture can hold
+pub struct SvgLoader {
+    // largest texture side in pixels, lowered further to what the backend supports
+    limit: usize,
+    pass: AtomicU64,
+    cache: Mutex<HashMap<String, Entry>>,
+}
+
+impl SvgLoader {
+    pub const ID: &'static str = egui::generate_loader_id!(SvgLoader);
+
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            pass: AtomicU64::new(0),
+            cache: Default::default(),
+        }
+    }
+}
+
+impl ImageLoader for SvgLoader {
+    fn id(&self) -> &str {
+        Self::ID
+    }
+
+    fn load(&self, ctx: &egui::Context, uri: &str, hint: SizeHint) -> ImageLoadResult {
+        if !to_path(uri).is_some_and(|path| is_svg(&path)) {
+            return Err(LoadError::NotSupported);
+        }
+        let mut cache = self.cache.lock();
+        if !cache.contains_key(uri) {
+            let bytes = match ctx.try_load_bytes(uri)? {
+                BytesPoll::Ready { bytes, .. } => bytes,
+                BytesPoll::Pending { size } => return Ok(ImagePoll::Pending { size }),
+            };
+            let svg = parse(&bytes).map(Arc::new);
+            if let Err(err) = &svg {
+                debug!("Failed to parse {}: {}", uri, err);
+            }
+            cache.insert(
+                uri.to_owned(),
+                Entry {
+                    svg,
+                    renders: HashMap::new(),
+                },
+            );
+        }
+        let entry = cache.get_mut(uri).expect("entry was just inserted");
+        let pass = self.pass.load(Relaxed);
+        if let Some((image, used)) = entry.renders.get(&hint) {
+            used.store(pass, Relaxed);
+            return Ok(ImagePoll::Ready {
+                image: image.clone(),
+            });
+        }
+        let svg = entry.svg.clone().map_err(LoadError::Loading)?;
+        let limit = ctx.input(|i| i.max_texture_side).min(self.limit) as u32;
+        let image =
+            Arc::new(render(&svg, hinted_size(svg.size, hint, limit)).map_err(LoadError::Loading)?);
+        entry
+            .renders
+            .insert(hint, (image.clone(), AtomicU64::new(pass)));
+        Ok(ImagePoll::Ready { image })
+    }
+
+    fn forget(&self, uri: &str) {
+        self.cache.lock().remove(uri);
+    }
+
+    fn forget_all(&self) {
+        self.cache.lock().clear();
+    }
+
+    // zooming renders many sizes, only those painted in the last pass are kept
+    fn end_pass(&self, pass: u64) {
+        self.pass.store(pass, Relaxed);
+        for entry in self.cache.lock().values_mut() {
+            entry
+                .renders
+                .retain(|_, (_, used)| used.load(Relaxed) + 1 >= pass);
+        }
+    }
+
+    fn byte_size(&self) -> usize {
+        self.cache
+            .lock()
+            .values()
+            .flat_map(|entry| entry.renders.values())
+            .map(|(image, _)| image.pixels.len() * 4)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_follow_the_hint() {
+        let size = Size::from_wh(200.0, 100.0).unwrap();
+        let fit = SizeHint::Size {
+            width: 100,
+            height: 100,
+            maintain_aspect_ratio: true,
+        };
+        assert_eq!(hinted_size(size, fit, 4096), [100, 50]);
+        assert_eq!(hinted_size(size, SizeHint::Width(400), 4096), [400, 200]);
+        // painted larger than a texture can be, the aspect ratio is kept
+        let exact = SizeHint::Size {
+            width: 10000,
+            height: 5000,
+            maintain_aspect_ratio: false,
+        };
+        assert_eq!(hinted_size(size, exact, 4096), [4096, 2048]);
+
+        let svg = parse(b"<svg xmlns='http://www.w3.org/2000/svg'/>").unwrap();
+        assert_eq!(svg.size, Size::from_wh(512.0, 512.0).unwrap());
+        let svg = parse(b"<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 16'/>").unwrap();
+        assert_eq!(svg.size, Size::from_wh(24.0, 16.0).unwrap());
+        assert!(parse(b"<svg").is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Condvar, Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::SystemTime,
+};
+
+use eframe::egui::{self, ColorImage, TextureHandle, TextureOptions};
+use log::debug;
+
+use crate::{loader, svg};
+
+// longest side of a thumbnail in pixels
+pub const SIZE: u32 = 128;
+// thumbnails kept in memory, about 64 MiB
+const LIMIT: usize = 1000;
+const WORKERS: usize = 2;
+
+// a file as it was when its thumbnail was made, a newer mtime makes a new one
+type Key = (PathBuf, Option<SystemTime>);
+
+enum Entry {
+    Pending,
+    Ready(TextureHandle),
+    Failed,
+}
+
+// files waiting for a worker, the most recently requested last
+#[derive(Default)]
+struct Queue {
+    keys: Vec<Key>,
+    closed: bool,
+}
+
+// small previews for the filmstrip and the grid, decoded on background threads only for the
+// files that are on screen
+pub struct Thumbnails {
+    entries: HashMap<Key, (Entry, u64)>,
+    clock: u64,
+    // files asked for in this frame
+    requested: HashSet<Key>,
+    queue: Arc<(Mutex<Queue>, Condvar)>,
+    results: Receiver<(Key, Option<ColorImage>)>,
+}
+
+impl Thumbnails {
+    pub fn new(ctx: &egui::Context) -> Self {
+        let queue = Arc::new((Mutex::new(Queue::default()), Condvar::new()));
+        let (tx, results) = mpsc::channel();
+        for _ in 0..WORKERS {
+            let (queue, tx, ctx) = (queue.clone(), tx.clone(), ctx.clone());
+            thread::spawn(move || work(&queue, &tx, &ctx));
+        }
+        Self {
+            entries: HashMap::new(),
+            clock: 0,
+            requested: HashSet::new(),
+            queue,
+            results,
+        }
+    }
+
+    // the thumbnail of `path` if it is ready, otherwise it is queued
+    pub fn get(
+        &mut self,
+        ctx: &egui::Context,
+        path: &Path,
+        modified: Option<SystemTime>,
+    ) -> Option<TextureHandle> {
+        self.receive(ctx);
+        let key = (path.to_path_buf(), modified);
+        self.requested.insert(key.clone());
+        match self.entries.get_mut(&key) {
+            Some((entry, used)) => {
+                *used = self.clock;
+                match entry {
+                    Entry::Ready(texture) => Some(texture.clone()),
+                    Entry::Pending | Entry::Failed => None,
+                }
+            }
+            None => {
+                self.entries
+                    .insert(key.clone(), (Entry::Pending, self.clock));
+                let (queue, ready) = &*self.queue;
+                queue.lock().unwrap().keys.push(key);
+                ready.notify_one();
+                None
+            }
+        }
+    }
+
+    // called once per frame after drawing: files that scrolled out of view are not decoded
+    // anymore and the least recently shown thumbnails are dropped
+    pub fn end_frame(&mut self) {
+        let requested = std::mem::take(&mut self.requested);
+        let mut queue = self.queue.0.lock().unwrap();
+        let before = queue.keys.len();
+        queue.keys.retain(|key| requested.contains(key));
+        if queue.keys.len() != before {
+            debug!(
+                "Dropped {} thumbnails that left the view",
+                before - queue.keys.len()
+            );
+        }
+        drop(queue);
+        // a result that arrives for a dropped entry is thrown away
+        self.entries
+            .retain(|key, (entry, _)| !matches!(entry, Entry::Pending) || requested.contains(key));
+
+        if self.entries.len() > LIMIT {
+            let mut used: Vec<_> = self.entries.values().map(|(_, used)| *used).collect();
+            used.sort_unstable();
+            let cutoff = used[self.entries.len() - LIMIT];
+            self.entries.retain(|_, (_, used)| *used >= cutoff);
+        }
+        self.clock += 1;
+    }
+
+    // upload the thumbnails the workers finished
+    fn receive(&mut self, ctx: &egui::Context) {
+        while let Ok((key, image)) = self.results.try_recv() {
+            let Some((entry, _)) = self.entries.get_mut(&key) else {
+                continue;
+            };
+            *entry = match image {
+                Some(image) => Entry::Ready(ctx.load_texture(
+                    format!("thumb://{}", key.0.display()),
+                    image,
+                    TextureOptions::LINEAR,
+                )),
+                None => Entry::Failed,
+            };
+        }
+    }
+}
+
+impl Drop for Thumbnails {
+    fn drop(&mut self) {
+        let (queue, ready) = &*self.queue;
+        queue.lock().unwrap().closed = true;
+        ready.notify_all();
+    }
+}
+
+fn work(
+    queue: &(Mutex<Queue>, Condvar),
+    tx: &Sender<(Key, Option<ColorImage>)>,
+    ctx: &egui::Context,
+) {
+    let (queue, ready) = queue;
+    loop {
+        let key = {
+            let mut q = queue.lock().unwrap();
+            loop {
+                if q.closed {
+                    return;
+                }
+                if let Some(key) = q.keys.pop() {
+                    break key;
+                }
+                q = ready.wait(q).unwrap();
+            }
+        };
+        let image = decode(&key.0);
+        if tx.send((key, image)).is_err() {
+            return;
+        }
+        ctx.request_repaint();
+    }
+}
+
+fn decode(path: &Path) -> Option<ColorImage> {
+    if svg::is_svg(path) {
+        return svg::thumbnail(path, SIZE)
+            .inspect_err(|err| debug!("No thumbnail for {:?}: {}", path, err))
+            .ok();
+    }
+    let image = match loader::open(path) {
+        Ok(image) => image,
+        Err(err) => {
+            debug!("No thumbnail for {:?}: {}", path, err);
+            return None;
+        }
+    };
+    let thumb = image.thumbnail(SIZE, SIZE).to_rgba8();
+    let size = [thumb.width() as usize, thumb.height() as usize];
+    Some(ColorImage::from_rgba_unmultiplied(size, thumb.as_raw()))
+}
@@ -0,0 +1,153 @@
+// short notices stacked in the bottom right corner of the image area, newest at the bottom;
+// each goes away on its own, a click dismisses it and the pointer over it stops its time
+
+use std::time::Duration;
+
+use eframe::egui;
+
+// at most this many are on screen, the oldest make room
+const SHOWN: usize = 5;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    // seconds on screen, problems stay longer to be read
+    fn duration(self) -> f64 {
+        match self {
+            Level::Info => 2.5,
+            Level::Warn => 4.0,
+            Level::Error => 8.0,
+        }
+    }
+}
+
+struct Toast {
+    id: u64,
+    level: Level,
+    message: String,
+    // input time it goes away
+    until: f64,
+    // seconds it had left when the pointer went over it
+    paused: Option<f64>,
+}
+
+#[derive(Default)]
+pub struct Toasts {
+    shown: Vec<Toast>,
+    next_id: u64,
+}
+
+impl Toasts {
+    pub fn push(&mut self, level: Level, message: String, time: f64) {
+        // the same notice again, e.g. from a repeated key, only keeps it longer
+        if let Some(last) = self
+            .shown
+            .last_mut()
+            .filter(|t| t.level == level && t.message == message)
+        {
+            last.until = time + level.duration();
+            return;
+        }
+        if self.shown.len() >= SHOWN {
+            self.shown.remove(0);
+        }
+        self.shown.push(Toast {
+            id: self.next_id,
+            level,
+            message,
+            until: time + level.duration(),
+            paused: None,
+        });
+        self.next_id += 1;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, area: egui::Rect, time: f64) {
+        self.shown.retain(|t| t.paused.is_some() || t.until > time);
+        if self.shown.is_empty() {
+            return;
+        }
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::RIGHT_BOTTOM)
+            .fixed_pos(area.right_bottom() - egui::vec2(12.0, 12.0))
+            .show(ctx, |ui| {
+                ui.with_layout(egui::Layout::top_down(egui::Align::Max), |ui| {
+                    for toast in &mut self.shown {
+                        let response = frame(ui, toast).interact(egui::Sense::click());
+                        if response.clicked() {
+                            dismissed = Some(toast.id);
+                        }
+                        match (response.hovered(), toast.paused) {
+                            (true, None) => toast.paused = Some(toast.until - time),
+                            (false, Some(left)) => {
+                                toast.until = time + left;
+                                toast.paused = None;
+                            }
+                            _ => {}
+                        }
+                    }
+                });
+            });
+        if let Some(id) = dismissed {
+            self.shown.retain(|t| t.id != id);
+        }
+        // moving the pointer off a paused one repaints anyway
+        let next = self
+            .shown
+            .iter()
+            .filter(|t| t.paused.is_none())
+            .map(|t| t.until - time)
+            .reduce(f64::min);
+        if let Some(next) = next {
+            ctx.request_repaint_after(Duration::from_secs_f64(next.max(0.0)));
+        }
+    }
+}
+
+fn frame(ui: &mut egui::Ui, toast: &Toast) -> egui::Response {
+    let visuals = ui.visuals();
+    let (icon, color) = match toast.level {
+        Level::Info => ("ℹ", visuals.text_color()),
+        Level::Warn => ("⚠", visuals.warn_fg_color),
+        Level::Error => ("✖", visuals.error_fg_color),
+    };
+    let mut frame = egui::Frame::popup(ui.style());
+    if toast.level != Level::Info {
+        frame = frame.stroke(egui::Stroke::new(1.0, color));
+    }
+    frame
+        .show(ui, |ui| {
+            ui.set_max_width(360.0);
+            ui.horizontal(|ui| {
+                ui.colored_label(color, icon);
+                ui.add(egui::Label::new(&toast.message).selectable(false));
+            });
+        })
+        .response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_notices_are_merged() {
+        let mut toasts = Toasts::default();
+        toasts.push(Level::Info, "saved".to_owned(), 0.0);
+        toasts.push(Level::Info, "saved".to_owned(), 2.0);
+        assert_eq!(toasts.shown.len(), 1);
+        assert_eq!(toasts.shown[0].until, 2.0 + Level::Info.duration());
+
+        for i in 0..SHOWN {
+            toasts.push(Level::Error, format!("failed {}", i), 3.0);
+        }
+        assert_eq!(toasts.shown.len(), SHOWN);
+        assert_eq!(toasts.shown[0].message, "failed 0");
+    }
+}
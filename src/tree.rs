@@ -0,0 +1,248 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use eframe::egui::{self, collapsing_header::CollapsingState};
+use log::debug;
+
+use crate::{
+    path::{PathSortable, file_name},
+    scan::ScanOptions,
+};
+
+// what the tree shows of a directory
+#[derive(Default)]
+pub struct Listing {
+    // subdirectories in natural order
+    pub dirs: Vec<PathBuf>,
+    // image files directly inside
+    pub images: usize,
+}
+
+// read `dir` without descending, an unreadable directory looks empty
+pub fn list(dir: &Path, options: &ScanOptions) -> Listing {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            debug!("Failed to list {:?}: {}", dir, err);
+            return Listing::default();
+        }
+    };
+    let mut dirs = Vec::new();
+    let mut images = 0;
+    for path in entries.filter_map(Result::ok).map(|e| e.path()) {
+        if !options.hidden && file_name(&path).starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            dirs.push(PathSortable::from(path));
+        } else if path.is_file() && options.accepts(&path) {
+            images += 1;
+        }
+    }
+    dirs.sort();
+    Listing {
+        dirs: dirs.iter().map(|p| p.to_path_buf()).collect(),
+        images,
+    }
+}
+
+// the directory after or before `dir` among its siblings that has images, skipping the others
+pub fn sibling(dir: &Path, forward: bool, options: &ScanOptions) -> Option<PathBuf> {
+    let dirs = list(dir.parent()?, options).dirs;
+    let pos = dirs.iter().position(|d| d == dir)?;
+    let candidates: Vec<&PathBuf> = if forward {
+        dirs[pos + 1..].iter().collect()
+    } else {
+        dirs[..pos].iter().rev().collect()
+    };
+    candidates
+        .into_iter()
+        .find(|d| has_images(d, options))
+        .cloned()
+}
+
+// whether `dir` is readable and has at least one image directly inside, stops at the first one
+fn has_images(dir: &Path, options: &ScanOptions) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return false;
+    };
+    entries.filter_map(Result::ok).map(|e| e.path()).any(|p| {
+        (options.hidden || !file_name(&p).starts_with('.')) && p.is_file() && options.accepts(&p)
+    })
+}
+
+// sidebar with the folders next to the open one, each folder is read on a background thread
+// once it comes into view
+pub struct DirTree {
+    // parent of the open directory, the filesystem roots if there is none
+    root: Option<PathBuf>,
+    // None while the directory is being read
+    listings: HashMap<PathBuf, Option<Listing>>,
+    requests: Sender<(PathBuf, ScanOptions)>,
+    results: Receiver<(PathBuf, Listing)>,
+}
+
+impl DirTree {
+    pub fn new(ctx: &egui::Context) -> Self {
+        let (requests, queue) = mpsc::channel::<(PathBuf, ScanOptions)>();
+        let (tx, results) = mpsc::channel();
+        let ctx = ctx.clone();
+        // ends when the tree is dropped together with the sender
+        thread::spawn(move || {
+            for (dir, options) in queue {
+                let listing = list(&dir, &options);
+                if tx.send((dir, listing)).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            }
+        });
+        Self {
+            root: None,
+            listings: HashMap::new(),
+            requests,
+            results,
+        }
+    }
+
+    // draw the tree around `current`, returns the directory that was clicked
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        current: Option<&Path>,
+        options: &ScanOptions,
+    ) -> Option<PathBuf> {
+        while let Ok((dir, listing)) = self.results.try_recv() {
+            self.listings.insert(dir, Some(listing));
+        }
+        let root = current.map(|dir| dir.parent().unwrap_or(dir).to_path_buf());
+        if root != self.root {
+            // a directory was opened some other way, read everything around it again
+            self.listings.clear();
+            self.root = root;
+        }
+        let roots = match &self.root {
+            Some(root) => vec![root.clone()],
+            None => filesystem_roots(),
+        };
+
+        let mut clicked = None;
+        egui::ScrollArea::both()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for root in &roots {
+                    let open = roots.len() == 1;
+                    self.node(ui, root, current, options, open, &mut clicked);
+                }
+            });
+        clicked
+    }
+
+    fn node(
+        &mut self,
+        ui: &mut egui::Ui,
+        dir: &Path,
+        current: Option<&Path>,
+        options: &ScanOptions,
+        open: bool,
+        clicked: &mut Option<PathBuf>,
+    ) {
+        let listing = self.listing(dir, options);
+        let name = match dir.file_name() {
+            Some(_) => file_name(dir),
+            None => dir.display().to_string(),
+        };
+        let text = match listing {
+            Some(listing) if listing.images > 0 => format!("{} ({})", name, listing.images),
+            _ => name,
+        };
+        let dirs = listing.map(|listing| listing.dirs.clone());
+        let selected = current == Some(dir);
+        let label = |ui: &mut egui::Ui| {
+            if ui.selectable_label(selected, text).clicked() {
+                *clicked = Some(dir.to_path_buf());
+            }
+        };
+
+        if dirs.as_ref().is_some_and(Vec::is_empty) {
+            // nothing to expand, line up with the labels that have an arrow
+            ui.horizontal(|ui| {
+                ui.add_space(ui.spacing().icon_width + ui.spacing().icon_spacing);
+                label(ui);
+            });
+            return;
+        }
+        let id = ui.make_persistent_id(dir);
+        CollapsingState::load_with_default_open(ui.ctx(), id, open)
+            .show_header(ui, label)
+            .body(|ui| match dirs {
+                Some(dirs) => {
+                    for child in &dirs {
+                        self.node(ui, child, current, options, false, clicked);
+                    }
+                }
+                None => {
+                    ui.spinner();
+                }
+            });
+    }
+
+    // the contents of `dir` if they were read, otherwise they are requested
+    fn listing(&mut self, dir: &Path, options: &ScanOptions) -> Option<&Listing> {
+        if !self.listings.contains_key(dir) {
+            self.listings.insert(dir.to_path_buf(), None);
+            let _ = self.requests.send((dir.to_path_buf(), options.clone()));
+        }
+        self.listings.get(dir)?.as_ref()
+    }
+}
+
+#[cfg(windows)]
+fn filesystem_roots() -> Vec<PathBuf> {
+    ('A'..='Z')
+        .map(|drive| PathBuf::from(format!("{}:\\", drive)))
+        .filter(|root| root.exists())
+        .collect()
+}
+
+#[cfg(not(windows))]
+fn filesystem_roots() -> Vec<PathBuf> {
+    vec![PathBuf::from("/")]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sibling_skips_folders_without_images() {
+        let root = std::env::temp_dir().join(format!("view-rs-tree-{}", std::process::id()));
+        for dir in ["a", "b", "c10", "c9"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["a/1.jpg", "b/notes.txt", "c10/1.png", "c9/1.png"] {
+            std::fs::write(root.join(file), b"").unwrap();
+        }
+        let options = ScanOptions {
+            recursive: false,
+            hidden: false,
+            extensions: vec!["jpg".to_owned(), "png".to_owned()],
+            sniff: false,
+        };
+        assert_eq!(
+            sibling(&root.join("a"), true, &options),
+            Some(root.join("c9"))
+        );
+        assert_eq!(
+            sibling(&root.join("c10"), false, &options),
+            Some(root.join("c9"))
+        );
+        assert_eq!(sibling(&root.join("c10"), true, &options), None);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
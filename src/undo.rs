@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+use log::warn;
+
+// number of deletes that can be undone
+const LIMIT: usize = 32;
+
+// a file moved to the trash and where it was in the list, its trash entry is only looked up when
+// it is restored since listing the trash gets slow as it grows
+pub struct Deleted {
+    pub path: PathBuf,
+    pub index: usize,
+}
+
+#[derive(Default)]
+pub struct UndoStack {
+    entries: Vec<Deleted>,
+}
+
+impl UndoStack {
+    // remember `path` right after it was moved to the trash
+    pub fn push(&mut self, path: PathBuf, index: usize) {
+        if self.entries.len() == LIMIT {
+            self.entries.remove(0);
+        }
+        self.entries.push(Deleted { path, index });
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // put the most recently deleted file back, the entry is dropped even if that fails
+    pub fn restore(&mut self) -> Option<Result<Deleted, String>> {
+        let deleted = self.entries.pop()?;
+        Some(restore(&deleted).map(|()| deleted))
+    }
+}
+
+// the trash entry of `path` that was created last
+#[cfg(any(windows, all(unix, not(target_os = "macos"))))]
+fn find(path: &std::path::Path) -> Option<trash::TrashItem> {
+    match trash::os_limited::list() {
+        Ok(items) => items
+            .into_iter()
+            .filter(|item| item.original_path() == path)
+            .max_by_key(|item| item.time_deleted),
+        Err(err) => {
+            warn!("Failed to list the trash: {}", err);
+            None
+        }
+    }
+}
+
+#[cfg(any(windows, all(unix, not(target_os = "macos"))))]
+fn restore(deleted: &Deleted) -> Result<(), String> {
+    let item = find(&deleted.path)
+        .ok_or_else(|| format!("{} is no longer in the trash", deleted.path.display()))?;
+    trash::os_limited::restore_all([item]).map_err(|err| {
+        warn!("Failed to restore {:?}: {}", deleted.path, err);
+        format!("Could not restore {}: {}", deleted.path.display(), err)
+    })
+}
+
+#[cfg(not(any(windows, all(unix, not(target_os = "macos")))))]
+fn restore(deleted: &Deleted) -> Result<(), String> {
+    warn!("Cannot restore {:?} on this platform", deleted.path);
+    Err("Restoring from the trash is not supported on this platform".to_owned())
+}
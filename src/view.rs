@@ -0,0 +1,535 @@
+use eframe::egui::{self, Rect, Vec2, pos2, vec2};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+pub const MIN_ZOOM: f32 = 0.05;
+pub const MAX_ZOOM: f32 = 32.0;
+// multiplier of a single `+`/`-` key press
+pub const ZOOM_STEP: f32 = 1.25;
+// fraction of the viewport moved by a single arrow key press
+pub const PAN_STEP: f32 = 0.1;
+// of the viewport height scrolled by Space, a little of the last screen stays in sight
+const PAGE_STEP: f32 = 0.9;
+const MIN_GLIDE_SPEED: f32 = 20.0;
+const GLIDE_FRICTION: f32 = 6.0;
+
+// what of the image is fitted into the viewport
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum Fit {
+    #[default]
+    Whole,
+    // tall screenshots and comic pages, scrolled through from the top
+    Width,
+    Height,
+}
+
+impl Fit {
+    pub const ALL: [Fit; 3] = [Fit::Whole, Fit::Width, Fit::Height];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Fit::Whole => "Fit",
+            Fit::Width => "Fit width",
+            Fit::Height => "Fit height",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Fit::Whole => Fit::Width,
+            Fit::Width => Fit::Height,
+            Fit::Height => Fit::Whole,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DisplayMode {
+    // scale the image to fit into the viewport
+    Fit(Fit),
+    // one image pixel per physical pixel of the monitor
+    ActualSize,
+    // free zoom factor relative to the intrinsic image size
+    Zoom(f32),
+}
+
+impl Default for DisplayMode {
+    fn default() -> Self {
+        DisplayMode::Fit(Fit::Whole)
+    }
+}
+
+#[derive(Default)]
+pub struct View {
+    pub mode: DisplayMode,
+    // what `reset` goes back to, kept for the following images
+    pub fit: Fit,
+    // scroll offset of the central panel, kept between frames so zooming can anchor on a point
+    pub offset: Vec2,
+    // `offset` was changed by us and has to be pushed to the scroll area in the next frame
+    pub moved: bool,
+    // panning speed left over after a drag is released, in points per second
+    pub velocity: Vec2,
+    // show the bottom of the image once its size is known, after going back with Shift+Space
+    pub to_end: bool,
+    // of the monitor the window is on, updated every frame; 0 before the first one
+    pub pixels_per_point: f32,
+}
+
+impl View {
+    // effective scale from image pixels to points
+    pub fn scale(&self, viewport: Vec2, image: Vec2) -> f32 {
+        match self.mode {
+            DisplayMode::Fit(fit) => fit_scale(fit, viewport, image),
+            DisplayMode::ActualSize => self.pixel(),
+            DisplayMode::Zoom(zoom) => zoom,
+        }
+    }
+
+    // size of a physical pixel in points
+    fn pixel(&self) -> f32 {
+        if self.pixels_per_point > 0.0 {
+            1.0 / self.pixels_per_point
+        } else {
+            1.0
+        }
+    }
+
+    // physical pixels per image pixel, 1 at actual size whatever the scaling of the monitor
+    pub fn zoom(&self, viewport: Vec2, image: Vec2) -> f32 {
+        self.scale(viewport, image) / self.pixel()
+    }
+
+    // size of the scrollable content: the scaled image, but never smaller than the viewport
+    pub fn content_size(&self, viewport: Vec2, image: Vec2) -> Vec2 {
+        (image * self.scale(viewport, image)).max(viewport)
+    }
+
+    // rect of the image inside the scroll `content`, centered when smaller than the viewport;
+    // it starts on a physical pixel so image pixels don't shimmer between two of them
+    pub fn image_rect(&self, content: Rect, viewport: Vec2, image: Vec2) -> Rect {
+        let scaled = image * self.scale(viewport, image);
+        let min = content.min + padding(content.size(), scaled);
+        let pixel = self.pixel();
+        let min = pos2(
+            (min.x / pixel).round() * pixel,
+            (min.y / pixel).round() * pixel,
+        );
+        Rect::from_min_size(min, scaled)
+    }
+
+    // multiply the zoom by `factor`, keeping the point at `anchor` (relative to the viewport) fixed
+    pub fn zoom_by(&mut self, factor: f32, viewport: Vec2, image: Vec2, anchor: Vec2) {
+        let old = self.scale(viewport, image);
+        let new = (old * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        if new == old && !matches!(self.mode, DisplayMode::Fit(_)) {
+            return;
+        }
+        self.set_mode(DisplayMode::Zoom(new), viewport, image, anchor, anchor);
+    }
+
+    // switch to `mode`, moving the image point under `anchor` to `dest` (both relative to the viewport)
+    pub fn set_mode(
+        &mut self,
+        mode: DisplayMode,
+        viewport: Vec2,
+        image: Vec2,
+        anchor: Vec2,
+        dest: Vec2,
+    ) {
+        let old = self.scale(viewport, image);
+        // position of the anchor in image pixels
+        let point = (self.offset + anchor - padding(viewport, image * old)) / old;
+
+        self.mode = mode;
+        let new = self.scale(viewport, image);
+        let content = self.content_size(viewport, image);
+        self.offset = (padding(viewport, image * new) + point * new - dest)
+            .clamp(Vec2::ZERO, (content - viewport).max(Vec2::ZERO));
+        self.moved = true;
+    }
+
+    // zoom and pan so that `area` of the image, in image pixels, fills the viewport
+    pub fn zoom_to(&mut self, area: Rect, viewport: Vec2, image: Vec2) {
+        let scale = (viewport.x / area.width())
+            .min(viewport.y / area.height())
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+        self.mode = DisplayMode::Zoom(scale);
+        let content = self.content_size(viewport, image);
+        let center = padding(viewport, image * scale) + area.center().to_vec2() * scale;
+        self.offset =
+            (center - viewport * 0.5).clamp(Vec2::ZERO, (content - viewport).max(Vec2::ZERO));
+        self.velocity = Vec2::ZERO;
+        self.moved = true;
+    }
+
+    // whether the scaled image is larger than the viewport in any direction
+    pub fn pannable(&self, viewport: Vec2, image: Vec2) -> bool {
+        let scaled = image * self.scale(viewport, image);
+        scaled.x > viewport.x || scaled.y > viewport.y
+    }
+
+    // move the visible part of the image by `delta` points
+    pub fn pan_by(&mut self, delta: Vec2, viewport: Vec2, image: Vec2) {
+        let content = self.content_size(viewport, image);
+        self.offset = (self.offset + delta).clamp(Vec2::ZERO, (content - viewport).max(Vec2::ZERO));
+        self.moved = true;
+    }
+
+    // scroll a screen down or up, false if the image already ends there
+    pub fn page(&mut self, down: bool, viewport: Vec2, image: Vec2) -> bool {
+        let bottom = (self.content_size(viewport, image).y - viewport.y).max(0.0);
+        let at_edge = if down {
+            self.offset.y >= bottom - 0.5
+        } else {
+            self.offset.y <= 0.5
+        };
+        if at_edge {
+            return false;
+        }
+        let step = viewport.y * PAGE_STEP;
+        self.pan_by(vec2(0.0, if down { step } else { -step }), viewport, image);
+        true
+    }
+
+    // continue panning after a drag release, returns whether the view is still moving
+    pub fn glide(&mut self, dt: f32, viewport: Vec2, image: Vec2) -> bool {
+        if self.velocity.length() < MIN_GLIDE_SPEED {
+            self.velocity = Vec2::ZERO;
+            return false;
+        }
+        self.pan_by(-self.velocity * dt, viewport, image);
+        self.velocity *= (-GLIDE_FRICTION * dt).exp();
+        true
+    }
+
+    pub fn reset(&mut self) {
+        self.mode = DisplayMode::Fit(self.fit);
+        self.offset = Vec2::ZERO;
+        self.to_end = false;
+        self.velocity = Vec2::ZERO;
+        self.moved = true;
+    }
+}
+
+// how the image is turned and mirrored on screen, the file is left alone
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct Transform {
+    // clockwise quarter turns, 0-3
+    pub turns: u8,
+    // mirror the texture before it is turned
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl Transform {
+    // what turns an image stored with EXIF `orientation` upright
+    pub fn from_orientation(orientation: u32) -> Self {
+        let (turns, flip_x, flip_y) = match orientation {
+            2 => (0, true, false),
+            3 => (2, false, false),
+            4 => (0, false, true),
+            5 => (3, true, false),
+            6 => (1, false, false),
+            7 => (1, true, false),
+            8 => (3, false, false),
+            _ => (0, false, false),
+        };
+        Self {
+            turns,
+            flip_x,
+            flip_y,
+        }
+    }
+
+    // the EXIF orientation value that stands for `self`
+    pub fn orientation(&self) -> u32 {
+        (1..=8)
+            .find(|&o| Transform::from_orientation(o).normalized() == self.normalized())
+            .unwrap_or(1)
+    }
+
+    // the same transform without a vertical flip, which is a horizontal one and a half turn
+    fn normalized(mut self) -> Self {
+        if self.flip_y {
+            self.flip_y = false;
+            self.flip_x = !self.flip_x;
+            self.turns = (self.turns + 2) % 4;
+        }
+        self
+    }
+
+    // `self` followed by `other` on screen
+    pub fn then(mut self, other: Transform) -> Self {
+        if other.flip_x {
+            self.flip_horizontal();
+        }
+        if other.flip_y {
+            self.flip_vertical();
+        }
+        for _ in 0..other.turns {
+            self.rotate(true);
+        }
+        self
+    }
+
+    pub fn rotate(&mut self, clockwise: bool) {
+        self.turns = (self.turns + if clockwise { 1 } else { 3 }) % 4;
+    }
+
+    // mirror left and right as seen on screen
+    pub fn flip_horizontal(&mut self) {
+        if !self.sideways() {
+            self.flip_x = !self.flip_x;
+        } else {
+            self.flip_y = !self.flip_y;
+        }
+    }
+
+    pub fn flip_vertical(&mut self) {
+        if !self.sideways() {
+            self.flip_y = !self.flip_y;
+        } else {
+            self.flip_x = !self.flip_x;
+        }
+    }
+
+    // turned by 90° or 270°
+    fn sideways(&self) -> bool {
+        self.turns % 2 == 1
+    }
+
+    // size of an `image` on screen, width and height swap on a quarter turn
+    pub fn size(&self, image: Vec2) -> Vec2 {
+        if self.sideways() {
+            vec2(image.y, image.x)
+        } else {
+            image
+        }
+    }
+
+    // draw `image` into `rect`, which has the transformed size
+    pub fn paint(&self, image: egui::Image, ui: &egui::Ui, rect: Rect) {
+        let mut uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        if self.flip_x {
+            std::mem::swap(&mut uv.min.x, &mut uv.max.x);
+        }
+        if self.flip_y {
+            std::mem::swap(&mut uv.min.y, &mut uv.max.y);
+        }
+        let mut image = image.uv(uv);
+        if self.turns != 0 {
+            let angle = f32::from(self.turns) * std::f32::consts::FRAC_PI_2;
+            image = image.rotate(angle, Vec2::splat(0.5));
+        }
+        // rotated around its center, so it is laid out untransformed
+        image.paint_at(
+            ui,
+            Rect::from_center_size(rect.center(), self.size(rect.size())),
+        );
+    }
+
+    // point in the texture, both 0-1, that ends up at `display` inside the painted rect
+    pub fn texture_uv(&self, display: Vec2) -> Vec2 {
+        let mut uv = display;
+        // undo the clockwise turns one at a time, each moves (x, y) to (1 - y, x)
+        for _ in 0..self.turns {
+            uv = vec2(uv.y, 1.0 - uv.x);
+        }
+        if self.flip_x {
+            uv.x = 1.0 - uv.x;
+        }
+        if self.flip_y {
+            uv.y = 1.0 - uv.y;
+        }
+        uv
+    }
+
+    // the pixels of `image` as they are shown
+    pub fn apply(&self, mut image: DynamicImage) -> DynamicImage {
+        if self.flip_x {
+            image = image.fliph();
+        }
+        if self.flip_y {
+            image = image.flipv();
+        }
+        match self.turns {
+            1 => image.rotate90(),
+            2 => image.rotate180(),
+            3 => image.rotate270(),
+            _ => image,
+        }
+    }
+
+    // e.g. "↻ 90° ⇋", empty without a transform
+    pub fn label(&self) -> String {
+        let mut parts = Vec::new();
+        if self.turns != 0 {
+            parts.push(format!("↻ {}°", u32::from(self.turns) * 90));
+        }
+        if self.flip_x {
+            parts.push("⇋".to_owned());
+        }
+        if self.flip_y {
+            parts.push("⇅".to_owned());
+        }
+        parts.join(" ")
+    }
+}
+
+// light and dark squares of `cell` points filling `rect`, starting at its corner so they move with
+// the image, only the part inside `clip` is built
+pub fn paint_checkerboard(painter: &egui::Painter, rect: Rect, clip: Rect, cell: f32) {
+    const LIGHT: egui::Color32 = egui::Color32::from_gray(204);
+    const DARK: egui::Color32 = egui::Color32::from_gray(153);
+    let visible = rect.intersect(clip);
+    if !visible.is_positive() {
+        return;
+    }
+    painter.rect_filled(visible, 0.0, LIGHT);
+    let cell = cell.max(1.0);
+    let first = ((visible.min - rect.min) / cell).floor();
+    let last = ((visible.max - rect.min) / cell).ceil();
+    let mut mesh = egui::Mesh::default();
+    for row in first.y as i64..last.y as i64 {
+        for col in first.x as i64..last.x as i64 {
+            if (row + col) % 2 == 0 {
+                continue;
+            }
+            let min = rect.min + vec2(col as f32, row as f32) * cell;
+            let square = Rect::from_min_size(min, Vec2::splat(cell)).intersect(visible);
+            mesh.add_colored_rect(square, DARK);
+        }
+    }
+    painter.add(mesh);
+}
+
+// a line between each two image pixels of `step` points inside `rect`, only the part inside `clip`
+pub fn paint_pixel_grid(painter: &egui::Painter, rect: Rect, clip: Rect, step: f32) {
+    let visible = rect.intersect(clip);
+    if !visible.is_positive() {
+        return;
+    }
+    let stroke = egui::Stroke::new(1.0, egui::Color32::from_white_alpha(24));
+    let first = ((visible.min - rect.min) / step).ceil();
+    let last = ((visible.max - rect.min) / step).floor();
+    for col in first.x as i64..=last.x as i64 {
+        let x = rect.min.x + col as f32 * step;
+        painter.vline(x, visible.y_range(), stroke);
+    }
+    for row in first.y as i64..=last.y as i64 {
+        let y = rect.min.y + row as f32 * step;
+        painter.hline(visible.x_range(), y, stroke);
+    }
+}
+
+// scale that makes `image`, or only its width or height, fit inside `viewport`
+pub fn fit_scale(fit: Fit, viewport: Vec2, image: Vec2) -> f32 {
+    if image.x <= 0.0 || image.y <= 0.0 {
+        return 1.0;
+    }
+    match fit {
+        Fit::Whole => (viewport.x / image.x).min(viewport.y / image.y),
+        Fit::Width => viewport.x / image.x,
+        Fit::Height => viewport.y / image.y,
+    }
+}
+
+// empty space around an image of `scaled` size centered in `viewport`
+fn padding(viewport: Vec2, scaled: Vec2) -> Vec2 {
+    ((viewport - scaled) * 0.5).max(Vec2::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_width_scrolls_from_the_top() {
+        let mut view = View {
+            fit: Fit::Width,
+            ..View::default()
+        };
+        view.reset();
+        let (viewport, page) = (vec2(800.0, 600.0), vec2(400.0, 3000.0));
+        assert_eq!(view.scale(viewport, page), 2.0);
+        assert_eq!(view.content_size(viewport, page), vec2(800.0, 6000.0));
+        assert_eq!(view.offset, Vec2::ZERO);
+        assert!(!view.page(false, viewport, page));
+        let mut pages = 0;
+        while view.page(true, viewport, page) {
+            pages += 1;
+        }
+        // 5400 points below the first screen, 540 at a time
+        assert_eq!((pages, view.offset.y), (10, 5400.0));
+        assert_eq!(fit_scale(Fit::Height, viewport, page), 0.2);
+
+        // a quarter of a page fills the window
+        let area = Rect::from_min_size(pos2(200.0, 1000.0), vec2(100.0, 75.0));
+        view.zoom_to(area, viewport, page);
+        assert_eq!(view.scale(viewport, page), 8.0);
+        assert_eq!(view.offset, vec2(1600.0, 8000.0));
+        assert_eq!(fit_scale(Fit::Whole, viewport, page), 0.2);
+
+        // pixel for pixel on a monitor scaled to 150%
+        view.mode = DisplayMode::ActualSize;
+        view.pixels_per_point = 1.5;
+        let shot = vec2(300.0, 150.0);
+        assert_eq!(view.zoom(viewport, shot), 1.0);
+        assert_eq!(view.scale(viewport, shot) * 1.5, 1.0);
+        let content = Rect::from_min_size(pos2(0.3, 0.0), viewport);
+        let rect = view.image_rect(content, viewport, shot);
+        assert_eq!((rect.min.x * 1.5).fract(), 0.0);
+        assert_eq!(rect.width() * 1.5, 300.0);
+    }
+
+    #[test]
+    fn flips_follow_the_screen() {
+        let mut transform = Transform::default();
+        transform.rotate(true);
+        assert_eq!(transform.size(vec2(40.0, 30.0)), vec2(30.0, 40.0));
+        // left and right on screen are the texture's top and bottom after a quarter turn
+        transform.flip_horizontal();
+        assert!(transform.flip_y && !transform.flip_x);
+        transform.rotate(false);
+        transform.rotate(false);
+        assert_eq!(transform.turns, 3);
+        assert_eq!(transform.label(), "↻ 270° ⇅");
+    }
+
+    #[test]
+    fn screen_points_map_back_to_the_texture() {
+        let mut transform = Transform::default();
+        transform.rotate(true);
+        // the bottom left corner of the texture is at the top left after a clockwise turn
+        assert_eq!(transform.texture_uv(vec2(0.0, 0.0)), vec2(0.0, 1.0));
+        assert_eq!(transform.texture_uv(vec2(1.0, 0.0)), vec2(0.0, 0.0));
+        transform.flip_horizontal();
+        assert_eq!(transform.texture_uv(vec2(0.0, 0.0)), vec2(0.0, 0.0));
+        assert_eq!(
+            Transform::default().texture_uv(vec2(0.25, 0.5)),
+            vec2(0.25, 0.5)
+        );
+    }
+
+    #[test]
+    fn orientation_composes_with_manual_turns() {
+        // stored sideways, shown upright and then turned back by hand
+        let upright = Transform::from_orientation(6);
+        assert_eq!(upright.size(vec2(40.0, 30.0)), vec2(30.0, 40.0));
+        let mut back = Transform::default();
+        back.rotate(false);
+        assert!(upright.then(back) == Transform::default());
+        // a transposed image turned a quarter is only mirrored
+        let transposed = Transform::from_orientation(5);
+        let mut turn = Transform::default();
+        turn.rotate(true);
+        assert!(transposed.then(turn) == Transform::from_orientation(2));
+        for orientation in 1..=8 {
+            assert_eq!(
+                Transform::from_orientation(orientation).orientation(),
+                orientation
+            );
+        }
+    }
+}
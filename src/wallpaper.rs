@@ -0,0 +1,196 @@
+// the desktop background, set through the tools each desktop comes with; paths are handed over
+// in environment variables so nothing has to be quoted for a script
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use directories::ProjectDirs;
+use eframe::egui;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+// how the image covers the screen, macOS and KDE keep their own setting
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum WallpaperMode {
+    #[default]
+    Fill,
+    Fit,
+    Stretch,
+    Center,
+    Tile,
+}
+
+impl WallpaperMode {
+    pub const ALL: [WallpaperMode; 5] = [
+        WallpaperMode::Fill,
+        WallpaperMode::Fit,
+        WallpaperMode::Stretch,
+        WallpaperMode::Center,
+        WallpaperMode::Tile,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WallpaperMode::Fill => "Fill",
+            WallpaperMode::Fit => "Fit",
+            WallpaperMode::Stretch => "Stretch",
+            WallpaperMode::Center => "Center",
+            WallpaperMode::Tile => "Tile",
+        }
+    }
+}
+
+// where images that are not files of their own are saved to become the wallpaper, the one set
+// before is removed since desktops only notice a new name
+pub fn save(bytes: &[u8]) -> Result<PathBuf, String> {
+    let dirs = ProjectDirs::from("", "", "view-rs").ok_or("No cache directory")?;
+    let dir = dirs.cache_dir();
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with("wallpaper-")
+            {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+    let ext = image::guess_format(bytes)
+        .ok()
+        .and_then(|f| f.extensions_str().first().copied())
+        .unwrap_or("png");
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let path = dir.join(format!("wallpaper-{}.{}", stamp, ext));
+    std::fs::write(&path, bytes).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+// the commands to run in order, each with whether it has to succeed
+type Commands = Vec<(Command, bool)>;
+
+// set `path` as the wallpaper on a background thread, the channel gets the outcome
+pub fn set(path: &Path, mode: WallpaperMode, ctx: &egui::Context) -> Receiver<Result<(), String>> {
+    let (tx, done) = mpsc::channel();
+    let ctx = ctx.clone();
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    thread::spawn(move || {
+        info!("Setting {:?} as the wallpaper", path);
+        let result =
+            commands(&path, mode)
+                .into_iter()
+                .try_for_each(|(command, required)| match run(command) {
+                    Err(err) if !required => {
+                        debug!("{}", err);
+                        Ok(())
+                    }
+                    result => result,
+                });
+        let _ = tx.send(result);
+        ctx.request_repaint();
+    });
+    done
+}
+
+fn run(mut command: Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    debug!("Running {:?}", command);
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("{}: {}", program, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} failed: {}", program, stderr.trim()));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn commands(path: &Path, mode: WallpaperMode) -> Commands {
+    const SCRIPT: &str = r#"
+        $desktop = 'HKCU:\Control Panel\Desktop'
+        Set-ItemProperty $desktop WallpaperStyle $env:VIEW_RS_STYLE
+        Set-ItemProperty $desktop TileWallpaper $env:VIEW_RS_TILE
+        $code = '[DllImport("user32.dll", CharSet = CharSet.Unicode)] public static extern bool SystemParametersInfo(uint action, uint param, string path, uint flags);'
+        $user32 = Add-Type -MemberDefinition $code -Name User32 -Namespace ViewRs -PassThru
+        # SPI_SETDESKWALLPAPER, written to the profile and announced to the other programs
+        if (-not $user32::SystemParametersInfo(20, 0, $env:VIEW_RS_WALLPAPER, 3)) { exit 1 }
+    "#;
+    let (style, tile) = match mode {
+        WallpaperMode::Fill => ("10", "0"),
+        WallpaperMode::Fit => ("6", "0"),
+        WallpaperMode::Stretch => ("2", "0"),
+        WallpaperMode::Center => ("0", "0"),
+        WallpaperMode::Tile => ("0", "1"),
+    };
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+        .env("VIEW_RS_WALLPAPER", path)
+        .env("VIEW_RS_STYLE", style)
+        .env("VIEW_RS_TILE", tile);
+    vec![(command, true)]
+}
+
+#[cfg(target_os = "macos")]
+fn commands(path: &Path, _mode: WallpaperMode) -> Commands {
+    const SCRIPT: &str = r#"tell application "System Events" to tell every desktop to set picture to (system attribute "VIEW_RS_WALLPAPER")"#;
+    let mut command = Command::new("osascript");
+    command.args(["-e", SCRIPT]).env("VIEW_RS_WALLPAPER", path);
+    vec![(command, true)]
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn commands(path: &Path, mode: WallpaperMode) -> Commands {
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .to_lowercase();
+    if desktop.contains("kde") {
+        let mut command = Command::new("plasma-apply-wallpaperimage");
+        command.arg(path);
+        return vec![(command, true)];
+    }
+    if ["gnome", "unity", "budgie", "pantheon"]
+        .iter()
+        .any(|d| desktop.contains(d))
+    {
+        let options = match mode {
+            WallpaperMode::Fill => "zoom",
+            WallpaperMode::Fit => "scaled",
+            WallpaperMode::Stretch => "stretched",
+            WallpaperMode::Center => "centered",
+            WallpaperMode::Tile => "wallpaper",
+        };
+        let url = crate::path::to_url(path);
+        let gsettings = |key: &str, value: &str| {
+            let mut command = Command::new("gsettings");
+            command.args(["set", "org.gnome.desktop.background", key, value]);
+            command
+        };
+        // the dark variant is only known since GNOME 42
+        return vec![
+            (gsettings("picture-options", options), true),
+            (gsettings("picture-uri", &url), true),
+            (gsettings("picture-uri-dark", &url), false),
+        ];
+    }
+    // window managers without a desktop of their own
+    let fill = match mode {
+        WallpaperMode::Fill => "--bg-fill",
+        WallpaperMode::Fit => "--bg-max",
+        WallpaperMode::Stretch => "--bg-scale",
+        WallpaperMode::Center => "--bg-center",
+        WallpaperMode::Tile => "--bg-tile",
+    };
+    let mut command = Command::new("feh");
+    command.arg(fill).arg(path);
+    vec![(command, true)]
+}
@@ -0,0 +1,117 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant, SystemTime},
+};
+
+use eframe::egui;
+use log::debug;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+// quiet time after the last event before the changes are handed out, so a burst of new files
+// is added at once
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+// paths in a directory that were created, removed or renamed, stops watching when dropped
+pub struct DirWatch {
+    _watcher: RecommendedWatcher,
+    events: Receiver<Vec<PathBuf>>,
+    // changed paths of the current burst and when the last one arrived
+    pending: HashSet<PathBuf>,
+    last: Instant,
+    ctx: egui::Context,
+}
+
+impl DirWatch {
+    pub fn start(ctx: &egui::Context, dir: &Path, recursive: bool) -> Result<Self, String> {
+        let (tx, events) = mpsc::channel();
+        let repaint = ctx.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                    if tx.send(event.paths).is_ok() {
+                        repaint.request_repaint();
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => debug!("Watch error: {}", err),
+            })
+            .map_err(|e| e.to_string())?;
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher.watch(dir, mode).map_err(|e| e.to_string())?;
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            pending: HashSet::new(),
+            last: Instant::now(),
+            ctx: ctx.clone(),
+        })
+    }
+
+    // the paths that changed once no event arrived for a while, whether they exist now has to
+    // be checked by the caller
+    pub fn poll(&mut self) -> Option<Vec<PathBuf>> {
+        for paths in self.events.try_iter() {
+            self.pending.extend(paths);
+            self.last = Instant::now();
+        }
+        if self.pending.is_empty() {
+            return None;
+        }
+        let quiet = self.last.elapsed();
+        if quiet < DEBOUNCE {
+            self.ctx.request_repaint_after(DEBOUNCE - quiet);
+            return None;
+        }
+        Some(self.pending.drain().collect())
+    }
+}
+
+// the displayed file, noticing when an editor saves it
+pub struct FileWatch {
+    // the directory is watched because editors often replace the file instead of writing into it
+    watch: DirWatch,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl FileWatch {
+    pub fn start(ctx: &egui::Context, path: &Path) -> Result<Self, String> {
+        let dir = path.parent().ok_or("no parent directory")?;
+        Ok(Self {
+            watch: DirWatch::start(ctx, dir, false)?,
+            path: path.to_path_buf(),
+            modified: modified(path),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // whether the file was written to since the last call
+    pub fn changed(&mut self) -> bool {
+        let Some(paths) = self.watch.poll() else {
+            return false;
+        };
+        if !paths.contains(&self.path) {
+            return false;
+        }
+        let modified = modified(&self.path);
+        // a file that is gone or not written to is left alone
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,92 @@
+// all visible files one below the other, each as wide as the window, only the ones near the
+// viewport are loaded
+
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use eframe::egui;
+use log::debug;
+
+// height over width of an image whose size is not known yet, panels are mostly tall
+const ESTIMATE: f32 = 1.5;
+// images loaded above and below the visible ones
+pub const MARGIN: usize = 2;
+
+#[derive(Default)]
+pub struct Strip {
+    // height over width of the files whose size is known
+    ratios: HashMap<PathBuf, f32>,
+    // URLs of the images that were loaded, forgotten once they are far from the viewport
+    loaded: HashSet<String>,
+    // scroll to the top of this position in the next frame
+    pub scroll_to: Option<usize>,
+    // scroll by this many points in the next frame
+    pub scroll_by: f32,
+}
+
+impl Strip {
+    pub fn ratio(&self, path: &Path) -> f32 {
+        self.ratios.get(path).copied().unwrap_or(ESTIMATE)
+    }
+
+    // record the intrinsic size of an image, returns how much taller it got at `width`
+    pub fn measured(&mut self, path: &Path, size: egui::Vec2, width: f32) -> f32 {
+        let ratio = size.y / size.x.max(1.0);
+        let before = self.ratio(path);
+        self.ratios.insert(path.to_path_buf(), ratio);
+        (ratio - before) * width
+    }
+
+    // forget the images that were loaded but are not in `wanted` anymore
+    pub fn keep(&mut self, ctx: &egui::Context, wanted: &HashSet<String>) {
+        self.loaded.retain(|uri| {
+            let keep = wanted.contains(uri);
+            if !keep {
+                debug!("Forgetting scrolled away {}", uri);
+                ctx.forget_image(uri);
+            }
+            keep
+        });
+        self.loaded.extend(wanted.iter().cloned());
+    }
+
+    // forget everything when leaving the mode, the current image is loaded again anyway
+    pub fn clear(&mut self, ctx: &egui::Context) {
+        self.keep(ctx, &HashSet::new());
+        self.scroll_to = None;
+        self.scroll_by = 0.0;
+    }
+}
+
+// the positions overlapping `offset..offset + height` given where each one starts, `tops` ends
+// with the total height
+pub fn visible(tops: &[f32], offset: f32, height: f32) -> Range<usize> {
+    let count = tops.len().saturating_sub(1);
+    let start = tops[1..].partition_point(|&bottom| bottom <= offset);
+    let end = tops[..count].partition_point(|&top| top < offset + height);
+    start.min(count)..end.max(start.min(count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visible_range() {
+        let tops = [0.0, 100.0, 250.0, 300.0, 600.0];
+        assert_eq!(visible(&tops, 0.0, 100.0), 0..1);
+        assert_eq!(visible(&tops, 120.0, 150.0), 1..3);
+        assert_eq!(visible(&tops, 299.0, 1000.0), 2..4);
+        assert_eq!(visible(&tops, 700.0, 100.0), 4..4);
+        assert_eq!(visible(&[0.0], 0.0, 100.0), 0..0);
+
+        let mut strip = Strip::default();
+        let path = Path::new("a.png");
+        assert_eq!(strip.ratio(path), ESTIMATE);
+        assert_eq!(strip.measured(path, egui::vec2(200.0, 400.0), 100.0), 50.0);
+        assert_eq!(strip.ratio(path), 2.0);
+    }
+}
@@ -0,0 +1,179 @@
+// size and place of the window, kept in the session state and restored at the next start
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+// the least of the window that stays on screen when it is moved back
+const VISIBLE: f32 = 64.0;
+// width of the border a window without a frame is resized at
+const EDGE: f32 = 6.0;
+
+#[derive(Clone, Copy, PartialEq, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Geometry {
+    // inner size in points
+    pub size: [f32; 2],
+    // of the outer top left corner, unknown on Wayland
+    pub position: Option<[f32; 2]>,
+    pub maximized: bool,
+    pub fullscreen: bool,
+    // size of the monitor it was on, positions are only kept on the same one
+    pub monitor: Option<[f32; 2]>,
+}
+
+impl Geometry {
+    pub fn of(info: &egui::ViewportInfo) -> Option<Self> {
+        let inner = info.inner_rect?;
+        Some(Self {
+            size: inner.size().into(),
+            position: info.outer_rect.map(|r| r.min.into()),
+            maximized: info.maximized.unwrap_or(false),
+            fullscreen: info.fullscreen.unwrap_or(false),
+            monitor: info.monitor_size.map(Into::into),
+        })
+    }
+
+    // follow the window, keeping the size and place it had before it was maximized or made
+    // fullscreen so it gets them back
+    pub fn follow(geometry: &mut Option<Self>, info: &egui::ViewportInfo) {
+        let Some(now) = Self::of(info) else {
+            return;
+        };
+        match geometry {
+            Some(kept) if now.maximized || now.fullscreen => {
+                kept.maximized = now.maximized;
+                kept.fullscreen = now.fullscreen;
+            }
+            _ => *geometry = Some(now),
+        }
+    }
+
+    pub fn apply(&self, builder: egui::ViewportBuilder) -> egui::ViewportBuilder {
+        let mut builder = builder
+            .with_inner_size(self.size)
+            .with_maximized(self.maximized)
+            .with_fullscreen(self.fullscreen);
+        if let Some(position) = self.position {
+            builder = builder.with_position(position);
+        }
+        builder
+    }
+
+    // where the window has to go so it can be reached on `monitor`, None if it's fine where it is
+    pub fn moved_onto(&self, monitor: egui::Vec2) -> Option<egui::Pos2> {
+        let [x, y] = self.position?;
+        if self.monitor == Some(monitor.into()) {
+            return None;
+        }
+        let clamped = egui::pos2(
+            x.clamp(
+                (VISIBLE - self.size[0]).min(0.0),
+                (monitor.x - VISIBLE).max(0.0),
+            ),
+            y.clamp(0.0, (monitor.y - VISIBLE).max(0.0)),
+        );
+        (clamped != egui::pos2(x, y)).then_some(clamped)
+    }
+}
+
+// the side of `window` the pointer at `pos` would resize, in a window without a frame to do it
+pub fn edge(window: egui::Rect, pos: egui::Pos2) -> Option<egui::ResizeDirection> {
+    use egui::ResizeDirection::*;
+    let inner = window.shrink(EDGE);
+    let (west, east) = (pos.x < inner.left(), pos.x > inner.right());
+    let (north, south) = (pos.y < inner.top(), pos.y > inner.bottom());
+    match (north, south, west, east) {
+        (true, _, true, _) => Some(NorthWest),
+        (true, _, _, true) => Some(NorthEast),
+        (_, true, true, _) => Some(SouthWest),
+        (_, true, _, true) => Some(SouthEast),
+        (true, ..) => Some(North),
+        (_, true, ..) => Some(South),
+        (_, _, true, _) => Some(West),
+        (_, _, _, true) => Some(East),
+        _ => None,
+    }
+}
+
+pub fn resize_cursor(direction: egui::ResizeDirection) -> egui::CursorIcon {
+    use egui::ResizeDirection::*;
+    match direction {
+        North => egui::CursorIcon::ResizeNorth,
+        South => egui::CursorIcon::ResizeSouth,
+        East => egui::CursorIcon::ResizeEast,
+        West => egui::CursorIcon::ResizeWest,
+        NorthEast => egui::CursorIcon::ResizeNorthEast,
+        SouthEast => egui::CursorIcon::ResizeSouthEast,
+        NorthWest => egui::CursorIcon::ResizeNorthWest,
+        SouthWest => egui::CursorIcon::ResizeSouthWest,
+    }
+}
+
+// `WxH` or `WxH+X+Y` as with X11 programs, offsets may be negative like `+-20`
+pub fn parse(text: &str) -> Result<Geometry, String> {
+    let invalid = || format!("expected WIDTHxHEIGHT or WIDTHxHEIGHT+X+Y, got {:?}", text);
+    let (size, position) = match text.split_once('+') {
+        Some((size, position)) => (size, Some(position)),
+        None => (text, None),
+    };
+    let (w, h) = size.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let number = |s: &str| s.trim().parse::<f32>().ok().filter(|n| n.is_finite());
+    let size = [number(w), number(h)];
+    let [Some(w), Some(h)] = size else {
+        return Err(invalid());
+    };
+    if w < 1.0 || h < 1.0 {
+        return Err(invalid());
+    }
+    let position = match position {
+        Some(position) => {
+            let (x, y) = position.split_once('+').ok_or_else(invalid)?;
+            let (Some(x), Some(y)) = (number(x), number(y)) else {
+                return Err(invalid());
+            };
+            Some([x, y])
+        }
+        None => None,
+    };
+    Ok(Geometry {
+        size: [w, h],
+        position,
+        ..Geometry::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geometry_is_parsed_and_kept_on_screen() {
+        let geometry = parse("800x600+-20+40").unwrap();
+        assert_eq!(geometry.size, [800.0, 600.0]);
+        assert_eq!(geometry.position, Some([-20.0, 40.0]));
+        assert_eq!(parse("1024x768").unwrap().position, None);
+        assert!(parse("1024").is_err());
+        assert!(parse("0x768").is_err());
+        assert!(parse("800x600+10").is_err());
+
+        // left behind on a larger monitor that is gone
+        let saved = Geometry {
+            position: Some([3000.0, 1800.0]),
+            monitor: Some([3840.0, 2160.0]),
+            ..geometry
+        };
+        let monitor = egui::vec2(1920.0, 1080.0);
+        assert_eq!(saved.moved_onto(monitor), Some(egui::pos2(1856.0, 1016.0)));
+        let same = Geometry {
+            monitor: Some([1920.0, 1080.0]),
+            ..saved
+        };
+        assert_eq!(same.moved_onto(monitor), None);
+
+        let window = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0));
+        let at = |x, y| edge(window, egui::pos2(x, y));
+        assert_eq!(at(400.0, 300.0), None);
+        assert_eq!(at(2.0, 300.0), Some(egui::ResizeDirection::West));
+        assert_eq!(at(798.0, 598.0), Some(egui::ResizeDirection::SouthEast));
+    }
+}